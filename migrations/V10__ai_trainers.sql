@@ -0,0 +1,8 @@
+-- The strategy the server plays a computer-controlled trainer by; NULL for
+-- trainers people play.
+ALTER TABLE trainer ADD COLUMN ai_strategy TEXT;
+
+-- The computer-controlled trainer that sent out opponent_id and the strategy
+-- it played by, or NULL when both pokemon used random abilities.
+ALTER TABLE battle ADD COLUMN ai_trainer_id INT REFERENCES trainer (trainer_id) ON DELETE SET NULL;
+ALTER TABLE battle ADD COLUMN ai_strategy TEXT;
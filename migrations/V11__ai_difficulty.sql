@@ -0,0 +1,10 @@
+-- How hard a computer-controlled trainer plays; NULL for trainers people
+-- play. AI trainers created before difficulties existed play on normal.
+ALTER TABLE trainer ADD COLUMN ai_difficulty TEXT;
+UPDATE trainer SET ai_difficulty = 'normal' WHERE ai_strategy IS NOT NULL;
+
+-- The difficulty an AI trainer played at, and what the challenger earned.
+-- NULL for battles fought before difficulties existed, which replay with
+-- the bare strategy, and for battles against other players.
+ALTER TABLE battle ADD COLUMN ai_difficulty TEXT;
+ALTER TABLE battle ADD COLUMN reward INT;
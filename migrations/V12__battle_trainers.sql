@@ -0,0 +1,4 @@
+-- The trainer who asked for the battle, which the per-trainer battle quota
+-- counts by. NULL for battles fought before battles needed signing in.
+ALTER TABLE battle ADD COLUMN trainer_id INT REFERENCES trainer (trainer_id) ON DELETE SET NULL;
+CREATE INDEX battle_trainer_fought_at ON battle (trainer_id, fought_at);
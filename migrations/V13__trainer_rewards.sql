@@ -0,0 +1,2 @@
+-- What the trainer has earned beating computer-controlled trainers.
+ALTER TABLE trainer ADD COLUMN rewards INT NOT NULL DEFAULT 0;
@@ -0,0 +1,29 @@
+-- Slugs are generated once, on insert, and kept when a pokemon is renamed so
+-- that links to it keep working. Names that slugify alike get a numeric
+-- suffix, the oldest pokemon keeping the bare slug.
+ALTER TABLE pokemon ADD COLUMN slug TEXT UNIQUE;
+
+DO $$
+DECLARE
+    p RECORD;
+    base TEXT;
+    candidate TEXT;
+    n INT;
+BEGIN
+    FOR p IN SELECT pokemon_id, name FROM pokemon ORDER BY pokemon_id LOOP
+        base := trim(both '-' from lower(regexp_replace(p.name, '[^A-Za-z0-9]+', '-', 'g')));
+        candidate := base;
+        n := 1;
+        WHILE EXISTS (SELECT 1 FROM pokemon WHERE slug = candidate) LOOP
+            n := n + 1;
+            candidate := base || '-' || n;
+        END LOOP;
+        UPDATE pokemon SET slug = candidate WHERE pokemon_id = p.pokemon_id;
+    END LOOP;
+END $$;
+
+ALTER TABLE pokemon ALTER COLUMN slug SET NOT NULL;
+
+-- The view's columns were fixed when it was created.
+CREATE OR REPLACE VIEW published_pokemon AS
+    SELECT * FROM pokemon WHERE publish_at IS NULL OR publish_at <= now();
@@ -0,0 +1,6 @@
+-- Backs the substring matching of GET /ability/suggest, which a b-tree
+-- index can't serve. pg_trgm is a trusted extension, so the app's role can
+-- create it on Postgres 13 and later.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX ability_name_trgm ON ability USING gin (name gin_trgm_ops);
@@ -0,0 +1,6 @@
+-- A slug without a letter is either empty or looks like a numeric id, so
+-- it could never be looked up. Names are now checked for one before a
+-- pokemon is inserted; pokemon slugged before that fall back to their id.
+UPDATE pokemon SET slug = 'pokemon-' || pokemon_id WHERE slug !~ '[a-z]';
+
+ALTER TABLE pokemon ADD CONSTRAINT pokemon_slug_has_letter CHECK (slug ~ '[a-z]');
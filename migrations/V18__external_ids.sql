@@ -0,0 +1,13 @@
+-- Random ids to hand out in links and accept in paths alongside the serial
+-- ones, which give away how many rows there are and are easy to guess.
+-- Existing rows are filled in by the default.
+ALTER TABLE region ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+ALTER TABLE trainer ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+ALTER TABLE pokemon ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+ALTER TABLE ability ADD COLUMN uuid UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+
+-- The views' columns were fixed when they were created.
+CREATE OR REPLACE VIEW published_pokemon AS
+    SELECT * FROM pokemon WHERE publish_at IS NULL OR publish_at <= now();
+CREATE OR REPLACE VIEW published_ability AS
+    SELECT * FROM ability WHERE publish_at IS NULL OR publish_at <= now();
@@ -0,0 +1,21 @@
+-- A row of trainerspokemon is a pokemon the trainer owns: one instance of
+-- the species the pokemon table catalogs, with its own id, nickname, level
+-- and individual values. Existing rows become level 5 instances with random
+-- IVs; the volatile default is worked out row by row. A trainer still owns
+-- at most one of each species.
+ALTER TABLE trainerspokemon
+    ADD COLUMN instance_id SERIAL UNIQUE,
+    ADD COLUMN nickname TEXT,
+    ADD COLUMN level INT NOT NULL DEFAULT 5 CHECK (level BETWEEN 1 AND 100),
+    ADD COLUMN iv_hp INT NOT NULL DEFAULT floor(random() * 32)
+        CHECK (iv_hp BETWEEN 0 AND 31),
+    ADD COLUMN iv_attack INT NOT NULL DEFAULT floor(random() * 32)
+        CHECK (iv_attack BETWEEN 0 AND 31),
+    ADD COLUMN iv_defense INT NOT NULL DEFAULT floor(random() * 32)
+        CHECK (iv_defense BETWEEN 0 AND 31),
+    ADD COLUMN iv_special_attack INT NOT NULL DEFAULT floor(random() * 32)
+        CHECK (iv_special_attack BETWEEN 0 AND 31),
+    ADD COLUMN iv_special_defense INT NOT NULL DEFAULT floor(random() * 32)
+        CHECK (iv_special_defense BETWEEN 0 AND 31),
+    ADD COLUMN iv_speed INT NOT NULL DEFAULT floor(random() * 32)
+        CHECK (iv_speed BETWEEN 0 AND 31);
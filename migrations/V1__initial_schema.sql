@@ -0,0 +1,58 @@
+-- Tables are created only if missing so that databases set up by hand before
+-- migrations existed can adopt them without being rebuilt.
+
+CREATE TABLE IF NOT EXISTS region (
+    region_id SERIAL PRIMARY KEY,
+    region_name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS trainer (
+    trainer_id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    gym_leader BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE TABLE IF NOT EXISTS pokemon (
+    pokemon_id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    region_id INT NOT NULL REFERENCES region (region_id)
+);
+
+CREATE TABLE IF NOT EXISTS ability (
+    ability_id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    damage INT NOT NULL,
+    status_effect TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS attribute (
+    attribute_id SERIAL PRIMARY KEY,
+    attribute_name TEXT NOT NULL,
+    weakness TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS trainerspokemon (
+    trainer_id INT NOT NULL REFERENCES trainer (trainer_id),
+    pokemon_id INT NOT NULL REFERENCES pokemon (pokemon_id),
+    PRIMARY KEY (trainer_id, pokemon_id)
+);
+
+CREATE TABLE IF NOT EXISTS pokemonabilities (
+    pokemon_id INT NOT NULL REFERENCES pokemon (pokemon_id),
+    ability_id INT NOT NULL REFERENCES ability (ability_id),
+    PRIMARY KEY (pokemon_id, ability_id)
+);
+
+CREATE TABLE IF NOT EXISTS pokemonattributes (
+    pokemon_id INT NOT NULL REFERENCES pokemon (pokemon_id),
+    attribute_id INT NOT NULL REFERENCES attribute (attribute_id),
+    PRIMARY KEY (pokemon_id, attribute_id)
+);
+
+-- Upserts use ON CONFLICT on these names, which needs a unique index.
+CREATE UNIQUE INDEX IF NOT EXISTS region_name_key ON region (region_name);
+CREATE UNIQUE INDEX IF NOT EXISTS pokemon_name_key ON pokemon (name);
+CREATE UNIQUE INDEX IF NOT EXISTS ability_name_key ON ability (name);
+
+-- Views in this schema are served under /reports.
+CREATE SCHEMA IF NOT EXISTS reports;
@@ -0,0 +1,39 @@
+-- Tags sort abilities by damage category (physical, special or status) and
+-- by elemental type. An ability may carry any number of them.
+CREATE TABLE tag (
+    tag_id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL CHECK (kind IN ('category', 'type'))
+);
+
+CREATE TABLE ability_tag (
+    ability_id INT NOT NULL REFERENCES ability (ability_id) ON DELETE CASCADE,
+    tag_id INT NOT NULL REFERENCES tag (tag_id) ON DELETE CASCADE,
+    PRIMARY KEY (ability_id, tag_id)
+);
+
+-- Filtering by tag starts from the tag.
+CREATE INDEX ability_tag_tag_id ON ability_tag (tag_id);
+
+INSERT INTO tag (name, kind) VALUES
+    ('physical', 'category'),
+    ('special', 'category'),
+    ('status', 'category'),
+    ('normal', 'type'),
+    ('fire', 'type'),
+    ('water', 'type'),
+    ('electric', 'type'),
+    ('grass', 'type'),
+    ('ice', 'type'),
+    ('fighting', 'type'),
+    ('poison', 'type'),
+    ('ground', 'type'),
+    ('flying', 'type'),
+    ('psychic', 'type'),
+    ('bug', 'type'),
+    ('rock', 'type'),
+    ('ghost', 'type'),
+    ('dragon', 'type'),
+    ('dark', 'type'),
+    ('steel', 'type'),
+    ('fairy', 'type');
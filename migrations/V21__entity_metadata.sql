@@ -0,0 +1,14 @@
+-- Free-form attributes clients attach to trainers and pokemon without a
+-- schema change. Always a JSON object. The jsonb_path_ops indexes serve the
+-- @> containment that ?meta.<key>=<value> filters compile to.
+ALTER TABLE trainer ADD COLUMN metadata JSONB NOT NULL DEFAULT '{}'
+    CHECK (jsonb_typeof(metadata) = 'object');
+ALTER TABLE pokemon ADD COLUMN metadata JSONB NOT NULL DEFAULT '{}'
+    CHECK (jsonb_typeof(metadata) = 'object');
+
+CREATE INDEX trainer_metadata ON trainer USING GIN (metadata jsonb_path_ops);
+CREATE INDEX pokemon_metadata ON pokemon USING GIN (metadata jsonb_path_ops);
+
+-- The view's columns were fixed when it was created.
+CREATE OR REPLACE VIEW published_pokemon AS
+    SELECT * FROM pokemon WHERE publish_at IS NULL OR publish_at <= now();
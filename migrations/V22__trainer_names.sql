@@ -0,0 +1,60 @@
+-- Names trainers gave up, by renaming or by being deleted. A released name
+-- stays reserved for 30 days, so the leaderboard and battle history don't
+-- show a different trainer under it straight away. Its old trainer may
+-- take it back at any time.
+CREATE TABLE trainer_name_history (
+    history_id SERIAL PRIMARY KEY,
+    -- Not a foreign key: the record outlives a deleted trainer.
+    trainer_id INT NOT NULL,
+    name TEXT NOT NULL,
+    released_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX trainer_name_history_trainer ON trainer_name_history (trainer_id, released_at);
+CREATE INDEX trainer_name_history_name ON trainer_name_history (lower(name), released_at);
+CREATE INDEX trainer_name_lower ON trainer (lower(name));
+
+-- Names are compared without case. Trainers that already share a name keep
+-- it; only new names are checked, so this can't be a unique index.
+CREATE FUNCTION check_trainer_name() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' AND lower(NEW.name) = lower(OLD.name) THEN
+        RETURN NEW;
+    END IF;
+
+    -- Serializes claims on the same name, so two transactions can't both
+    -- find it free. Each query below takes a snapshot after the lock.
+    PERFORM pg_advisory_xact_lock(hashtext('trainer_name:' || lower(NEW.name)));
+    IF EXISTS (
+        SELECT 1 FROM trainer
+        WHERE lower(name) = lower(NEW.name) AND trainer_id <> NEW.trainer_id
+    ) OR EXISTS (
+        SELECT 1 FROM trainer_name_history
+        WHERE lower(name) = lower(NEW.name) AND trainer_id <> NEW.trainer_id
+          AND released_at > now() - interval '30 days'
+    ) THEN
+        RAISE EXCEPTION 'trainer name "%" is taken', NEW.name
+            USING ERRCODE = 'unique_violation', CONSTRAINT = 'trainer_name_taken';
+    END IF;
+
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trainer_name_taken BEFORE INSERT OR UPDATE OF name ON trainer
+    FOR EACH ROW EXECUTE FUNCTION check_trainer_name();
+
+CREATE FUNCTION release_trainer_name() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO trainer_name_history (trainer_id, name) VALUES (OLD.trainer_id, OLD.name);
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trainer_renamed AFTER UPDATE OF name ON trainer
+    FOR EACH ROW WHEN (OLD.name IS DISTINCT FROM NEW.name)
+    EXECUTE FUNCTION release_trainer_name();
+
+CREATE TRIGGER trainer_deleted AFTER DELETE ON trainer
+    FOR EACH ROW EXECUTE FUNCTION release_trainer_name();
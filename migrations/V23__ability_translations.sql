@@ -0,0 +1,10 @@
+-- Ability names and status effects in other languages. The ability row
+-- itself is English, the fallback for any locale without a translation.
+-- Locales are lowercase BCP 47 tags, such as `fr` or `pt-br`.
+CREATE TABLE ability_translation (
+    ability_id INT NOT NULL REFERENCES ability (ability_id) ON DELETE CASCADE,
+    locale TEXT NOT NULL CHECK (locale ~ '^[a-z]{2,3}(-[a-z0-9]{1,8})*$' AND locale !~ '^en(-|$)'),
+    name TEXT NOT NULL,
+    status_effect TEXT NOT NULL,
+    PRIMARY KEY (ability_id, locale)
+);
@@ -0,0 +1,24 @@
+-- Long-form markdown on pokemon and abilities. `search` indexes it along
+-- with the name for GET /search; it is kept up to date by Postgres.
+ALTER TABLE pokemon ADD COLUMN description TEXT, ADD COLUMN lore TEXT;
+ALTER TABLE ability ADD COLUMN description TEXT, ADD COLUMN lore TEXT;
+
+ALTER TABLE pokemon ADD COLUMN search TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', name), 'A')
+    || setweight(to_tsvector('english', coalesce(description, '')), 'B')
+    || setweight(to_tsvector('english', coalesce(lore, '')), 'C')
+) STORED;
+ALTER TABLE ability ADD COLUMN search TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', name), 'A')
+    || setweight(to_tsvector('english', coalesce(description, '')), 'B')
+    || setweight(to_tsvector('english', coalesce(lore, '')), 'C')
+) STORED;
+
+CREATE INDEX pokemon_search ON pokemon USING GIN (search);
+CREATE INDEX ability_search ON ability USING GIN (search);
+
+-- The views' columns were fixed when they were created.
+CREATE OR REPLACE VIEW published_pokemon AS
+    SELECT * FROM pokemon WHERE publish_at IS NULL OR publish_at <= now();
+CREATE OR REPLACE VIEW published_ability AS
+    SELECT * FROM ability WHERE publish_at IS NULL OR publish_at <= now();
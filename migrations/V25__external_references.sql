@@ -0,0 +1,17 @@
+-- Links from a pokemon or an ability to pages elsewhere, for "learn more"
+-- links. Each belongs to exactly one of them. The API checks that URLs are
+-- https and that PokeAPI ids are numbers; the table only checks the kind.
+CREATE TABLE external_reference (
+    reference_id SERIAL PRIMARY KEY,
+    pokemon_id INT REFERENCES pokemon (pokemon_id) ON DELETE CASCADE,
+    ability_id INT REFERENCES ability (ability_id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('bulbapedia', 'pokeapi', 'sprite')),
+    value TEXT NOT NULL,
+    CHECK ((pokemon_id IS NULL) <> (ability_id IS NULL))
+);
+
+-- Attaching the same link twice is refused.
+CREATE UNIQUE INDEX external_reference_pokemon
+    ON external_reference (pokemon_id, kind, value) WHERE pokemon_id IS NOT NULL;
+CREATE UNIQUE INDEX external_reference_ability
+    ON external_reference (ability_id, kind, value) WHERE ability_id IS NOT NULL;
@@ -0,0 +1,31 @@
+-- Every version of each pokemon and ability, recorded by the API after each
+-- write that changes the row. `snapshot` holds the columns a rollback
+-- restores; versions count up from 1 per row.
+CREATE TABLE pokemon_version (
+    pokemon_id INT NOT NULL REFERENCES pokemon (pokemon_id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    snapshot JSONB NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (pokemon_id, version)
+);
+
+CREATE TABLE ability_version (
+    ability_id INT NOT NULL REFERENCES ability (ability_id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    snapshot JSONB NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (ability_id, version)
+);
+
+-- Rows that already exist start at version 1 as they are now.
+INSERT INTO pokemon_version (pokemon_id, version, snapshot)
+SELECT pokemon_id, 1, jsonb_build_object(
+    'name', name, 'region_id', region_id, 'metadata', metadata,
+    'description', description, 'lore', lore
+) FROM pokemon;
+
+INSERT INTO ability_version (ability_id, version, snapshot)
+SELECT ability_id, 1, jsonb_build_object(
+    'name', name, 'damage', damage, 'status_effect', status_effect,
+    'description', description, 'lore', lore
+) FROM ability;
@@ -0,0 +1,14 @@
+-- The pokemon and abilities each user looked at last, for a "recently
+-- viewed" rail that follows them across devices. One row per user and
+-- entity, moved to the front on each view; the API keeps the newest few.
+-- Entities are not foreign keys, so views of deleted ones are simply no
+-- longer listed.
+CREATE TABLE recent_view (
+    user_id INT NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('pokemon', 'ability')),
+    entity_id INT NOT NULL,
+    viewed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, kind, entity_id)
+);
+
+CREATE INDEX recent_view_latest ON recent_view (user_id, viewed_at DESC);
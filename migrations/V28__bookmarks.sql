@@ -0,0 +1,10 @@
+-- Pokemon and abilities users keep an eye on. Like recent views, entities
+-- are not foreign keys: bookmarks of deleted ones stay, so their deletion
+-- still reaches the user's stream, but are no longer listed.
+CREATE TABLE bookmark (
+    user_id INT NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('pokemon', 'ability')),
+    entity_id INT NOT NULL,
+    bookmarked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, kind, entity_id)
+);
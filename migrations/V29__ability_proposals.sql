@@ -0,0 +1,21 @@
+-- Rebalances users propose for an ability. Admins approve or reject each
+-- one once; approving applies it to the ability in the same transaction.
+-- Reviewed proposals are kept as the record of who changed what and why.
+CREATE TABLE ability_proposal (
+    proposal_id SERIAL PRIMARY KEY,
+    ability_id INT NOT NULL REFERENCES ability (ability_id) ON DELETE CASCADE,
+    proposed_by INT NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    damage INT NOT NULL,
+    status_effect TEXT NOT NULL,
+    reason TEXT,
+    proposed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected')),
+    reviewed_by INT REFERENCES users (user_id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    review_note TEXT,
+    CHECK ((status = 'pending') = (reviewed_at IS NULL))
+);
+
+CREATE INDEX ability_proposal_pending ON ability_proposal (proposed_at)
+    WHERE status = 'pending';
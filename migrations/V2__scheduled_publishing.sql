@@ -0,0 +1,20 @@
+-- New pokemon and abilities can be staged with a publish time. Rows whose
+-- publish_at is still in the future are hidden from the public endpoints,
+-- which read through the published_* views instead of the tables.
+ALTER TABLE pokemon ADD COLUMN publish_at TIMESTAMPTZ;
+ALTER TABLE ability ADD COLUMN publish_at TIMESTAMPTZ;
+
+CREATE VIEW published_pokemon AS
+    SELECT * FROM pokemon WHERE publish_at IS NULL OR publish_at <= now();
+
+CREATE VIEW published_ability AS
+    SELECT * FROM ability WHERE publish_at IS NULL OR publish_at <= now();
+
+-- Rebalances of abilities that are already live wait here until the
+-- publisher job copies them onto the ability at publish_at.
+CREATE TABLE ability_changes (
+    ability_id INT PRIMARY KEY REFERENCES ability (ability_id),
+    damage INT NOT NULL,
+    status_effect TEXT NOT NULL,
+    publish_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Each user's exposures to the variant of an experiment they were shown.
+-- Experiments and their variants are defined in code; see experiments.rs.
+CREATE TABLE experiment_exposure (
+    user_id INT NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    experiment TEXT NOT NULL,
+    variant TEXT NOT NULL,
+    exposures INT NOT NULL DEFAULT 1,
+    first_exposed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_exposed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, experiment, variant)
+);
+
+CREATE INDEX experiment_exposure_variant ON experiment_exposure (experiment, variant);
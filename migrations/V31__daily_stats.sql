@@ -0,0 +1,9 @@
+-- Key counts as they stood each day (UTC), for trend charts. The stats job
+-- rewrites the current day's row each time it runs, so a day's value is
+-- the last one recorded that day. Days the job didn't run are missing.
+CREATE TABLE daily_stat (
+    day DATE NOT NULL,
+    metric TEXT NOT NULL CHECK (metric IN ('trainers', 'catches', 'battles')),
+    value BIGINT NOT NULL,
+    PRIMARY KEY (metric, day)
+);
@@ -0,0 +1,21 @@
+-- Questions of the "Who's that pokemon?" quiz, so that an answer is checked
+-- against the pokemon the trainer was shown rather than one the client
+-- names. Each question is answered at most once.
+CREATE TABLE quiz_question (
+    question_id SERIAL PRIMARY KEY,
+    trainer_id INT NOT NULL REFERENCES trainer (trainer_id) ON DELETE CASCADE,
+    pokemon_id INT NOT NULL REFERENCES pokemon (pokemon_id) ON DELETE CASCADE,
+    asked_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    answered_at TIMESTAMPTZ,
+    correct BOOLEAN,
+    CHECK ((answered_at IS NULL) = (correct IS NULL))
+);
+
+-- Each trainer's running quiz score, for the leaderboard.
+CREATE TABLE quiz_score (
+    trainer_id INT PRIMARY KEY REFERENCES trainer (trainer_id) ON DELETE CASCADE,
+    answered INT NOT NULL DEFAULT 0,
+    correct INT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX quiz_score_ranking ON quiz_score (correct DESC, answered, trainer_id);
@@ -0,0 +1,12 @@
+-- Every account plays as one trainer, which the /me routes act on. Deleting
+-- the trainer leaves the account in place without one.
+ALTER TABLE users
+    ADD COLUMN trainer_id INT UNIQUE REFERENCES trainer (trainer_id) ON DELETE SET NULL;
+
+-- Accounts created before this get a trainer named after them.
+WITH created AS (
+    INSERT INTO trainer (name, gym_leader)
+    SELECT username, false FROM users ORDER BY user_id
+    RETURNING trainer_id, name
+)
+UPDATE users u SET trainer_id = c.trainer_id FROM created c WHERE c.name = u.username;
@@ -0,0 +1,14 @@
+-- Admins may act as any trainer through the X-Impersonate-Trainer header.
+-- There is no API for granting it; set is_admin directly in the database.
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT false;
+
+-- One row per impersonated request, written before the request runs.
+CREATE TABLE impersonation_audit (
+    audit_id BIGSERIAL PRIMARY KEY,
+    admin_user_id INT NOT NULL REFERENCES users (user_id),
+    trainer_id INT NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    request_id TEXT,
+    at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,67 @@
+-- Postgres keeps no record of when a table last changed, so every table
+-- stamps its row here after each statement that writes to it. Tables added
+-- by later migrations need the same triggers.
+CREATE TABLE table_modifications (
+    table_name TEXT PRIMARY KEY,
+    modified_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE FUNCTION stamp_table_modification() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO table_modifications (table_name, modified_at)
+    VALUES (TG_TABLE_NAME, now())
+    ON CONFLICT (table_name) DO UPDATE SET modified_at = EXCLUDED.modified_at;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Statement triggers also fire for statements that matched no rows, such as
+-- the publisher's periodic UPDATEs, so those only count if `changed` (the
+-- transition table of affected rows) has any.
+CREATE FUNCTION stamp_table_modification_if_changed() RETURNS trigger AS $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM changed) THEN
+        INSERT INTO table_modifications (table_name, modified_at)
+        VALUES (TG_TABLE_NAME, now())
+        ON CONFLICT (table_name) DO UPDATE SET modified_at = EXCLUDED.modified_at;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+DECLARE
+    t TEXT;
+BEGIN
+    FOR t IN
+        SELECT tablename FROM pg_tables
+        WHERE schemaname = 'public'
+          AND tablename NOT IN ('schema_migrations', 'table_modifications')
+    LOOP
+        -- A trigger with a transition table may only handle one event.
+        EXECUTE format(
+            'CREATE TRIGGER stamp_insert AFTER INSERT ON %I
+             REFERENCING NEW TABLE AS changed
+             FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed()',
+            t
+        );
+        EXECUTE format(
+            'CREATE TRIGGER stamp_update AFTER UPDATE ON %I
+             REFERENCING NEW TABLE AS changed
+             FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed()',
+            t
+        );
+        EXECUTE format(
+            'CREATE TRIGGER stamp_delete AFTER DELETE ON %I
+             REFERENCING OLD TABLE AS changed
+             FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed()',
+            t
+        );
+        EXECUTE format(
+            'CREATE TRIGGER stamp_truncate AFTER TRUNCATE ON %I
+             FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification()',
+            t
+        );
+    END LOOP;
+END;
+$$;
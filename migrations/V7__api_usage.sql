@@ -0,0 +1,23 @@
+-- Database cost of the requests each client made, per day. Filled in from
+-- memory by the usage flusher, so the current minute or so may be missing.
+CREATE TABLE api_usage (
+    client TEXT NOT NULL,
+    day DATE NOT NULL,
+    requests BIGINT NOT NULL,
+    queries BIGINT NOT NULL,
+    query_micros BIGINT NOT NULL,
+    request_micros BIGINT NOT NULL,
+    PRIMARY KEY (client, day)
+);
+
+CREATE TRIGGER stamp_insert AFTER INSERT ON api_usage
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_update AFTER UPDATE ON api_usage
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_delete AFTER DELETE ON api_usage
+REFERENCING OLD TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_truncate AFTER TRUNCATE ON api_usage
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification();
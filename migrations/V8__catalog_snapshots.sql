@@ -0,0 +1,44 @@
+-- The battle-relevant parts of the catalog frozen at one point in time, so
+-- that battles fought against a snapshot give the same results after
+-- abilities or attributes are rebalanced.
+CREATE TABLE catalog_snapshot (
+    snapshot_id SERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Each pokemon published when the snapshot was taken, as the battle
+-- simulator saw it. Not tied to pokemon, so that deleting one later
+-- leaves the snapshot whole.
+CREATE TABLE snapshot_fighter (
+    snapshot_id INT NOT NULL REFERENCES catalog_snapshot (snapshot_id) ON DELETE CASCADE,
+    pokemon_id INT NOT NULL,
+    types TEXT[] NOT NULL,
+    weaknesses TEXT[] NOT NULL,
+    -- [{"name", "damage", "status_effect"}], in ability id order.
+    moves JSONB NOT NULL,
+    PRIMARY KEY (snapshot_id, pokemon_id)
+);
+
+CREATE TRIGGER stamp_insert AFTER INSERT ON catalog_snapshot
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_update AFTER UPDATE ON catalog_snapshot
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_delete AFTER DELETE ON catalog_snapshot
+REFERENCING OLD TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_truncate AFTER TRUNCATE ON catalog_snapshot
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification();
+
+CREATE TRIGGER stamp_insert AFTER INSERT ON snapshot_fighter
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_update AFTER UPDATE ON snapshot_fighter
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_delete AFTER DELETE ON snapshot_fighter
+REFERENCING OLD TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_truncate AFTER TRUNCATE ON snapshot_fighter
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification();
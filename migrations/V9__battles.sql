@@ -0,0 +1,27 @@
+-- Every simulated battle, with the inputs needed to replay it exactly.
+CREATE TABLE battle (
+    battle_id SERIAL PRIMARY KEY,
+    pokemon_id INT NOT NULL,
+    opponent_id INT NOT NULL,
+    -- The u64 seed, reinterpreted as signed.
+    seed BIGINT NOT NULL,
+    snapshot_id INT REFERENCES catalog_snapshot (snapshot_id),
+    -- Both pokemon as the simulator saw them:
+    -- [{"pokemon_id", "types", "weaknesses", "moves"}].
+    fighters JSONB NOT NULL,
+    winner INT,
+    turns JSONB NOT NULL,
+    fought_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TRIGGER stamp_insert AFTER INSERT ON battle
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_update AFTER UPDATE ON battle
+REFERENCING NEW TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_delete AFTER DELETE ON battle
+REFERENCING OLD TABLE AS changed
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification_if_changed();
+CREATE TRIGGER stamp_truncate AFTER TRUNCATE ON battle
+FOR EACH STATEMENT EXECUTE FUNCTION stamp_table_modification();
@@ -0,0 +1,96 @@
+-- Sample catalog loaded by POST /admin/reset?seed=true. Runs right after
+-- the tables are truncated, so every id starts again at 1.
+
+INSERT INTO region (region_name) VALUES ('Kanto'), ('Johto');
+
+INSERT INTO attribute (attribute_name, weakness) VALUES
+    ('Electric', 'Ground'),
+    ('Rock', 'Water'),
+    ('Grass', 'Fire'),
+    ('Water', 'Electric');
+
+INSERT INTO pokemon (name, slug, region_id)
+SELECT p.name, p.slug, r.region_id
+FROM (VALUES
+    ('Pikachu', 'pikachu', 'Kanto'),
+    ('Onix', 'onix', 'Kanto'),
+    ('Mr. Mime', 'mr-mime', 'Kanto'),
+    ('Chikorita', 'chikorita', 'Johto'),
+    ('Totodile', 'totodile', 'Johto')
+) AS p (name, slug, region)
+JOIN region r ON r.region_name = p.region;
+
+INSERT INTO ability (name, damage, status_effect) VALUES
+    ('Thunderbolt', 90, 'paralysis'),
+    ('Rock Throw', 50, 'none'),
+    ('Razor Leaf', 55, 'none'),
+    ('Surf', 90, 'none'),
+    ('Bubble', 20, 'none');
+
+INSERT INTO pokemonattributes (pokemon_id, attribute_id)
+SELECT p.pokemon_id, a.attribute_id
+FROM (VALUES
+    ('Pikachu', 'Electric'),
+    ('Onix', 'Rock'),
+    ('Chikorita', 'Grass'),
+    ('Totodile', 'Water')
+) AS link (pokemon, attribute)
+JOIN pokemon p ON p.name = link.pokemon
+JOIN attribute a ON a.attribute_name = link.attribute;
+
+INSERT INTO pokemonabilities (pokemon_id, ability_id)
+SELECT p.pokemon_id, ab.ability_id
+FROM (VALUES
+    ('Pikachu', 'Thunderbolt'),
+    ('Onix', 'Rock Throw'),
+    ('Chikorita', 'Razor Leaf'),
+    ('Totodile', 'Surf'),
+    ('Totodile', 'Bubble')
+) AS link (pokemon, ability)
+JOIN pokemon p ON p.name = link.pokemon
+JOIN ability ab ON ab.name = link.ability;
+
+INSERT INTO trainer (name, gym_leader) VALUES
+    ('Ash', false),
+    ('Brock', true),
+    ('Misty', true);
+
+INSERT INTO tag (name, kind) VALUES
+    ('physical', 'category'),
+    ('special', 'category'),
+    ('status', 'category'),
+    ('normal', 'type'),
+    ('fire', 'type'),
+    ('water', 'type'),
+    ('electric', 'type'),
+    ('grass', 'type'),
+    ('ice', 'type'),
+    ('fighting', 'type'),
+    ('poison', 'type'),
+    ('ground', 'type'),
+    ('flying', 'type'),
+    ('psychic', 'type'),
+    ('bug', 'type'),
+    ('rock', 'type'),
+    ('ghost', 'type'),
+    ('dragon', 'type'),
+    ('dark', 'type'),
+    ('steel', 'type'),
+    ('fairy', 'type');
+
+INSERT INTO ability_tag (ability_id, tag_id)
+SELECT ab.ability_id, t.tag_id
+FROM (VALUES
+    ('Thunderbolt', 'special'),
+    ('Thunderbolt', 'electric'),
+    ('Rock Throw', 'physical'),
+    ('Rock Throw', 'rock'),
+    ('Razor Leaf', 'physical'),
+    ('Razor Leaf', 'grass'),
+    ('Surf', 'special'),
+    ('Surf', 'water'),
+    ('Bubble', 'special'),
+    ('Bubble', 'water')
+) AS link (ability, tag)
+JOIN ability ab ON ab.name = link.ability
+JOIN tag t ON t.name = link.tag;
@@ -0,0 +1,424 @@
+use crate::battle::{BattleAgent, Fighter, RandomAgent, Rng, Status};
+use crate::team::{recommend, Combatant};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use utoipa::ToSchema;
+
+/// How a computer-controlled trainer plays: which of its pokemon it sends
+/// out against the challenger's, and which ability that pokemon uses each
+/// turn.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Sends out a random pokemon, which uses random abilities.
+    Random,
+    /// Sends out the pokemon with the strongest ability, which always uses
+    /// its strongest ability.
+    Greedy,
+    /// Sends out the pokemon with the best type matchup. It opens with an
+    /// ability that inflicts a status, then goes for damage.
+    TypeAware,
+}
+
+impl Strategy {
+    /// The name stored in `trainer.ai_strategy` and `battle.ai_strategy`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Random => "random",
+            Self::Greedy => "greedy",
+            Self::TypeAware => "type_aware",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "random" => Some(Self::Random),
+            "greedy" => Some(Self::Greedy),
+            "type_aware" => Some(Self::TypeAware),
+            _ => None,
+        }
+    }
+
+    pub fn agent(self) -> &'static dyn BattleAgent {
+        match self {
+            Self::Random => &RandomAgent,
+            Self::Greedy => &GreedyAgent,
+            Self::TypeAware => &TypeAwareAgent,
+        }
+    }
+
+    /// Index into `team`, which must not be empty, of the pokemon to send out
+    /// against `opponent`. Ties go to the earlier pokemon.
+    pub fn choose_fighter(self, team: &[Fighter], opponent: &Fighter, rng: &mut Rng) -> usize {
+        let best = |score: &dyn Fn(&Fighter) -> i32| {
+            (0..team.len())
+                .max_by_key(|&i| (score(&team[i]), Reverse(i)))
+                .unwrap_or(0)
+        };
+
+        match self {
+            Self::Random => rng.below(team.len() as u64) as usize,
+            Self::Greedy => best(&best_damage),
+            Self::TypeAware => best(&|f| {
+                let matchup =
+                    f.hits_weakness_of(opponent) as i32 - opponent.hits_weakness_of(f) as i32;
+                // Matchup first; damage only breaks ties.
+                (matchup * 10_000).saturating_add(best_damage(f))
+            }),
+        }
+    }
+}
+
+/// Reward for beating an AI trainer on `Difficulty::Normal`.
+const BASE_REWARD: i32 = 100;
+/// Bounds on how far `Difficulty::scale` weakens or strengthens the AI's
+/// pokemon, in percent of its abilities' damage.
+const MIN_POWER_PERCENT: i32 = 50;
+const MAX_POWER_PERCENT: i32 = 200;
+
+/// How hard a computer-controlled trainer plays, relative to whoever
+/// challenges it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    /// Fields a pokemon weaker than the challenger's team, and makes a
+    /// random choice instead of its strategy's two times in five.
+    Easy,
+    /// Fields a pokemon as strong as the challenger's team, and makes a
+    /// random choice instead of its strategy's about one time in seven.
+    #[default]
+    Normal,
+    /// Fields a pokemon stronger than the challenger's team, and always
+    /// follows its strategy.
+    Hard,
+}
+
+impl Difficulty {
+    /// The name stored in `trainer.ai_difficulty` and `battle.ai_difficulty`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Self::Easy),
+            "normal" => Some(Self::Normal),
+            "hard" => Some(Self::Hard),
+            _ => None,
+        }
+    }
+
+    /// Strength the AI's pokemon is scaled to, in percent of the
+    /// challenger's team's.
+    fn strength_percent(self) -> i32 {
+        match self {
+            Self::Easy => 75,
+            Self::Normal => 100,
+            Self::Hard => 125,
+        }
+    }
+
+    /// Chance, in percent, that a choice follows the strategy rather than
+    /// being random.
+    fn accuracy_percent(self) -> u64 {
+        match self {
+            Self::Easy => 60,
+            Self::Normal => 85,
+            Self::Hard => 100,
+        }
+    }
+
+    /// What the challenger earns for the battle: nothing unless they won.
+    pub fn reward(self, challenger_won: bool) -> i32 {
+        if !challenger_won {
+            return 0;
+        }
+
+        match self {
+            Self::Easy => BASE_REWARD / 2,
+            Self::Normal => BASE_REWARD,
+            Self::Hard => BASE_REWARD * 2,
+        }
+    }
+
+    /// Like `Strategy::choose_fighter`, except that a missed choice sends
+    /// out a random pokemon.
+    pub fn choose_fighter(
+        self,
+        strategy: Strategy,
+        team: &[Fighter],
+        opponent: &Fighter,
+        rng: &mut Rng,
+    ) -> usize {
+        if self.follows_strategy(rng) {
+            strategy.choose_fighter(team, opponent, rng)
+        } else {
+            Strategy::Random.choose_fighter(team, opponent, rng)
+        }
+    }
+
+    /// The agent `strategy` plays with at this difficulty.
+    pub fn agent(self, strategy: Strategy) -> TieredAgent {
+        TieredAgent {
+            difficulty: self,
+            agent: strategy.agent(),
+        }
+    }
+
+    /// Scales the damage of `fighter`'s abilities so that its strength
+    /// against the challenger's `team`, as the team analyzer scores it, is
+    /// `strength_percent` of the team's strength against it. Both are
+    /// averaged over the team, so a team of one is compared head to head.
+    /// Returns the percent the damage was scaled by.
+    pub fn scale(self, fighter: &mut Fighter, team: &[Fighter]) -> i32 {
+        let members = team.len().max(1) as i64;
+        // Strengths and damage can come close to `i32::MAX` for abilities
+        // stored before damage was capped, so the scaling is done in `i64`.
+        let theirs = recommend(
+            team.iter().map(combatant).collect(),
+            &[combatant(fighter)],
+            team.len(),
+        )
+        .iter()
+        .map(|r| i64::from(r.score))
+        .sum::<i64>()
+            / members;
+        let ours = team
+            .iter()
+            .map(|member| i64::from(strength(fighter, member)))
+            .sum::<i64>()
+            / members;
+        let percent = (i64::from(self.strength_percent()) * theirs.max(1) / ours.max(1))
+            .clamp(MIN_POWER_PERCENT.into(), MAX_POWER_PERCENT.into());
+
+        for m in &mut fighter.moves {
+            m.damage = (i64::from(m.damage) * percent / 100).min(i32::MAX.into()) as i32;
+        }
+
+        percent as i32
+    }
+
+    /// Draws whether the next choice follows the strategy. `Hard` draws
+    /// nothing, so it plays exactly as the bare strategy does.
+    fn follows_strategy(self, rng: &mut Rng) -> bool {
+        let accuracy = self.accuracy_percent();
+        accuracy >= 100 || rng.below(100) < accuracy
+    }
+}
+
+/// A strategy's agent that misses some of its choices, depending on the
+/// difficulty.
+pub struct TieredAgent {
+    difficulty: Difficulty,
+    agent: &'static dyn BattleAgent,
+}
+
+impl BattleAgent for TieredAgent {
+    fn choose_move(
+        &self,
+        me: &Fighter,
+        them: &Fighter,
+        their_status: Option<Status>,
+        rng: &mut Rng,
+    ) -> usize {
+        if self.difficulty.follows_strategy(rng) {
+            self.agent.choose_move(me, them, their_status, rng)
+        } else {
+            RandomAgent.choose_move(me, them, their_status, rng)
+        }
+    }
+}
+
+/// Score of `fighter` against `opponent`, from the team analyzer.
+fn strength(fighter: &Fighter, opponent: &Fighter) -> i32 {
+    recommend(vec![combatant(fighter)], &[combatant(opponent)], 1)
+        .first()
+        .map_or(0, |r| r.score)
+}
+
+fn combatant(fighter: &Fighter) -> Combatant {
+    Combatant {
+        pokemon_id: fighter.pokemon_id,
+        // Only shows up in recommendations, which aren't kept.
+        name: String::new(),
+        types: fighter.types.clone(),
+        weaknesses: fighter.weaknesses.clone(),
+        best_damage: best_damage(fighter),
+    }
+}
+
+fn best_damage(fighter: &Fighter) -> i32 {
+    fighter.moves.iter().map(|m| m.damage).max().unwrap_or(0)
+}
+
+/// Index of the most damaging move among those `eligible`, the earliest on
+/// ties.
+fn strongest_move(me: &Fighter, eligible: impl Fn(usize) -> bool) -> Option<usize> {
+    (0..me.moves.len())
+        .filter(|&i| eligible(i))
+        .max_by_key(|&i| (me.moves[i].damage, Reverse(i)))
+}
+
+struct GreedyAgent;
+
+impl BattleAgent for GreedyAgent {
+    fn choose_move(&self, me: &Fighter, _: &Fighter, _: Option<Status>, _: &mut Rng) -> usize {
+        strongest_move(me, |_| true).unwrap_or(0)
+    }
+}
+
+struct TypeAwareAgent;
+
+impl BattleAgent for TypeAwareAgent {
+    fn choose_move(
+        &self,
+        me: &Fighter,
+        _: &Fighter,
+        their_status: Option<Status>,
+        _: &mut Rng,
+    ) -> usize {
+        // A status only sticks to a pokemon without one.
+        let status_move = their_status
+            .is_none()
+            .then(|| strongest_move(me, |i| Status::parse(&me.moves[i].status_effect).is_some()))
+            .flatten();
+
+        status_move
+            .or_else(|| strongest_move(me, |_| true))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Difficulty, Strategy};
+    use crate::battle::{Fighter, Move, Rng, Status};
+
+    fn fighter(pokemon_id: i32, types: &str, weakness: &str, damage: i32) -> Fighter {
+        Fighter {
+            pokemon_id,
+            types: vec![types.to_string()],
+            weaknesses: vec![weakness.to_string()],
+            moves: vec![Move {
+                name: "Tackle".to_string(),
+                damage,
+                status_effect: "none".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn ai_pokemon_are_scaled_against_the_whole_team() {
+        let onix = || fighter(95, "rock", "water", 100);
+        let pikachu = fighter(25, "electric", "ground", 100);
+        let squirtle = fighter(7, "water", "grass", 100);
+
+        // Head to head, the two are as strong as each other.
+        let mut alone = onix();
+        assert_eq!(
+            Difficulty::Normal.scale(&mut alone, std::slice::from_ref(&pikachu)),
+            100
+        );
+        assert_eq!(alone.moves[0].damage, 100);
+
+        // Squirtle hits Onix's weakness, so against both Onix needs more
+        // power: the team averages 125 against its 85.
+        let mut against_team = onix();
+        let power = Difficulty::Normal.scale(&mut against_team, &[pikachu, squirtle]);
+        assert_eq!(power, 147);
+        assert_eq!(against_team.moves[0].damage, 147);
+    }
+
+    #[test]
+    fn strategies_send_out_the_pokemon_they_favour() {
+        let pikachu = fighter(25, "electric", "ground", 50);
+        let team = [
+            // The strongest, but Pikachu hits its weakness.
+            fighter(7, "water", "electric", 120),
+            fighter(27, "ground", "water", 60),
+            fighter(95, "rock", "water", 60),
+        ];
+        let mut rng = Rng::new(1);
+
+        assert_eq!(
+            Strategy::Greedy.choose_fighter(&team, &pikachu, &mut rng),
+            0
+        );
+        assert_eq!(
+            Strategy::TypeAware.choose_fighter(&team, &pikachu, &mut rng),
+            1
+        );
+
+        let random = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..8)
+                .map(|_| Strategy::Random.choose_fighter(&team, &pikachu, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(random(3), random(3));
+        assert!(random(3).iter().all(|&i| i < team.len()));
+    }
+
+    #[test]
+    fn agents_pick_abilities_by_their_strategy() {
+        let mut pikachu = fighter(25, "electric", "ground", 40);
+        for (name, damage, status_effect) in
+            [("Thunder Wave", 0, "paralysis"), ("Thunder", 90, "none")]
+        {
+            pikachu.moves.push(Move {
+                name: name.to_string(),
+                damage,
+                status_effect: status_effect.to_string(),
+            });
+        }
+        let squirtle = fighter(7, "water", "electric", 40);
+        let mut rng = Rng::new(1);
+        let mut choose = |strategy: Strategy, status| {
+            strategy
+                .agent()
+                .choose_move(&pikachu, &squirtle, status, &mut rng)
+        };
+
+        assert_eq!(choose(Strategy::Greedy, None), 2);
+        assert_eq!(choose(Strategy::TypeAware, None), 1);
+        // A status only sticks once, so then it goes for damage.
+        assert_eq!(choose(Strategy::TypeAware, Some(Status::Paralysis)), 2);
+    }
+
+    #[test]
+    fn hard_always_follows_the_strategy_and_easier_tiers_sometimes_miss() {
+        let team = [
+            fighter(7, "water", "electric", 120),
+            fighter(27, "ground", "water", 10),
+        ];
+        let pikachu = fighter(25, "electric", "ground", 50);
+        let greedy_picks = |difficulty: Difficulty| {
+            let mut rng = Rng::new(9);
+            (0..200)
+                .filter(|_| {
+                    difficulty.choose_fighter(Strategy::Greedy, &team, &pikachu, &mut rng) == 0
+                })
+                .count()
+        };
+
+        assert_eq!(greedy_picks(Difficulty::Hard), 200);
+        assert!(greedy_picks(Difficulty::Normal) < 200);
+        assert!(greedy_picks(Difficulty::Easy) < greedy_picks(Difficulty::Normal));
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for strategy in [Strategy::Random, Strategy::Greedy, Strategy::TypeAware] {
+            assert_eq!(Strategy::from_name(strategy.name()), Some(strategy));
+        }
+        for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+            assert_eq!(Difficulty::from_name(difficulty.name()), Some(difficulty));
+        }
+        assert_eq!(Strategy::from_name("Greedy"), None);
+        assert_eq!(Difficulty::from_name("insane"), None);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::AppState;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::Span;
+
+/// Keys for signing and checking the HS256 tokens issued by `/auth/login`.
+#[derive(Clone)]
+pub struct AuthConfig {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    pub(crate) token_ttl: Duration,
+}
+
+impl AuthConfig {
+    pub fn new(secret: &[u8], token_ttl: Duration) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            token_ttl,
+        }
+    }
+
+    pub(crate) fn issue(&self, user_id: i32, username: String, now: OffsetDateTime) -> String {
+        let exp = (now + self.token_ttl).unix_timestamp().max(0) as u64;
+        let claims = AuthClaims {
+            sub: user_id,
+            username,
+            exp,
+        };
+
+        jsonwebtoken::encode(&Header::default(), &claims, &self.encoding)
+            .expect("HS256 signing cannot fail")
+    }
+
+    /// The claims of the bearer token in `headers`, if it carries a valid one
+    /// that hasn't expired by `now`.
+    pub(crate) fn verify(
+        &self,
+        headers: &HeaderMap,
+        now: OffsetDateTime,
+    ) -> Result<AuthClaims, ApiError> {
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(ApiError::Unauthorized)?;
+
+        // Expiry is checked against `now` below rather than the system
+        // clock, with the same leeway.
+        let mut validation = Validation::default();
+        validation.validate_exp = false;
+        let claims = jsonwebtoken::decode::<AuthClaims>(token, &self.decoding, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| ApiError::Unauthorized)?;
+        if claims.exp.saturating_add(validation.leeway) < now.unix_timestamp().max(0) as u64 {
+            return Err(ApiError::Unauthorized);
+        }
+
+        Ok(claims)
+    }
+}
+
+/// Claims of a valid bearer token. Taking this as a handler argument makes
+/// the route require a signed-in caller; requests without a valid token are
+/// answered with 401 before the handler runs.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct AuthClaims {
+    /// Id of the user the token was issued to.
+    pub(crate) sub: i32,
+    pub(crate) username: String,
+    /// Expiry as seconds since the Unix epoch.
+    exp: u64,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthClaims {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        state.auth.verify(&parts.headers, state.clock.now())
+    }
+}
+
+/// Claims of a signed-in admin. Taking this as a handler argument makes the
+/// route admin-only: requests without a valid token are answered with 401,
+/// and those of users who aren't admins with 403.
+pub(crate) struct AdminClaims(pub(crate) AuthClaims);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AdminClaims {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let claims = AuthClaims::from_request_parts(parts, state).await?;
+
+        let account = state.trainers.account(claims.sub).await.map_err(|e| {
+            tracing::error!("Failed to look up the user: {}", e);
+
+            ApiError::from(e)
+        })?;
+        if !account.is_some_and(|a| a.is_admin) {
+            tracing::warn!(
+                user_id = claims.sub,
+                path = parts.uri.path(),
+                "Non-admin called an admin route"
+            );
+
+            return Err(ApiError::Forbidden);
+        }
+
+        Ok(Self(claims))
+    }
+}
+
+/// Header an admin sends, with a trainer id, to act as that trainer.
+const IMPERSONATE_HEADER: &str = "x-impersonate-trainer";
+
+/// Header on the response telling the admin whose trainer they acted as.
+const IMPERSONATING_HEADER: &str = "x-impersonating-trainer";
+
+/// The trainer the signed-in user plays as. Routes taking this act only on
+/// the caller's own trainer, so there is no trainer id to get wrong or forge.
+/// Answers 401 without a valid token and 404 if the account's trainer was
+/// deleted.
+///
+/// Admins can instead act as any trainer by sending `X-Impersonate-Trainer`.
+/// Every such request is written to `impersonation_audit` before it runs,
+/// logged with the admin's name on the request span, and answered with an
+/// `X-Impersonating-Trainer` header.
+pub(crate) struct CurrentTrainer {
+    pub(crate) trainer_id: i32,
+    pub(crate) claims: AuthClaims,
+    /// Whether an admin is acting as this trainer rather than its owner.
+    pub(crate) impersonated: bool,
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for CurrentTrainer {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let impersonate = match parts.headers.get(IMPERSONATE_HEADER) {
+            Some(value) => Some(
+                value
+                    .to_str()
+                    .ok()
+                    .and_then(|v| v.trim().parse::<i32>().ok())
+                    .ok_or_else(|| {
+                        ApiError::BadRequest(format!("{} must be a trainer id", IMPERSONATE_HEADER))
+                    })?,
+            ),
+            None => None,
+        };
+        let claims = AuthClaims::from_request_parts(parts, state).await?;
+
+        let account = state.trainers.account(claims.sub).await.map_err(|e| {
+            tracing::error!("Failed to look up the user's trainer: {}", e);
+
+            ApiError::from(e)
+        })?;
+        let Some(account) = account else {
+            return Err(ApiError::NotFound);
+        };
+
+        let Some(trainer_id) = impersonate else {
+            return match account.trainer_id {
+                Some(trainer_id) => Ok(Self {
+                    trainer_id,
+                    claims,
+                    impersonated: false,
+                }),
+                None => Err(ApiError::NotFound),
+            };
+        };
+
+        if !account.is_admin {
+            tracing::warn!(
+                user_id = claims.sub,
+                trainer_id,
+                "Non-admin tried to impersonate a trainer"
+            );
+
+            return Err(ApiError::Forbidden);
+        }
+
+        // The audit row is written first: a request that can't be audited
+        // doesn't run.
+        let db = DbConn::from_request_parts(parts, state).await?;
+        let request_id = parts
+            .headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok());
+        let audited = db
+            .query(
+                "INSERT INTO impersonation_audit (admin_user_id, trainer_id, method, path, request_id)
+                 SELECT $1, $2, $3, $4, $5
+                 WHERE EXISTS (SELECT 1 FROM trainer WHERE trainer_id = $2)
+                 RETURNING audit_id",
+                &[
+                    &claims.sub,
+                    &trainer_id,
+                    &parts.method.as_str(),
+                    &parts.uri.path(),
+                    &request_id,
+                ],
+            )
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to audit impersonation: {}", e);
+
+                ApiError::from(e)
+            })?;
+        if audited.is_empty() {
+            return Err(ApiError::NotFound);
+        }
+
+        Span::current().record("impersonated_by", claims.username.as_str());
+        tracing::warn!(
+            admin = claims.username,
+            trainer_id,
+            "Admin is impersonating a trainer"
+        );
+        if let Some(marker) = parts.extensions.get::<ImpersonationMarker>() {
+            marker.0.store(trainer_id, Ordering::Relaxed);
+        }
+
+        Ok(Self {
+            trainer_id,
+            claims,
+            impersonated: true,
+        })
+    }
+}
+
+impl CurrentTrainer {
+    /// Answers 403 unless `trainer_id` is the caller's own trainer, or the
+    /// one an admin is impersonating.
+    pub(crate) fn require_owner(&self, trainer_id: i32) -> Result<(), ApiError> {
+        if trainer_id != self.trainer_id {
+            tracing::warn!(
+                user_id = self.claims.sub,
+                trainer_id,
+                "User tried to change another user's trainer"
+            );
+
+            return Err(ApiError::Forbidden);
+        }
+
+        Ok(())
+    }
+}
+
+/// Filled in by `CurrentTrainer` once impersonation is allowed, so that
+/// `mark_impersonation` can label the response. Zero means nobody.
+#[derive(Clone, Default)]
+struct ImpersonationMarker(Arc<AtomicI32>);
+
+/// Adds `X-Impersonating-Trainer` to responses to impersonated requests.
+pub(crate) async fn mark_impersonation(mut request: Request, next: Next) -> Response {
+    if !request.headers().contains_key(IMPERSONATE_HEADER) {
+        return next.run(request).await;
+    }
+
+    let marker = ImpersonationMarker::default();
+    request.extensions_mut().insert(marker.clone());
+    let mut response = next.run(request).await;
+
+    let trainer_id = marker.0.load(Ordering::Relaxed);
+    if trainer_id != 0 {
+        response
+            .headers_mut()
+            .insert(IMPERSONATING_HEADER, HeaderValue::from(trainer_id));
+    }
+    response
+}
@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Hit points each pokemon enters the battle with.
+const STARTING_HP: i32 = 200;
+/// Battles still running after this many turns end in a draw.
+const MAX_TURNS: u32 = 100;
+/// Damage dealt by a pokemon without any abilities.
+const STRUGGLE_DAMAGE: i32 = 10;
+/// Percent chance that an ability's status effect sticks.
+const STATUS_CHANCE: u64 = 30;
+/// Percent chance that a paralyzed pokemon loses its turn.
+const PARALYSIS_SKIP_CHANCE: u64 = 25;
+
+/// A pokemon as seen by the simulator: its types (attribute names), the
+/// types it is weak to, and the abilities it attacks with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fighter {
+    pub pokemon_id: i32,
+    pub types: Vec<String>,
+    pub weaknesses: Vec<String>,
+    pub moves: Vec<Move>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Move {
+    pub name: String,
+    pub damage: i32,
+    pub status_effect: String,
+}
+
+impl Fighter {
+    pub fn hits_weakness_of(&self, other: &Fighter) -> bool {
+        self.types
+            .iter()
+            .any(|t| other.weaknesses.iter().any(|w| w.eq_ignore_ascii_case(t)))
+    }
+}
+
+/// The status effects the simulator knows about. Abilities with any other
+/// status effect, including `none`, only deal damage.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// A quarter of the pokemon's turns are lost.
+    Paralysis,
+    /// The pokemon loses its next one to three turns, the last of them to
+    /// waking up.
+    Sleep,
+    /// The pokemon loses 1/16 of its starting HP after each of its turns.
+    Burn,
+    Poison,
+}
+
+impl Status {
+    pub fn parse(status_effect: &str) -> Option<Self> {
+        match status_effect.to_ascii_lowercase().as_str() {
+            "paralysis" | "paralyze" | "paralyzed" => Some(Self::Paralysis),
+            "sleep" | "asleep" => Some(Self::Sleep),
+            "burn" | "burned" => Some(Self::Burn),
+            "poison" | "poisoned" => Some(Self::Poison),
+            _ => None,
+        }
+    }
+}
+
+/// What the pokemon whose turn it was did.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    Attack {
+        /// `None` when the pokemon has no abilities and struggles instead.
+        ability: Option<String>,
+        damage: i32,
+        super_effective: bool,
+        /// Set when the attack left the defender with a new status.
+        status_applied: Option<Status>,
+    },
+    /// The pokemon's status kept it from acting.
+    Skipped { status: Status },
+    /// The pokemon woke up and lost the turn doing so.
+    WokeUp,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Turn {
+    pub turn: u32,
+    /// The pokemon whose turn it was.
+    pub pokemon_id: i32,
+    pub action: Action,
+    /// Damage the pokemon then took from its own burn or poison.
+    pub status_damage: i32,
+    pub attacker_hp: i32,
+    pub defender_hp: i32,
+}
+
+pub struct Outcome {
+    /// `None` when both pokemon were still standing after `MAX_TURNS`.
+    pub winner: Option<i32>,
+    pub turns: Vec<Turn>,
+}
+
+/// Where battles that weren't given a seed get one, so that tests can pin
+/// it.
+pub trait SeedSource: Send + Sync {
+    fn seed(&self) -> u64;
+}
+
+/// A fresh random seed for every battle.
+pub struct RandomSeeds;
+
+impl SeedSource for RandomSeeds {
+    fn seed(&self) -> u64 {
+        rand::random()
+    }
+}
+
+/// SplitMix64: small, fast and the same on every platform, so a seed always
+/// replays the same battle.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`. The modulo bias is irrelevant for the small `n`
+    /// used here.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn percent(&mut self, chance: u64) -> bool {
+        self.below(100) < chance
+    }
+}
+
+/// Decides for one side of a battle which ability its pokemon uses each
+/// turn. Players' pokemon use `RandomAgent`; `ai::Strategy` has the agents
+/// of computer-controlled trainers.
+pub trait BattleAgent: Sync {
+    /// Index into `me.moves` of the ability to use. Only asked when `me` has
+    /// any.
+    fn choose_move(
+        &self,
+        me: &Fighter,
+        them: &Fighter,
+        their_status: Option<Status>,
+        rng: &mut Rng,
+    ) -> usize;
+}
+
+/// Uses a random ability each turn.
+pub struct RandomAgent;
+
+impl BattleAgent for RandomAgent {
+    fn choose_move(&self, me: &Fighter, _: &Fighter, _: Option<Status>, rng: &mut Rng) -> usize {
+        rng.below(me.moves.len() as u64) as usize
+    }
+}
+
+struct Side<'a> {
+    fighter: &'a Fighter,
+    agent: &'a dyn BattleAgent,
+    hp: i32,
+    status: Option<Status>,
+    /// Turns left asleep before the one spent waking up.
+    sleep_turns: u32,
+}
+
+/// Simulates a turn-based battle between two pokemon until one faints.
+///
+/// The first attacker is picked at random, then the two alternate. Each
+/// turn the attacker uses one of its abilities at random. Damage is the
+/// ability's damage scaled by 85-100%, doubled when one of the attacker's
+/// types hits the defender's weakness, and the ability's status effect
+/// sticks `STATUS_CHANCE` percent of the time unless the defender already
+/// has one. All randomness comes from `rng`.
+pub fn simulate(a: &Fighter, b: &Fighter, rng: &mut Rng) -> Outcome {
+    simulate_with([(a, &RandomAgent), (b, &RandomAgent)], rng)
+}
+
+/// Like `simulate`, but each pokemon uses the abilities its agent picks.
+pub fn simulate_with(sides: [(&Fighter, &dyn BattleAgent); 2], rng: &mut Rng) -> Outcome {
+    let mut sides = sides.map(|(fighter, agent)| Side {
+        fighter,
+        agent,
+        hp: STARTING_HP,
+        status: None,
+        sleep_turns: 0,
+    });
+    let mut attacker = rng.below(2) as usize;
+    let mut turns = Vec::new();
+
+    for turn in 1..=MAX_TURNS {
+        let [first, second] = &mut sides;
+        let (me, them) = if attacker == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let action = take_turn(me, them, rng);
+        let status_damage = match me.status {
+            Some(Status::Burn | Status::Poison) => (STARTING_HP / 16).min(me.hp),
+            _ => 0,
+        };
+        me.hp -= status_damage;
+
+        turns.push(Turn {
+            turn,
+            pokemon_id: me.fighter.pokemon_id,
+            action,
+            status_damage,
+            attacker_hp: me.hp,
+            defender_hp: them.hp,
+        });
+
+        if them.hp == 0 {
+            return Outcome {
+                winner: Some(me.fighter.pokemon_id),
+                turns,
+            };
+        }
+        if me.hp == 0 {
+            return Outcome {
+                winner: Some(them.fighter.pokemon_id),
+                turns,
+            };
+        }
+        attacker = 1 - attacker;
+    }
+
+    Outcome {
+        winner: None,
+        turns,
+    }
+}
+
+fn take_turn(me: &mut Side, them: &mut Side, rng: &mut Rng) -> Action {
+    match me.status {
+        Some(Status::Sleep) if me.sleep_turns == 0 => {
+            me.status = None;
+            return Action::WokeUp;
+        }
+        Some(Status::Sleep) => {
+            me.sleep_turns -= 1;
+            return Action::Skipped {
+                status: Status::Sleep,
+            };
+        }
+        Some(Status::Paralysis) if rng.percent(PARALYSIS_SKIP_CHANCE) => {
+            return Action::Skipped {
+                status: Status::Paralysis,
+            };
+        }
+        _ => {}
+    }
+
+    let moves = &me.fighter.moves;
+    let chosen = match moves.len() {
+        0 => None,
+        _ => {
+            let i = me
+                .agent
+                .choose_move(me.fighter, them.fighter, them.status, rng);
+            Some(&moves[i])
+        }
+    };
+    let base = chosen.map_or(STRUGGLE_DAMAGE, |m| m.damage);
+    let super_effective = me.fighter.hits_weakness_of(them.fighter);
+
+    // Abilities stored before damage was capped can be near `i32::MAX`, so
+    // this is worked out in `i64` and only narrowed once clamped to the HP.
+    let mut damage = i64::from(base) * (85 + rng.below(16) as i64) / 100;
+    if super_effective {
+        damage *= 2;
+    }
+    let damage = damage.clamp(0, i64::from(them.hp)) as i32;
+    them.hp -= damage;
+
+    let status_applied = chosen
+        .and_then(|m| Status::parse(&m.status_effect))
+        .filter(|_| them.status.is_none() && them.hp > 0 && rng.percent(STATUS_CHANCE));
+    if let Some(status) = status_applied {
+        them.status = Some(status);
+        if status == Status::Sleep {
+            them.sleep_turns = rng.below(3) as u32;
+        }
+    }
+
+    Action::Attack {
+        ability: chosen.map(|m| m.name.clone()),
+        damage,
+        super_effective,
+        status_applied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fighter(
+        pokemon_id: i32,
+        types: &[&str],
+        weaknesses: &[&str],
+        moves: &[(&str, i32, &str)],
+    ) -> Fighter {
+        Fighter {
+            pokemon_id,
+            types: types.iter().map(|t| t.to_string()).collect(),
+            weaknesses: weaknesses.iter().map(|w| w.to_string()).collect(),
+            moves: moves
+                .iter()
+                .map(|&(name, damage, status_effect)| Move {
+                    name: name.to_string(),
+                    damage,
+                    status_effect: status_effect.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn pikachu() -> Fighter {
+        fighter(
+            25,
+            &["electric"],
+            &["ground"],
+            &[
+                ("thunderbolt", 45, "paralysis"),
+                ("quick attack", 20, "none"),
+            ],
+        )
+    }
+
+    fn squirtle() -> Fighter {
+        fighter(
+            7,
+            &["water"],
+            &["electric"],
+            &[("water gun", 40, "none"), ("bubble", 25, "none")],
+        )
+    }
+
+    /// Who attacked, with what, and for how much damage, turn by turn.
+    fn log(outcome: &Outcome) -> Vec<(i32, Option<&str>, i32)> {
+        outcome
+            .turns
+            .iter()
+            .map(|turn| match &turn.action {
+                Action::Attack {
+                    ability, damage, ..
+                } => (turn.pokemon_id, ability.as_deref(), *damage),
+                _ => (turn.pokemon_id, None, 0),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rng_is_pinned_by_its_seed() {
+        let mut rng = Rng::new(42);
+        let rolls: Vec<u64> = (0..4).map(|_| rng.below(100)).collect();
+
+        assert_eq!(rolls, [13, 91, 58, 64]);
+    }
+
+    #[test]
+    fn a_seed_always_replays_the_same_battle() {
+        let outcome = simulate(&pikachu(), &squirtle(), &mut Rng::new(42));
+
+        assert_eq!(outcome.winner, Some(25));
+        assert_eq!(
+            log(&outcome),
+            [
+                (7, Some("bubble"), 21),
+                (25, Some("thunderbolt"), 78),
+                (7, Some("bubble"), 22),
+                (25, Some("quick attack"), 38),
+                (7, Some("bubble"), 24),
+                (25, Some("thunderbolt"), 82),
+                (7, Some("water gun"), 36),
+                (25, Some("quick attack"), 2),
+            ]
+        );
+        assert_eq!(outcome.turns.last().unwrap().defender_hp, 0);
+
+        let replay = simulate(&pikachu(), &squirtle(), &mut Rng::new(42));
+        assert_eq!(log(&replay), log(&outcome));
+    }
+
+    #[test]
+    fn pokemon_without_abilities_struggle() {
+        let magikarp = fighter(129, &["water"], &[], &[]);
+        let feebas = fighter(349, &["water"], &[], &[]);
+        let outcome = simulate(&magikarp, &feebas, &mut Rng::new(7));
+
+        assert!(log(&outcome)
+            .iter()
+            .all(|&(_, ability, damage)| ability.is_none() && damage <= STRUGGLE_DAMAGE));
+        assert!(outcome.winner.is_some());
+    }
+
+    #[test]
+    fn huge_damage_knocks_out_without_overflowing() {
+        let mewtwo = fighter(150, &["psychic"], &[], &[("psystrike", i32::MAX, "none")]);
+        let machamp = fighter(
+            68,
+            &["fighting"],
+            &["psychic"],
+            &[("cross chop", 50, "none")],
+        );
+        let outcome = simulate(&mewtwo, &machamp, &mut Rng::new(42));
+
+        assert_eq!(outcome.winner, Some(150));
+        assert!(log(&outcome)
+            .iter()
+            .all(|&(id, _, damage)| id != 150 || damage == STARTING_HP));
+    }
+}
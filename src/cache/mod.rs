@@ -0,0 +1,123 @@
+use crate::events::{ChangeHook, Entity};
+use axum::{
+    async_trait,
+    body::Bytes,
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+mod redis;
+
+pub(crate) use self::redis::RedisCache;
+
+/// Serialized JSON bodies of catalog pages, keyed by path and query string.
+/// Hits are served as-is, so a popular page is serialized once per write
+/// rather than once per request.
+///
+/// Registered as a change hook: any write to pokemon, abilities or regions
+/// (all of which show up in catalog pages) empties it.
+#[async_trait]
+pub(crate) trait Cache: ChangeHook {
+    async fn get(&self, key: &str) -> Option<Bytes>;
+
+    /// Marks the start of a read whose result may be stored with `insert`.
+    fn generation(&self) -> u64;
+
+    /// Stores `body` under `key`, unless the cache was invalidated since
+    /// `generation` was taken.
+    async fn insert(&self, key: String, generation: u64, body: Bytes);
+
+    fn invalidate(&self);
+}
+
+/// Catalog pages show pokemon, abilities and regions, but no trainers.
+fn shows_in_catalog(entity: Entity) -> bool {
+    !matches!(entity, Entity::Trainer)
+}
+
+/// Serializes a response body for storing in a `Cache`.
+pub(crate) fn to_json_bytes<T: Serialize>(value: &T) -> Bytes {
+    Bytes::from(serde_json::to_vec(value).expect("response serializes to JSON"))
+}
+
+/// A `Cache` in this instance's memory. The least used pages are evicted
+/// once `capacity` is reached. Writes the hooks never see, such as those
+/// made by another instance, only show once the TTL expires the pages they
+/// affect; use `RedisCache` when running more than one instance.
+pub(crate) struct ResponseCache {
+    /// Bumped on every invalidation so that a page read before a write can't
+    /// be stored after the write has cleared the cache.
+    generation: AtomicU64,
+    /// Held while checking `generation` and storing, and while invalidating.
+    write_lock: Mutex<()>,
+    entries: moka::sync::Cache<String, Bytes>,
+}
+
+impl ResponseCache {
+    /// Without a `ttl`, pages are kept until the next write or eviction.
+    pub(crate) fn new(capacity: u64, ttl: Option<Duration>) -> Self {
+        let mut entries = moka::sync::Cache::builder().max_capacity(capacity);
+        if let Some(ttl) = ttl {
+            entries = entries.time_to_live(ttl);
+        }
+
+        Self {
+            generation: AtomicU64::new(0),
+            write_lock: Mutex::new(()),
+            entries: entries.build(),
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for ResponseCache {
+    async fn get(&self, key: &str) -> Option<Bytes> {
+        self.entries.get(key)
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    async fn insert(&self, key: String, generation: u64, body: Bytes) {
+        let _guard = self.write_lock.lock().unwrap();
+        if self.generation() == generation {
+            self.entries.insert(key, body);
+        }
+    }
+
+    fn invalidate(&self) {
+        let _guard = self.write_lock.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.entries.invalidate_all();
+    }
+}
+
+impl ChangeHook for ResponseCache {
+    fn on_created(&self, entity: Entity, _id: i32) {
+        if shows_in_catalog(entity) {
+            self.invalidate();
+        }
+    }
+
+    fn on_updated(&self, entity: Entity, _id: i32) {
+        if shows_in_catalog(entity) {
+            self.invalidate();
+        }
+    }
+
+    fn on_deleted(&self, entity: Entity, _id: i32) {
+        if shows_in_catalog(entity) {
+            self.invalidate();
+        }
+    }
+}
+
+/// A 200 response carrying already-serialized JSON.
+pub(crate) fn json_bytes(body: Bytes) -> Response {
+    ([(CONTENT_TYPE, "application/json")], body).into_response()
+}
@@ -0,0 +1,405 @@
+use super::{shows_in_catalog, Cache};
+use crate::events::{ChangeHook, Entity};
+use axum::{async_trait, body::Bytes};
+use futures_util::StreamExt;
+use redis::{aio::ConnectionManager, Client, RedisResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Hash holding every cached page, keyed by path and query string. Keeping
+/// them in one key lets a write drop them all with a single `DEL`.
+const PAGES_KEY: &str = "catalog:pages";
+/// Channel each instance announces its invalidations on.
+const INVALIDATE_CHANNEL: &str = "catalog:invalidate";
+/// Longest a request waits on Redis before treating the page as a miss.
+/// While Redis is down, commands would otherwise wait out its reconnects.
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(250);
+/// Wait before resubscribing after losing the subscription.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// A `Cache` in Redis, shared by every instance behind the load balancer.
+///
+/// A write empties the shared pages and publishes the invalidation, so that
+/// every instance bumps its generation: a page one of them read before the
+/// write is then not stored after it. A page stored in the moment before the
+/// message arrives lives until the TTL expires it.
+///
+/// Redis being unreachable only turns hits into misses; requests still get
+/// served from the database.
+pub(crate) struct RedisCache {
+    conn: ConnectionManager,
+    /// Tells this instance's invalidations apart from the others'.
+    instance: u64,
+    /// Pages are dropped at most this long after the first of them was
+    /// stored. Redis can't expire single hash fields on older versions, so
+    /// they all go together.
+    ttl: Option<Duration>,
+    generation: AtomicU64,
+}
+
+impl RedisCache {
+    /// Connects to the Redis at `url` and starts listening for the other
+    /// instances' invalidations.
+    pub(crate) async fn connect(url: &str, ttl: Option<Duration>) -> RedisResult<Arc<Self>> {
+        let client = Client::open(url)?;
+        let cache = Arc::new(Self {
+            conn: client.get_connection_manager().await?,
+            instance: rand::random(),
+            ttl,
+            generation: AtomicU64::new(0),
+        });
+
+        tokio::spawn(listen(client, Arc::downgrade(&cache)));
+
+        Ok(cache)
+    }
+
+    fn bump_generation(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Bumps the generation whenever another instance invalidates, until the
+/// cache is dropped. Invalidations sent while the subscription was down are
+/// lost, so it bumps once more on every resubscribe.
+async fn listen(client: Client, cache: Weak<RedisCache>) {
+    loop {
+        match client.get_async_pubsub().await {
+            Ok(mut pubsub) => match pubsub.subscribe(INVALIDATE_CHANNEL).await {
+                Ok(()) => {
+                    let mut messages = pubsub.into_on_message();
+                    let Some(instance) = cache.upgrade().map(|c| c.instance) else {
+                        return;
+                    };
+                    while let Some(message) = messages.next().await {
+                        let Some(cache) = cache.upgrade() else {
+                            return;
+                        };
+                        if message.get_payload::<u64>().ok() != Some(instance) {
+                            cache.bump_generation();
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to subscribe to cache invalidations: {}", e),
+            },
+            Err(e) => tracing::warn!("Failed to subscribe to cache invalidations: {}", e),
+        }
+
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        match cache.upgrade() {
+            Some(cache) => cache.bump_generation(),
+            None => return,
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Option<Bytes> {
+        let mut conn = self.conn.clone();
+        let mut hget = redis::cmd("HGET");
+        hget.arg(PAGES_KEY).arg(key);
+        match timeout(
+            COMMAND_TIMEOUT,
+            hget.query_async::<Option<Vec<u8>>>(&mut conn),
+        )
+        .await
+        {
+            Ok(Ok(body)) => body.map(Bytes::from),
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to read cached page: {}", e);
+
+                None
+            }
+            Err(_) => {
+                tracing::warn!("Timed out reading cached page");
+
+                None
+            }
+        }
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    async fn insert(&self, key: String, generation: u64, body: Bytes) {
+        // An invalidation landing during the round trip is not caught; the
+        // TTL covers it.
+        if self.generation() != generation {
+            return;
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.cmd("HSET").arg(PAGES_KEY).arg(key).arg(body.as_ref());
+        if let Some(ttl) = self.ttl {
+            pipe.cmd("EXPIRE")
+                .arg(PAGES_KEY)
+                .arg(ttl.as_secs().max(1))
+                .arg("NX");
+        }
+
+        let mut conn = self.conn.clone();
+        match timeout(COMMAND_TIMEOUT, pipe.query_async::<()>(&mut conn)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to cache page: {}", e),
+            Err(_) => tracing::warn!("Timed out caching page"),
+        }
+    }
+
+    fn invalidate(&self) {
+        self.bump_generation();
+
+        let mut conn = self.conn.clone();
+        let instance = self.instance;
+        tokio::spawn(async move {
+            let result = redis::pipe()
+                .cmd("DEL")
+                .arg(PAGES_KEY)
+                .cmd("PUBLISH")
+                .arg(INVALIDATE_CHANNEL)
+                .arg(instance)
+                .query_async::<()>(&mut conn)
+                .await;
+            if let Err(e) = result {
+                tracing::error!("Failed to invalidate cached pages: {}", e);
+            }
+        });
+    }
+}
+
+impl ChangeHook for RedisCache {
+    fn on_created(&self, entity: Entity, _id: i32) {
+        if shows_in_catalog(entity) {
+            self.invalidate();
+        }
+    }
+
+    fn on_updated(&self, entity: Entity, _id: i32) {
+        if shows_in_catalog(entity) {
+            self.invalidate();
+        }
+    }
+
+    fn on_deleted(&self, entity: Entity, _id: i32) {
+        if shows_in_catalog(entity) {
+            self.invalidate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RedisCache, INVALIDATE_CHANNEL};
+    use crate::cache::Cache;
+    use axum::body::Bytes;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+    use tokio::task::JoinHandle;
+
+    /// Just enough of a Redis server, speaking RESP2, for the commands the
+    /// cache sends.
+    #[derive(Default)]
+    struct FakeRedis {
+        pages: HashMap<Vec<u8>, Vec<u8>>,
+        subscribers: Vec<UnboundedSender<Vec<u8>>>,
+        commands: Vec<Vec<String>>,
+        tasks: Vec<JoinHandle<()>>,
+    }
+
+    fn bulk(value: &[u8]) -> Vec<u8> {
+        let mut reply = format!("${}\r\n", value.len()).into_bytes();
+        reply.extend_from_slice(value);
+        reply.extend_from_slice(b"\r\n");
+        reply
+    }
+
+    /// Starts the server and returns it with its URL.
+    async fn fake_redis() -> (Arc<Mutex<FakeRedis>>, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let redis = Arc::new(Mutex::new(FakeRedis::default()));
+
+        let server = redis.clone();
+        let accept = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let task = tokio::spawn(serve(server.clone(), socket));
+                server.lock().unwrap().tasks.push(task);
+            }
+        });
+        redis.lock().unwrap().tasks.push(accept);
+
+        (redis, url)
+    }
+
+    /// Drops every connection and stops accepting new ones.
+    fn shut_down(redis: &Mutex<FakeRedis>) {
+        for task in redis.lock().unwrap().tasks.drain(..) {
+            task.abort();
+        }
+    }
+
+    async fn serve(redis: Arc<Mutex<FakeRedis>>, socket: TcpStream) {
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(reader);
+        let (replies, mut outgoing) = unbounded_channel::<Vec<u8>>();
+        tokio::spawn(async move {
+            while let Some(reply) = outgoing.recv().await {
+                if writer.write_all(&reply).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let count: usize = line.trim_start_matches('*').trim().parse().unwrap();
+            let mut args = Vec::with_capacity(count);
+            for _ in 0..count {
+                line.clear();
+                reader.read_line(&mut line).await.unwrap();
+                let len: usize = line.trim_start_matches('$').trim().parse().unwrap();
+                let mut arg = vec![0; len + 2];
+                reader.read_exact(&mut arg).await.unwrap();
+                arg.truncate(len);
+                args.push(arg);
+            }
+
+            let mut redis = redis.lock().unwrap();
+            let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+            redis.commands.push(
+                args.iter()
+                    .map(|a| String::from_utf8_lossy(a).into_owned())
+                    .collect(),
+            );
+            let reply = match name.as_str() {
+                "CLIENT" => b"+OK\r\n".to_vec(),
+                "HGET" => match redis.pages.get(&args[2]) {
+                    Some(page) => bulk(page),
+                    None => b"$-1\r\n".to_vec(),
+                },
+                "HSET" => {
+                    redis.pages.insert(args[2].clone(), args[3].clone());
+                    b":1\r\n".to_vec()
+                }
+                "EXPIRE" => b":1\r\n".to_vec(),
+                "DEL" => {
+                    redis.pages.clear();
+                    b":1\r\n".to_vec()
+                }
+                "PUBLISH" => {
+                    let mut message = b"*3\r\n".to_vec();
+                    message.extend(bulk(b"message"));
+                    message.extend(bulk(&args[1]));
+                    message.extend(bulk(&args[2]));
+                    redis
+                        .subscribers
+                        .retain(|subscriber| subscriber.send(message.clone()).is_ok());
+                    format!(":{}\r\n", redis.subscribers.len()).into_bytes()
+                }
+                "SUBSCRIBE" => {
+                    redis.subscribers.push(replies.clone());
+                    let mut reply = b"*3\r\n".to_vec();
+                    reply.extend(bulk(b"subscribe"));
+                    reply.extend(bulk(&args[1]));
+                    reply.extend(b":1\r\n");
+                    reply
+                }
+                _ => b"-ERR unknown command\r\n".to_vec(),
+            };
+            let _ = replies.send(reply);
+        }
+    }
+
+    /// Waits for `done` to hold, failing the test after a second.
+    async fn eventually(done: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out");
+    }
+
+    #[tokio::test]
+    async fn pages_are_shared_and_a_write_anywhere_invalidates_them_everywhere() {
+        let (redis, url) = fake_redis().await;
+        let ttl = Some(Duration::from_secs(60));
+        let a = RedisCache::connect(&url, ttl).await.unwrap();
+        let b = RedisCache::connect(&url, ttl).await.unwrap();
+        eventually(|| redis.lock().unwrap().subscribers.len() == 2).await;
+
+        a.insert("/pokemon".to_string(), a.generation(), Bytes::from("[]"))
+            .await;
+        assert_eq!(b.get("/pokemon").await, Some(Bytes::from("[]")));
+        // The TTL runs from the first page stored.
+        assert!(redis
+            .lock()
+            .unwrap()
+            .commands
+            .iter()
+            .any(|c| c[0] == "EXPIRE" && c[2] == "60" && c[3] == "NX"));
+
+        let read_before_write = b.generation();
+        let own_generation = a.generation();
+        a.invalidate();
+        assert_eq!(a.generation(), own_generation + 1);
+        eventually(|| b.generation() != read_before_write).await;
+        assert_eq!(b.get("/pokemon").await, None);
+
+        // A page read before the write is not stored after it.
+        b.insert(
+            "/pokemon".to_string(),
+            read_before_write,
+            Bytes::from("stale"),
+        )
+        .await;
+        assert_eq!(a.get("/pokemon").await, None);
+
+        // An instance ignores its own announcement, having already bumped.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(a.generation(), own_generation + 1);
+        assert!(redis
+            .lock()
+            .unwrap()
+            .commands
+            .iter()
+            .any(|c| c[0] == "PUBLISH" && c[1] == INVALIDATE_CHANNEL));
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_redis_turns_hits_into_misses() {
+        let (redis, url) = fake_redis().await;
+        let cache = RedisCache::connect(&url, None).await.unwrap();
+        cache
+            .insert(
+                "/ability".to_string(),
+                cache.generation(),
+                Bytes::from("[]"),
+            )
+            .await;
+        assert!(cache.get("/ability").await.is_some());
+
+        shut_down(&redis);
+        assert_eq!(cache.get("/ability").await, None);
+        // Storing fails quietly too.
+        cache
+            .insert(
+                "/ability".to_string(),
+                cache.generation(),
+                Bytes::from("[]"),
+            )
+            .await;
+    }
+}
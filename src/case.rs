@@ -0,0 +1,236 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value};
+
+/// Largest JSON body renamed on its way in or out. Matches the limit axum's
+/// `Json` extractor applies anyway.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Fields holding free-form JSON rather than a model: entity metadata,
+/// version snapshots and the changes between them, and report rows. Their
+/// keys are user data or column names, so they are passed on as they are.
+const FREE_FORM_FIELDS: [&str; 5] = ["metadata", "snapshot", "from", "to", "rows"];
+
+/// Naming of the fields in JSON bodies. The models all use snake_case;
+/// `Camel` renames their fields at the edge, for clients such as the JS
+/// frontend that expect camelCase.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum FieldCase {
+    #[default]
+    Snake,
+    Camel,
+}
+
+/// Renames the fields of JSON responses to camelCase, and those of JSON
+/// request bodies back to snake_case. Request fields already in snake_case
+/// come through unchanged, so clients written against the old names keep
+/// working while they move over. Only model fields are renamed: the
+/// contents of `FREE_FORM_FIELDS` and query parameters are left as they are.
+pub(crate) async fn camel_case_json(request: Request, next: Next) -> Response {
+    let request = if is_json(request.headers()) {
+        let (mut parts, body) = request.into_parts();
+        match rename_body(body, to_snake_case).await {
+            Ok(body) => {
+                parts.headers.remove(CONTENT_LENGTH);
+                Request::from_parts(parts, body)
+            }
+            Err(status) => return status.into_response(),
+        }
+    } else {
+        request
+    };
+
+    let response = next.run(request).await;
+    if !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    match rename_body(body, to_camel_case).await {
+        Ok(body) => {
+            parts.headers.remove(CONTENT_LENGTH);
+            Response::from_parts(parts, body)
+        }
+        Err(status) => status.into_response(),
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// Renames the model fields in a JSON body with `rename`. A body that fails
+/// to parse is passed on untouched, so that the handler's extractor reports
+/// it as usual.
+async fn rename_body(body: Body, rename: fn(&str) -> String) -> Result<Body, StatusCode> {
+    let bytes = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE)?;
+
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(mut value) => {
+            rename_keys(&mut value, rename);
+            Ok(Body::from(
+                serde_json::to_vec(&value).expect("a JSON value always serializes"),
+            ))
+        }
+        Err(_) => Ok(Body::from(bytes)),
+    }
+}
+
+fn rename_keys(value: &mut Value, rename: fn(&str) -> String) {
+    match value {
+        Value::Object(object) => {
+            let renamed: Map<String, Value> = std::mem::take(object)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    let key = rename(&key);
+                    if !FREE_FORM_FIELDS.contains(&to_snake_case(&key).as_str()) {
+                        rename_keys(&mut value, rename);
+                    }
+                    (key, value)
+                })
+                .collect();
+            *object = renamed;
+        }
+        Value::Array(items) => {
+            for item in items {
+                rename_keys(item, rename);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Renames the properties of every schema in an OpenAPI spec to camelCase,
+/// so the spec matches what `camel_case_json` serves.
+pub(crate) fn camel_case_schemas(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Object(properties)) = object.get_mut("properties") {
+                *properties = std::mem::take(properties)
+                    .into_iter()
+                    .map(|(name, schema)| (to_camel_case(&name), schema))
+                    .collect();
+            }
+            if let Some(Value::Array(required)) = object.get_mut("required") {
+                for name in required.iter_mut() {
+                    if let Value::String(name) = name {
+                        *name = to_camel_case(name);
+                    }
+                }
+            }
+            for value in object.values_mut() {
+                camel_case_schemas(value);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                camel_case_schemas(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `total_count` becomes `totalCount`.
+fn to_camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' && !out.is_empty() {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `totalCount` becomes `total_count`; snake_case names stay as they are.
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if !out.is_empty() {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FieldCase;
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send_in, state, token};
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const CAMEL: FieldCase = FieldCase::Camel;
+
+    #[tokio::test]
+    async fn camel_case_clients_round_trip_model_fields() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let state = state(repo).with_field_case(CAMEL);
+        let admin = token(&state, admin);
+        let app = build_app(state);
+
+        let body = json!({"damage": 40, "statusEffect": "paralysis"});
+        let uri = "/ability/Static";
+        let (status, body) = send_in(CAMEL, &app, Method::PUT, uri, Some(&admin), Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["statusEffect"], "paralysis");
+        assert!(body.get("status_effect").is_none());
+        let (_, body) = send_in(CAMEL, &app, Method::GET, uri, None, None).await;
+        assert_eq!(body["statusEffect"], "paralysis");
+        assert!(body.get("abilityId").is_some());
+
+        // Snapshots are free-form, so they keep the names they were stored
+        // under.
+        let uri = "/ability/Static/versions";
+        let (status, body) = send_in(CAMEL, &app, Method::GET, uri, Some(&admin), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["versions"][0]["snapshot"]["status_effect"],
+            "paralysis"
+        );
+        assert!(body["versions"][0].get("recordedAt").is_some());
+    }
+
+    #[tokio::test]
+    async fn snake_case_fields_are_still_accepted_from_older_clients() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let state = state(repo).with_field_case(CAMEL);
+        let admin = token(&state, admin);
+        let app = build_app(state);
+
+        let body = json!({"damage": 40, "status_effect": "burn"});
+        let uri = "/ability/Ember";
+        let (status, body) = send_in(CAMEL, &app, Method::PUT, uri, Some(&admin), Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["statusEffect"], "burn");
+    }
+}
@@ -0,0 +1,103 @@
+use crate::AppState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Fault injection for exercising client retry and error handling. Only
+/// installed when `CHAOS_ENABLED` is set, and never in release builds.
+#[derive(Clone, Default)]
+pub struct ChaosConfig {
+    /// Delay added before each targeted request is handled.
+    pub latency_ms: u64,
+    /// Fraction of targeted requests answered with a 500 instead.
+    pub error_rate: f64,
+    /// Matched route paths to target, such as `/pokemon/:key`. Empty
+    /// targets every public route.
+    pub routes: Vec<String>,
+}
+
+impl ChaosConfig {
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("CHAOS_ENABLED")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        if !cfg!(debug_assertions) {
+            tracing::warn!("CHAOS_ENABLED is ignored in release builds");
+            return None;
+        }
+
+        Some(Self {
+            latency_ms: std::env::var("CHAOS_LATENCY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            error_rate: std::env::var("CHAOS_ERROR_RATE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.0),
+            routes: std::env::var("CHAOS_ROUTES")
+                .map(|v| {
+                    v.split(',')
+                        .map(|r| r.trim().to_string())
+                        .filter(|r| !r.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+fn chaos_header<T: std::str::FromStr>(request: &Request, name: &str) -> Option<T> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+/// Applies the configured faults. `X-Chaos-Latency-Ms` and
+/// `X-Chaos-Error-Rate` override the config for a single request and target
+/// it regardless of `routes`, so a client can trigger failures on demand.
+pub(crate) async fn inject_chaos(
+    State(state): State<Arc<AppState>>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(chaos) = &state.chaos else {
+        return next.run(request).await;
+    };
+
+    let latency_override = chaos_header::<u64>(&request, "x-chaos-latency-ms");
+    let error_override = chaos_header::<f64>(&request, "x-chaos-error-rate");
+    let targeted = latency_override.is_some()
+        || error_override.is_some()
+        || chaos.routes.is_empty()
+        || matched.is_some_and(|m| chaos.routes.iter().any(|r| r == m.as_str()));
+    if !targeted {
+        return next.run(request).await;
+    }
+
+    let latency_ms = latency_override.unwrap_or(chaos.latency_ms);
+    if latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(latency_ms)).await;
+    }
+
+    if rand::random::<f64>() < error_override.unwrap_or(chaos.error_rate) {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [("x-chaos-injected", "error")],
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
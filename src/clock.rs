@@ -0,0 +1,16 @@
+use time::OffsetDateTime;
+
+/// Where handlers read the current time: token expiry, usage days and
+/// publish times all go through it, so that tests can pin it.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// The system's wall clock, in UTC.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
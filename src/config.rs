@@ -0,0 +1,626 @@
+use crate::case::FieldCase;
+use axum::http::{HeaderValue, Method};
+use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use rustls::pki_types::{pem::PemObject, CertificateDer};
+use rustls::{ClientConfig, RootCertStore};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio_postgres::config::SslMode;
+use tokio_postgres::NoTls;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+/// The server's settings, read from the environment. Every missing or
+/// invalid variable is reported at once, and none falls back to its default
+/// silently.
+///
+/// The database is given either as `DATABASE_URL` or as `POSTGRES_USER`,
+/// `POSTGRES_PASS` and the optional `DB_HOST`, `DB_PORT` and `DB_NAME`.
+/// `POSTGRES_SSLMODE` (`disable`, `prefer` or `require`) overrides the mode
+/// of either form; `prefer` and `require` connect over TLS, trusting the
+/// public web roots plus the PEM certificates in `POSTGRES_SSLROOTCERT`, if
+/// set.
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub jwt_secret: String,
+    /// `PORT`, 3000 by default.
+    pub port: u16,
+    /// `SLOW_QUERY_MS`: queries taking longer are logged. 200 by default.
+    pub slow_query: Duration,
+    /// `JWT_TTL_SECS`: how long issued tokens stay valid. An hour by default.
+    pub token_ttl: Duration,
+    /// `RECORD_REQUESTS`: how many of the latest requests to keep for
+    /// `/admin/recent-requests`. None are kept when unset.
+    pub record_requests: Option<usize>,
+    /// `CATALOG_CACHE_TTL_SECS`, 300 by default. Zero keeps cached catalog
+    /// pages until the next write.
+    pub catalog_cache_ttl: Option<Duration>,
+    /// `COMPRESSION_MIN_BYTES`, 1024 by default. Zero turns compression off.
+    pub compression_min_bytes: u16,
+    /// `RATE_LIMIT_PER_MINUTE`, 600 by default. Zero turns rate limiting off.
+    pub rate_limit_per_minute: u32,
+    /// `AUTH_RATE_LIMIT_PER_MINUTE`, 10 by default. Zero turns it off for
+    /// signing in too.
+    pub auth_rate_limit_per_minute: u32,
+    /// `PUBLISH_INTERVAL_SECS`, 30 by default.
+    pub publish_interval: Duration,
+    /// `USAGE_FLUSH_SECS`, 60 by default.
+    pub usage_flush_interval: Duration,
+    /// `STATS_INTERVAL_SECS`, an hour by default.
+    pub stats_interval: Duration,
+    /// Whether `POST /admin/reset` is served. Resetting wipes the database,
+    /// so it takes an `APP_ENV` of `dev` or `test`; an unset one does not
+    /// count.
+    pub reset_enabled: bool,
+    /// `JSON_FIELD_CASE`, `snake` (the default) or `camel`.
+    pub field_case: FieldCase,
+}
+
+pub struct DatabaseConfig {
+    postgres: tokio_postgres::Config,
+    root_cert: Option<PathBuf>,
+    pool_max_size: usize,
+    wait_timeout: Duration,
+    create_timeout: Duration,
+    recycle_timeout: Duration,
+}
+
+/// Which cross-origin callers may use the public API, read from the
+/// environment.
+///
+/// `CORS_ALLOWED_ORIGINS` lists the allowed origins, separated by commas, or
+/// is `*` for any origin. Left unset, any origin is allowed when `APP_ENV` is
+/// `dev` or `test` and none otherwise, so production stays locked down until
+/// its origins are configured. `CORS_ALLOWED_METHODS` defaults to GET, POST,
+/// PUT, PATCH and DELETE. `CORS_ALLOW_CREDENTIALS=true` lets browsers send
+/// cookies and `Authorization`; it needs an explicit origin list.
+#[derive(Clone)]
+pub struct CorsConfig {
+    pub(crate) origins: AllowedOrigins,
+    pub(crate) methods: Vec<Method>,
+    pub(crate) credentials: bool,
+}
+
+#[derive(Clone)]
+pub(crate) enum AllowedOrigins {
+    Any,
+    List(Vec<HeaderValue>),
+}
+
+impl Default for CorsConfig {
+    /// No cross-origin callers at all.
+    fn default() -> Self {
+        Self {
+            origins: AllowedOrigins::List(Vec::new()),
+            methods: vec![
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ],
+            credentials: false,
+        }
+    }
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let defaults = Self::default();
+        let development = matches!(var("APP_ENV").as_deref(), Some("dev" | "test"));
+
+        let origins = match var("CORS_ALLOWED_ORIGINS").as_deref() {
+            None if development => AllowedOrigins::Any,
+            None => defaults.origins,
+            Some("*") => AllowedOrigins::Any,
+            Some(list) => AllowedOrigins::List(
+                split_list(list)
+                    .map(parse_origin)
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        let methods = match var("CORS_ALLOWED_METHODS") {
+            None => defaults.methods,
+            Some(list) => split_list(&list)
+                .map(|method| {
+                    Method::from_bytes(method.to_ascii_uppercase().as_bytes()).map_err(|_| {
+                        ConfigError::Invalid {
+                            var: "CORS_ALLOWED_METHODS",
+                            reason: format!("{:?} is not a method", method),
+                        }
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        let credentials = match var("CORS_ALLOW_CREDENTIALS").as_deref() {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => {
+                return Err(ConfigError::Invalid {
+                    var: "CORS_ALLOW_CREDENTIALS",
+                    reason: format!("expected true or false, got {:?}", other),
+                })
+            }
+        };
+        // Browsers refuse credentialed responses that allow any origin.
+        if credentials && matches!(origins, AllowedOrigins::Any) {
+            return Err(ConfigError::Invalid {
+                var: "CORS_ALLOW_CREDENTIALS",
+                reason: "needs CORS_ALLOWED_ORIGINS to list the origins".to_string(),
+            });
+        }
+
+        Ok(Self {
+            origins,
+            methods,
+            credentials,
+        })
+    }
+}
+
+/// Where catalog pages are cached, read from the environment.
+///
+/// `CATALOG_CACHE` is `memory` (the default) or `redis`, which needs
+/// `REDIS_URL`. Instances behind a load balancer should share a Redis
+/// cache, so that each sees the pages the others' writes invalidate.
+pub enum CatalogCacheConfig {
+    Memory,
+    Redis { url: String },
+}
+
+impl CatalogCacheConfig {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        match var("CATALOG_CACHE").as_deref() {
+            None | Some("memory") => Ok(Self::Memory),
+            Some("redis") => match var("REDIS_URL") {
+                Some(url) => Ok(Self::Redis { url }),
+                None => Err(ConfigError::Missing(vec![
+                    "REDIS_URL (for CATALOG_CACHE=redis)",
+                ])),
+            },
+            Some(other) => Err(ConfigError::Invalid {
+                var: "CATALOG_CACHE",
+                reason: format!("expected memory or redis, got {:?}", other),
+            }),
+        }
+    }
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// An origin is a scheme and host with an optional port, such as
+/// `https://app.example.com`, and never has a path.
+fn parse_origin(origin: &str) -> Result<HeaderValue, ConfigError> {
+    let invalid = |reason: &str| ConfigError::Invalid {
+        var: "CORS_ALLOWED_ORIGINS",
+        reason: format!("{:?} {}", origin, reason),
+    };
+    let Some((_, host)) = origin
+        .split_once("://")
+        .filter(|(scheme, _)| matches!(*scheme, "http" | "https"))
+    else {
+        return Err(invalid("must start with http:// or https://"));
+    };
+    if host.is_empty() || host.contains('/') {
+        return Err(invalid("must be a scheme and host, without a path"));
+    }
+
+    HeaderValue::from_str(origin).map_err(|_| invalid("is not a valid header value"))
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// Required variables that are unset, all reported at once.
+    Missing(Vec<&'static str>),
+    Invalid {
+        var: &'static str,
+        reason: String,
+    },
+    /// More than one of the above.
+    Several(Vec<ConfigError>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(vars) => write!(
+                f,
+                "missing required environment variables: {}",
+                vars.join(", ")
+            ),
+            Self::Invalid { var, reason } => write!(f, "invalid {}: {}", var, reason),
+            Self::Several(errors) => {
+                for (i, e) in errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// Reads variables through `lookup`, noting each one that is missing or
+/// invalid instead of stopping at the first, so that they can all be
+/// reported together.
+struct Env<'a> {
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+    missing: Vec<&'static str>,
+    invalid: Vec<ConfigError>,
+}
+
+impl<'a> Env<'a> {
+    fn new(lookup: &'a dyn Fn(&str) -> Option<String>) -> Self {
+        Self {
+            lookup,
+            missing: Vec::new(),
+            invalid: Vec::new(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        (self.lookup)(key).filter(|v| !v.is_empty())
+    }
+
+    fn required(&mut self, key: &'static str) -> Option<String> {
+        let value = self.get(key);
+        if value.is_none() {
+            self.missing.push(key);
+        }
+        value
+    }
+
+    fn check<T>(&mut self, result: Result<T, ConfigError>) -> Option<T> {
+        result.map_err(|e| self.invalid.push(e)).ok()
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, key: &'static str, default: T) -> T {
+        let Some(value) = self.get(key) else {
+            return default;
+        };
+        match value.parse() {
+            Ok(number) => number,
+            Err(_) => {
+                self.invalid.push(ConfigError::Invalid {
+                    var: key,
+                    reason: format!("expected a whole number, got {:?}", value),
+                });
+                default
+            }
+        }
+    }
+
+    fn millis(&mut self, key: &'static str, default: u64) -> Duration {
+        Duration::from_millis(self.number(key, default))
+    }
+
+    /// Seconds between runs of a background job, which can't be zero.
+    fn interval(&mut self, key: &'static str, default: u64) -> Duration {
+        let secs = self.number(key, default);
+        if secs == 0 {
+            self.invalid.push(ConfigError::Invalid {
+                var: key,
+                reason: "must be at least 1".to_string(),
+            });
+            return Duration::from_secs(default);
+        }
+        Duration::from_secs(secs)
+    }
+
+    /// Everything that was missing or invalid, if anything was.
+    fn into_error(self) -> Option<ConfigError> {
+        let mut errors = self.invalid;
+        if !self.missing.is_empty() {
+            errors.insert(0, ConfigError::Missing(self.missing));
+        }
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(ConfigError::Several(errors)),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(&var)
+    }
+
+    /// Reads the settings from `lookup` rather than the environment.
+    fn from_vars(lookup: &dyn Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut env = Env::new(lookup);
+
+        let postgres = match env.get("DATABASE_URL") {
+            Some(url) => env.check(parse_url(&url)),
+            None => discrete_config(&mut env),
+        };
+        let jwt_secret = env.required("JWT_SECRET");
+        let ssl_mode = ssl_mode(&env);
+        let ssl_mode = env.check(ssl_mode).flatten();
+        // Applies to every pooled connection; requests that run longer fail
+        // with 504 instead of holding a connection indefinitely.
+        let statement_timeout_ms: u64 = env.number("STATEMENT_TIMEOUT_MS", 5000);
+        let root_cert = env.get("POSTGRES_SSLROOTCERT").map(PathBuf::from);
+        let pool_max_size = env.number("DB_POOL_MAX_SIZE", 16);
+        let wait_timeout = env.millis("DB_POOL_WAIT_TIMEOUT_MS", 5000);
+        let create_timeout = env.millis("DB_POOL_CREATE_TIMEOUT_MS", 5000);
+        let recycle_timeout = env.millis("DB_POOL_RECYCLE_TIMEOUT_MS", 5000);
+
+        let port = env.number("PORT", 3000);
+        let slow_query = env.millis("SLOW_QUERY_MS", 200);
+        let token_ttl = Duration::from_secs(env.number("JWT_TTL_SECS", 3600));
+        let record_requests = env
+            .get("RECORD_REQUESTS")
+            .map(|_| env.number("RECORD_REQUESTS", 0));
+        let catalog_cache_ttl = Some(env.number("CATALOG_CACHE_TTL_SECS", 300))
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs);
+        let compression_min_bytes = env.number("COMPRESSION_MIN_BYTES", 1024);
+        let rate_limit_per_minute = env.number("RATE_LIMIT_PER_MINUTE", 600);
+        let auth_rate_limit_per_minute = env.number("AUTH_RATE_LIMIT_PER_MINUTE", 10);
+        let publish_interval = env.interval("PUBLISH_INTERVAL_SECS", 30);
+        let usage_flush_interval = env.interval("USAGE_FLUSH_SECS", 60);
+        let stats_interval = env.interval("STATS_INTERVAL_SECS", 3600);
+        let reset_enabled = matches!(env.get("APP_ENV").as_deref(), Some("dev" | "test"));
+        let field_case = match env.get("JSON_FIELD_CASE").as_deref() {
+            None | Some("snake") => FieldCase::Snake,
+            Some("camel") => FieldCase::Camel,
+            Some(other) => {
+                env.invalid.push(ConfigError::Invalid {
+                    var: "JSON_FIELD_CASE",
+                    reason: format!("expected snake or camel, got {:?}", other),
+                });
+                FieldCase::Snake
+            }
+        };
+
+        if let Some(e) = env.into_error() {
+            return Err(e);
+        }
+        let (Some(mut postgres), Some(jwt_secret)) = (postgres, jwt_secret) else {
+            unreachable!("unset required variables are reported as missing");
+        };
+        if let Some(ssl_mode) = ssl_mode {
+            postgres.ssl_mode(ssl_mode);
+        }
+        add_option(
+            &mut postgres,
+            &format!("-c statement_timeout={}", statement_timeout_ms),
+        );
+
+        Ok(Self {
+            database: DatabaseConfig {
+                postgres,
+                root_cert,
+                pool_max_size,
+                wait_timeout,
+                create_timeout,
+                recycle_timeout,
+            },
+            jwt_secret,
+            port,
+            slow_query,
+            token_ttl,
+            record_requests,
+            catalog_cache_ttl,
+            compression_min_bytes,
+            rate_limit_per_minute,
+            auth_rate_limit_per_minute,
+            publish_interval,
+            usage_flush_interval,
+            stats_interval,
+            reset_enabled,
+            field_case,
+        })
+    }
+}
+
+/// Appends `option` to the command-line options sent to the server on
+/// connecting, keeping any that `DATABASE_URL` set.
+fn add_option(postgres: &mut tokio_postgres::Config, option: &str) {
+    let options = match postgres.get_options() {
+        Some(existing) if !existing.is_empty() => format!("{} {}", existing, option),
+        _ => option.to_string(),
+    };
+    postgres.options(&options);
+}
+
+fn parse_url(url: &str) -> Result<tokio_postgres::Config, ConfigError> {
+    url.parse()
+        .map_err(|e: tokio_postgres::Error| ConfigError::Invalid {
+            var: "DATABASE_URL",
+            reason: e.to_string(),
+        })
+}
+
+fn ssl_mode(env: &Env) -> Result<Option<SslMode>, ConfigError> {
+    match env.get("POSTGRES_SSLMODE").as_deref() {
+        None => Ok(None),
+        Some("disable") => Ok(Some(SslMode::Disable)),
+        Some("prefer") => Ok(Some(SslMode::Prefer)),
+        Some("require") => Ok(Some(SslMode::Require)),
+        Some(other) => Err(ConfigError::Invalid {
+            var: "POSTGRES_SSLMODE",
+            reason: format!("expected disable, prefer or require, got {:?}", other),
+        }),
+    }
+}
+
+/// Builds the connection settings from the `POSTGRES_*` and `DB_*`
+/// variables, or notes in `env` what is missing or invalid.
+fn discrete_config(env: &mut Env) -> Option<tokio_postgres::Config> {
+    let user = env.get("POSTGRES_USER");
+    let pass = env.get("POSTGRES_PASS");
+    if user.is_none() {
+        env.missing.push("POSTGRES_USER (or DATABASE_URL)");
+    }
+    if pass.is_none() {
+        env.missing.push("POSTGRES_PASS (or DATABASE_URL)");
+    }
+    let port = match env.get("DB_PORT") {
+        Some(port) => {
+            let port = port.parse().map_err(|_| ConfigError::Invalid {
+                var: "DB_PORT",
+                reason: format!("{:?} is not a port number", port),
+            });
+            env.check(port)?
+        }
+        None => 5432,
+    };
+    let (user, pass) = (user?, pass?);
+
+    let mut config = tokio_postgres::Config::new();
+    config
+        .host(
+            &env.get("DB_HOST")
+                .unwrap_or_else(|| "localhost".to_string()),
+        )
+        .port(port)
+        .dbname(&env.get("DB_NAME").unwrap_or_else(|| "postgres".to_string()))
+        .user(&user)
+        .password(pass)
+        .ssl_mode(SslMode::Disable);
+
+    Some(config)
+}
+
+impl DatabaseConfig {
+    /// Builds the connection pool. No connection is opened until the pool is
+    /// first used.
+    pub fn create_pool(&self) -> Result<Pool, ConfigError> {
+        let manager_config = ManagerConfig {
+            recycling_method: RecyclingMethod::Fast,
+        };
+        let manager = match self.postgres.get_ssl_mode() {
+            SslMode::Disable => Manager::from_config(self.postgres.clone(), NoTls, manager_config),
+            _ => Manager::from_config(
+                self.postgres.clone(),
+                MakeRustlsConnect::new(self.tls_config()?),
+                manager_config,
+            ),
+        };
+
+        Pool::builder(manager)
+            .max_size(self.pool_max_size)
+            .wait_timeout(Some(self.wait_timeout))
+            .create_timeout(Some(self.create_timeout))
+            .recycle_timeout(Some(self.recycle_timeout))
+            .runtime(Runtime::Tokio1)
+            .build()
+            .map_err(|e| ConfigError::Invalid {
+                var: "DB_POOL_MAX_SIZE",
+                reason: e.to_string(),
+            })
+    }
+
+    fn tls_config(&self) -> Result<ClientConfig, ConfigError> {
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        if let Some(path) = &self.root_cert {
+            let invalid = |e: rustls::pki_types::pem::Error| ConfigError::Invalid {
+                var: "POSTGRES_SSLROOTCERT",
+                reason: format!("{}: {}", path.display(), e),
+            };
+            for cert in CertificateDer::pem_file_iter(path).map_err(invalid)? {
+                roots
+                    .add(cert.map_err(invalid)?)
+                    .map_err(|e| ConfigError::Invalid {
+                        var: "POSTGRES_SSLROOTCERT",
+                        reason: format!("{}: {}", path.display(), e),
+                    })?;
+            }
+        }
+
+        Ok(ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConfigError};
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::from_vars(&|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let e = config(&[
+            ("DATABASE_URL", "not a url"),
+            ("PORT", "http"),
+            ("PUBLISH_INTERVAL_SECS", "0"),
+        ])
+        .err()
+        .unwrap();
+
+        let message = e.to_string();
+        for var in [
+            "JWT_SECRET",
+            "DATABASE_URL",
+            "PORT",
+            "PUBLISH_INTERVAL_SECS",
+        ] {
+            assert!(message.contains(var), "{} is not in {:?}", var, message);
+        }
+    }
+
+    #[test]
+    fn the_statement_timeout_keeps_options_from_the_url() {
+        let config = config(&[
+            (
+                "DATABASE_URL",
+                "postgres://app@db/pokedex?options=-c%20search_path%3Dapp",
+            ),
+            ("JWT_SECRET", "secret"),
+            ("STATEMENT_TIMEOUT_MS", "750"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            config.database.postgres.get_options(),
+            Some("-c search_path=app -c statement_timeout=750")
+        );
+    }
+
+    #[test]
+    fn reset_is_enabled_only_in_dev_and_test() {
+        let reset_enabled = |app_env: Option<&str>| {
+            let mut vars = vec![
+                ("DATABASE_URL", "postgres://app@db/pokedex"),
+                ("JWT_SECRET", "secret"),
+            ];
+            vars.extend(app_env.map(|env| ("APP_ENV", env)));
+            config(&vars).unwrap().reset_enabled
+        };
+
+        assert!(reset_enabled(Some("dev")));
+        assert!(reset_enabled(Some("test")));
+        for app_env in [
+            None,
+            Some("prod"),
+            Some("production"),
+            Some("staging"),
+            Some("Dev"),
+            Some(""),
+        ] {
+            assert!(!reset_enabled(app_env), "{:?}", app_env);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Checks responses against the OpenAPI spec, so a handler and the schema
+//! it publishes can't drift apart unnoticed. `testing::send` runs every
+//! JSON response through `check`, which makes each route test a contract
+//! test as well.
+
+use crate::case::{camel_case_schemas, FieldCase};
+use crate::routes::docs::openapi;
+use axum::http::{Method, StatusCode};
+use jsonschema::{Draft, JSONSchema};
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+
+/// Statuses the middleware answers with for any route, which handlers
+/// don't document one by one.
+const MIDDLEWARE_STATUSES: [StatusCode; 4] = [
+    StatusCode::PAYLOAD_TOO_LARGE,
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// Paths served outside the spec. GraphQL publishes its own schema.
+const UNDOCUMENTED_PATHS: [&str; 1] = ["/graphql"];
+
+/// The spec as served for `case`, with its schemas turned from OpenAPI
+/// 3.0's dialect into plain JSON Schema.
+fn spec(case: FieldCase) -> &'static Value {
+    static SNAKE: OnceLock<Value> = OnceLock::new();
+    static CAMEL: OnceLock<Value> = OnceLock::new();
+    let spec = match case {
+        FieldCase::Snake => &SNAKE,
+        FieldCase::Camel => &CAMEL,
+    };
+    spec.get_or_init(|| {
+        let mut spec = serde_json::to_value(openapi()).unwrap();
+        if case == FieldCase::Camel {
+            camel_case_schemas(&mut spec);
+        }
+        to_json_schema(&mut spec);
+        spec
+    })
+}
+
+/// Rewrites `nullable`, which JSON Schema lacks, as a `null` type.
+fn to_json_schema(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.values_mut().for_each(to_json_schema);
+            if object.remove("nullable") != Some(Value::Bool(true)) {
+                return;
+            }
+            if let Some(Value::Array(options)) = object.get_mut("enum") {
+                options.push(Value::Null);
+            }
+            match object.get_mut("type") {
+                Some(Value::String(t)) => {
+                    let t = std::mem::take(t);
+                    object.insert("type".to_string(), json!([t, "null"]));
+                }
+                _ => {
+                    let schema = Value::Object(std::mem::take(object));
+                    object.insert("anyOf".to_string(), json!([schema, {"type": "null"}]));
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(to_json_schema),
+        _ => {}
+    }
+}
+
+/// The operation documenting `method` on `path`. Where several templates
+/// match, as `/ability/stats` and `/ability/{name}` do, the one with the
+/// most literal segments wins, as it does in the router.
+fn operation<'a>(spec: &'a Value, method: &Method, path: &str) -> Option<(&'a str, &'a Value)> {
+    let segments: Vec<&str> = path.split('/').collect();
+    let method = method.as_str().to_lowercase();
+    spec["paths"]
+        .as_object()?
+        .iter()
+        .filter_map(|(template, item)| {
+            let parts: Vec<&str> = template.split('/').collect();
+            if parts.len() != segments.len() {
+                return None;
+            }
+            let mut literal = 0;
+            for (part, segment) in parts.iter().zip(&segments) {
+                if part.starts_with('{') {
+                    continue;
+                }
+                if part != segment {
+                    return None;
+                }
+                literal += 1;
+            }
+            Some((literal, template.as_str(), item.get(&method)?))
+        })
+        .max_by_key(|(literal, ..)| *literal)
+        .map(|(_, template, operation)| (template, operation))
+}
+
+/// Panics, naming the route, if `body` is not what the spec served for
+/// `case` documents for `status` on `method` and `uri`.
+pub(crate) fn check(case: FieldCase, method: &Method, uri: &str, status: StatusCode, body: &Value) {
+    let spec = spec(case);
+    let path = uri.split('?').next().unwrap_or_default();
+    if UNDOCUMENTED_PATHS.contains(&path) {
+        return;
+    }
+    let Some((template, operation)) = operation(spec, method, path) else {
+        panic!("{} {} is not in the spec", method, path);
+    };
+    let route = format!("{} {}", method, template);
+    let Some(response) = operation["responses"].get(status.as_str()) else {
+        assert!(
+            MIDDLEWARE_STATUSES.contains(&status),
+            "{} answered {}, which the spec doesn't document",
+            route,
+            status
+        );
+        return;
+    };
+    let Some(schema) = response["content"]["application/json"].get("schema") else {
+        panic!(
+            "{} answered {} with JSON the spec doesn't describe",
+            route, status
+        );
+    };
+
+    let mut document = Map::new();
+    document.insert("allOf".to_string(), json!([schema]));
+    document.insert("components".to_string(), spec["components"].clone());
+    let document = Value::Object(document);
+    let validator = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&document)
+        .unwrap_or_else(|e| panic!("the schema of {} {} is invalid: {}", route, status, e));
+    let errors: Vec<String> = match validator.validate(body) {
+        Ok(()) => return,
+        Err(errors) => errors
+            .map(|e| format!("{} at {}", e, e.instance_path))
+            .collect(),
+    };
+    panic!(
+        "{} answered {} with a body that doesn't match the spec:\n{}\n{}",
+        route,
+        status,
+        errors.join("\n"),
+        body
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check;
+    use crate::build_app;
+    use crate::case::FieldCase;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn read_routes_answer_as_the_spec_documents() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let static_ability = repo.add_ability("Static", 10, "paralysis");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[static_ability]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let admin = repo.add_user(Some(ash), true);
+        let state = state(repo);
+        let admin = token(&state, admin);
+        let app = build_app(state);
+
+        // Routes that query the database directly are left out, as the test
+        // state has none to answer them.
+        let uris = [
+            "/me".to_string(),
+            "/me/pokemon".to_string(),
+            "/me/inventory".to_string(),
+            "/me/recent".to_string(),
+            "/me/bookmarks".to_string(),
+            "/me/proposals".to_string(),
+            "/me/experiments".to_string(),
+            "/trainer".to_string(),
+            format!("/trainer/{}", ash),
+            format!("/trainer/{}/owned", ash),
+            format!("/trainer/{}/name-history", ash),
+            "/pokemon".to_string(),
+            "/pokemon/Pikachu".to_string(),
+            "/pokemon/Pikachu/versions".to_string(),
+            "/ability".to_string(),
+            "/ability/Static".to_string(),
+            "/ability/Static/translations".to_string(),
+            "/ability/stats".to_string(),
+            "/ability/proposals".to_string(),
+            "/admin/experiments".to_string(),
+        ];
+        for uri in &uris {
+            let (status, _) = send(&app, Method::GET, uri, Some(&admin), None).await;
+            assert!(status.is_success(), "GET {} answered {}", uri, status);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't match the spec")]
+    fn drift_from_the_spec_fails() {
+        let body = json!({"experiments": [{"experiment": "pokedex_layout"}]});
+        check(
+            FieldCase::Snake,
+            &Method::GET,
+            "/me/experiments",
+            StatusCode::OK,
+            &body,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't document")]
+    fn undocumented_statuses_fail() {
+        check(
+            FieldCase::Snake,
+            &Method::GET,
+            "/me/experiments",
+            StatusCode::CONFLICT,
+            &json!({}),
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use crate::error::ApiError;
+use crate::usage;
+use crate::AppState;
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use deadpool_postgres::{GenericClient, Object, Pool, PoolError, Transaction};
+use futures_util::{pin_mut, stream, StreamExt, TryStreamExt};
+use std::error::Error;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_postgres::types::{FromSql, ToSql, Type};
+use tokio_postgres::{IsolationLevel, Row};
+
+/// Failure of a repository call: either no connection could be checked out,
+/// the statement itself failed, or it returned more rows than one request
+/// may hold (see `DbConn::query_capped`).
+#[derive(Debug)]
+pub enum DbError {
+    Pool(PoolError),
+    Query(tokio_postgres::Error),
+    TooManyRows(usize),
+    /// A trainer was given a name another trainer holds, or held within
+    /// the last 30 days.
+    NameTaken,
+}
+
+/// Whether `e` was raised by the `trainer_name_taken` trigger.
+pub(crate) fn is_name_taken(e: &tokio_postgres::Error) -> bool {
+    e.as_db_error()
+        .and_then(|e| e.constraint())
+        .is_some_and(|c| c == "trainer_name_taken")
+}
+
+impl From<PoolError> for DbError {
+    fn from(e: PoolError) -> Self {
+        Self::Pool(e)
+    }
+}
+
+impl From<tokio_postgres::Error> for DbError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        if is_name_taken(&e) {
+            return Self::NameTaken;
+        }
+
+        Self::Query(e)
+    }
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pool(e) => e.fmt(f),
+            Self::Query(e) => e.fmt(f),
+            Self::TooManyRows(max) => write!(f, "result exceeds {} rows", max),
+            Self::NameTaken => f.write_str("trainer name is taken"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct DbMetrics {
+    pub(crate) queries: AtomicU64,
+    pub(crate) slow_queries: AtomicU64,
+    pub(crate) query_micros: AtomicU64,
+    pub(crate) checkouts: AtomicU64,
+    pub(crate) checkout_wait_micros: AtomicU64,
+    pub(crate) rows: AtomicU64,
+    /// Wire size of every row returned, a rough stand-in for the memory
+    /// the rows and their decoded values take up.
+    pub(crate) row_bytes: AtomicU64,
+    /// Most row bytes any one request (one checked-out connection) has read.
+    pub(crate) max_request_row_bytes: AtomicU64,
+}
+
+pub struct Db {
+    pub(crate) pool: Pool,
+    slow_query_threshold: Duration,
+    /// Most rows `DbConn::query_capped` will materialize.
+    pub(crate) max_rows: usize,
+    pub(crate) metrics: DbMetrics,
+}
+
+impl Db {
+    pub fn new(pool: Pool, slow_query_threshold: Duration) -> Self {
+        Self {
+            pool,
+            slow_query_threshold,
+            max_rows: usize::MAX,
+            metrics: DbMetrics::default(),
+        }
+    }
+
+    /// Checks a connection out of the pool, recording how long it waited.
+    pub(crate) async fn conn(self: &Arc<Self>) -> Result<DbConn, PoolError> {
+        let start = Instant::now();
+        let client = self.pool.get().await;
+        self.metrics.checkouts.fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .checkout_wait_micros
+            .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+
+        Ok(DbConn {
+            client: client?,
+            db: self.clone(),
+            row_bytes: AtomicU64::new(0),
+        })
+    }
+
+    /// Runs `lookup` once per key, up to `concurrency` at a time, each on a
+    /// connection of its own. Results come back in completion order, paired
+    /// with their key.
+    pub(crate) async fn lookup_all<K, T, F, Fut>(
+        self: &Arc<Self>,
+        keys: impl IntoIterator<Item = K>,
+        concurrency: usize,
+        lookup: F,
+    ) -> Result<Vec<(K, T)>, DbError>
+    where
+        K: Clone,
+        F: Fn(DbConn, K) -> Fut,
+        Fut: Future<Output = Result<T, tokio_postgres::Error>>,
+    {
+        let lookup = &lookup;
+        stream::iter(keys)
+            .map(|key| async move {
+                let db = self.conn().await?;
+                let value = lookup(db, key.clone()).await?;
+                Ok::<_, DbError>((key, value))
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    pub(crate) fn record(&self, statement: &str, params: &[&(dyn ToSql + Sync)], elapsed: Duration) {
+        self.metrics.queries.fetch_add(1, Ordering::Relaxed);
+        self.metrics
+            .query_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        usage::charge_query(elapsed);
+
+        if elapsed >= self.slow_query_threshold {
+            self.metrics.slow_queries.fetch_add(1, Ordering::Relaxed);
+
+            // Parameter values may contain user data, so only their positions are logged.
+            let params = (1..=params.len())
+                .map(|i| format!("${}=<redacted>", i))
+                .collect::<Vec<_>>()
+                .join(", ");
+            tracing::warn!(
+                "Slow query ({} ms): {} [{}]",
+                elapsed.as_millis(),
+                statement,
+                params
+            );
+        }
+    }
+}
+
+/// A pooled connection, checked out for the duration of one request.
+pub(crate) struct DbConn {
+    client: Object,
+    pub(crate) db: Arc<Db>,
+    row_bytes: AtomicU64,
+}
+
+impl DbConn {
+    fn recorded(&self) -> Recorded<'_, Object> {
+        Recorded {
+            client: &self.client,
+            db: &self.db,
+            row_bytes: &self.row_bytes,
+        }
+    }
+
+    pub(crate) async fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, tokio_postgres::Error> {
+        self.recorded().query(statement, params).await
+    }
+
+    /// Parses and plans `statement` without running it.
+    pub(crate) async fn prepare(&self, statement: &str) -> Result<(), tokio_postgres::Error> {
+        self.client.prepare(statement).await.map(|_| ())
+    }
+
+    /// Like `query`, but stops reading and fails with `TooManyRows` once the
+    /// result grows past `Db::max_rows`. For statements whose result size
+    /// the caller doesn't bound with a `LIMIT`.
+    pub(crate) async fn query_capped(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, DbError> {
+        self.recorded().query_capped(statement, params).await
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, tokio_postgres::Error> {
+        self.recorded().execute(statement, params).await
+    }
+
+    /// Starts a transaction that rolls back unless committed.
+    pub(crate) async fn transaction(&mut self) -> Result<DbTransaction<'_>, tokio_postgres::Error> {
+        let tx = self.client.transaction().await?;
+
+        Ok(DbTransaction {
+            tx,
+            db: &self.db,
+            row_bytes: &self.row_bytes,
+        })
+    }
+
+    /// Starts a `READ ONLY, REPEATABLE READ` transaction, so that reads
+    /// spanning several statements, such as a count and the page it counts,
+    /// all see the same snapshot.
+    pub(crate) async fn read_only(&mut self) -> Result<DbTransaction<'_>, tokio_postgres::Error> {
+        let tx = self
+            .client
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .read_only(true)
+            .start()
+            .await?;
+
+        Ok(DbTransaction {
+            tx,
+            db: &self.db,
+            row_bytes: &self.row_bytes,
+        })
+    }
+}
+
+/// A transaction on a `DbConn`. Its statements are recorded like those run
+/// on the connection itself.
+pub(crate) struct DbTransaction<'a> {
+    tx: Transaction<'a>,
+    db: &'a Arc<Db>,
+    row_bytes: &'a AtomicU64,
+}
+
+impl DbTransaction<'_> {
+    fn recorded(&self) -> Recorded<'_, Transaction<'_>> {
+        Recorded {
+            client: &self.tx,
+            db: self.db,
+            row_bytes: self.row_bytes,
+        }
+    }
+
+    pub(crate) async fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, tokio_postgres::Error> {
+        self.recorded().query(statement, params).await
+    }
+
+    /// Like `query`, but fails unless exactly one row comes back.
+    pub(crate) async fn query_one(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, tokio_postgres::Error> {
+        self.recorded().query_one(statement, params).await
+    }
+
+    /// As `DbConn::query_capped`.
+    pub(crate) async fn query_capped(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, DbError> {
+        self.recorded().query_capped(statement, params).await
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, tokio_postgres::Error> {
+        self.recorded().execute(statement, params).await
+    }
+
+    /// Runs `statements`, separated by semicolons, without parameters.
+    pub(crate) async fn batch_execute(
+        &self,
+        statements: &str,
+    ) -> Result<(), tokio_postgres::Error> {
+        let start = Instant::now();
+        let res = self.tx.batch_execute(statements).await;
+        self.db.record(statements, &[], start.elapsed());
+        res
+    }
+
+    pub(crate) async fn commit(self) -> Result<(), tokio_postgres::Error> {
+        self.tx.commit().await
+    }
+}
+
+/// Runs statements on `client`, recording each in the slow query log and
+/// metrics of `db` and adding the bytes of the rows it returns to
+/// `row_bytes`.
+struct Recorded<'a, C> {
+    client: &'a C,
+    db: &'a Db,
+    row_bytes: &'a AtomicU64,
+}
+
+impl<C: GenericClient> Recorded<'_, C> {
+    async fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, tokio_postgres::Error> {
+        let start = Instant::now();
+        let res = self.client.query(statement, params).await;
+        self.db.record(statement, params, start.elapsed());
+        if let Ok(rows) = &res {
+            self.record_rows(rows);
+        }
+        res
+    }
+
+    async fn query_one(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, tokio_postgres::Error> {
+        let start = Instant::now();
+        let res = self.client.query_one(statement, params).await;
+        self.db.record(statement, params, start.elapsed());
+        if let Ok(row) = &res {
+            self.record_rows(std::slice::from_ref(row));
+        }
+        res
+    }
+
+    async fn query_capped(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, DbError> {
+        let start = Instant::now();
+        let res = self.collect_capped(statement, params).await;
+        self.db.record(statement, params, start.elapsed());
+        if let Ok(rows) = &res {
+            self.record_rows(rows);
+        }
+        res
+    }
+
+    async fn collect_capped(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, DbError> {
+        let max = self.db.max_rows;
+        let stream = self
+            .client
+            .query_raw(statement, params.iter().copied())
+            .await?;
+        pin_mut!(stream);
+
+        let mut rows = Vec::new();
+        while let Some(row) = stream.try_next().await? {
+            if rows.len() == max {
+                return Err(DbError::TooManyRows(max));
+            }
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    fn record_rows(&self, rows: &[Row]) {
+        let bytes = rows.iter().map(row_bytes).sum();
+        let metrics = &self.db.metrics;
+        metrics.rows.fetch_add(rows.len() as u64, Ordering::Relaxed);
+        metrics.row_bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.row_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    async fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, tokio_postgres::Error> {
+        let start = Instant::now();
+        let res = self.client.execute(statement, params).await;
+        self.db.record(statement, params, start.elapsed());
+        res
+    }
+}
+
+impl Drop for DbConn {
+    fn drop(&mut self) {
+        self.db
+            .metrics
+            .max_request_row_bytes
+            .fetch_max(*self.row_bytes.get_mut(), Ordering::Relaxed);
+    }
+}
+
+/// Size of one column value as sent by the server, whatever its type.
+struct WireSize(usize);
+
+impl<'a> FromSql<'a> for WireSize {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(Self(raw.len()))
+    }
+
+    fn from_sql_null(_: &Type) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(Self(0))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+fn row_bytes(row: &Row) -> u64 {
+    (0..row.len())
+        .map(|i| row.get::<_, WireSize>(i).0 as u64)
+        .sum()
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for DbConn {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        _parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        state.db.conn().await.map_err(|e| {
+            tracing::error!("Failed to check out a connection: {}", e);
+
+            e.into()
+        })
+    }
+}
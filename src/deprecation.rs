@@ -0,0 +1,64 @@
+use axum::{
+    http::{HeaderName, HeaderValue},
+    response::{IntoResponseParts, ResponseParts},
+};
+use time::OffsetDateTime;
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+const WARNING: HeaderName = HeaderName::from_static("warning");
+
+/// Returned alongside a response whose shape is slated to change, so that
+/// clients learn about it before it happens. Adds a `Deprecation` header
+/// (RFC 9745) with the time the old shape was deprecated, a `Sunset` header
+/// (RFC 8594) with the time it stops being served, and a `Warning` saying
+/// what to use instead.
+///
+/// The deprecated fields should also be marked `#[schema(deprecated)]`, and
+/// the route's responses should list the headers, so the OpenAPI spec says
+/// the same.
+#[derive(Clone, Copy)]
+pub(crate) struct Deprecated {
+    /// Unix time the old shape was deprecated.
+    pub(crate) since: i64,
+    /// Unix time the old shape goes away.
+    pub(crate) sunset: i64,
+    pub(crate) note: &'static str,
+}
+
+impl IntoResponseParts for Deprecated {
+    type Error = std::convert::Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let headers = res.headers_mut();
+        let values = [
+            (DEPRECATION, format!("@{}", self.since)),
+            (SUNSET, http_date(self.sunset)),
+            // 299 is the "miscellaneous persistent warning" code.
+            (WARNING, format!("299 - \"{}\"", self.note)),
+        ];
+        for (name, value) in values {
+            if let Ok(value) = HeaderValue::try_from(value) {
+                headers.insert(name, value);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Formats a Unix time as an HTTP date, e.g. `Thu, 01 Apr 2027 00:00:00 GMT`.
+fn http_date(unix: i64) -> String {
+    let at = OffsetDateTime::from_unix_timestamp(unix).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        &at.weekday().to_string()[..3],
+        at.day(),
+        &at.month().to_string()[..3],
+        at.year(),
+        at.hour(),
+        at.minute(),
+        at.second()
+    )
+}
@@ -0,0 +1,190 @@
+use crate::db::{is_name_taken, DbError};
+use crate::validation::FieldError;
+use async_graphql::ErrorExtensions;
+use axum::{
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use deadpool_postgres::PoolError;
+use serde::Serialize;
+use std::time::Duration;
+use tokio_postgres::error::SqlState;
+use utoipa::ToSchema;
+
+/// Error returned by handlers. Every variant renders as a JSON body of the
+/// form `{ "error": "...", "code": 404 }` where `code` repeats the status.
+#[derive(Debug)]
+pub(crate) enum ApiError {
+    NotFound,
+    BadRequest(String),
+    /// The bearer token is missing, malformed or expired, or a login failed.
+    Unauthorized,
+    /// Signed in, but not allowed to do this.
+    Forbidden,
+    Conflict(String),
+    /// The request body, path or query string parsed but some of its fields
+    /// are missing, of the wrong type or out of bounds.
+    Validation(Vec<FieldError>),
+    /// The client used up its request allowance (see `rate_limit`) or the
+    /// trainer its battle quota. Answered with a `Retry-After` of this long.
+    TooManyRequests(Duration),
+    /// No database connection became free within the pool wait timeout.
+    Unavailable,
+    /// The statement ran past `statement_timeout`.
+    Timeout,
+    /// The request was turned away before it reached the handler, such as a
+    /// body without a JSON content type. Carries axum's status and reason.
+    Rejected(StatusCode, String),
+    /// Any other database failure. Handlers log the detail; clients only
+    /// see a generic message.
+    DatabaseError,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct ErrorBody {
+    error: String,
+    code: u16,
+    /// Only present on 422 responses, one entry per rejected field.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<FieldError>,
+}
+
+impl ApiError {
+    pub(crate) fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::Rejected(status, _) => *status,
+            Self::DatabaseError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Says which item of a bulk request the error is about.
+    pub(crate) fn at_item(self, index: usize) -> Self {
+        match self {
+            Self::BadRequest(message) => Self::BadRequest(format!("[{}]: {}", index, message)),
+            Self::Conflict(message) => Self::Conflict(format!("[{}]: {}", index, message)),
+            e => e,
+        }
+    }
+}
+
+impl From<PoolError> for ApiError {
+    fn from(e: PoolError) -> Self {
+        match e {
+            PoolError::Backend(e) => e.into(),
+            // Every connection stayed busy for the whole wait timeout.
+            PoolError::Timeout(_) => Self::Unavailable,
+            _ => Self::DatabaseError,
+        }
+    }
+}
+
+impl From<DbError> for ApiError {
+    fn from(e: DbError) -> Self {
+        match e {
+            DbError::Pool(e) => e.into(),
+            DbError::Query(e) => e.into(),
+            DbError::TooManyRows(max) => Self::BadRequest(format!(
+                "result exceeds {} rows; request it in pages with ?page= and ?per_page=",
+                max
+            )),
+            DbError::NameTaken => Self::name_taken(),
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for ApiError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        // Postgres reports statement_timeout expiry as query_canceled.
+        match e.code() {
+            Some(code) if *code == SqlState::QUERY_CANCELED => Self::Timeout,
+            Some(_) if is_name_taken(&e) => Self::name_taken(),
+            Some(code) if *code == SqlState::UNIQUE_VIOLATION => {
+                Self::Conflict("resource already exists".to_string())
+            }
+            Some(code) if *code == SqlState::FOREIGN_KEY_VIOLATION => {
+                Self::Conflict("resource is still referenced".to_string())
+            }
+            _ => Self::DatabaseError,
+        }
+    }
+}
+
+impl ApiError {
+    fn name_taken() -> Self {
+        Self::Conflict(
+            "name belongs to another trainer, or did within the last 30 days".to_string(),
+        )
+    }
+
+    /// The status, the `error` text clients see, and any rejected fields.
+    fn into_parts(self) -> (StatusCode, String, Vec<FieldError>) {
+        let status = self.status();
+        let (error, fields) = match self {
+            Self::BadRequest(message) | Self::Conflict(message) | Self::Rejected(_, message) => {
+                (message, Vec::new())
+            }
+            Self::Validation(fields) => ("validation failed".to_string(), fields),
+            Self::DatabaseError => ("internal database error".to_string(), Vec::new()),
+            _ => (
+                status.canonical_reason().unwrap_or_default().to_lowercase(),
+                Vec::new(),
+            ),
+        };
+
+        (status, error, fields)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = match &self {
+            Self::TooManyRequests(wait) => Some(wait.as_secs().max(1)),
+            _ => None,
+        };
+        let (status, error, fields) = self.into_parts();
+
+        let mut response = (
+            status,
+            Json(ErrorBody {
+                error,
+                code: status.as_u16(),
+                fields,
+            }),
+        )
+            .into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+/// GraphQL errors carry the status REST would have answered with as their
+/// `code` extension, plus `fields` for validation failures.
+impl From<ApiError> for async_graphql::Error {
+    fn from(e: ApiError) -> Self {
+        let (status, error, fields) = e.into_parts();
+
+        async_graphql::Error::new(error).extend_with(|_, ext| {
+            ext.set("code", status.as_u16());
+            if !fields.is_empty() {
+                ext.set(
+                    "fields",
+                    async_graphql::Value::from_json(serde_json::json!(fields)).unwrap_or_default(),
+                );
+            }
+        })
+    }
+}
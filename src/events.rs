@@ -0,0 +1,171 @@
+use crate::models::CatalogKind;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Entity {
+    Trainer,
+    Pokemon,
+    Ability,
+    Region,
+}
+
+/// Receives a callback after every successful write. Hooks run inline on the
+/// request task, so anything slow should be spawned.
+pub(crate) trait ChangeHook: Send + Sync {
+    fn on_created(&self, _entity: Entity, _id: i32) {}
+    fn on_updated(&self, _entity: Entity, _id: i32) {}
+    fn on_deleted(&self, _entity: Entity, _id: i32) {}
+    /// A pokemon joined a trainer's team.
+    fn on_caught(&self, _trainer_id: i32, _pokemon_id: i32) {}
+    /// A battle the trainer asked for was simulated and stored. `winner` is
+    /// `None` for a draw.
+    fn on_battle_finished(&self, _battle_id: i32, _trainer_id: i32, _winner: Option<i32>) {}
+}
+
+/// Published on the event bus for every change. Subscribers (such as the
+/// `/events` stream) receive them without the handlers knowing about them.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub(crate) enum DomainEvent {
+    TrainerCreated {
+        trainer_id: i32,
+    },
+    TrainerUpdated {
+        trainer_id: i32,
+    },
+    TrainerDeleted {
+        trainer_id: i32,
+    },
+    PokemonCaught {
+        trainer_id: i32,
+        pokemon_id: i32,
+    },
+    BattleFinished {
+        battle_id: i32,
+        trainer_id: i32,
+        winner: Option<i32>,
+    },
+    PokemonCreated {
+        pokemon_id: i32,
+    },
+    PokemonUpdated {
+        pokemon_id: i32,
+    },
+    PokemonDeleted {
+        pokemon_id: i32,
+    },
+    AbilityCreated {
+        ability_id: i32,
+    },
+    AbilityUpdated {
+        ability_id: i32,
+    },
+    AbilityDeleted {
+        ability_id: i32,
+    },
+    RegionCreated {
+        region_id: i32,
+    },
+    RegionUpdated {
+        region_id: i32,
+    },
+    RegionDeleted {
+        region_id: i32,
+    },
+}
+
+impl DomainEvent {
+    /// The pokemon or ability the event says was updated or deleted.
+    pub(crate) fn catalog_change(&self) -> Option<(CatalogKind, i32)> {
+        match *self {
+            Self::PokemonUpdated { pokemon_id } | Self::PokemonDeleted { pokemon_id } => {
+                Some((CatalogKind::Pokemon, pokemon_id))
+            }
+            Self::AbilityUpdated { ability_id } | Self::AbilityDeleted { ability_id } => {
+                Some((CatalogKind::Ability, ability_id))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Forwards entity changes onto the broadcast channel as typed events.
+pub(crate) struct EventBus {
+    pub(crate) sender: broadcast::Sender<DomainEvent>,
+}
+
+impl EventBus {
+    fn publish(&self, event: DomainEvent) {
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.sender.send(event);
+    }
+}
+
+impl ChangeHook for EventBus {
+    fn on_created(&self, entity: Entity, id: i32) {
+        self.publish(match entity {
+            Entity::Trainer => DomainEvent::TrainerCreated { trainer_id: id },
+            Entity::Pokemon => DomainEvent::PokemonCreated { pokemon_id: id },
+            Entity::Ability => DomainEvent::AbilityCreated { ability_id: id },
+            Entity::Region => DomainEvent::RegionCreated { region_id: id },
+        });
+    }
+
+    fn on_updated(&self, entity: Entity, id: i32) {
+        match entity {
+            Entity::Pokemon => self.publish(DomainEvent::PokemonUpdated { pokemon_id: id }),
+            Entity::Ability => self.publish(DomainEvent::AbilityUpdated { ability_id: id }),
+            Entity::Region => self.publish(DomainEvent::RegionUpdated { region_id: id }),
+            Entity::Trainer => self.publish(DomainEvent::TrainerUpdated { trainer_id: id }),
+        }
+    }
+
+    fn on_deleted(&self, entity: Entity, id: i32) {
+        match entity {
+            Entity::Trainer => self.publish(DomainEvent::TrainerDeleted { trainer_id: id }),
+            Entity::Pokemon => self.publish(DomainEvent::PokemonDeleted { pokemon_id: id }),
+            Entity::Region => self.publish(DomainEvent::RegionDeleted { region_id: id }),
+            Entity::Ability => self.publish(DomainEvent::AbilityDeleted { ability_id: id }),
+        }
+    }
+
+    fn on_caught(&self, trainer_id: i32, pokemon_id: i32) {
+        self.publish(DomainEvent::PokemonCaught {
+            trainer_id,
+            pokemon_id,
+        });
+    }
+
+    fn on_battle_finished(&self, battle_id: i32, trainer_id: i32, winner: Option<i32>) {
+        self.publish(DomainEvent::BattleFinished {
+            battle_id,
+            trainer_id,
+            winner,
+        });
+    }
+}
+
+pub(crate) struct LogHook;
+
+impl ChangeHook for LogHook {
+    fn on_created(&self, entity: Entity, id: i32) {
+        tracing::info!("{:?} {} created", entity, id);
+    }
+
+    fn on_updated(&self, entity: Entity, id: i32) {
+        tracing::info!("{:?} {} updated", entity, id);
+    }
+
+    fn on_deleted(&self, entity: Entity, id: i32) {
+        tracing::info!("{:?} {} deleted", entity, id);
+    }
+
+    fn on_caught(&self, trainer_id: i32, pokemon_id: i32) {
+        tracing::info!("Trainer {} caught pokemon {}", trainer_id, pokemon_id);
+    }
+
+    fn on_battle_finished(&self, battle_id: i32, trainer_id: i32, _winner: Option<i32>) {
+        tracing::info!("Battle {} finished for trainer {}", battle_id, trainer_id);
+    }
+}
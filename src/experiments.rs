@@ -0,0 +1,49 @@
+/// An A/B test. Every signed-in user sees one of its variants, and always
+/// the same one.
+pub(crate) struct Experiment {
+    pub(crate) key: &'static str,
+    pub(crate) variants: &'static [&'static str],
+}
+
+/// The experiments running now. Adding or removing a variant reassigns
+/// users, so change one by adding another under a new key.
+pub(crate) const EXPERIMENTS: &[Experiment] = &[Experiment {
+    key: "pokedex_layout",
+    variants: &["grid", "list"],
+}];
+
+pub(crate) fn find(key: &str) -> Option<&'static Experiment> {
+    EXPERIMENTS.iter().find(|e| e.key == key)
+}
+
+impl Experiment {
+    /// The user's variant, from a hash of the user and the experiment's key,
+    /// so each experiment splits users independently of the others.
+    pub(crate) fn assign(&self, user_id: i32) -> &'static str {
+        let hash = fnv1a(format!("{}:{}", self.key, user_id).as_bytes());
+        self.variants[(hash % self.variants.len() as u64) as usize]
+    }
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is fixed, so assignments stay
+/// the same across releases and Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn users_are_split_stably_and_about_evenly() {
+        let layout = find("pokedex_layout").unwrap();
+        let assigned: Vec<_> = (1..=4).map(|user| layout.assign(user)).collect();
+        assert_eq!(assigned, ["list", "grid", "list", "grid"]);
+
+        let grid = (1..=1000).filter(|&u| layout.assign(u) == "grid").count();
+        assert!((450..=550).contains(&grid), "{} of 1000 on grid", grid);
+    }
+}
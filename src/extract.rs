@@ -0,0 +1,244 @@
+//! Extractors standing in for axum's `Json`, `Path` and `Query`. They are
+//! rejected with `ApiError`, so a malformed request gets the same JSON
+//! error body as any other failure, and one of the wrong shape has each
+//! field at fault listed under `fields` with a 422.
+
+use crate::error::ApiError;
+use crate::validation::{is_locale, FieldError};
+use axum::{
+    async_trait,
+    extract::{
+        path::ErrorKind,
+        rejection::{JsonRejection, PathRejection},
+        FromRequest, FromRequestParts, RawPathParams, Request,
+    },
+    http::{header::ACCEPT_LANGUAGE, request::Parts},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use std::convert::Infallible;
+use std::fmt::Display;
+
+/// A JSON request body.
+pub(crate) struct JsonBody<T>(pub(crate) T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        // Parsed into a `Value` first, so that syntax errors and the content
+        // type are told apart from fields that don't fit `T`.
+        let Json(value) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(|e: JsonRejection| ApiError::Rejected(e.status(), e.body_text()))?;
+
+        serde_path_to_error::deserialize(value)
+            .map(Self)
+            .map_err(field_error)
+    }
+}
+
+/// Path parameters.
+pub(crate) struct Path<T>(pub(crate) T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // A lone parameter parsed as a scalar, or by its own `Deserialize`,
+        // fails without naming its key, so it is looked up here.
+        let only_key = match RawPathParams::from_request_parts(parts, state).await {
+            Ok(params) if params.iter().count() == 1 => {
+                params.iter().next().map(|(k, _)| k.to_string())
+            }
+            _ => None,
+        };
+
+        match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => Ok(Self(value)),
+            Err(PathRejection::FailedToDeserializePathParams(e)) => {
+                let (field, message) = match e.kind() {
+                    ErrorKind::ParseErrorAtKey {
+                        key,
+                        value,
+                        expected_type,
+                    } => (key.clone(), parse_error(value, expected_type)),
+                    ErrorKind::ParseErrorAtIndex {
+                        index,
+                        value,
+                        expected_type,
+                    } => (index.to_string(), parse_error(value, expected_type)),
+                    ErrorKind::ParseError {
+                        value,
+                        expected_type,
+                    } => (
+                        only_key.unwrap_or_else(|| "path".to_string()),
+                        parse_error(value, expected_type),
+                    ),
+                    ErrorKind::InvalidUtf8InPathParam { key } => {
+                        (key.clone(), "must be valid UTF-8".to_string())
+                    }
+                    ErrorKind::Message(message) => (
+                        only_key.unwrap_or_else(|| "path".to_string()),
+                        message.clone(),
+                    ),
+                    // The route and the handler disagree about the path, which
+                    // no request can fix.
+                    _ => {
+                        tracing::error!("Failed to extract path parameters: {}", e.body_text());
+
+                        return Err(ApiError::Rejected(e.status(), e.body_text()));
+                    }
+                };
+
+                Err(ApiError::Validation(vec![FieldError::new(field, message)]))
+            }
+            Err(e) => {
+                tracing::error!("Failed to extract path parameters: {}", e.body_text());
+
+                Err(ApiError::Rejected(e.status(), e.body_text()))
+            }
+        }
+    }
+}
+
+fn parse_error(value: &str, expected_type: &str) -> String {
+    format!("`{}` is not a valid {}", value, expected_type)
+}
+
+/// Query string parameters.
+pub(crate) struct Query<T>(pub(crate) T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let deserializer =
+            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+
+        serde_path_to_error::deserialize(deserializer)
+            .map(Self)
+            .map_err(field_error)
+    }
+}
+
+/// Most locales looked up for one request.
+const MAX_LOCALES: usize = 8;
+
+/// The locales `Accept-Language` asks for, in the order translations are
+/// looked up: by preference, each tag followed by its shorter prefixes, so
+/// `fr-CA` falls back to `fr`. English is what abilities are written in, so
+/// the list stops where English is preferred; it is empty when the header
+/// is missing or asks for nothing else.
+pub(crate) struct AcceptLanguage(pub(crate) Vec<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AcceptLanguage
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+
+        Ok(Self(accepted_locales(header)))
+    }
+}
+
+fn accepted_locales(header: &str) -> Vec<String> {
+    // Ranges without a valid weight are skipped rather than rejected, as
+    // they are only a preference.
+    let mut ranges: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim().to_lowercase().replace('_', "-");
+            let q = match parts.next().map(str::trim) {
+                None => 1.0,
+                Some(q) => q.strip_prefix("q=")?.parse().ok()?,
+            };
+            (q > 0.0 && tag != "*").then_some((tag, q))
+        })
+        .collect();
+    // Stable, so ranges of equal weight keep the order they were sent in.
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut locales: Vec<String> = Vec::new();
+    for (tag, _) in ranges {
+        if tag == "en" || tag.starts_with("en-") {
+            break;
+        }
+        let mut prefix = tag.as_str();
+        loop {
+            if is_locale(prefix) && !locales.iter().any(|l| l == prefix) {
+                locales.push(prefix.to_string());
+            }
+            match prefix.rfind('-') {
+                Some(i) => prefix = &prefix[..i],
+                None => break,
+            }
+        }
+    }
+    locales.truncate(MAX_LOCALES);
+
+    locales
+}
+
+/// Names the field that didn't fit. A missing one is reported under its own
+/// name rather than its parent's.
+fn field_error<E: Display>(e: serde_path_to_error::Error<E>) -> ApiError {
+    let path = e.path().to_string();
+    let message = e.inner().to_string();
+
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|m| m.strip_suffix('`'));
+    let error = match (missing, path.as_str()) {
+        (Some(field), ".") => FieldError::new(field, "is required"),
+        (Some(field), parent) => FieldError::new(format!("{}.{}", parent, field), "is required"),
+        (None, ".") => FieldError::new("body", message),
+        (None, _) => FieldError::new(path, message),
+    };
+
+    ApiError::Validation(vec![error])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::accepted_locales;
+
+    #[test]
+    fn locales_are_looked_up_by_preference_down_to_english() {
+        assert_eq!(
+            accepted_locales("de;q=0.5, fr-CA, en;q=0.8, *"),
+            ["fr-ca", "fr"]
+        );
+        assert_eq!(
+            accepted_locales("pt_BR, ja;q=0.9, de;q=0"),
+            ["pt-br", "pt", "ja"]
+        );
+        assert!(accepted_locales("en-GB, fr").is_empty());
+        assert!(accepted_locales("").is_empty());
+    }
+}
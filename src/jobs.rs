@@ -0,0 +1,96 @@
+use crate::db::DbError;
+use crate::events::Entity;
+use crate::service::CatalogHistory;
+use crate::AppState;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Periodically publishes staged catalog changes whose time has come.
+///
+/// Staged pokemon and abilities already appear on the public endpoints once
+/// their `publish_at` passes, since those read through the `published_*`
+/// views. The job clears the timestamp so their creation is announced to the
+/// change hooks, and copies staged ability rebalances onto the live rows.
+///
+/// The task ends once the state starts shutting down, never mid-run.
+pub fn spawn_publisher(state: AppState, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        let shutting_down = state.shutting_down();
+        tokio::pin!(shutting_down);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = &mut shutting_down => return,
+            }
+            if let Err(e) = publish_due(&state).await {
+                tracing::error!("Failed to publish staged changes: {}", e);
+            }
+        }
+    })
+}
+
+pub(crate) async fn publish_due(state: &AppState) -> Result<(), DbError> {
+    let now = state.clock.now();
+    let history = CatalogHistory::new(state);
+
+    for pokemon_id in state.pokemon.publish_due(now).await? {
+        state.created(Entity::Pokemon, pokemon_id);
+    }
+
+    let abilities = state.abilities.publish_due(now).await?;
+    for ability_id in abilities.created {
+        history.record_ability(ability_id).await;
+        state.created(Entity::Ability, ability_id);
+    }
+    for ability_id in abilities.rebalanced {
+        history.record_ability(ability_id).await;
+        state.updated(Entity::Ability, ability_id);
+    }
+
+    Ok(())
+}
+
+/// Periodically records today's key counts into `daily_stat`, for
+/// `GET /stats/history`. Each run overwrites the day's earlier one, so
+/// running more often than daily only keeps the current day fresher, and
+/// a restart doesn't lose the day.
+///
+/// The task ends once the state starts shutting down, never mid-run.
+pub fn spawn_stats_recorder(state: AppState, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        let shutting_down = state.shutting_down();
+        tokio::pin!(shutting_down);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = &mut shutting_down => return,
+            }
+            if let Err(e) = record_stats(&state).await {
+                tracing::error!("Failed to record daily stats: {}", e);
+            }
+        }
+    })
+}
+
+/// Counts people's trainers, the pokemon those trainers caught and every
+/// battle fought, as of now. Computer-controlled trainers and their teams
+/// are left out.
+pub(crate) async fn record_stats(state: &AppState) -> Result<(), DbError> {
+    let day = state.clock.now().date();
+    let db = state.db.conn().await?;
+    db.execute(
+        "INSERT INTO daily_stat (day, metric, value)
+         VALUES ($1, 'trainers', (SELECT count(*) FROM trainer WHERE ai_strategy IS NULL)),
+                ($1, 'catches', (SELECT count(*) FROM trainerspokemon tp
+                                 JOIN trainer t ON t.trainer_id = tp.trainer_id
+                                 WHERE t.ai_strategy IS NULL)),
+                ($1, 'battles', (SELECT count(*) FROM battle))
+         ON CONFLICT (metric, day) DO UPDATE SET value = EXCLUDED.value",
+        &[&day],
+    )
+    .await?;
+
+    Ok(())
+}
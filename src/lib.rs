@@ -0,0 +1,804 @@
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::Request,
+    http::{header, HeaderName, StatusCode},
+    middleware,
+    response::Response,
+    BoxError, Router,
+};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower::load_shed::error::Overloaded;
+use tower::ServiceBuilder;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer, ExposeHeaders};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::Span;
+
+mod ai;
+mod auth;
+mod battle;
+mod cache;
+mod case;
+mod chaos;
+mod clock;
+mod config;
+#[cfg(test)]
+mod contract;
+mod db;
+mod deprecation;
+mod error;
+mod events;
+mod experiments;
+mod extract;
+mod jobs;
+mod markdown;
+mod migrations;
+mod models;
+mod quiz;
+mod rate_limit;
+mod recorder;
+mod repository;
+mod routes;
+mod service;
+mod slo;
+mod team;
+#[cfg(test)]
+mod testing;
+mod usage;
+mod validation;
+mod warmup;
+
+pub use auth::AuthConfig;
+pub use battle::{RandomSeeds, SeedSource};
+pub use case::FieldCase;
+pub use chaos::ChaosConfig;
+pub use clock::{Clock, SystemClock};
+pub use config::{CatalogCacheConfig, Config, ConfigError, CorsConfig, DatabaseConfig};
+pub use db::{Db, DbError};
+pub use jobs::{spawn_publisher, spawn_stats_recorder};
+pub use migrations::migrate;
+pub use slo::SloTargets;
+pub use usage::{flush_usage, spawn_usage_flusher};
+pub use warmup::spawn_warm_up;
+
+use auth::mark_impersonation;
+use cache::{Cache, RedisCache, ResponseCache};
+use case::camel_case_json;
+use chaos::inject_chaos;
+use config::AllowedOrigins;
+use events::{ChangeHook, DomainEvent, Entity, EventBus, LogHook};
+use rate_limit::{rate_limit, rate_limit_auth, RateLimiter};
+use recorder::{record_request, RequestLog};
+use repository::{
+    AbilityRepository, BattleRepository, PgAbilityRepository, PgBattleRepository,
+    PgPokemonRepository, PgQuizRepository, PgTrainerRepository, PgUserRepository,
+    PokemonRepository, QuizRepository, TrainerRepository, UserRepository,
+};
+use slo::{track_route_metrics, RouteMetrics};
+use usage::{track_usage, UsageLedger};
+
+/// Distinct `/pokemon` query strings whose responses are kept serialized.
+const CATALOG_CACHE_CAPACITY: u64 = 256;
+
+#[derive(Clone)]
+pub struct AppState {
+    db: Arc<Db>,
+    trainers: Arc<dyn TrainerRepository>,
+    pokemon: Arc<dyn PokemonRepository>,
+    abilities: Arc<dyn AbilityRepository>,
+    users: Arc<dyn UserRepository>,
+    battles: Arc<dyn BattleRepository>,
+    quiz: Arc<dyn QuizRepository>,
+    auth: AuthConfig,
+    catalog_cache: Arc<dyn Cache>,
+    hooks: Vec<Arc<dyn ChangeHook>>,
+    events: broadcast::Sender<DomainEvent>,
+    limits: Limits,
+    request_log: Option<Arc<RequestLog>>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Allowance for `/auth/*`, kept apart from `rate_limiter`.
+    auth_rate_limiter: Option<Arc<RateLimiter>>,
+    route_metrics: Arc<RouteMetrics>,
+    usage: Arc<UsageLedger>,
+    slo: SloTargets,
+    chaos: Option<ChaosConfig>,
+    shutdown: Arc<watch::Sender<bool>>,
+    /// Cleared while `spawn_warm_up` runs; readiness fails until it is set.
+    warmed_up: Arc<AtomicBool>,
+    reset_enabled: bool,
+    field_case: FieldCase,
+    /// Smallest response body compressed; `None` leaves responses as they are.
+    compression_min_bytes: Option<u16>,
+    cors: CorsConfig,
+    clock: Arc<dyn Clock>,
+    seeds: Arc<dyn SeedSource>,
+}
+
+impl AppState {
+    pub fn new(db: Db, limits: Limits, auth: AuthConfig) -> Self {
+        let (events, _) = broadcast::channel(256);
+        let mut db = db;
+        db.max_rows = limits.max_rows_per_request;
+        let db = Arc::new(db);
+        let catalog_cache: Arc<dyn Cache> =
+            Arc::new(ResponseCache::new(CATALOG_CACHE_CAPACITY, None));
+
+        Self {
+            trainers: Arc::new(PgTrainerRepository::new(db.clone())),
+            pokemon: Arc::new(PgPokemonRepository::new(db.clone())),
+            abilities: Arc::new(PgAbilityRepository::new(db.clone())),
+            users: Arc::new(PgUserRepository::new(db.clone())),
+            battles: Arc::new(PgBattleRepository::new(db.clone())),
+            quiz: Arc::new(PgQuizRepository::new(db.clone())),
+            db,
+            auth,
+            hooks: vec![
+                Arc::new(LogHook),
+                Arc::new(EventBus {
+                    sender: events.clone(),
+                }),
+                catalog_cache.clone() as Arc<dyn ChangeHook>,
+            ],
+            catalog_cache,
+            events,
+            limits,
+            request_log: None,
+            rate_limiter: None,
+            auth_rate_limiter: None,
+            route_metrics: Arc::default(),
+            usage: Arc::default(),
+            slo: SloTargets::default(),
+            chaos: None,
+            shutdown: Arc::new(watch::Sender::new(false)),
+            warmed_up: Arc::new(AtomicBool::new(true)),
+            reset_enabled: false,
+            field_case: FieldCase::default(),
+            compression_min_bytes: None,
+            cors: CorsConfig::default(),
+            clock: Arc::new(SystemClock),
+            seeds: Arc::new(RandomSeeds),
+        }
+    }
+
+    /// Reads the time from `clock` instead of the system's.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Seeds battles that weren't given a seed from `seeds` instead of at
+    /// random.
+    pub fn with_seed_source(mut self, seeds: Arc<dyn SeedSource>) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    pub fn with_slo_targets(mut self, slo: SloTargets) -> Self {
+        self.slo = slo;
+        self
+    }
+
+    pub fn with_chaos(mut self, chaos: ChaosConfig) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Records the last `capacity` requests for `/admin/recent-requests`.
+    pub fn with_request_log(mut self, capacity: usize) -> Self {
+        self.request_log = Some(Arc::new(RequestLog {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }));
+        self
+    }
+
+    /// Expires cached catalog pages `ttl` after they were stored, so that
+    /// writes the change hooks never see still show up eventually.
+    pub fn with_catalog_cache_ttl(self, ttl: Duration) -> Self {
+        self.with_catalog_cache(Arc::new(ResponseCache::new(
+            CATALOG_CACHE_CAPACITY,
+            Some(ttl),
+        )))
+    }
+
+    /// Keeps catalog pages in the Redis at `url` instead of in memory, so
+    /// that instances behind a load balancer share them and see each
+    /// other's writes. Pages expire `ttl` after they were stored.
+    pub async fn with_redis_catalog_cache(
+        self,
+        url: &str,
+        ttl: Option<Duration>,
+    ) -> Result<Self, redis::RedisError> {
+        let cache = RedisCache::connect(url, ttl).await?;
+
+        Ok(self.with_catalog_cache(cache))
+    }
+
+    fn with_catalog_cache(mut self, cache: Arc<dyn Cache>) -> Self {
+        let old: Arc<dyn ChangeHook> = self.catalog_cache.clone();
+        for hook in &mut self.hooks {
+            if Arc::ptr_eq(hook, &old) {
+                *hook = cache.clone();
+            }
+        }
+        self.catalog_cache = cache;
+        self
+    }
+
+    /// Compresses response bodies of at least `min_bytes` with brotli or
+    /// gzip, whichever the client's `Accept-Encoding` prefers.
+    pub fn with_compression(mut self, min_bytes: u16) -> Self {
+        self.compression_min_bytes = Some(min_bytes);
+        self
+    }
+
+    /// Lets the cross-origin callers `cors` allows use the public API. Without
+    /// it, none may; admin routes never allow any.
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// Limits each client address to `per_minute` requests, answering 429
+    /// beyond that. Probes and event streams are exempt.
+    pub fn with_rate_limit(mut self, per_minute: u32) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(per_minute)));
+        self
+    }
+
+    /// Limits each client address to `per_minute` requests to `/auth/*`,
+    /// answering 429 beyond that. These don't count against the allowance
+    /// of `with_rate_limit`.
+    pub fn with_auth_rate_limit(mut self, per_minute: u32) -> Self {
+        self.auth_rate_limiter = Some(Arc::new(RateLimiter::new(per_minute)));
+        self
+    }
+
+    /// Enables `POST /admin/reset`, which truncates every table. Never for
+    /// production.
+    pub fn with_reset_endpoint(mut self) -> Self {
+        self.reset_enabled = true;
+        self
+    }
+
+    /// Names the fields of REST request and response bodies, and of the
+    /// OpenAPI spec, in `case`. GraphQL keeps its own naming.
+    pub fn with_field_case(mut self, case: FieldCase) -> Self {
+        self.field_case = case;
+        self
+    }
+
+    /// Starts shutting down: readiness starts failing, event streams end so
+    /// they don't hold the server open, and background jobs stop after their
+    /// current run.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    pub(crate) fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    pub(crate) fn is_warmed_up(&self) -> bool {
+        self.warmed_up.load(Ordering::Acquire)
+    }
+
+    /// Resolves once `shutdown` has been called.
+    pub(crate) fn shutting_down(&self) -> impl Future<Output = ()> {
+        let mut shutdown = self.shutdown.subscribe();
+        async move {
+            let _ = shutdown.wait_for(|&down| down).await;
+        }
+    }
+
+    fn created(&self, entity: Entity, id: i32) {
+        for hook in &self.hooks {
+            hook.on_created(entity, id);
+        }
+    }
+
+    fn updated(&self, entity: Entity, id: i32) {
+        for hook in &self.hooks {
+            hook.on_updated(entity, id);
+        }
+    }
+
+    fn deleted(&self, entity: Entity, id: i32) {
+        for hook in &self.hooks {
+            hook.on_deleted(entity, id);
+        }
+    }
+
+    fn caught(&self, trainer_id: i32, pokemon_id: i32) {
+        for hook in &self.hooks {
+            hook.on_caught(trainer_id, pokemon_id);
+        }
+    }
+
+    fn battle_finished(&self, battle_id: i32, trainer_id: i32, winner: Option<i32>) {
+        for hook in &self.hooks {
+            hook.on_battle_finished(battle_id, trainer_id, winner);
+        }
+    }
+}
+
+/// The repositories are crate-private, so only tests swap them out.
+#[cfg(test)]
+impl AppState {
+    /// Serves trainers from `trainers` instead of Postgres, such as an
+    /// `InMemoryRepository`.
+    pub(crate) fn with_trainer_repository(mut self, trainers: Arc<dyn TrainerRepository>) -> Self {
+        self.trainers = trainers;
+        self
+    }
+
+    /// Serves pokemon from `pokemon` instead of Postgres.
+    pub(crate) fn with_pokemon_repository(mut self, pokemon: Arc<dyn PokemonRepository>) -> Self {
+        self.pokemon = pokemon;
+        self
+    }
+
+    /// Serves abilities from `abilities` instead of Postgres.
+    pub(crate) fn with_ability_repository(mut self, abilities: Arc<dyn AbilityRepository>) -> Self {
+        self.abilities = abilities;
+        self
+    }
+
+    /// Keeps users' own data in `users` instead of Postgres.
+    pub(crate) fn with_user_repository(mut self, users: Arc<dyn UserRepository>) -> Self {
+        self.users = users;
+        self
+    }
+
+    /// Keeps battles and catalog snapshots in `battles` instead of Postgres.
+    pub(crate) fn with_battle_repository(mut self, battles: Arc<dyn BattleRepository>) -> Self {
+        self.battles = battles;
+        self
+    }
+
+    /// Keeps quiz questions and scores in `quiz` instead of Postgres.
+    pub(crate) fn with_quiz_repository(mut self, quiz: Arc<dyn QuizRepository>) -> Self {
+        self.quiz = quiz;
+        self
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Limits {
+    pub max_concurrent_requests: usize,
+    pub heavy_concurrency_limit: usize,
+    /// How many independent lookups one request may run at once, each on
+    /// its own pooled connection.
+    pub lookup_concurrency: usize,
+    /// Most rows an unpaginated query may return before the request is
+    /// rejected with a 400 pointing at pagination.
+    pub max_rows_per_request: usize,
+    /// Most pokemon a trainer may have; attaching one more answers 409.
+    pub max_team_size: usize,
+    /// Battles each trainer may simulate in any rolling hour before the
+    /// battle routes answer 429. Zero lifts the quota.
+    pub battles_per_hour: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: 256,
+            heavy_concurrency_limit: 4,
+            lookup_concurrency: 4,
+            max_rows_per_request: 5000,
+            max_team_size: 6,
+            battles_per_hour: 20,
+        }
+    }
+}
+
+impl Limits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            max_concurrent_requests: std::env::var("MAX_CONCURRENT_REQUESTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_concurrent_requests),
+            heavy_concurrency_limit: std::env::var("HEAVY_CONCURRENCY_LIMIT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.heavy_concurrency_limit),
+            lookup_concurrency: std::env::var("LOOKUP_CONCURRENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.lookup_concurrency),
+            max_rows_per_request: std::env::var("MAX_ROWS_PER_REQUEST")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_rows_per_request),
+            max_team_size: std::env::var("MAX_TEAM_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_team_size),
+            battles_per_hour: std::env::var("BATTLES_PER_HOUR")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.battles_per_hour),
+        }
+    }
+}
+
+/// Builds the full application router. Everything the handlers depend on
+/// comes in through `state`, so tests can build the same router `main` serves.
+pub fn build_app(state: AppState) -> Router {
+    let state = Arc::new(state);
+
+    // Reports and aggregations scan whole tables and battles are simulated
+    // on the request thread, so they share a small concurrency budget of
+    // their own instead of competing with catalog reads.
+    let heavy_limit = GlobalConcurrencyLimitLayer::new(state.limits.heavy_concurrency_limit);
+    let heavy = limit_concurrency(
+        routes::pokemon::heavy_router()
+            .merge(routes::reports::router())
+            .merge(routes::battle::router()),
+        heavy_limit.clone(),
+    );
+
+    let mut public = Router::new()
+        .merge(routes::me::router())
+        .merge(routes::trainer::router())
+        .merge(routes::owned::router())
+        .merge(routes::pokemon::router())
+        .merge(routes::ability::router())
+        .merge(routes::region::router())
+        .merge(routes::team::router())
+        .merge(routes::search::router())
+        .merge(routes::quiz::router())
+        .merge(routes::stats::router())
+        .merge(heavy);
+    if state.field_case == FieldCase::Camel {
+        public = public.layer(middleware::from_fn(camel_case_json));
+    }
+    let mut public = public
+        .merge(routes::graphql::router())
+        .merge(routes::docs::router());
+    if state.chaos.is_some() {
+        public = public.layer(middleware::from_fn_with_state(
+            state.clone(),
+            inject_chaos,
+        ));
+    }
+    let public = public
+        .layer(middleware::from_fn(mark_impersonation))
+        .layer(public_cors(&state.cors));
+
+    // Admin routes get their own CORS policy, which allows no cross-origin
+    // callers. The admin reports draw on the same heavy budget as the
+    // public ones.
+    let mut admin = routes::admin::router().merge(limit_concurrency(
+        routes::admin::heavy_router(),
+        heavy_limit,
+    ));
+    if state.field_case == FieldCase::Camel {
+        admin = admin.layer(middleware::from_fn(camel_case_json));
+    }
+    let admin = admin.layer(CorsLayer::new());
+
+    let mut limited = public
+        .merge(admin)
+        .layer(middleware::from_fn_with_state(state.clone(), track_usage))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            track_route_metrics,
+        ));
+    if state.request_log.is_some() {
+        limited = limited.layer(middleware::from_fn_with_state(
+            state.clone(),
+            record_request,
+        ));
+    }
+
+    // Probes are merged after the limits are applied so that an instance
+    // that is busy but healthy never fails them.
+    let probes = routes::system::probes();
+
+    // Signing in is merged alongside them, so that clients can still get a
+    // token while the instance is saturated or they are over their rate. It
+    // has a stricter rate of its own instead.
+    let mut auth = routes::auth::router();
+    if state.field_case == FieldCase::Camel {
+        auth = auth.layer(middleware::from_fn(camel_case_json));
+    }
+    let mut auth = auth
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            track_route_metrics,
+        ))
+        .layer(public_cors(&state.cors));
+    if state.auth_rate_limiter.is_some() {
+        auth = auth.layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_auth,
+        ));
+    }
+
+    // Event streams stay open indefinitely, so they would pin a concurrency
+    // permit each if they sat behind the limits.
+    let streams = routes::system::streams()
+        .merge(routes::me::streams())
+        .layer(public_cors(&state.cors));
+
+    // Rate limiting sits outside the concurrency limit so that a client over
+    // its allowance is turned away without taking a permit.
+    let mut limited = limit_concurrency(
+        limited,
+        GlobalConcurrencyLimitLayer::new(state.limits.max_concurrent_requests),
+    );
+    if state.rate_limiter.is_some() {
+        limited = limited.layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit,
+        ));
+    }
+
+    // Compression wraps everything else, CORS included, so the other layers
+    // only ever see uncompressed bodies.
+    let mut app = limited.merge(probes).merge(auth).merge(streams);
+    if let Some(min_bytes) = state.compression_min_bytes {
+        app = app.layer(compression(min_bytes));
+    }
+
+    trace_requests(app).with_state(state)
+}
+
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Gives every request an id, reusing the caller's `X-Request-Id` if it sent
+/// one, and returns it in the same header. Each request runs in a span
+/// carrying the id, so every line logged while handling it can be matched
+/// up, and ends with one line giving its status and latency.
+fn trace_requests<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::new(
+                REQUEST_ID_HEADER.clone(),
+                MakeRequestUuid,
+            ))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(|request: &Request| {
+                        let request_id = request
+                            .headers()
+                            .get(&REQUEST_ID_HEADER)
+                            .and_then(|id| id.to_str().ok())
+                            .unwrap_or_default();
+                        tracing::info_span!(
+                            "request",
+                            request_id,
+                            method = %request.method(),
+                            path = request.uri().path(),
+                            impersonated_by = tracing::field::Empty,
+                        )
+                    })
+                    .on_request(())
+                    .on_response(|response: &Response, latency: Duration, _: &Span| {
+                        tracing::info!(
+                            status = response.status().as_u16(),
+                            latency_ms = latency.as_secs_f64() * 1000.0,
+                            "Finished request"
+                        );
+                    }),
+            )
+            .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER)),
+    )
+}
+
+/// Response headers that scripts on an allowed origin may read. Listed
+/// because credentialed requests can't expose every header with `*`.
+const EXPOSED_HEADERS: [HeaderName; 7] = [
+    REQUEST_ID_HEADER,
+    HeaderName::from_static("x-impersonating-trainer"),
+    HeaderName::from_static("deprecation"),
+    HeaderName::from_static("sunset"),
+    HeaderName::from_static("warning"),
+    header::RETRY_AFTER,
+    header::LINK,
+];
+
+/// Request headers are mirrored from the preflight rather than listed, as
+/// credentialed requests can't allow every header with `*` either.
+fn public_cors(cors: &CorsConfig) -> CorsLayer {
+    let layer = CorsLayer::new()
+        .allow_methods(cors.methods.clone())
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(cors.credentials);
+
+    match &cors.origins {
+        AllowedOrigins::Any => layer.allow_origin(Any).expose_headers(Any),
+        AllowedOrigins::List(origins) => layer
+            .allow_origin(AllowOrigin::list(origins.clone()))
+            .expose_headers(ExposeHeaders::list(EXPOSED_HEADERS)),
+    }
+}
+
+/// Event streams are left alone: compressing them would hold events back
+/// until enough had piled up to fill a compressed block.
+fn compression(min_bytes: u16) -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        SizeAbove::new(min_bytes)
+            .and(NotForContentType::SSE)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::GRPC),
+    )
+}
+
+/// Caps in-flight requests across every route in `router` with `limit`,
+/// answering 503 instead of queueing once the cap is reached. Routers given
+/// clones of one `limit` share its permits.
+fn limit_concurrency<S>(router: Router<S>, limit: GlobalConcurrencyLimitLayer) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(handle_overload))
+            .load_shed()
+            .layer(limit),
+    )
+}
+
+async fn handle_overload(err: BoxError) -> StatusCode {
+    if err.is::<Overloaded>() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        tracing::error!("Unhandled middleware error: {}", err);
+
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token, TestClock};
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::{header, Method, Request, StatusCode};
+    use serde_json::json;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use time::OffsetDateTime;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn health_answers_while_the_database_is_down() {
+        let app = build_app(state(Arc::default()));
+
+        let (status, _) = send(&app, Method::GET, "/health", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::GET, "/ready", None, None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn operational_endpoints_require_an_admin() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let user = repo.add_user(None, false);
+        let state = state(repo);
+        let (admin, user) = (token(&state, admin), token(&state, user));
+        let app = build_app(state);
+
+        for uri in [
+            "/metrics",
+            "/admin/slo",
+            "/admin/recent-requests",
+            "/admin/tables",
+        ] {
+            let (status, _) = send(&app, Method::GET, uri, None, None).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", uri);
+            let (status, _) = send(&app, Method::GET, uri, Some(&user), None).await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}", uri);
+        }
+
+        let (status, _) = send(&app, Method::GET, "/admin/slo", Some(&admin), None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn signing_in_has_a_rate_of_its_own() {
+        let state = state(Arc::default())
+            .with_rate_limit(100)
+            .with_auth_rate_limit(1);
+        let app = build_app(state);
+        let login = || {
+            let body = json!({"username": "ash", "password": "pikachu!"});
+            Request::post("/auth/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))))
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // The database is down, so the attempt fails, but it still counts.
+        let response = app.clone().oneshot(login()).await.unwrap();
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app.clone().oneshot(login()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn tokens_expire_by_the_state_clock() {
+        // Long past, so the system clock would have refused the token.
+        let clock = TestClock::at(OffsetDateTime::from_unix_timestamp(1_577_836_800).unwrap());
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let state = state(repo).with_clock(clock.clone());
+        let token = token(&state, admin);
+        let app = build_app(state);
+        let ash = json!({"name": "Ash", "gym_leader": false});
+
+        let create = |token| {
+            send(
+                &app,
+                Method::POST,
+                "/trainer",
+                Some(token),
+                Some(ash.clone()),
+            )
+        };
+
+        let (status, _) = create("nonsense").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = create(&token).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Past the TTL and the leeway.
+        clock.advance(Duration::from_secs(300));
+        let (status, body) = create(&token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], 401);
+    }
+
+    #[tokio::test]
+    async fn trainers_can_be_created_renamed_and_deleted() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let state = state(repo.clone());
+        let admin = token(&state, admin);
+        let app = build_app(state.clone());
+
+        let brock = json!({"name": "Brock", "gym_leader": true});
+        let (status, _) = send(&app, Method::POST, "/trainer", Some(&admin), Some(brock)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, Method::GET, "/trainer", None, None).await;
+        let id = body["trainers"][0]["trainer_id"].as_i64().unwrap();
+        let uri = format!("/trainer/{}", id);
+
+        // Renaming and deleting are up to the user playing as the trainer.
+        let token = token(&state, repo.add_user(Some(id as i32), false));
+
+        let rename = json!({"name": "Forrest"});
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(rename)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "Forrest");
+        assert_eq!(body["gym_leader"], true);
+
+        let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -1,532 +1,144 @@
-use axum::{
-    extract::{Path, State},
-    http::{Method, StatusCode},
-    response::{IntoResponse, Response},
-    routing::{delete, get, post},
-    Json, Router,
-};
 use dotenv::dotenv;
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio_postgres::NoTls;
-use tower_http::cors::{Any, CorsLayer};
-
-#[derive(Serialize)]
-struct Message {
-    message: String,
-}
-
-enum ApiResponse<T> {
-    OK,
-    Error,
-    JsonData(T),
-}
-
-impl<T> IntoResponse for ApiResponse<T>
-where
-    T: Serialize,
-{
-    fn into_response(self) -> Response {
-        match self {
-            Self::OK => (StatusCode::OK).into_response(),
-            Self::Error => (StatusCode::INTERNAL_SERVER_ERROR).into_response(),
-            Self::JsonData(data) => (StatusCode::OK, Json(data)).into_response(),
-        }
-    }
-}
-
-#[derive(Clone)]
-struct AppState {
-    db: Arc<tokio_postgres::Client>,
-}
+use server::{
+    build_app, flush_usage, migrate, spawn_publisher, spawn_stats_recorder, spawn_usage_flusher,
+    spawn_warm_up, AppState, AuthConfig, CatalogCacheConfig, ChaosConfig, Config, ConfigError,
+    CorsConfig, Db, Limits, SloTargets,
+};
+use std::net::SocketAddr;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
     tracing_subscriber::fmt::init();
 
-    let user = std::env::var("POSTGRES_USER").expect("Missing user env var");
-    let pass = std::env::var("POSTGRES_PASS").expect("Missing postgres pass");
-    let (client, connection) = tokio_postgres::connect(
-        format!("postgres://{}:{}@localhost/postgres", user, pass).as_str(),
-        NoTls,
-    )
-    .await
-    .unwrap();
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-
-    let app_state = AppState {
-        db: Arc::new(client),
+    // Exit with every problem spelled out rather than a panic backtrace.
+    let exit = |e: ConfigError| -> ! {
+        tracing::error!("Invalid configuration: {}", e);
+        std::process::exit(1);
     };
+    let config = Config::from_env().unwrap_or_else(|e| exit(e));
+    let pool = config.database.create_pool().unwrap_or_else(|e| exit(e));
 
-    let app = Router::new()
-        .route("/trainer", get(get_trainers))
-        .route("/trainer/:id", get(get_trainer))
-        .route("/trainer/:id", delete(delete_trainer))
-        .route("/trainer", post(create_trainer))
-        .route("/pokemon", get(get_pokemon))
-        .route("/pokemon-abilities/:id", get(get_ability))
-        .route("/pokemon-attributes/:id", get(get_attribute))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods([Method::GET, Method::POST, Method::DELETE])
-                .allow_headers(Any)
-                .expose_headers(Any),
-        )
-        .with_state(Arc::new(app_state));
-
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port))
-        .await
-        .unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Trainer {
-    trainer_id: i32,
-    name: String,
-    gym_leader: bool,
-    pokemon: Option<Vec<Pokemon>>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Pokemon {
-    pokemon_id: i32,
-    name: String,
-    region: String,
-}
-
-#[derive(Serialize)]
-struct GetTrainersResponse {
-    trainers: Vec<Trainer>,
-}
-
-async fn get_trainers(State(state): State<Arc<AppState>>) -> ApiResponse<GetTrainersResponse> {
-    let db = state.db.clone();
-
-    match db.query("SELECT * FROM trainer", &[]).await {
-        Ok(rows) => {
-            let mut trainers = Vec::new();
-            for r in rows {
-                let trainer_id: i32 = r.get(0);
-
-                let pokemon_res = db
-                    .query(
-                        "SELECT pokemon_id FROM trainerspokemon WHERE trainer_id = $1",
-                        &[&trainer_id],
-                    )
-                    .await
-                    .unwrap();
-
-                let mut pokemon_list = Vec::new();
-                for p_row in pokemon_res {
-                    let pokemon_id: i32 = p_row.get(0);
-                    let p = db
-                        .query(
-                            "SELECT * FROM pokemon WHERE pokemon_id = $1",
-                            &[&pokemon_id],
-                        )
-                        .await
-                        .unwrap();
-
-                    for pokemon in p {
-                        let region_id: i32 = pokemon.get(2);
-                        let region_res = db
-                            .query(
-                                "SELECT region_name FROM region WHERE region_id = $1",
-                                &[&region_id],
-                            )
-                            .await
-                            .unwrap();
-
-                        let region = region_res.first().unwrap();
-                        let pokemon = Pokemon {
-                            pokemon_id: pokemon.get(0),
-                            name: pokemon.get(1),
-                            region: region.get(0),
-                        };
-
-                        pokemon_list.push(pokemon);
-                    }
-                }
-
-                let trainer = Trainer {
-                    trainer_id,
-                    name: r.get(1),
-                    gym_leader: r.get(2),
-                    pokemon: Some(pokemon_list),
-                };
-                trainers.push(trainer);
-            }
-
-            tracing::info!("{:?}", trainers);
-
-            ApiResponse::JsonData(GetTrainersResponse { trainers })
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch trainers: {:?}", e);
-
-            ApiResponse::Error
-        }
+    let applied = migrate(&pool).await.unwrap_or_else(|e| {
+        tracing::error!("Failed to run migrations: {}", e);
+        std::process::exit(1);
+    });
+    for name in &applied {
+        tracing::info!("Applied migration {}", name);
     }
-}
-
-#[derive(Serialize)]
-struct GetTrainerResponse {
-    trainers: Vec<Trainer>,
-}
-
-async fn get_trainer(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
-) -> ApiResponse<GetTrainerResponse> {
-    let db = state.db.clone();
-
-    match db
-        .query("SELECT * FROM trainer WHERE trainer_id = $1", &[&id])
-        .await
-    {
-        Ok(rows) => {
-            let mut trainers = Vec::new();
-            for r in rows {
-                let trainer = Trainer {
-                    trainer_id: r.get(0),
-                    name: r.get(1),
-                    gym_leader: r.get(2),
-                    pokemon: None,
-                };
-                trainers.push(trainer);
-            }
-
-            tracing::info!("{:?}", trainers);
-
-            return ApiResponse::JsonData(GetTrainerResponse { trainers });
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch trainers: {:?}", e);
-
-            return ApiResponse::Error;
-        }
+    // Lets deploys apply migrations as a separate step before rolling out.
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        return;
     }
-}
-
-#[derive(Serialize)]
-struct GetAbilityResponse {
-    ability: Vec<Ability>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Ability {
-    ability_id: i32,
-    name: String,
-    damage: i32,
-    status_effect: String,
-}
-#[derive(Serialize, Deserialize, Debug)]
-struct PokemonAbilities {
-    pokemon_id: i32,
-    ability_id: i32,
-}
-
-
-
-async fn get_ability(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
-) -> ApiResponse<GetAbilityResponse> {
-    let db = state.db.clone();
-
-
-    match db
-        .query("SELECT * FROM pokemonabilities WHERE pokemon_id = $1", &[&id])
-        .await
-    {
-        Ok(rows) => {
-            let mut abilities: Vec<Ability> = Vec::new();
-            for r in rows {
-                let ability_id: i32 = r.get(1);
-
-                let ability_res = db
-                    .query(
-                        "SELECT * FROM ability WHERE ability_id = $1",
-                        &[&ability_id],
-                    )
-                    .await
-                    .unwrap();
-
-                for ability in ability_res {
-                    let ability = Ability {
-                        ability_id: ability.get(0),
-                        name: ability.get(1),
-                        damage: ability.get(2),
-                        status_effect: ability.get(3),
-                    };
-                    abilities.push(ability);
-                }
-            }
-
-            tracing::info!("{:?}", abilities);
 
-            return ApiResponse::JsonData(GetAbilityResponse { ability: abilities });
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch abilities: {:?}", e);
+    let db_pool = pool.clone();
 
-            return ApiResponse::Error;
-        }
+    let mut app_state = AppState::new(
+        Db::new(pool, config.slow_query),
+        Limits::from_env(),
+        AuthConfig::new(config.jwt_secret.as_bytes(), config.token_ttl),
+    )
+    .with_slo_targets(SloTargets::from_env());
+    if let Some(capacity) = config.record_requests {
+        app_state = app_state.with_request_log(capacity);
     }
-}
-
-#[derive(Serialize)]
-struct GetAttributeResponse {
-    attributes: Vec<Attribute>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Attribute {
-    attribute_id: i32,
-    attribute_name: String,
-    weakness: String,
-}
-#[derive(Serialize, Deserialize, Debug)]
-struct PokemonAttributes {
-    pokemon_id: i32,
-    attribute_id: i32,
-}
-
-
-
-async fn get_attribute(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
-) -> ApiResponse<GetAttributeResponse> {
-    let db = state.db.clone();
-
-
-    match db
-        .query("SELECT * FROM pokemonattributes WHERE pokemon_id = $1", &[&id])
-        .await
-    {
-        Ok(rows) => {
-            let mut attributes: Vec<Attribute> = Vec::new();
-            for r in rows {
-                let attribute_id: i32 = r.get(1);
-
-                let attribute_res = db
-                    .query(
-                        "SELECT * FROM attribute WHERE attribute_id = $1",
-                        &[&attribute_id],
-                    )
-                    .await
-                    .unwrap();
-
-                for attribute in attribute_res {
-                    let attribute = Attribute {
-                        attribute_id: attribute.get(0),
-                        attribute_name: attribute.get(1),
-                        weakness: attribute.get(2),
-                    };
-                    attributes.push(attribute);
-                }
-            }
-
-            tracing::info!("{:?}", attributes);
-
-            return ApiResponse::JsonData(GetAttributeResponse { attributes });
+    let catalog_cache_ttl = config.catalog_cache_ttl;
+    match CatalogCacheConfig::from_env().unwrap_or_else(|e| exit(e)) {
+        CatalogCacheConfig::Redis { url } => {
+            app_state = app_state
+                .with_redis_catalog_cache(&url, catalog_cache_ttl)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("Failed to connect to Redis: {}", e);
+                    std::process::exit(1);
+                });
         }
-        Err(e) => {
-            tracing::error!("Failed to fetch attributes: {:?}", e);
-
-            return ApiResponse::Error;
+        CatalogCacheConfig::Memory => {
+            if let Some(ttl) = catalog_cache_ttl {
+                app_state = app_state.with_catalog_cache_ttl(ttl);
+            }
         }
     }
-
-}
-
-
-
-#[derive(Deserialize)]
-struct CreateUserRequest {
-    name: String,
-    gym_leader: bool,
-}
-
-async fn create_trainer(
-    State(state): State<Arc<AppState>>,
-    Json(payload): Json<CreateUserRequest>,
-) -> ApiResponse<()> {
-    let db = state.db.clone();
-
-    match db
-        .execute(
-            "INSERT INTO trainer (name, gym_leader) VALUES ($1, $2)",
-            &[&payload.name, &payload.gym_leader],
-        )
-        .await
-    {
-        Ok(_) => ApiResponse::OK,
-        Err(e) => {
-            tracing::error!("Failed to create trainer: {}", e);
-
-            ApiResponse::Error
-        }
+    if config.compression_min_bytes > 0 {
+        app_state = app_state.with_compression(config.compression_min_bytes);
     }
-}
-
-async fn delete_trainer(
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<i32>,
-) -> ApiResponse<()> {
-    let db = state.db.clone();
-
-    match db
-        .execute("DELETE FROM trainer WHERE trainer_id = $1", &[&id])
+    if config.rate_limit_per_minute > 0 {
+        app_state = app_state.with_rate_limit(config.rate_limit_per_minute);
+    }
+    if config.auth_rate_limit_per_minute > 0 {
+        app_state = app_state.with_auth_rate_limit(config.auth_rate_limit_per_minute);
+    }
+    if config.reset_enabled {
+        tracing::warn!("POST /admin/reset enabled");
+        app_state = app_state.with_reset_endpoint();
+    }
+    app_state = app_state
+        .with_cors(CorsConfig::from_env().unwrap_or_else(|e| exit(e)))
+        .with_field_case(config.field_case);
+    if let Some(chaos) = ChaosConfig::from_env() {
+        tracing::warn!("Chaos injection enabled");
+        app_state = app_state.with_chaos(chaos);
+    }
+    let publisher = spawn_publisher(app_state.clone(), config.publish_interval);
+    let usage_flusher = spawn_usage_flusher(app_state.clone(), config.usage_flush_interval);
+    let stats_recorder = spawn_stats_recorder(app_state.clone(), config.stats_interval);
+    spawn_warm_up(app_state.clone());
+    let shutdown_state = app_state.clone();
+    let usage_state = app_state.clone();
+    let app = build_app(app_state);
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", config.port))
         .await
-    {
-        Ok(_) => ApiResponse::OK,
-        Err(e) => {
-            tracing::error!("Failed to delete trainer: {}", e);
+        .unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        tracing::info!("Shutting down, draining in-flight requests");
+        shutdown_state.shutdown();
+    })
+    .await
+    .unwrap();
 
-            ApiResponse::Error
-        }
+    // Every request has finished by now; wait for the background jobs'
+    // current runs too, and record the last of the usage, before closing
+    // the pool under them.
+    if let Err(e) = publisher.await {
+        tracing::error!("Publisher task failed: {}", e);
     }
+    if let Err(e) = usage_flusher.await {
+        tracing::error!("Usage flusher task failed: {}", e);
+    }
+    if let Err(e) = stats_recorder.await {
+        tracing::error!("Stats recorder task failed: {}", e);
+    }
+    flush_usage(&usage_state).await;
+    db_pool.close();
+    tracing::info!("Shutdown complete");
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct PokemonFull {
-    pokemon_id: i32,
-    name: String,
-    region: String,
-    abilities: Vec<Ability>,
-    attributes: Vec<Attribute>,
-}
-#[derive(Serialize)]
-struct GetPokemonResponse {
-    pokemons: Vec<PokemonFull>,
-}
-
-async fn get_pokemon(State(state): State<Arc<AppState>>) -> ApiResponse<GetPokemonResponse> {
-    let db = state.db.clone();
-
-    match db.query("SELECT * FROM pokemon", &[]).await {
-        Ok(rows) => {
-            let mut pokemon_rows = Vec::new();
-            for r in rows {
-                let region_id: i32 = r.get(2);
-                let region_res = db
-                    .query(
-                        "SELECT region_name FROM region WHERE region_id = $1",
-                        &[&region_id],
-                    )
-                    .await
-                    .unwrap();
-                let pokemon = Pokemon {
-                    pokemon_id: r.get(0),
-                    name: r.get(1),
-                    region: region_res.first().unwrap().get(0),
-                };
-
-                let ability_res = db
-                    .query(
-                        "SELECT * FROM pokemonabilities WHERE pokemon_id = $1",
-                        &[&pokemon.pokemon_id],
-                    )
-                    .await
-                    .unwrap();
-
-                let mut abilities = Vec::new();
-                for ability_row in ability_res {
-                    let ability_id: i32 = ability_row.get(1);
-                    let ability_res = db
-                        .query(
-                            "SELECT * FROM ability WHERE ability_id = $1",
-                            &[&ability_id],
-                        )
-                        .await
-                        .unwrap();
-
-                    for ability in ability_res {
-                        let ability = Ability {
-                            ability_id: ability.get(0),
-                            name: ability.get(1),
-                            damage: ability.get(2),
-                            status_effect: ability.get(3),
-                        };
-                        abilities.push(ability);
-                    }
-                }
-
-                let attribute_res = db
-                    .query(
-                        "SELECT * FROM pokemonattributes WHERE pokemon_id = $1",
-                        &[&pokemon.pokemon_id],
-                    )
-                    .await
-                    .unwrap();
-
-                let mut attributes = Vec::new();
-                for attribute_row in attribute_res {
-                    let attribute_id: i32 = attribute_row.get(1);
-                    let attribute_res = db
-                        .query(
-                            "SELECT * FROM attribute WHERE attribute_id = $1",
-                            &[&attribute_id],
-                        )
-                        .await
-                        .unwrap();
-
-                    for attribute in attribute_res {
-                        let attribute = Attribute {
-                            attribute_id: attribute.get(0),
-                            attribute_name: attribute.get(1),
-                            weakness: attribute.get(2),
-                        };
-                        attributes.push(attribute);
-                    }
-                }
-
-
-                let pokemon = PokemonFull {
-                    pokemon_id: pokemon.pokemon_id,
-                    name: pokemon.name,
-                    region: pokemon.region,
-                    abilities,
-                    attributes,
-                };
-
-                pokemon_rows.push(pokemon);
-            }
-
-            tracing::info!("{:?}", pokemon_rows);
+/// Resolves on Ctrl-C, or on SIGTERM where there is one.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl-C");
+    };
 
-            ApiResponse::JsonData(GetPokemonResponse {
-                pokemons: pokemon_rows,
-            })
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch pokemon: {:?}", e);
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-            ApiResponse::Error
-        }
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
 }
-
-// #[derive(Deserialize)]
-// struct CreatePokemonRequest {
-//     name: String,
-//     region: String,
-// }
-
-// async fn create_pokemon(
-//     State(state): State<Arc<AppState>>,
-//     Json(payload): Json<CreateUserRequest>,
-// ) -> ApiResponse<()> {
-//     ApiResponse::OK
-// }
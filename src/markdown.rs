@@ -0,0 +1,33 @@
+//! The markdown that descriptions and lore are written in, rendered to HTML
+//! that is safe to put straight into a page.
+
+use pulldown_cmark::{html, Options, Parser};
+
+pub(crate) fn to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(markdown, options));
+
+    // pulldown-cmark passes HTML in the markdown through untouched, so
+    // scripts, event handlers and `javascript:` links are only stripped
+    // here.
+    ammonia::clean(&rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_html;
+
+    #[test]
+    fn markdown_is_rendered_without_scripts() {
+        let html = to_html(
+            "**Static** <script>alert(1)</script>\n\n\
+             [wiki](javascript:alert(1)) <img src=x onerror=alert(1)>",
+        );
+
+        assert!(html.starts_with("<p><strong>Static</strong>"), "{}", html);
+        assert!(!html.contains("script"), "{}", html);
+        assert!(!html.contains("javascript:"), "{}", html);
+        assert!(!html.contains("onerror"), "{}", html);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::db::{DbConn, DbError};
+use deadpool_postgres::Pool;
+
+/// A schema migration embedded in the binary. Migrations are applied in
+/// order of `version` and are never edited once released; a change to the
+/// schema gets a new file instead.
+struct Migration {
+    version: i32,
+    name: &'static str,
+    sql: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            sql: include_str!(concat!("../migrations/V", $version, "__", $name, ".sql")),
+        }
+    };
+}
+
+const MIGRATIONS: &[Migration] = &[
+    migration!(1, "initial_schema"),
+    migration!(2, "scheduled_publishing"),
+    migration!(3, "users"),
+    migration!(4, "user_trainers"),
+    migration!(5, "impersonation"),
+    migration!(6, "table_modifications"),
+    migration!(7, "api_usage"),
+    migration!(8, "catalog_snapshots"),
+    migration!(9, "battles"),
+    migration!(10, "ai_trainers"),
+    migration!(11, "ai_difficulty"),
+    migration!(12, "battle_trainers"),
+    migration!(13, "trainer_rewards"),
+    migration!(14, "pokemon_slugs"),
+    migration!(15, "ability_name_trigrams"),
+    migration!(16, "pokemon_slug_letters"),
+    // 17 added a shop that was dropped before release.
+    migration!(18, "external_ids"),
+    // Splits owned pokemon from species in place, adding columns to
+    // trainerspokemon rather than copying it to new tables, so there is no
+    // old and new table to write to both while it rolls out.
+    migration!(19, "owned_pokemon"),
+    migration!(20, "ability_tags"),
+    migration!(21, "entity_metadata"),
+    migration!(22, "trainer_names"),
+    migration!(23, "ability_translations"),
+    migration!(24, "descriptions"),
+    migration!(25, "external_references"),
+    migration!(26, "catalog_versions"),
+    migration!(27, "recent_views"),
+    migration!(28, "bookmarks"),
+    migration!(29, "ability_proposals"),
+    migration!(30, "experiment_exposures"),
+    migration!(31, "daily_stats"),
+    migration!(32, "quiz"),
+];
+
+/// Arbitrary key for the advisory lock that keeps instances starting at the
+/// same time from applying the same migration twice.
+const MIGRATION_LOCK: i64 = 4347;
+
+/// Applies every migration that has not been recorded in `schema_migrations`
+/// yet and returns the names of the ones it applied. Everything runs in a
+/// single transaction, so a failing migration leaves the schema untouched.
+pub async fn migrate(pool: &Pool) -> Result<Vec<&'static str>, DbError> {
+    let mut client = pool.get().await?;
+    let tx = client.transaction().await?;
+
+    // Migrations can take longer than the statement timeout set for requests.
+    tx.batch_execute("SET LOCAL statement_timeout = 0").await?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])
+        .await?;
+    tx.batch_execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+             version INT PRIMARY KEY,
+             name TEXT NOT NULL,
+             applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+         )",
+    )
+    .await?;
+
+    let applied: Vec<i32> = tx
+        .query("SELECT version FROM schema_migrations", &[])
+        .await?
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+
+    let mut names = Vec::new();
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+
+        tx.batch_execute(migration.sql).await?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES ($1, $2)",
+            &[&migration.version, &migration.name],
+        )
+        .await?;
+        names.push(migration.name);
+    }
+
+    tx.commit().await?;
+
+    Ok(names)
+}
+
+/// Names of the embedded migrations the database has not recorded, so a
+/// running instance can tell its schema is behind the binary.
+pub(crate) async fn pending(db: &DbConn) -> Result<Vec<&'static str>, DbError> {
+    let applied: Vec<i32> = db
+        .query("SELECT version FROM schema_migrations", &[])
+        .await?
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .map(|m| m.name)
+        .collect())
+}
+
+/// Sample catalog for `reset`.
+const SEED: &str = include_str!("../seeds/catalog.sql");
+
+/// Empties every table except `schema_migrations`, restarting their ids,
+/// and loads the sample catalog if `seed` is set. Returns the names of the
+/// truncated tables. Everything runs in one transaction, and the single
+/// `TRUNCATE` covers all tables at once, so foreign keys between them never
+/// get in the way.
+pub(crate) async fn reset(db: &mut DbConn, seed: bool) -> Result<Vec<String>, DbError> {
+    let tx = db.transaction().await?;
+
+    let tables: Vec<String> = tx
+        .query(
+            "SELECT tablename FROM pg_tables
+             WHERE schemaname = 'public' AND tablename <> 'schema_migrations'
+             ORDER BY tablename",
+            &[],
+        )
+        .await?
+        .iter()
+        .map(|r| r.get(0))
+        .collect();
+    let quoted: Vec<String> = tables
+        .iter()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect();
+
+    tx.batch_execute(&format!("TRUNCATE {} RESTART IDENTITY", quoted.join(", ")))
+        .await?;
+    if seed {
+        tx.batch_execute(SEED).await?;
+    }
+    tx.commit().await?;
+
+    Ok(tables)
+}
@@ -0,0 +1,891 @@
+use crate::markdown;
+use async_graphql::SimpleObject;
+use axum::http::HeaderValue;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub(crate) struct Trainer {
+    pub(crate) trainer_id: i32,
+    /// Accepted in paths in place of `trainer_id`, and what links to the
+    /// trainer should carry.
+    pub(crate) uuid: Uuid,
+    pub(crate) name: String,
+    pub(crate) gym_leader: bool,
+    /// What the trainer has earned beating AI trainers in `POST /battle/ai`.
+    pub(crate) rewards: i32,
+    /// Free-form attributes set by clients.
+    #[schema(value_type = Object)]
+    pub(crate) metadata: Metadata,
+    /// Left out when the endpoint doesn't load the trainer's pokemon; a
+    /// trainer without any has an empty list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pokemon: Option<Vec<Pokemon>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, SimpleObject)]
+pub(crate) struct Pokemon {
+    pub(crate) pokemon_id: i32,
+    /// Accepted in paths in place of `pokemon_id`, and what links to the
+    /// pokemon should carry.
+    pub(crate) uuid: Uuid,
+    pub(crate) name: String,
+    /// Unique. Made from the name on insert and kept across renames.
+    pub(crate) slug: String,
+    pub(crate) region: String,
+    /// Free-form attributes set by clients.
+    #[schema(value_type = Object)]
+    #[graphql(skip)]
+    pub(crate) metadata: Metadata,
+}
+
+/// A pokemon as `GET /pokemon/{key}` returns it.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct PokemonDetail {
+    #[serde(flatten)]
+    pub(crate) pokemon: Pokemon,
+    /// Where to learn more about it, oldest first.
+    pub(crate) references: Vec<ExternalReference>,
+}
+
+/// Attributes clients attach to trainers and pokemon: always a JSON object.
+pub(crate) type Metadata = serde_json::Map<String, serde_json::Value>;
+
+/// The object that metadata must contain to match the `meta.<key>=<value>`
+/// pairs of `query`, or `None` without any. Values are matched as strings,
+/// and a key given twice keeps its last value.
+pub(crate) fn metadata_filter(query: Option<&str>) -> Option<Metadata> {
+    let filter: Metadata = form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .filter_map(|(key, value)| {
+            let key = key.strip_prefix("meta.")?.to_string();
+            Some((key, serde_json::Value::String(value.into_owned())))
+        })
+        .collect();
+
+    (!filter.is_empty()).then_some(filter)
+}
+
+/// A field of a PATCH body. Leaving the field out keeps what is stored,
+/// `null` clears it, and any other value replaces it. The field must be
+/// marked `#[serde(default)]` for a missing field to come through as
+/// `Absent`.
+#[derive(Debug, Default)]
+pub(crate) enum Patch<T> {
+    #[default]
+    Absent,
+    Null,
+    Value(T),
+}
+
+impl<T> Patch<T> {
+    pub(crate) fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// `None` when the field was left out; `Some(None)` when it was `null`.
+    pub(crate) fn into_change(self) -> Option<Option<T>> {
+        match self {
+            Self::Absent => None,
+            Self::Null => Some(None),
+            Self::Value(value) => Some(Some(value)),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Only called when the field is present, so `None` means `null`.
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Self::Value(value),
+            None => Self::Null,
+        })
+    }
+}
+
+/// Lowercases `name` and collapses every run of non-alphanumeric characters
+/// into a single `-`. A pokemon's slug is made from its name with this when
+/// it is inserted.
+pub(crate) fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// A path segment that is a numeric id, a UUID or a slug.
+pub(crate) enum EntityKey {
+    Id(i32),
+    Uuid(Uuid),
+    Slug(String),
+}
+
+impl<'de> Deserialize<'de> for EntityKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let key = String::deserialize(deserializer)?;
+        if let Ok(id) = key.parse() {
+            return Ok(Self::Id(id));
+        }
+        Ok(match Uuid::parse_str(&key) {
+            Ok(uuid) => Self::Uuid(uuid),
+            Err(_) => Self::Slug(slugify(&key)),
+        })
+    }
+}
+
+/// A path segment that is a numeric id or a UUID, for entities without
+/// slugs.
+#[derive(Clone, Copy)]
+pub(crate) enum IdOrUuid {
+    Id(i32),
+    Uuid(Uuid),
+}
+
+impl<'de> Deserialize<'de> for IdOrUuid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let key = String::deserialize(deserializer)?;
+        if let Ok(id) = key.parse() {
+            return Ok(Self::Id(id));
+        }
+        Uuid::parse_str(&key)
+            .map(Self::Uuid)
+            .map_err(|_| serde::de::Error::custom("must be an id or a UUID"))
+    }
+}
+
+const DEFAULT_PER_PAGE: i64 = 50;
+
+const MAX_PER_PAGE: i64 = 200;
+
+/// `?page=&per_page=&count=` for list endpoints. Pages start at 1.
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct PageParams {
+    pub(crate) page: Option<i64>,
+    pub(crate) per_page: Option<i64>,
+    /// How the total count is worked out. Defaults to `exact`.
+    pub(crate) count: Option<CountStrategy>,
+}
+
+impl PageParams {
+    pub(crate) fn page(&self) -> i64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub(crate) fn per_page(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+
+    pub(crate) fn offset(&self) -> i64 {
+        (self.page() - 1).saturating_mul(self.per_page())
+    }
+
+    pub(crate) fn count(&self) -> CountStrategy {
+        self.count.unwrap_or(CountStrategy::Exact)
+    }
+
+    /// The `Link` header (RFC 8288) for a page of `path` that came back with
+    /// `len` items: `first`, `prev` and `next` where there are such pages,
+    /// and `last` when `total` is known. The rest of `query` is kept. Without
+    /// a total, `next` is given whenever the page came back full.
+    pub(crate) fn links(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        len: usize,
+        total: Option<i64>,
+    ) -> HeaderValue {
+        let page = self.page();
+        let per_page = self.per_page();
+        let last = total.map(|total| ((total + per_page - 1) / per_page).max(1));
+
+        let mut links = vec![("first", 1)];
+        if page > 1 {
+            links.push(("prev", last.map_or(page - 1, |last| (page - 1).min(last))));
+        }
+        let has_next = match last {
+            Some(last) => page < last,
+            None => len as i64 == per_page,
+        };
+        if has_next {
+            links.push(("next", page + 1));
+        }
+        if let Some(last) = last {
+            links.push(("last", last));
+        }
+
+        let kept: Vec<(String, String)> =
+            form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+                .filter(|(key, _)| key != "page")
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+        let link = links
+            .iter()
+            .map(|(rel, page)| {
+                let query = form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(&kept)
+                    .append_pair("page", &page.to_string())
+                    .finish();
+                format!("<{}?{}>; rel=\"{}\"", path, query, rel)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        HeaderValue::try_from(link).expect("percent-encoded links are valid header values")
+    }
+}
+
+/// How a list endpoint works out its total count. Exact counts scan every
+/// matching row, which gets slow on large tables.
+#[derive(Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CountStrategy {
+    Exact,
+    /// The planner's row estimate, from table statistics.
+    Estimate,
+    /// Skip counting altogether.
+    None,
+}
+
+/// Total number of rows a list endpoint could page through.
+pub(crate) struct TotalCount {
+    /// `None` when counting was skipped.
+    pub(crate) value: Option<i64>,
+    pub(crate) approximate: bool,
+}
+
+/// Collation used to order results by name, chosen with `?collation=`.
+#[derive(Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum NameCollation {
+    /// ICU root locale: accented letters sort next to their base letter.
+    Icu,
+    /// Byte order, matching the default behaviour of the "C" locale.
+    Binary,
+}
+
+impl NameCollation {
+    pub(crate) fn sql_name(self) -> &'static str {
+        match self {
+            Self::Icu => "und-x-icu",
+            Self::Binary => "C",
+        }
+    }
+
+    pub(crate) fn query_value(self) -> &'static str {
+        match self {
+            Self::Icu => "icu",
+            Self::Binary => "binary",
+        }
+    }
+
+    pub(crate) fn order_by(self, column: &str) -> String {
+        format!(" ORDER BY {} COLLATE \"{}\"", column, self.sql_name())
+    }
+}
+
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SortParams {
+    pub(crate) collation: Option<NameCollation>,
+}
+
+impl SortParams {
+    pub(crate) fn order_by(&self, column: &str) -> String {
+        self.collation
+            .map(|c| c.order_by(column))
+            .unwrap_or_default()
+    }
+}
+
+/// What an external reference points at.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReferenceKind {
+    /// An `https` URL of a Bulbapedia article.
+    Bulbapedia,
+    /// The id of the same pokemon or ability in PokeAPI.
+    Pokeapi,
+    /// An `https` URL of an image.
+    Sprite,
+}
+
+impl ReferenceKind {
+    /// As stored in `external_reference.kind`.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Bulbapedia => "bulbapedia",
+            Self::Pokeapi => "pokeapi",
+            Self::Sprite => "sprite",
+        }
+    }
+
+    pub(crate) fn from_db(kind: &str) -> Self {
+        match kind {
+            "bulbapedia" => Self::Bulbapedia,
+            "pokeapi" => Self::Pokeapi,
+            _ => Self::Sprite,
+        }
+    }
+}
+
+/// A link from a pokemon or ability to a page or resource elsewhere.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct ExternalReference {
+    pub(crate) reference_id: i32,
+    pub(crate) kind: ReferenceKind,
+    /// As it was attached: a URL, or a PokeAPI id.
+    pub(crate) value: String,
+    /// Where the link goes. A PokeAPI id is turned into its API URL.
+    pub(crate) url: String,
+}
+
+impl ExternalReference {
+    /// `resource` is the PokeAPI resource the owner is, `pokemon` or
+    /// `ability`.
+    pub(crate) fn new(
+        reference_id: i32,
+        kind: ReferenceKind,
+        value: String,
+        resource: &str,
+    ) -> Self {
+        let url = match kind {
+            ReferenceKind::Pokeapi => format!("https://pokeapi.co/api/v2/{}/{}/", resource, value),
+            ReferenceKind::Bulbapedia | ReferenceKind::Sprite => value.clone(),
+        };
+
+        Self {
+            reference_id,
+            kind,
+            value,
+            url,
+        }
+    }
+}
+
+/// A reference to attach to a pokemon or ability.
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct NewReference {
+    pub(crate) kind: ReferenceKind,
+    pub(crate) value: String,
+}
+
+/// An ability as `GET /ability/{name}` returns it.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct AbilityDetail {
+    #[serde(flatten)]
+    pub(crate) ability: Ability,
+    /// Where to learn more about it, oldest first.
+    pub(crate) references: Vec<ExternalReference>,
+}
+
+/// Long-form text on a pokemon or an ability, written in markdown.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, ToSchema)]
+pub(crate) struct Description {
+    /// What it is, in a paragraph or two.
+    pub(crate) description: Option<String>,
+    /// Stories and flavor text.
+    pub(crate) lore: Option<String>,
+}
+
+impl Description {
+    /// The text as `render` asks for it.
+    pub(crate) fn render(self, render: Render) -> Self {
+        match render {
+            Render::Markdown => self,
+            Render::Html => Self {
+                description: self.description.as_deref().map(markdown::to_html),
+                lore: self.lore.as_deref().map(markdown::to_html),
+            },
+        }
+    }
+}
+
+/// How long-form text is returned.
+#[derive(Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Render {
+    /// As it was written.
+    #[default]
+    Markdown,
+    /// Rendered to HTML with anything unsafe, such as scripts, removed.
+    Html,
+}
+
+/// `?render=` for endpoints returning long-form text.
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct RenderParams {
+    pub(crate) render: Option<Render>,
+}
+
+/// A pokemon or ability whose name or text matches a search.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct SearchHit {
+    /// `pokemon` or `ability`.
+    pub(crate) kind: &'static str,
+    pub(crate) id: i32,
+    pub(crate) name: String,
+    /// Higher is better. Name matches weigh most, then the description,
+    /// then the lore.
+    pub(crate) rank: f32,
+}
+
+/// A pokemon or an ability, in lists that mix both.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum CatalogEntry {
+    Pokemon(Pokemon),
+    Ability(Ability),
+}
+
+/// Which of the two a `CatalogEntry` is, as paths name it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CatalogKind {
+    Pokemon,
+    Ability,
+}
+
+impl CatalogKind {
+    /// As stored in `kind` columns, and as the entity's table is named.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Pokemon => "pokemon",
+            Self::Ability => "ability",
+        }
+    }
+}
+
+/// A pokemon or ability the user bookmarked.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct Bookmark {
+    #[serde(flatten)]
+    pub(crate) entry: CatalogEntry,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) bookmarked_at: OffsetDateTime,
+}
+
+/// The variant of an experiment a user sees.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct ExperimentAssignment {
+    pub(crate) experiment: String,
+    pub(crate) variant: String,
+}
+
+/// How many users were shown a variant of an experiment, and how often.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct VariantExposures {
+    pub(crate) variant: String,
+    pub(crate) users: i64,
+    pub(crate) exposures: i64,
+}
+
+/// A pokemon or ability the user looked at.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct RecentView {
+    #[serde(flatten)]
+    pub(crate) entry: CatalogEntry,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) viewed_at: OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, SimpleObject)]
+pub(crate) struct Ability {
+    pub(crate) ability_id: i32,
+    pub(crate) name: String,
+    pub(crate) damage: i32,
+    pub(crate) status_effect: String,
+}
+
+/// Where a proposed change stands.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ProposalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ProposalStatus {
+    /// As stored in `ability_proposal.status`.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Rejected => "rejected",
+        }
+    }
+
+    pub(crate) fn from_db(status: &str) -> Self {
+        match status {
+            "approved" => Self::Approved,
+            "rejected" => Self::Rejected,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// A rebalance of an ability that a user proposed and an admin reviews.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct AbilityProposal {
+    pub(crate) proposal_id: i32,
+    pub(crate) ability_id: i32,
+    /// The ability's name.
+    pub(crate) ability: String,
+    /// Id of the user who proposed it.
+    pub(crate) proposed_by: i32,
+    pub(crate) damage: i32,
+    pub(crate) status_effect: String,
+    pub(crate) reason: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) proposed_at: OffsetDateTime,
+    pub(crate) status: ProposalStatus,
+    /// Id of the admin who reviewed it, unless pending or their account is
+    /// gone.
+    pub(crate) reviewed_by: Option<i32>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub(crate) reviewed_at: Option<OffsetDateTime>,
+    pub(crate) review_note: Option<String>,
+}
+
+/// `?status=` for listing proposals.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct ProposalParams {
+    /// Only proposals in this state; all of them when left out.
+    pub(crate) status: Option<ProposalStatus>,
+}
+
+/// An ability's name and status effect in a language other than English.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct AbilityTranslation {
+    /// Lowercase BCP 47 tag, such as `fr` or `pt-br`.
+    pub(crate) locale: String,
+    pub(crate) name: String,
+    pub(crate) status_effect: String,
+}
+
+/// An ability with the names of its tags.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct TaggedAbility {
+    pub(crate) ability_id: i32,
+    pub(crate) name: String,
+    pub(crate) damage: i32,
+    pub(crate) status_effect: String,
+    pub(crate) tags: Vec<String>,
+}
+
+/// How many published abilities carry a tag, and how hard they hit.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct TagStats {
+    pub(crate) name: String,
+    /// `category` (physical, special or status) or elemental `type`.
+    pub(crate) kind: String,
+    pub(crate) ability_count: i64,
+    /// `null` when no ability carries the tag.
+    pub(crate) average_damage: Option<f64>,
+}
+
+/// A pokemon a trainer owns: one instance of a species from the catalog.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct OwnedPokemon {
+    pub(crate) instance_id: i32,
+    pub(crate) trainer_id: i32,
+    /// As `GET /pokemon/{key}` returns it.
+    pub(crate) species: Pokemon,
+    pub(crate) nickname: Option<String>,
+    /// Between 1 and 100.
+    pub(crate) level: i32,
+    pub(crate) ivs: Ivs,
+}
+
+/// Individual values, each between 0 and 31, fixed when the pokemon is
+/// caught.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub(crate) struct Ivs {
+    pub(crate) hp: i32,
+    pub(crate) attack: i32,
+    pub(crate) defense: i32,
+    pub(crate) special_attack: i32,
+    pub(crate) special_defense: i32,
+    pub(crate) speed: i32,
+}
+
+/// A name a trainer went by before.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct PastName {
+    pub(crate) name: String,
+    /// When the trainer was renamed. Other trainers may take the name 30
+    /// days later.
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) released_at: OffsetDateTime,
+}
+
+/// A pokemon or ability as one of its writes left it.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct Version {
+    /// Counts up from 1 with each write that changed the row.
+    pub(crate) version: i32,
+    /// The fields a rollback restores: name, damage, status effect,
+    /// description and lore of an ability; name, region id, metadata,
+    /// description and lore of a pokemon.
+    #[schema(value_type = Object)]
+    pub(crate) snapshot: serde_json::Map<String, serde_json::Value>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) recorded_at: OffsetDateTime,
+}
+
+/// A field that differs between two versions.
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub(crate) struct FieldChange {
+    pub(crate) field: String,
+    /// `null` when the field is missing from the older version.
+    #[schema(value_type = Value)]
+    pub(crate) from: serde_json::Value,
+    #[schema(value_type = Value)]
+    pub(crate) to: serde_json::Value,
+}
+
+impl Version {
+    /// The fields of `self` that `to` changed, in the order `to` has them.
+    pub(crate) fn diff(&self, to: &Version) -> Vec<FieldChange> {
+        let null = serde_json::Value::Null;
+        let mut fields: Vec<&String> = to.snapshot.keys().collect();
+        fields.extend(
+            self.snapshot
+                .keys()
+                .filter(|k| !to.snapshot.contains_key(*k)),
+        );
+
+        fields
+            .into_iter()
+            .filter_map(|field| {
+                let from = self.snapshot.get(field).unwrap_or(&null);
+                let to = to.snapshot.get(field).unwrap_or(&null);
+                (from != to).then(|| FieldChange {
+                    field: field.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// `?from=&to=` for comparing two versions.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct DiffParams {
+    pub(crate) from: i32,
+    pub(crate) to: i32,
+}
+
+/// What changed from one version to another.
+#[derive(Serialize, Debug, ToSchema)]
+pub(crate) struct VersionDiff {
+    pub(crate) from: i32,
+    pub(crate) to: i32,
+    pub(crate) changes: Vec<FieldChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, SimpleObject)]
+pub(crate) struct Attribute {
+    pub(crate) attribute_id: i32,
+    pub(crate) attribute_name: String,
+    pub(crate) weakness: String,
+}
+
+/// Escapes `%`, `_` and `\` so user input matches literally inside LIKE.
+pub(crate) fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, SimpleObject)]
+pub(crate) struct PokemonFull {
+    pub(crate) pokemon_id: i32,
+    pub(crate) uuid: Uuid,
+    pub(crate) name: String,
+    pub(crate) slug: String,
+    pub(crate) region: String,
+    pub(crate) generation: Option<i32>,
+    pub(crate) abilities: Vec<Ability>,
+    pub(crate) attributes: Vec<Attribute>,
+    /// Free-form attributes set by clients.
+    #[schema(value_type = Object)]
+    #[graphql(skip)]
+    pub(crate) metadata: Metadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema, SimpleObject)]
+pub(crate) struct Region {
+    pub(crate) region_id: i32,
+    pub(crate) region_name: String,
+}
+
+/// Region each main-series generation introduced. Hisui shares generation 8
+/// with Galar.
+const GENERATIONS: &[(i32, &str)] = &[
+    (1, "kanto"),
+    (2, "johto"),
+    (3, "hoenn"),
+    (4, "sinnoh"),
+    (5, "unova"),
+    (6, "kalos"),
+    (7, "alola"),
+    (8, "galar"),
+    (8, "hisui"),
+    (9, "paldea"),
+];
+
+pub(crate) fn generation_of(region: &str) -> Option<i32> {
+    let region = region.to_lowercase();
+    GENERATIONS
+        .iter()
+        .find(|(_, name)| *name == region)
+        .map(|(generation, _)| *generation)
+}
+
+pub(crate) fn regions_in_generation(generation: i32) -> Vec<String> {
+    GENERATIONS
+        .iter()
+        .filter(|(g, _)| *g == generation)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+#[derive(Deserialize, IntoParams, Default)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct PokemonFilter {
+    pub(crate) generation: Option<i32>,
+    /// Case-insensitive substring of the pokemon's name.
+    pub(crate) name: Option<String>,
+    /// Region name, compared case-insensitively.
+    pub(crate) region: Option<String>,
+    /// Name of an ability the pokemon has, compared case-insensitively.
+    pub(crate) ability: Option<String>,
+    /// Case-insensitive prefix of the pokemon's name.
+    pub(crate) starts_with: Option<String>,
+    /// From the `meta.<key>=<value>` parameters; see `metadata_filter`.
+    #[serde(skip)]
+    pub(crate) metadata: Option<Metadata>,
+}
+
+/// A region given either by id or by name in a request body.
+#[derive(Deserialize, ToSchema)]
+#[serde(untagged)]
+pub(crate) enum RegionRef {
+    Id(i32),
+    Name(String),
+}
+
+/// Ids of the rows a bulk create inserted, in request order.
+#[derive(Serialize, ToSchema)]
+pub(crate) struct BulkCreateResponse {
+    pub(crate) ids: Vec<i32>,
+}
+
+/// A pinned copy of the catalog that battles can be fought with.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct CatalogSnapshot {
+    pub(crate) snapshot_id: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub(crate) created_at: OffsetDateTime,
+    /// Pokemon pinned in the snapshot: every one published when it was
+    /// taken.
+    pub(crate) pokemon: i64,
+}
+
+/// A trainer's standing in the "Who's that pokemon?" quiz.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct QuizScore {
+    pub(crate) trainer_id: i32,
+    pub(crate) name: String,
+    /// Questions answered right.
+    pub(crate) correct: i32,
+    /// Questions answered, right or wrong.
+    pub(crate) answered: i32,
+}
+
+/// A "Who's that pokemon?" question.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct QuizQuestion {
+    /// For `POST /quiz/{id}/answer`.
+    pub(crate) question_id: i32,
+    /// The pokemon's name with all but its first letter blanked out as `_`.
+    pub(crate) masked_name: String,
+    /// The pokemon's sprite, for the client to show as a silhouette.
+    pub(crate) sprite_url: String,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub(crate) struct QuizAnswer {
+    /// Whether the answer named the pokemon, give or take a typo.
+    pub(crate) correct: bool,
+    /// The pokemon's actual name.
+    pub(crate) pokemon: String,
+    /// The trainer's score with this answer counted.
+    pub(crate) score: QuizScore,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generation_of, regions_in_generation, NameCollation, SortParams};
+
+    fn sort(query: &str) -> Result<SortParams, serde_urlencoded::de::Error> {
+        serde_urlencoded::from_str(query)
+    }
+
+    #[test]
+    fn names_sort_with_the_collation_asked_for() {
+        assert_eq!(sort("").unwrap().order_by("p.name"), "");
+        assert_eq!(
+            sort("collation=icu").unwrap().order_by("p.name"),
+            " ORDER BY p.name COLLATE \"und-x-icu\""
+        );
+        assert_eq!(
+            sort("collation=binary").unwrap().order_by("t.name"),
+            " ORDER BY t.name COLLATE \"C\""
+        );
+
+        assert!(sort("collation=ICU").is_err());
+        assert!(sort("collation=fr_FR").is_err());
+    }
+
+    #[test]
+    fn collations_round_trip_through_links() {
+        for collation in [NameCollation::Icu, NameCollation::Binary] {
+            let query = format!("collation={}", collation.query_value());
+            let parsed = sort(&query).unwrap().collation.unwrap();
+            assert_eq!(parsed.sql_name(), collation.sql_name());
+        }
+    }
+
+    #[test]
+    fn regions_map_to_generations_in_any_case() {
+        assert_eq!(generation_of("Kanto"), Some(1));
+        assert_eq!(generation_of("HISUI"), Some(8));
+        assert_eq!(generation_of("Orre"), None);
+
+        assert_eq!(regions_in_generation(8), ["galar", "hisui"]);
+        assert_eq!(regions_in_generation(1), ["kanto"]);
+        assert!(regions_in_generation(10).is_empty());
+    }
+}
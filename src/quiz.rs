@@ -0,0 +1,87 @@
+//! The "Who's that pokemon?" quiz: how a name is hidden from the player and
+//! how close a guess has to come to it.
+
+/// `name` with every letter and digit but the first turned into `_`, so
+/// the player sees its length and where its words break.
+pub(crate) fn mask_name(name: &str) -> String {
+    let mut shown = false;
+    name.chars()
+        .map(|c| {
+            if !c.is_alphanumeric() {
+                c
+            } else if !shown {
+                shown = true;
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Whether `answer` names the pokemon called `name`. Case, spaces and
+/// punctuation don't count, and longer names forgive a typo or two.
+pub(crate) fn is_match(answer: &str, name: &str) -> bool {
+    let answer = normalize(answer);
+    let name = normalize(name);
+    if answer.is_empty() {
+        return false;
+    }
+
+    let typos = match name.len() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    };
+    edit_distance(&answer, &name) <= typos
+}
+
+fn normalize(s: &str) -> Vec<char> {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Levenshtein distance: the fewest insertions, deletions and substitutions
+/// that turn `a` into `b`.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_masked_but_their_shape_shows() {
+        assert_eq!(mask_name("Pikachu"), "P______");
+        assert_eq!(mask_name("Mr. Mime"), "M_. ____");
+        assert_eq!(mask_name("Porygon-Z"), "P______-_");
+    }
+
+    #[test]
+    fn answers_may_be_a_little_off() {
+        assert!(is_match("pikachu", "Pikachu"));
+        assert!(is_match("Pikatchu", "Pikachu"));
+        assert!(is_match("mr mime", "Mr. Mime"));
+        assert!(is_match("Charmandr", "Charmander"));
+        assert!(is_match("Charmandar", "Charmander"));
+
+        assert!(!is_match("Pikablu", "Pikachu"));
+        assert!(!is_match("Mew", "Muk"));
+        assert!(!is_match("", "Muk"));
+        assert!(!is_match("Raichu", "Pikachu"));
+    }
+}
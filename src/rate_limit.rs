@@ -0,0 +1,206 @@
+use crate::error::ApiError;
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a bucket takes to refill completely, and so how often idle ones
+/// are swept out: once full, a bucket is no different from one that was
+/// never created.
+const REFILL: Duration = Duration::from_secs(60);
+
+/// Per-client token buckets. Each client may burst up to `per_minute`
+/// requests and then gets one more every `60 / per_minute` seconds.
+pub(crate) struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+    clients: HashMap<IpAddr, Bucket>,
+    swept_at: Instant,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            buckets: Mutex::new(Buckets {
+                clients: HashMap::new(),
+                swept_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token for `client`, or returns how long until one is free.
+    fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = self.per_minute as f64;
+        let per_second = capacity / REFILL.as_secs_f64();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        // Sweeping visits every client, so it runs once per refill period
+        // rather than on every request.
+        if now.duration_since(buckets.swept_at) >= REFILL {
+            buckets
+                .clients
+                .retain(|_, b| now.duration_since(b.refilled_at) < REFILL);
+            buckets.swept_at = now;
+        }
+
+        let bucket = buckets.clients.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+/// Answers 429 with a `Retry-After` header once a client address runs out
+/// of requests. Requests without a peer address, such as those sent
+/// straight to the router in tests, are never limited.
+pub(crate) async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    limit(state.rate_limiter.as_deref(), request, next).await
+}
+
+/// Like `rate_limit`, but with the separate, stricter allowance for signing
+/// in, which keeps password guessing slow.
+pub(crate) async fn rate_limit_auth(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    limit(state.auth_rate_limiter.as_deref(), request, next).await
+}
+
+async fn limit(limiter: Option<&RateLimiter>, request: Request, next: Next) -> Response {
+    let (Some(limiter), Some(ConnectInfo(peer))) = (
+        limiter,
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+    ) else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(peer.ip()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => ApiError::TooManyRequests(wait).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimiter, REFILL};
+    use crate::build_app;
+    use crate::testing::state;
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::{header, Request, StatusCode};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    const ASH: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const GARY: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn each_client_has_a_bucket_that_refills() {
+        let limiter = RateLimiter::new(60);
+        let start = Instant::now();
+
+        for _ in 0..60 {
+            assert!(limiter.check_at(ASH, start).is_ok());
+        }
+        let wait = limiter.check_at(ASH, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        // Ash's burst leaves Gary's bucket alone.
+        assert!(limiter.check_at(GARY, start).is_ok());
+
+        let later = start + Duration::from_secs(2);
+        assert!(limiter.check_at(ASH, later).is_ok());
+        assert!(limiter.check_at(ASH, later).is_ok());
+        assert!(limiter.check_at(ASH, later).is_err());
+    }
+
+    #[test]
+    fn idle_clients_are_swept_once_a_refill_period() {
+        let limiter = RateLimiter::new(60);
+        let start = Instant::now();
+        limiter.buckets.lock().unwrap().swept_at = start;
+        let clients = || {
+            let buckets = limiter.buckets.lock().unwrap();
+            let mut clients: Vec<IpAddr> = buckets.clients.keys().copied().collect();
+            clients.sort();
+            clients
+        };
+
+        limiter.check_at(ASH, start).unwrap();
+        limiter.check_at(GARY, start + REFILL / 2).unwrap();
+        assert_eq!(clients(), [ASH, GARY]);
+
+        // The first sweep finds Ash's bucket full again.
+        limiter.check_at(GARY, start + REFILL).unwrap();
+        assert_eq!(clients(), [GARY]);
+
+        // Until the next sweep, a full bucket is kept.
+        let second = start + REFILL * 2;
+        limiter
+            .check_at(ASH, start + REFILL + Duration::from_secs(1))
+            .unwrap();
+        limiter.check_at(GARY, second).unwrap();
+        limiter.check_at(GARY, second + REFILL / 2).unwrap();
+        assert_eq!(clients(), [ASH, GARY]);
+
+        limiter.check_at(GARY, start + REFILL * 3).unwrap();
+        assert_eq!(clients(), [GARY]);
+    }
+
+    #[tokio::test]
+    async fn clients_out_of_requests_are_told_when_to_retry() {
+        let app = build_app(state(Arc::default()).with_rate_limit(1));
+        let request = |ip: IpAddr| {
+            Request::get("/trainer")
+                .extension(ConnectInfo(SocketAddr::new(ip, 4000)))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request(ASH)).await.unwrap();
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app.clone().oneshot(request(ASH)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // A token comes back a minute after it was taken.
+        let retry_after = &response.headers()[header::RETRY_AFTER];
+        assert!(matches!(retry_after.to_str().unwrap(), "59" | "60"));
+
+        let response = app.oneshot(request(GARY)).await.unwrap();
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
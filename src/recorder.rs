@@ -0,0 +1,90 @@
+use crate::AppState;
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+/// Largest request body the recorder will buffer in order to hash it.
+const MAX_RECORDED_BODY: usize = 2 * 1024 * 1024;
+
+/// One recorded request. Only the route template is kept, never the concrete
+/// path, query string or body, so the log holds no user data.
+#[derive(Serialize, Clone, ToSchema)]
+pub(crate) struct RecordedRequest {
+    at_ms: u64,
+    method: String,
+    pub(crate) route: String,
+    body_hash: Option<String>,
+    pub(crate) status: u16,
+    pub(crate) latency_ms: f64,
+}
+
+pub(crate) struct RequestLog {
+    pub(crate) capacity: usize,
+    pub(crate) entries: Mutex<VecDeque<RecordedRequest>>,
+}
+
+impl RequestLog {
+    pub(crate) fn push(&self, entry: RecordedRequest) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+pub(crate) async fn record_request(
+    State(state): State<Arc<AppState>>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(log) = state.request_log.clone() else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().to_string();
+    let route = matched
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| "<unmatched>".to_string());
+
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_RECORDED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    let body_hash = (!bytes.is_empty()).then(|| {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    });
+    let request = Request::from_parts(parts, Body::from(bytes));
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    log.push(RecordedRequest {
+        at_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        method,
+        route,
+        body_hash,
+        status: response.status().as_u16(),
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+    });
+
+    response
+}
@@ -0,0 +1,817 @@
+use super::{in_transaction, record_version, reference_from_row, version_from_row, AddReference};
+use crate::db::{Db, DbError};
+use crate::models::{
+    Ability, AbilityProposal, AbilityTranslation, Description, ExternalReference, NewReference,
+    PageParams, ProposalStatus, SearchHit, TagStats, TaggedAbility, Version,
+};
+use axum::async_trait;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio_postgres::Row;
+
+#[async_trait]
+pub(crate) trait AbilityRepository: Send + Sync {
+    /// Inserts the ability, or updates the one with this name, live right
+    /// away. A rebalance staged for it is dropped and a publish time cleared
+    /// in the same statement, so the publisher can't undo the change later.
+    /// Returns it and whether it just went live.
+    async fn apply(
+        &self,
+        name: &str,
+        damage: i32,
+        status_effect: &str,
+    ) -> Result<(Ability, bool), DbError>;
+
+    /// Inserts a hidden ability, or queues a rebalance if one with this name
+    /// already exists, either way going live at `publish_at`. Returns its id.
+    async fn stage(
+        &self,
+        name: &str,
+        damage: i32,
+        status_effect: &str,
+        publish_at: OffsetDateTime,
+    ) -> Result<i32, DbError>;
+
+    /// Clears the publish times that have passed by `now` and applies the
+    /// rebalances that are due.
+    async fn publish_due(&self, now: OffsetDateTime) -> Result<PublishedAbilities, DbError>;
+
+    /// A page of the published abilities carrying every one of `tags`, by
+    /// id, and how many there are in all. Each is named in the first of
+    /// `locales` it has a translation for, or else in English.
+    async fn list(
+        &self,
+        tags: &[String],
+        locales: &[String],
+        paging: &PageParams,
+    ) -> Result<(Vec<TaggedAbility>, i64), DbError>;
+
+    /// Replaces the tags of the ability named `name`, published or not.
+    async fn set_tags(&self, name: &str, tags: &[String]) -> Result<SetTags, DbError>;
+
+    /// Every tag, with how many published abilities carry it.
+    async fn tag_stats(&self) -> Result<Vec<TagStats>, DbError>;
+
+    /// The translations of the ability named `name`, published or not, by
+    /// locale. `None` if there is no such ability.
+    async fn translations(&self, name: &str) -> Result<Option<Vec<AbilityTranslation>>, DbError>;
+
+    /// Adds or replaces a translation of the ability named `name`. Returns
+    /// its id, or `None` if there is no such ability.
+    async fn set_translation(
+        &self,
+        name: &str,
+        translation: &AbilityTranslation,
+    ) -> Result<Option<i32>, DbError>;
+
+    /// Removes a translation of the ability named `name`. Returns its id, or
+    /// `None` if there is no such ability or translation.
+    async fn delete_translation(&self, name: &str, locale: &str) -> Result<Option<i32>, DbError>;
+
+    /// The description and lore of the ability named `name`, or `None` if
+    /// there is no such ability or it is not published yet.
+    async fn description(&self, name: &str) -> Result<Option<Description>, DbError>;
+
+    /// Replaces the description and lore of the ability named `name`,
+    /// published or not. Returns its id, or `None` if there is no such
+    /// ability.
+    async fn set_description(
+        &self,
+        name: &str,
+        description: &Description,
+    ) -> Result<Option<i32>, DbError>;
+
+    /// Up to `limit` published abilities whose name, description or lore
+    /// matches `query`, best first.
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, DbError>;
+
+    /// The published ability named `name`.
+    async fn find(&self, name: &str) -> Result<Option<Ability>, DbError>;
+
+    /// The external references of the ability, oldest first.
+    async fn references(&self, ability_id: i32) -> Result<Vec<ExternalReference>, DbError>;
+
+    /// Attaches a reference to the ability named `name`, published or not.
+    async fn add_reference(
+        &self,
+        name: &str,
+        reference: &NewReference,
+    ) -> Result<AddReference, DbError>;
+
+    /// Detaches a reference from the ability named `name`. Returns the
+    /// ability's id, or `None` if it has no such reference.
+    async fn delete_reference(&self, name: &str, reference_id: i32)
+        -> Result<Option<i32>, DbError>;
+
+    /// Records the ability as it now is as its next version, unless nothing
+    /// changed since the latest one. Returns the latest version, or `None`
+    /// if there is no such ability.
+    async fn record_version(&self, ability_id: i32) -> Result<Option<Version>, DbError>;
+
+    /// The id of the ability named `name`, published or not, and its
+    /// versions, oldest first. `None` if there is no such ability.
+    async fn versions(&self, name: &str) -> Result<Option<(i32, Vec<Version>)>, DbError>;
+
+    /// Files the rebalance the user proposes for the published ability
+    /// named `name`. `None` if there is no such ability.
+    async fn propose(
+        &self,
+        name: &str,
+        user_id: i32,
+        damage: i32,
+        status_effect: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<AbilityProposal>, DbError>;
+
+    /// Proposals oldest first, only those in `status` and by `proposed_by`
+    /// when given.
+    async fn proposals(
+        &self,
+        status: Option<ProposalStatus>,
+        proposed_by: Option<i32>,
+    ) -> Result<Vec<AbilityProposal>, DbError>;
+
+    /// Approves or rejects a pending proposal as the admin `reviewer`.
+    /// Approving applies the rebalance to the ability in the same
+    /// transaction, dropping any staged one as `apply` does.
+    async fn review(
+        &self,
+        proposal_id: i32,
+        reviewer: i32,
+        approve: bool,
+        note: Option<&str>,
+    ) -> Result<Review, DbError>;
+}
+
+/// What an ability version keeps: the columns a rollback restores.
+const ABILITY_SNAPSHOT: &str = "jsonb_build_object(
+    'name', name, 'damage', damage, 'status_effect', status_effect,
+    'description', description, 'lore', lore
+)";
+
+pub(crate) enum Review {
+    Reviewed(AbilityProposal),
+    NotFound,
+    /// It was approved or rejected before.
+    AlreadyReviewed(ProposalStatus),
+}
+
+/// The columns `proposal_from_row` reads, from `ability_proposal p` joined
+/// with `ability a`.
+const PROPOSAL_COLUMNS: &str = "p.proposal_id, p.ability_id, a.name, p.proposed_by, p.damage,
+    p.status_effect, p.reason, p.proposed_at, p.status, p.reviewed_by, p.reviewed_at,
+    p.review_note";
+
+fn proposal_from_row(r: &Row) -> AbilityProposal {
+    AbilityProposal {
+        proposal_id: r.get(0),
+        ability_id: r.get(1),
+        ability: r.get(2),
+        proposed_by: r.get(3),
+        damage: r.get(4),
+        status_effect: r.get(5),
+        reason: r.get(6),
+        proposed_at: r.get(7),
+        status: ProposalStatus::from_db(r.get(8)),
+        reviewed_by: r.get(9),
+        reviewed_at: r.get(10),
+        review_note: r.get(11),
+    }
+}
+
+pub(crate) enum SetTags {
+    /// The id of the ability tagged.
+    Set(i32),
+    NotFound,
+    UnknownTags(Vec<String>),
+}
+
+pub(crate) struct PublishedAbilities {
+    /// Abilities that went live.
+    pub(crate) created: Vec<i32>,
+    /// Live abilities whose staged rebalance was applied.
+    pub(crate) rebalanced: Vec<i32>,
+}
+
+pub(crate) struct PgAbilityRepository {
+    db: Arc<Db>,
+}
+
+impl PgAbilityRepository {
+    pub(crate) fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl AbilityRepository for PgAbilityRepository {
+    async fn apply(
+        &self,
+        name: &str,
+        damage: i32,
+        status_effect: &str,
+    ) -> Result<(Ability, bool), DbError> {
+        let db = self.db.conn().await?;
+        // Every CTE reads the snapshot from before the upsert, so `hidden`
+        // sees whether the ability was still waiting for its publish time.
+        let rows = db
+            .query(
+                "WITH hidden AS (
+                     SELECT 1 FROM ability WHERE name = $1 AND publish_at > now()
+                 ), upserted AS (
+                     INSERT INTO ability (name, damage, status_effect) VALUES ($1, $2, $3)
+                     ON CONFLICT (name) DO UPDATE
+                     SET damage = EXCLUDED.damage,
+                         status_effect = EXCLUDED.status_effect,
+                         publish_at = NULL
+                     -- xmax is only zero for a freshly inserted row version.
+                     RETURNING ability_id, name, damage, status_effect, xmax = 0 AS inserted
+                 ), unstaged AS (
+                     DELETE FROM ability_changes
+                     WHERE ability_id = (SELECT ability_id FROM upserted)
+                 )
+                 SELECT ability_id, name, damage, status_effect,
+                        inserted OR EXISTS (SELECT 1 FROM hidden)
+                 FROM upserted",
+                &[&name, &damage, &status_effect],
+            )
+            .await?;
+        let r = rows.first().unwrap();
+
+        Ok((
+            Ability {
+                ability_id: r.get(0),
+                name: r.get(1),
+                damage: r.get(2),
+                status_effect: r.get(3),
+            },
+            r.get(4),
+        ))
+    }
+
+    async fn stage(
+        &self,
+        name: &str,
+        damage: i32,
+        status_effect: &str,
+        publish_at: OffsetDateTime,
+    ) -> Result<i32, DbError> {
+        let db = self.db.conn().await?;
+        // The staged CTE reads the snapshot from before the insert, so it
+        // only finds the ability when the insert conflicted.
+        let rows = db
+            .query(
+                "WITH inserted AS (
+                     INSERT INTO ability (name, damage, status_effect, publish_at)
+                     VALUES ($1, $2, $3, $4)
+                     ON CONFLICT (name) DO NOTHING
+                     RETURNING ability_id
+                 ), staged AS (
+                     INSERT INTO ability_changes (ability_id, damage, status_effect, publish_at)
+                     SELECT ability_id, $2, $3, $4 FROM ability WHERE name = $1
+                     ON CONFLICT (ability_id) DO UPDATE
+                     SET damage = EXCLUDED.damage,
+                         status_effect = EXCLUDED.status_effect,
+                         publish_at = EXCLUDED.publish_at
+                     RETURNING ability_id
+                 )
+                 SELECT ability_id FROM inserted UNION ALL SELECT ability_id FROM staged",
+                &[&name, &damage, &status_effect, &publish_at],
+            )
+            .await?;
+
+        Ok(rows.first().unwrap().get(0))
+    }
+
+    async fn publish_due(&self, now: OffsetDateTime) -> Result<PublishedAbilities, DbError> {
+        let db = self.db.conn().await?;
+
+        let created = db
+            .query(
+                "UPDATE ability SET publish_at = NULL WHERE publish_at <= $1
+                 RETURNING ability_id",
+                &[&now],
+            )
+            .await?;
+
+        let rebalanced = db
+            .query(
+                "WITH due AS (
+                     DELETE FROM ability_changes WHERE publish_at <= $1 RETURNING *
+                 )
+                 UPDATE ability a
+                 SET damage = due.damage, status_effect = due.status_effect
+                 FROM due WHERE a.ability_id = due.ability_id
+                 RETURNING a.ability_id",
+                &[&now],
+            )
+            .await?;
+
+        Ok(PublishedAbilities {
+            created: created.iter().map(|r| r.get(0)).collect(),
+            rebalanced: rebalanced.iter().map(|r| r.get(0)).collect(),
+        })
+    }
+
+    async fn list(
+        &self,
+        tags: &[String],
+        locales: &[String],
+        paging: &PageParams,
+    ) -> Result<(Vec<TaggedAbility>, i64), DbError> {
+        let db = self.db.conn().await?;
+        // The count and the page come from one statement, so they always
+        // agree.
+        let rows = db
+            .query(
+                "WITH matching AS (
+                     SELECT a.* FROM published_ability a
+                     WHERE NOT EXISTS (
+                         SELECT 1 FROM unnest($1::text[]) AS wanted (name)
+                         WHERE NOT EXISTS (
+                             SELECT 1 FROM ability_tag at
+                             JOIN tag t ON t.tag_id = at.tag_id
+                             WHERE at.ability_id = a.ability_id AND t.name = wanted.name
+                         )
+                     )
+                 )
+                 SELECT c.total, m.ability_id,
+                        coalesce(tr.name, m.name), m.damage,
+                        coalesce(tr.status_effect, m.status_effect),
+                        ARRAY(
+                            SELECT t.name FROM ability_tag at
+                            JOIN tag t ON t.tag_id = at.tag_id
+                            WHERE at.ability_id = m.ability_id
+                            ORDER BY t.tag_id
+                        )
+                 FROM (SELECT count(*) AS total FROM matching) c
+                 LEFT JOIN LATERAL (
+                     SELECT * FROM matching ORDER BY ability_id LIMIT $2 OFFSET $3
+                 ) m ON true
+                 LEFT JOIN LATERAL (
+                     SELECT tr.name, tr.status_effect FROM ability_translation tr
+                     WHERE tr.ability_id = m.ability_id AND tr.locale = ANY($4)
+                     ORDER BY array_position($4, tr.locale)
+                     LIMIT 1
+                 ) tr ON true
+                 ORDER BY m.ability_id",
+                &[&tags, &paging.per_page(), &paging.offset(), &locales],
+            )
+            .await?;
+
+        let total = rows.first().map_or(0, |r| r.get(0));
+        let abilities = rows
+            .iter()
+            .filter(|r| r.get::<_, Option<i32>>(1).is_some())
+            .map(|r| TaggedAbility {
+                ability_id: r.get(1),
+                name: r.get(2),
+                damage: r.get(3),
+                status_effect: r.get(4),
+                tags: r.get(5),
+            })
+            .collect();
+
+        Ok((abilities, total))
+    }
+
+    async fn set_tags(&self, name: &str, tags: &[String]) -> Result<SetTags, DbError> {
+        let (name, tags) = (name.to_string(), tags.to_vec());
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                let rows = tx
+                    .query(
+                        "SELECT ability_id FROM ability WHERE name = $1 FOR UPDATE",
+                        &[&name],
+                    )
+                    .await?;
+                let Some(ability_id) = rows.first().map(|r| r.get::<_, i32>(0)) else {
+                    return Ok(SetTags::NotFound);
+                };
+
+                let unknown = tx
+                    .query(
+                        "SELECT DISTINCT name FROM unnest($1::text[]) AS wanted (name)
+                         WHERE NOT EXISTS (SELECT 1 FROM tag WHERE tag.name = wanted.name)
+                         ORDER BY name",
+                        &[&tags],
+                    )
+                    .await?;
+                if !unknown.is_empty() {
+                    return Ok(SetTags::UnknownTags(
+                        unknown.iter().map(|r| r.get(0)).collect(),
+                    ));
+                }
+
+                tx.execute(
+                    "DELETE FROM ability_tag WHERE ability_id = $1",
+                    &[&ability_id],
+                )
+                .await?;
+                tx.execute(
+                    "INSERT INTO ability_tag (ability_id, tag_id)
+                     SELECT $1, tag_id FROM tag WHERE name = ANY($2)",
+                    &[&ability_id, &tags],
+                )
+                .await?;
+
+                Ok(SetTags::Set(ability_id))
+            })
+        })
+        .await
+    }
+
+    async fn tag_stats(&self) -> Result<Vec<TagStats>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT t.name, t.kind, count(a.ability_id), avg(a.damage)::float8
+                 FROM tag t
+                 LEFT JOIN ability_tag at ON at.tag_id = t.tag_id
+                 LEFT JOIN published_ability a ON a.ability_id = at.ability_id
+                 GROUP BY t.tag_id
+                 ORDER BY t.tag_id",
+                &[],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| TagStats {
+                name: r.get(0),
+                kind: r.get(1),
+                ability_count: r.get(2),
+                average_damage: r.get(3),
+            })
+            .collect())
+    }
+
+    async fn translations(&self, name: &str) -> Result<Option<Vec<AbilityTranslation>>, DbError> {
+        let db = self.db.conn().await?;
+        // The ability is left joined, so that it is told apart from one
+        // without translations.
+        let rows = db
+            .query(
+                "SELECT tr.locale, tr.name, tr.status_effect FROM ability a
+                 LEFT JOIN ability_translation tr ON tr.ability_id = a.ability_id
+                 WHERE a.name = $1
+                 ORDER BY tr.locale",
+                &[&name],
+            )
+            .await?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            rows.iter()
+                .filter(|r| r.get::<_, Option<String>>(0).is_some())
+                .map(|r| AbilityTranslation {
+                    locale: r.get(0),
+                    name: r.get(1),
+                    status_effect: r.get(2),
+                })
+                .collect(),
+        ))
+    }
+
+    async fn set_translation(
+        &self,
+        name: &str,
+        translation: &AbilityTranslation,
+    ) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "INSERT INTO ability_translation (ability_id, locale, name, status_effect)
+                 SELECT ability_id, $2, $3, $4 FROM ability WHERE name = $1
+                 ON CONFLICT (ability_id, locale) DO UPDATE
+                 SET name = EXCLUDED.name, status_effect = EXCLUDED.status_effect
+                 RETURNING ability_id",
+                &[
+                    &name,
+                    &translation.locale,
+                    &translation.name,
+                    &translation.status_effect,
+                ],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn delete_translation(&self, name: &str, locale: &str) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "DELETE FROM ability_translation tr USING ability a
+                 WHERE tr.ability_id = a.ability_id AND a.name = $1 AND tr.locale = $2
+                 RETURNING tr.ability_id",
+                &[&name, &locale],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn description(&self, name: &str) -> Result<Option<Description>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT description, lore FROM published_ability WHERE name = $1",
+                &[&name],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| Description {
+            description: r.get(0),
+            lore: r.get(1),
+        }))
+    }
+
+    async fn set_description(
+        &self,
+        name: &str,
+        description: &Description,
+    ) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "UPDATE ability SET description = $2, lore = $3 WHERE name = $1
+                 RETURNING ability_id",
+                &[&name, &description.description, &description.lore],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT ability_id, name, ts_rank(search, q)
+                 FROM published_ability, websearch_to_tsquery('english', $1) q
+                 WHERE search @@ q
+                 ORDER BY 3 DESC, ability_id
+                 LIMIT $2",
+                &[&query, &limit],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| SearchHit {
+                kind: "ability",
+                id: r.get(0),
+                name: r.get(1),
+                rank: r.get(2),
+            })
+            .collect())
+    }
+
+    async fn find(&self, name: &str) -> Result<Option<Ability>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT ability_id, name, damage, status_effect FROM published_ability
+                 WHERE name = $1",
+                &[&name],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| Ability {
+            ability_id: r.get(0),
+            name: r.get(1),
+            damage: r.get(2),
+            status_effect: r.get(3),
+        }))
+    }
+
+    async fn references(&self, ability_id: i32) -> Result<Vec<ExternalReference>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT reference_id, kind, value FROM external_reference
+                 WHERE ability_id = $1
+                 ORDER BY reference_id",
+                &[&ability_id],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| reference_from_row(r, "ability"))
+            .collect())
+    }
+
+    async fn add_reference(
+        &self,
+        name: &str,
+        reference: &NewReference,
+    ) -> Result<AddReference, DbError> {
+        let db = self.db.conn().await?;
+        // A conflict inserts nothing, which leaves the ability without a
+        // reference id.
+        let rows = db
+            .query(
+                "WITH target AS (
+                     SELECT ability_id FROM ability WHERE name = $1
+                 ), inserted AS (
+                     INSERT INTO external_reference (ability_id, kind, value)
+                     SELECT ability_id, $2, $3 FROM target
+                     ON CONFLICT DO NOTHING
+                     RETURNING reference_id
+                 )
+                 SELECT t.ability_id, i.reference_id
+                 FROM target t LEFT JOIN inserted i ON true",
+                &[&name, &reference.kind.as_str(), &reference.value],
+            )
+            .await?;
+
+        Ok(match rows.first() {
+            None => AddReference::NotFound,
+            Some(r) => match r.get::<_, Option<i32>>(1) {
+                Some(reference_id) => AddReference::Added(
+                    ExternalReference::new(
+                        reference_id,
+                        reference.kind,
+                        reference.value.clone(),
+                        "ability",
+                    ),
+                    r.get(0),
+                ),
+                None => AddReference::Duplicate,
+            },
+        })
+    }
+
+    async fn delete_reference(
+        &self,
+        name: &str,
+        reference_id: i32,
+    ) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "DELETE FROM external_reference r USING ability a
+                 WHERE r.ability_id = a.ability_id AND r.reference_id = $2 AND a.name = $1
+                 RETURNING a.ability_id",
+                &[&name, &reference_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn record_version(&self, ability_id: i32) -> Result<Option<Version>, DbError> {
+        record_version(&self.db, "ability", ABILITY_SNAPSHOT, ability_id).await
+    }
+
+    async fn versions(&self, name: &str) -> Result<Option<(i32, Vec<Version>)>, DbError> {
+        let db = self.db.conn().await?;
+        // The ability is left joined, so that it is told apart from one
+        // without versions.
+        let rows = db
+            .query(
+                "SELECT v.version, v.snapshot, v.recorded_at, a.ability_id FROM ability a
+                 LEFT JOIN ability_version v ON v.ability_id = a.ability_id
+                 WHERE a.name = $1
+                 ORDER BY v.version",
+                &[&name],
+            )
+            .await?;
+        let Some(ability_id) = rows.first().map(|r| r.get(3)) else {
+            return Ok(None);
+        };
+
+        Ok(Some((
+            ability_id,
+            rows.iter()
+                .filter(|r| r.get::<_, Option<i32>>(0).is_some())
+                .map(version_from_row)
+                .collect(),
+        )))
+    }
+
+    async fn propose(
+        &self,
+        name: &str,
+        user_id: i32,
+        damage: i32,
+        status_effect: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<AbilityProposal>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                &format!(
+                    "WITH p AS (
+                         INSERT INTO ability_proposal
+                             (ability_id, proposed_by, damage, status_effect, reason)
+                         SELECT ability_id, $2, $3, $4, $5 FROM published_ability
+                         WHERE name = $1
+                         RETURNING *
+                     )
+                     SELECT {} FROM p JOIN ability a ON a.ability_id = p.ability_id",
+                    PROPOSAL_COLUMNS
+                ),
+                &[&name, &user_id, &damage, &status_effect, &reason],
+            )
+            .await?;
+
+        Ok(rows.first().map(proposal_from_row))
+    }
+
+    async fn proposals(
+        &self,
+        status: Option<ProposalStatus>,
+        proposed_by: Option<i32>,
+    ) -> Result<Vec<AbilityProposal>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                &format!(
+                    "SELECT {} FROM ability_proposal p
+                     JOIN ability a ON a.ability_id = p.ability_id
+                     WHERE ($1::text IS NULL OR p.status = $1)
+                       AND ($2::int IS NULL OR p.proposed_by = $2)
+                     ORDER BY p.proposed_at, p.proposal_id",
+                    PROPOSAL_COLUMNS
+                ),
+                &[&status.map(ProposalStatus::as_str), &proposed_by],
+            )
+            .await?;
+
+        Ok(rows.iter().map(proposal_from_row).collect())
+    }
+
+    async fn review(
+        &self,
+        proposal_id: i32,
+        reviewer: i32,
+        approve: bool,
+        note: Option<&str>,
+    ) -> Result<Review, DbError> {
+        let status = if approve {
+            ProposalStatus::Approved
+        } else {
+            ProposalStatus::Rejected
+        };
+        let note = note.map(str::to_string);
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                // Locks the proposal, so that two admins can't both review it.
+                let rows = tx
+                    .query(
+                        "SELECT status FROM ability_proposal WHERE proposal_id = $1 FOR UPDATE",
+                        &[&proposal_id],
+                    )
+                    .await?;
+                let Some(r) = rows.first() else {
+                    return Ok(Review::NotFound);
+                };
+                let current = ProposalStatus::from_db(r.get(0));
+                if current != ProposalStatus::Pending {
+                    return Ok(Review::AlreadyReviewed(current));
+                }
+
+                tx.execute(
+                    "UPDATE ability_proposal
+                     SET status = $2, reviewed_by = $3, reviewed_at = now(), review_note = $4
+                     WHERE proposal_id = $1",
+                    &[&proposal_id, &status.as_str(), &reviewer, &note],
+                )
+                .await?;
+                if approve {
+                    tx.execute(
+                        "WITH applied AS (
+                             UPDATE ability a SET damage = p.damage, status_effect = p.status_effect
+                             FROM ability_proposal p
+                             WHERE p.proposal_id = $1 AND a.ability_id = p.ability_id
+                             RETURNING a.ability_id
+                         )
+                         DELETE FROM ability_changes
+                         WHERE ability_id = (SELECT ability_id FROM applied)",
+                        &[&proposal_id],
+                    )
+                    .await?;
+                }
+                let rows = tx
+                    .query(
+                        &format!(
+                            "SELECT {} FROM ability_proposal p
+                             JOIN ability a ON a.ability_id = p.ability_id
+                             WHERE p.proposal_id = $1",
+                            PROPOSAL_COLUMNS
+                        ),
+                        &[&proposal_id],
+                    )
+                    .await?;
+
+                Ok(Review::Reviewed(proposal_from_row(&rows[0])))
+            })
+        })
+        .await
+    }
+}
@@ -0,0 +1,374 @@
+use super::in_transaction;
+use crate::ai::{Difficulty, Strategy};
+use crate::battle::{Fighter, Move, Turn};
+use crate::db::{Db, DbError, DbTransaction};
+use crate::models::CatalogSnapshot;
+use axum::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_postgres::types::Json;
+
+/// Battles, the computer-controlled trainers they are fought against, and
+/// the catalog snapshots they may be fought with.
+#[async_trait]
+pub(crate) trait BattleRepository: Send + Sync {
+    /// The pokemon with their types and abilities, in `ids` order, as they
+    /// are now or, given `snapshot_id`, as that snapshot pinned them. Those
+    /// missing or unpublished, or all of them if the snapshot is missing,
+    /// are left out.
+    async fn fighters(
+        &self,
+        snapshot_id: Option<i32>,
+        ids: &[i32],
+    ) -> Result<Vec<Fighter>, DbError>;
+
+    /// Computer-controlled trainer `trainer_id`, or `None` if there is no
+    /// such trainer or people play it.
+    async fn ai_trainer(&self, trainer_id: i32) -> Result<Option<AiTrainer>, DbError>;
+
+    /// Stores the battle and credits its reward to the trainer, unless the
+    /// trainer already fought `per_hour` battles within the last hour. Zero
+    /// lifts the quota. The check and the write happen in one transaction
+    /// that holds the trainer's row, so concurrent battles of the same
+    /// trainer can't all pass the check at once, and a battle is never
+    /// stored without its reward or credited twice.
+    async fn store(&self, battle: NewBattle, per_hour: usize) -> Result<StoreBattle, DbError>;
+
+    async fn battle(&self, battle_id: i32) -> Result<Option<StoredBattle>, DbError>;
+
+    /// Pins every published pokemon as it is now.
+    async fn take_snapshot(&self) -> Result<CatalogSnapshot, DbError>;
+
+    async fn snapshot(&self, snapshot_id: i32) -> Result<Option<CatalogSnapshot>, DbError>;
+}
+
+/// A computer-controlled trainer. The strategy and difficulty are `None`
+/// when the stored name isn't one this server knows.
+pub(crate) struct AiTrainer {
+    pub(crate) strategy: Option<Strategy>,
+    pub(crate) difficulty: Option<Difficulty>,
+    pub(crate) team: Vec<i32>,
+}
+
+/// A battle about to be stored.
+pub(crate) struct NewBattle {
+    /// The trainer who asked for the battle.
+    pub(crate) trainer_id: i32,
+    pub(crate) seed: u64,
+    pub(crate) snapshot_id: Option<i32>,
+    pub(crate) ai_trainer_id: Option<i32>,
+    pub(crate) ai_strategy: Option<Strategy>,
+    pub(crate) ai_difficulty: Option<Difficulty>,
+    pub(crate) reward: Option<i32>,
+    pub(crate) fighters: [Fighter; 2],
+    pub(crate) winner: Option<i32>,
+    pub(crate) turns: Vec<Turn>,
+}
+
+pub(crate) enum StoreBattle {
+    Stored(i32),
+    /// The quota is used up until the oldest battle counting towards it is
+    /// this much older.
+    QuotaUsedUp(Duration),
+}
+
+/// A battle as stored, for replaying it. Fields that no longer parse, as
+/// after the stored battle was altered, are `None`.
+pub(crate) struct StoredBattle {
+    pub(crate) seed: u64,
+    pub(crate) fighters: Option<[Fighter; 2]>,
+    pub(crate) winner: Option<i32>,
+    pub(crate) turns: Vec<serde_json::Value>,
+    /// `None` for a battle without an AI trainer.
+    pub(crate) ai_strategy: Option<String>,
+    /// `None` for a battle without an AI trainer, or one fought before
+    /// difficulties.
+    pub(crate) ai_difficulty: Option<String>,
+}
+
+pub(crate) struct PgBattleRepository {
+    db: Arc<Db>,
+}
+
+impl PgBattleRepository {
+    pub(crate) fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+}
+
+/// Loads the pokemon with their types and abilities from the live catalog,
+/// in `ids` order.
+async fn live_fighters(db: &DbTransaction<'_>, ids: &[i32]) -> Result<Vec<Fighter>, DbError> {
+    let rows = db
+        .query(
+            "SELECT p.pokemon_id,
+                    COALESCE(array_agg(a.attribute_name ORDER BY a.attribute_id)
+                             FILTER (WHERE a.attribute_id IS NOT NULL), '{}'),
+                    COALESCE(array_agg(a.weakness ORDER BY a.attribute_id)
+                             FILTER (WHERE a.attribute_id IS NOT NULL), '{}')
+             FROM published_pokemon p
+             LEFT JOIN pokemonattributes pat ON pat.pokemon_id = p.pokemon_id
+             LEFT JOIN attribute a ON a.attribute_id = pat.attribute_id
+             WHERE p.pokemon_id = ANY($1)
+             GROUP BY p.pokemon_id",
+            &[&ids],
+        )
+        .await?;
+    let moves = db
+        .query(
+            "SELECT pab.pokemon_id, ab.name, ab.damage, ab.status_effect
+             FROM pokemonabilities pab
+             JOIN published_ability ab ON ab.ability_id = pab.ability_id
+             WHERE pab.pokemon_id = ANY($1)
+             ORDER BY ab.ability_id",
+            &[&ids],
+        )
+        .await?;
+
+    let fighter = |pokemon_id: i32| {
+        let r = rows.iter().find(|r| r.get::<_, i32>(0) == pokemon_id)?;
+        Some(Fighter {
+            pokemon_id,
+            types: r.get(1),
+            weaknesses: r.get(2),
+            moves: moves
+                .iter()
+                .filter(|m| m.get::<_, i32>(0) == pokemon_id)
+                .map(|m| Move {
+                    name: m.get(1),
+                    damage: m.get(2),
+                    status_effect: m.get(3),
+                })
+                .collect(),
+        })
+    };
+
+    Ok(ids.iter().filter_map(|&id| fighter(id)).collect())
+}
+
+/// Like `live_fighters`, but as snapshot `snapshot_id` pinned them.
+async fn snapshot_fighters(
+    db: &DbTransaction<'_>,
+    snapshot_id: i32,
+    ids: &[i32],
+) -> Result<Vec<Fighter>, DbError> {
+    let rows = db
+        .query(
+            "SELECT pokemon_id, types, weaknesses, moves FROM snapshot_fighter
+             WHERE snapshot_id = $1 AND pokemon_id = ANY($2)",
+            &[&snapshot_id, &ids],
+        )
+        .await?;
+
+    let fighter = |pokemon_id: i32| {
+        let r = rows.iter().find(|r| r.get::<_, i32>(0) == pokemon_id)?;
+        Some(Fighter {
+            pokemon_id,
+            types: r.get(1),
+            weaknesses: r.get(2),
+            moves: r.get::<_, Json<Vec<Move>>>(3).0,
+        })
+    };
+
+    Ok(ids.iter().filter_map(|&id| fighter(id)).collect())
+}
+
+#[async_trait]
+impl BattleRepository for PgBattleRepository {
+    async fn fighters(
+        &self,
+        snapshot_id: Option<i32>,
+        ids: &[i32],
+    ) -> Result<Vec<Fighter>, DbError> {
+        let ids = ids.to_vec();
+        // One transaction, so the types and abilities are read as of the
+        // same moment.
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                match snapshot_id {
+                    Some(snapshot_id) => snapshot_fighters(tx, snapshot_id, &ids).await,
+                    None => live_fighters(tx, &ids).await,
+                }
+            })
+        })
+        .await
+    }
+
+    async fn ai_trainer(&self, trainer_id: i32) -> Result<Option<AiTrainer>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT t.ai_strategy, t.ai_difficulty,
+                        COALESCE(array_agg(tp.pokemon_id ORDER BY tp.pokemon_id)
+                                 FILTER (WHERE tp.pokemon_id IS NOT NULL), '{}')
+                 FROM trainer t
+                 LEFT JOIN trainerspokemon tp ON tp.trainer_id = t.trainer_id
+                 WHERE t.trainer_id = $1 AND t.ai_strategy IS NOT NULL
+                 GROUP BY t.trainer_id",
+                &[&trainer_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| AiTrainer {
+            strategy: Strategy::from_name(r.get(0)),
+            difficulty: r.get::<_, Option<&str>>(1).and_then(Difficulty::from_name),
+            team: r.get(2),
+        }))
+    }
+
+    async fn store(&self, battle: NewBattle, per_hour: usize) -> Result<StoreBattle, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                if per_hour > 0 {
+                    tx.execute(
+                        "SELECT 1 FROM trainer WHERE trainer_id = $1 FOR UPDATE",
+                        &[&battle.trainer_id],
+                    )
+                    .await?;
+                    // The battle `per_hour` back, if there is one within the
+                    // hour, is the one that has to age out before the
+                    // trainer may battle again.
+                    let rows = tx
+                        .query(
+                            "SELECT EXTRACT(EPOCH FROM fought_at + interval '1 hour' - now())::float8
+                             FROM battle
+                             WHERE trainer_id = $1 AND fought_at > now() - interval '1 hour'
+                             ORDER BY fought_at DESC
+                             OFFSET $2 LIMIT 1",
+                            &[&battle.trainer_id, &(per_hour as i64 - 1)],
+                        )
+                        .await?;
+                    if let Some(r) = rows.first() {
+                        return Ok(StoreBattle::QuotaUsedUp(Duration::from_secs_f64(
+                            r.get::<_, f64>(0).max(0.0),
+                        )));
+                    }
+                }
+
+                let [a, b] = &battle.fighters;
+                let rows = tx
+                    .query(
+                        "WITH stored AS (
+                             INSERT INTO battle
+                                 (trainer_id, pokemon_id, opponent_id, seed, snapshot_id,
+                                  ai_trainer_id, ai_strategy, ai_difficulty, reward, fighters,
+                                  winner, turns)
+                             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                             RETURNING battle_id, trainer_id, reward
+                         ), credited AS (
+                             UPDATE trainer t SET rewards = t.rewards + s.reward
+                             FROM stored s
+                             WHERE t.trainer_id = s.trainer_id AND s.reward > 0
+                         )
+                         SELECT battle_id FROM stored",
+                        &[
+                            &battle.trainer_id,
+                            &a.pokemon_id,
+                            &b.pokemon_id,
+                            &(battle.seed as i64),
+                            &battle.snapshot_id,
+                            &battle.ai_trainer_id,
+                            &battle.ai_strategy.map(Strategy::name),
+                            &battle.ai_difficulty.map(Difficulty::name),
+                            &battle.reward,
+                            &Json(&battle.fighters),
+                            &battle.winner,
+                            &Json(&battle.turns),
+                        ],
+                    )
+                    .await?;
+
+                Ok(StoreBattle::Stored(rows[0].get(0)))
+            })
+        })
+        .await
+    }
+
+    async fn battle(&self, battle_id: i32) -> Result<Option<StoredBattle>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT seed, fighters, winner, turns, ai_strategy, ai_difficulty
+                 FROM battle WHERE battle_id = $1",
+                &[&battle_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| StoredBattle {
+            seed: r.get::<_, i64>(0) as u64,
+            fighters: r.try_get::<_, Json<[Fighter; 2]>>(1).ok().map(|f| f.0),
+            winner: r.get(2),
+            turns: r.get::<_, Json<Vec<serde_json::Value>>>(3).0,
+            ai_strategy: r.get(4),
+            ai_difficulty: r.get(5),
+        }))
+    }
+
+    async fn take_snapshot(&self) -> Result<CatalogSnapshot, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                let row = tx
+                    .query_one(
+                        "INSERT INTO catalog_snapshot DEFAULT VALUES
+                         RETURNING snapshot_id, created_at",
+                        &[],
+                    )
+                    .await?;
+                let snapshot_id: i32 = row.get(0);
+                // A single statement, so every pokemon is pinned as of the
+                // same moment even if a rebalance commits while it runs.
+                let pokemon = tx
+                    .execute(
+                        "INSERT INTO snapshot_fighter
+                             (snapshot_id, pokemon_id, types, weaknesses, moves)
+                         SELECT $1, p.pokemon_id,
+                                COALESCE(array_agg(a.attribute_name ORDER BY a.attribute_id)
+                                         FILTER (WHERE a.attribute_id IS NOT NULL), '{}'),
+                                COALESCE(array_agg(a.weakness ORDER BY a.attribute_id)
+                                         FILTER (WHERE a.attribute_id IS NOT NULL), '{}'),
+                                COALESCE((SELECT jsonb_agg(jsonb_build_object(
+                                                      'name', ab.name,
+                                                      'damage', ab.damage,
+                                                      'status_effect', ab.status_effect)
+                                                  ORDER BY ab.ability_id)
+                                          FROM pokemonabilities pab
+                                          JOIN published_ability ab
+                                              ON ab.ability_id = pab.ability_id
+                                          WHERE pab.pokemon_id = p.pokemon_id), '[]')
+                         FROM published_pokemon p
+                         LEFT JOIN pokemonattributes pat ON pat.pokemon_id = p.pokemon_id
+                         LEFT JOIN attribute a ON a.attribute_id = pat.attribute_id
+                         GROUP BY p.pokemon_id",
+                        &[&snapshot_id],
+                    )
+                    .await?;
+
+                Ok(CatalogSnapshot {
+                    snapshot_id,
+                    created_at: row.get(1),
+                    pokemon: pokemon as i64,
+                })
+            })
+        })
+        .await
+    }
+
+    async fn snapshot(&self, snapshot_id: i32) -> Result<Option<CatalogSnapshot>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT s.snapshot_id, s.created_at,
+                        (SELECT count(*) FROM snapshot_fighter f
+                         WHERE f.snapshot_id = s.snapshot_id)
+                 FROM catalog_snapshot s WHERE s.snapshot_id = $1",
+                &[&snapshot_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| CatalogSnapshot {
+            snapshot_id: r.get(0),
+            created_at: r.get(1),
+            pokemon: r.get(2),
+        }))
+    }
+}
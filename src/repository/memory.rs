@@ -0,0 +1,2051 @@
+use super::ability::PublishedAbilities;
+use super::battle::StoredBattle;
+use super::quiz::{AskedQuestion, NewQuestion};
+use super::trainer::Account;
+use super::{AbilityRepository, PokemonRepository, QuizRepository, SetTags};
+use super::{
+    AddBookmark, AddReference, AiTrainer, Attach, BattleRepository, CreateAi, Delete, Grouping,
+    NewBattle, NewPokemon, Review, StoreBattle, TrainerChanges, Update,
+};
+use super::{TrainerRepository, UserRepository};
+use crate::ai::{Difficulty, Strategy};
+use crate::battle::{Fighter, Move};
+use crate::db::DbError;
+use crate::models::{
+    generation_of, regions_in_generation, slugify, Ability, AbilityProposal, AbilityTranslation,
+    Bookmark, CatalogEntry, CatalogKind, CatalogSnapshot, Description, EntityKey,
+    ExternalReference, IdOrUuid, Ivs, Metadata, NewReference, OwnedPokemon, PageParams, PastName,
+    Pokemon, PokemonFilter, PokemonFull, ProposalStatus, QuizScore, RecentView, ReferenceKind,
+    RegionRef, SearchHit, SortParams, TagStats, TaggedAbility, TotalCount, Trainer,
+    VariantExposures, Version,
+};
+use axum::async_trait;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Trainers, pokemon, regions and abilities kept in memory, for driving
+/// handlers in tests without a database. Serves as every repository, so
+/// that teams see the pokemon the catalog holds.
+///
+/// Every write goes through one lock, which stands in for the transactions
+/// of the Postgres repositories. Unique constraints aren't enforced, but
+/// slugs are suffixed on collision as they are there.
+#[derive(Default)]
+pub(crate) struct InMemoryRepository {
+    store: Mutex<Store>,
+    /// Calls to `teams`, so tests can tell batched loads from one per
+    /// trainer.
+    team_loads: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Store {
+    next_id: i32,
+    regions: BTreeMap<i32, String>,
+    abilities: BTreeMap<i32, StoredAbility>,
+    /// Damage, status effect and publish time of each staged rebalance.
+    ability_changes: BTreeMap<i32, (i32, String, OffsetDateTime)>,
+    pokemon: BTreeMap<i32, StoredPokemon>,
+    trainers: BTreeMap<i32, StoredTrainer>,
+    /// Trainer and admin flag of each user.
+    users: BTreeMap<i32, (Option<i32>, bool)>,
+    /// Of the users created through `UserRepository::create`.
+    usernames: BTreeMap<i32, String>,
+    /// One for each pokemon on a team, kept in step by `sync_instances`.
+    instances: BTreeMap<i32, StoredInstance>,
+    /// Name and kind of each tag.
+    tags: BTreeMap<i32, (String, String)>,
+    /// Ids of the tags on each ability.
+    ability_tags: BTreeMap<i32, Vec<i32>>,
+    /// Trainer, name and release time of every name given up, oldest first.
+    past_names: Vec<(i32, String, OffsetDateTime)>,
+    /// Name and status effect of each ability in each locale.
+    ability_translations: BTreeMap<(i32, String), (String, String)>,
+    /// Of the pokemon and abilities that have one.
+    descriptions: BTreeMap<i32, Description>,
+    /// Pokemon or ability, kind and value of each external reference.
+    references: BTreeMap<i32, (i32, ReferenceKind, String)>,
+    /// Of each pokemon and ability, oldest first.
+    versions: BTreeMap<i32, Vec<Version>>,
+    /// User, kind, entity and time of each recent view, oldest first.
+    recent_views: Vec<(i32, CatalogKind, i32, OffsetDateTime)>,
+    /// User, kind, entity and time of each bookmark, oldest first.
+    bookmarks: Vec<(i32, CatalogKind, i32, OffsetDateTime)>,
+    proposals: BTreeMap<i32, AbilityProposal>,
+    /// Times each user was shown each variant of each experiment.
+    exposures: BTreeMap<(i32, String, String), i64>,
+    battles: BTreeMap<i32, StoredBattleRow>,
+    /// Creation time and fighters of each catalog snapshot.
+    snapshots: BTreeMap<i32, (OffsetDateTime, Vec<Fighter>)>,
+    /// Trainer, pokemon and whether it was answered, of each quiz question.
+    quiz_questions: BTreeMap<i32, (i32, i32, bool)>,
+    /// Right answers and answers of each trainer who answered a question.
+    quiz_scores: BTreeMap<i32, (i32, i32)>,
+}
+
+struct StoredBattleRow {
+    trainer_id: i32,
+    fought_at: OffsetDateTime,
+    seed: u64,
+    /// As JSON, like the `fighters` and `turns` columns.
+    fighters: serde_json::Value,
+    winner: Option<i32>,
+    turns: Vec<serde_json::Value>,
+    ai_strategy: Option<Strategy>,
+    ai_difficulty: Option<Difficulty>,
+}
+
+struct StoredInstance {
+    trainer_id: i32,
+    pokemon_id: i32,
+    nickname: Option<String>,
+}
+
+struct StoredPokemon {
+    name: String,
+    slug: String,
+    region_id: i32,
+    publish_at: Option<OffsetDateTime>,
+    abilities: Vec<i32>,
+    metadata: Metadata,
+}
+
+struct StoredAbility {
+    name: String,
+    damage: i32,
+    status_effect: String,
+    publish_at: Option<OffsetDateTime>,
+}
+
+struct StoredTrainer {
+    name: String,
+    gym_leader: bool,
+    team: Vec<i32>,
+    rewards: i32,
+    metadata: Metadata,
+    /// Strategy and difficulty of a computer-controlled trainer.
+    ai: Option<(Strategy, Difficulty)>,
+}
+
+impl Store {
+    fn next_id(&mut self) -> i32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn is_published(&self, id: i32) -> bool {
+        self.pokemon.get(&id).is_some_and(|p| {
+            p.publish_at
+                .is_none_or(|at| at <= OffsetDateTime::now_utc())
+        })
+    }
+
+    fn pokemon(&self, id: i32) -> Option<Pokemon> {
+        self.is_published(id).then(|| self.stored_pokemon(id))
+    }
+
+    fn ability(&self, id: i32) -> Option<Ability> {
+        let a = self.abilities.get(&id)?;
+        a.publish_at
+            .is_none_or(|at| at <= OffsetDateTime::now_utc())
+            .then(|| Ability {
+                ability_id: id,
+                name: a.name.clone(),
+                damage: a.damage,
+                status_effect: a.status_effect.clone(),
+            })
+    }
+
+    /// The id of the pokemon `key` names, published or not.
+    fn pokemon_id(&self, key: &EntityKey) -> Option<i32> {
+        match key {
+            EntityKey::Id(id) => Some(*id),
+            EntityKey::Uuid(uuid) => id_of(*uuid),
+            EntityKey::Slug(slug) => self
+                .pokemon
+                .iter()
+                .find(|(_, p)| &p.slug == slug)
+                .map(|(&id, _)| id),
+        }
+        .filter(|id| self.pokemon.contains_key(id))
+    }
+
+    /// The id of the ability named `name`, published or not.
+    fn ability_id(&self, name: &str) -> Option<i32> {
+        self.abilities
+            .iter()
+            .find(|(_, a)| a.name == name)
+            .map(|(&id, _)| id)
+    }
+
+    /// The columns a version of the pokemon or ability keeps, as the
+    /// Postgres repositories snapshot them.
+    fn snapshot(&self, id: i32) -> Option<Metadata> {
+        let description = self.descriptions.get(&id).cloned().unwrap_or_default();
+        let snapshot = if let Some(p) = self.pokemon.get(&id) {
+            json!({
+                "name": p.name,
+                "region_id": p.region_id,
+                "metadata": p.metadata,
+                "description": description.description,
+                "lore": description.lore,
+            })
+        } else {
+            let a = self.abilities.get(&id)?;
+            json!({
+                "name": a.name,
+                "damage": a.damage,
+                "status_effect": a.status_effect,
+                "description": description.description,
+                "lore": description.lore,
+            })
+        };
+
+        snapshot.as_object().cloned()
+    }
+
+    fn record_version(&mut self, id: i32) -> Option<Version> {
+        let snapshot = self.snapshot(id)?;
+        let versions = self.versions.entry(id).or_default();
+        if versions.last().is_none_or(|v| v.snapshot != snapshot) {
+            versions.push(Version {
+                version: versions.len() as i32 + 1,
+                snapshot,
+                recorded_at: OffsetDateTime::now_utc(),
+            });
+        }
+
+        versions.last().cloned()
+    }
+
+    /// The pokemon or ability, if it is published.
+    fn entry(&self, kind: CatalogKind, id: i32) -> Option<CatalogEntry> {
+        match kind {
+            CatalogKind::Pokemon => self.pokemon(id).map(CatalogEntry::Pokemon),
+            CatalogKind::Ability => self.ability(id).map(CatalogEntry::Ability),
+        }
+    }
+
+    /// The references of the pokemon or ability `owner`, a PokeAPI
+    /// `resource`.
+    fn references_of(&self, owner: i32, resource: &str) -> Vec<ExternalReference> {
+        self.references
+            .iter()
+            .filter(|(_, (o, _, _))| *o == owner)
+            .map(|(&id, (_, kind, value))| {
+                ExternalReference::new(id, *kind, value.clone(), resource)
+            })
+            .collect()
+    }
+
+    fn add_reference(
+        &mut self,
+        owner: Option<i32>,
+        reference: &NewReference,
+        resource: &str,
+    ) -> AddReference {
+        let Some(owner) = owner else {
+            return AddReference::NotFound;
+        };
+        let duplicate = self
+            .references
+            .values()
+            .any(|r| *r == (owner, reference.kind, reference.value.clone()));
+        if duplicate {
+            return AddReference::Duplicate;
+        }
+
+        let id = self.next_id();
+        self.references
+            .insert(id, (owner, reference.kind, reference.value.clone()));
+        AddReference::Added(
+            ExternalReference::new(id, reference.kind, reference.value.clone(), resource),
+            owner,
+        )
+    }
+
+    fn delete_reference(&mut self, owner: Option<i32>, reference_id: i32) -> Option<i32> {
+        let owner = owner?;
+        if self.references.get(&reference_id)?.0 != owner {
+            return None;
+        }
+        self.references.remove(&reference_id);
+
+        Some(owner)
+    }
+
+    /// The pokemon with `id`, published or not.
+    fn stored_pokemon(&self, id: i32) -> Pokemon {
+        let p = &self.pokemon[&id];
+        Pokemon {
+            pokemon_id: id,
+            uuid: uuid_of(id),
+            name: p.name.clone(),
+            slug: p.slug.clone(),
+            region: self.regions[&p.region_id].clone(),
+            metadata: p.metadata.clone(),
+        }
+    }
+
+    fn pokemon_full(&self, id: i32) -> Option<PokemonFull> {
+        let pokemon = self.pokemon(id)?;
+        let abilities = self.pokemon[&id]
+            .abilities
+            .iter()
+            .filter_map(|&ability_id| self.ability(ability_id))
+            .collect();
+
+        Some(PokemonFull {
+            pokemon_id: id,
+            uuid: pokemon.uuid,
+            generation: generation_of(&pokemon.region),
+            name: pokemon.name,
+            slug: pokemon.slug,
+            region: pokemon.region,
+            abilities,
+            attributes: Vec::new(),
+            metadata: pokemon.metadata,
+        })
+    }
+
+    fn trainer(&self, id: i32, with_pokemon: bool) -> Option<Trainer> {
+        let t = self.trainers.get(&id)?;
+        Some(Trainer {
+            trainer_id: id,
+            uuid: uuid_of(id),
+            name: t.name.clone(),
+            gym_leader: t.gym_leader,
+            rewards: t.rewards,
+            metadata: t.metadata.clone(),
+            pokemon: with_pokemon.then(|| self.team(t)),
+        })
+    }
+
+    fn team(&self, trainer: &StoredTrainer) -> Vec<Pokemon> {
+        let mut ids = trainer.team.clone();
+        ids.sort_unstable();
+        ids.iter().filter_map(|&id| self.pokemon(id)).collect()
+    }
+
+    /// Drops the instances of released pokemon and adds ones for those just
+    /// caught, so that kept pokemon stay the same instances.
+    fn sync_instances(&mut self) {
+        let trainers = &self.trainers;
+        self.instances.retain(|_, i| {
+            trainers
+                .get(&i.trainer_id)
+                .is_some_and(|t| t.team.contains(&i.pokemon_id))
+        });
+
+        let caught: Vec<(i32, i32)> = trainers
+            .iter()
+            .flat_map(|(&trainer_id, t)| t.team.iter().map(move |&p| (trainer_id, p)))
+            .filter(|&(trainer_id, pokemon_id)| {
+                !self
+                    .instances
+                    .values()
+                    .any(|i| i.trainer_id == trainer_id && i.pokemon_id == pokemon_id)
+            })
+            .collect();
+        for (trainer_id, pokemon_id) in caught {
+            let id = self.next_id();
+            self.instances.insert(
+                id,
+                StoredInstance {
+                    trainer_id,
+                    pokemon_id,
+                    nickname: None,
+                },
+            );
+        }
+    }
+
+    /// Always level 5 with no IVs, where Postgres rolls them.
+    fn owned(&self, instance_id: i32) -> Option<OwnedPokemon> {
+        let i = self.instances.get(&instance_id)?;
+        Some(OwnedPokemon {
+            instance_id,
+            trainer_id: i.trainer_id,
+            species: self.pokemon(i.pokemon_id)?,
+            nickname: i.nickname.clone(),
+            level: 5,
+            ivs: Ivs::default(),
+        })
+    }
+
+    fn unknown_pokemon(&self, ids: &[i32]) -> Vec<i32> {
+        let mut unknown: Vec<i32> = ids
+            .iter()
+            .copied()
+            .filter(|id| !self.pokemon.contains_key(id))
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        unknown
+    }
+
+    /// Whether a trainer other than `trainer_id` has `name`, or gave it up
+    /// in the last 30 days, as the `trainer_name_taken` trigger checks.
+    fn name_taken(&self, name: &str, trainer_id: Option<i32>) -> bool {
+        let name = name.to_lowercase();
+        let other = |id: i32| Some(id) != trainer_id;
+        let cutoff = OffsetDateTime::now_utc() - time::Duration::days(30);
+        self.trainers
+            .iter()
+            .any(|(&id, t)| other(id) && t.name.to_lowercase() == name)
+            || self
+                .past_names
+                .iter()
+                .any(|(id, n, at)| other(*id) && n.to_lowercase() == name && *at > cutoff)
+    }
+
+    fn insert_trainer(&mut self, name: &str, gym_leader: bool, team: Vec<i32>) -> i32 {
+        let id = self.next_id();
+        self.trainers.insert(
+            id,
+            StoredTrainer {
+                name: name.to_string(),
+                gym_leader,
+                team,
+                rewards: 0,
+                metadata: Metadata::new(),
+                ai: None,
+            },
+        );
+        self.sync_instances();
+        id
+    }
+
+    fn insert_pokemon(&mut self, pokemon: NewPokemon) -> i32 {
+        let base = slugify(&pokemon.name);
+        let mut slug = base.clone();
+        for n in 2.. {
+            if self.pokemon.values().all(|p| p.slug != slug) {
+                break;
+            }
+            slug = format!("{}-{}", base, n);
+        }
+
+        let id = self.next_id();
+        self.pokemon.insert(
+            id,
+            StoredPokemon {
+                name: pokemon.name,
+                slug,
+                region_id: pokemon.region_id,
+                publish_at: pokemon.publish_at,
+                abilities: pokemon.abilities,
+                metadata: pokemon.metadata,
+            },
+        );
+        id
+    }
+}
+
+/// The UUID of the trainer or pokemon with `id`. Ids are unique across
+/// every kind of entity here, so the UUIDs are too.
+fn uuid_of(id: i32) -> Uuid {
+    Uuid::from_u128(id as u128)
+}
+
+fn id_of(uuid: Uuid) -> Option<i32> {
+    i32::try_from(uuid.as_u128()).ok()
+}
+
+/// How well `name` and `description` match every word of `query`, or `None`
+/// if one of the words is in neither. Weighted like the `search` columns.
+fn search_rank(query: &str, name: &str, description: Option<&Description>) -> Option<f32> {
+    let lower = |text: Option<&String>| text.map(|t| t.to_lowercase()).unwrap_or_default();
+    let fields = [
+        (name.to_lowercase(), 1.0),
+        (lower(description.and_then(|d| d.description.as_ref())), 0.4),
+        (lower(description.and_then(|d| d.lore.as_ref())), 0.2),
+    ];
+
+    let mut rank = 0.0;
+    for word in query.to_lowercase().split_whitespace() {
+        let weight = fields
+            .iter()
+            .filter(|(text, _)| text.contains(word))
+            .map(|(_, weight)| weight)
+            .sum::<f32>();
+        if weight == 0.0 {
+            return None;
+        }
+        rank += weight;
+    }
+
+    Some(rank)
+}
+
+/// Hits by descending rank, then id, cut to `limit`.
+fn best_hits(mut hits: Vec<SearchHit>, limit: i64) -> Vec<SearchHit> {
+    hits.sort_by(|a, b| b.rank.total_cmp(&a.rank).then(a.id.cmp(&b.id)));
+    hits.truncate(usize::try_from(limit).unwrap_or_default());
+
+    hits
+}
+
+/// Whether `metadata` has every key of `filter` with the same value, as
+/// `@>` sees it for the flat objects filters are.
+fn contains(metadata: &Metadata, filter: &Metadata) -> bool {
+    filter
+        .iter()
+        .all(|(key, value)| metadata.get(key) == Some(value))
+}
+
+/// One page of `items`, already in order, and their count.
+fn page<T>(items: Vec<T>, paging: &PageParams) -> (Vec<T>, TotalCount) {
+    let total = items.len() as i64;
+    let page = items
+        .into_iter()
+        .skip(paging.offset() as usize)
+        .take(paging.per_page() as usize)
+        .collect();
+
+    (
+        page,
+        TotalCount {
+            value: Some(total),
+            approximate: false,
+        },
+    )
+}
+
+impl InMemoryRepository {
+    pub(crate) fn add_region(&self, name: &str) -> i32 {
+        let mut store = self.store.lock().unwrap();
+        let id = store.next_id();
+        store.regions.insert(id, name.to_string());
+        id
+    }
+
+    pub(crate) fn add_ability(&self, name: &str, damage: i32, status_effect: &str) -> i32 {
+        let mut store = self.store.lock().unwrap();
+        let id = store.next_id();
+        store.abilities.insert(
+            id,
+            StoredAbility {
+                name: name.to_string(),
+                damage,
+                status_effect: status_effect.to_string(),
+                publish_at: None,
+            },
+        );
+        id
+    }
+
+    pub(crate) fn add_tag(&self, name: &str, kind: &str) -> i32 {
+        let mut store = self.store.lock().unwrap();
+        let id = store.next_id();
+        store.tags.insert(id, (name.to_string(), kind.to_string()));
+        id
+    }
+
+    /// The ability as the public sees it, if it is published.
+    pub(crate) fn ability(&self, id: i32) -> Option<Ability> {
+        self.store.lock().unwrap().ability(id)
+    }
+
+    pub(crate) fn add_pokemon(&self, name: &str, region_id: i32, abilities: &[i32]) -> i32 {
+        self.store.lock().unwrap().insert_pokemon(NewPokemon {
+            name: name.to_string(),
+            region_id,
+            publish_at: None,
+            abilities: abilities.to_vec(),
+            metadata: Metadata::new(),
+        })
+    }
+
+    pub(crate) fn add_trainer(&self, name: &str, team: &[i32]) -> i32 {
+        self.store
+            .lock()
+            .unwrap()
+            .insert_trainer(name, false, team.to_vec())
+    }
+
+    /// Credits `rewards` to the trainer, as beating an AI trainer would.
+    pub(crate) fn add_rewards(&self, trainer_id: i32, rewards: i32) {
+        let mut store = self.store.lock().unwrap();
+        store.trainers.get_mut(&trainer_id).unwrap().rewards += rewards;
+    }
+
+    /// Adds a user playing as `trainer_id` and returns their id.
+    pub(crate) fn add_user(&self, trainer_id: Option<i32>, is_admin: bool) -> i32 {
+        let mut store = self.store.lock().unwrap();
+        let id = store.next_id();
+        store.users.insert(id, (trainer_id, is_admin));
+        id
+    }
+
+    /// Edits one turn of a stored battle in place, as tampering with its row
+    /// would.
+    pub(crate) fn tamper_with_battle(
+        &self,
+        battle_id: i32,
+        turn: usize,
+        edit: impl FnOnce(&mut serde_json::Value),
+    ) {
+        let mut store = self.store.lock().unwrap();
+        edit(&mut store.battles.get_mut(&battle_id).unwrap().turns[turn]);
+    }
+
+    /// Ids of the trainer's pokemon, published or not, in id order.
+    pub(crate) fn team_of(&self, trainer_id: i32) -> Option<Vec<i32>> {
+        let store = self.store.lock().unwrap();
+        let mut team = store.trainers.get(&trainer_id)?.team.clone();
+        team.sort_unstable();
+        Some(team)
+    }
+
+    /// How many times teams were loaded, however many trainers each time.
+    pub(crate) fn team_loads(&self) -> usize {
+        self.team_loads.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl TrainerRepository for InMemoryRepository {
+    async fn list(
+        &self,
+        metadata: Option<&Metadata>,
+        sort: &SortParams,
+        paging: &PageParams,
+    ) -> Result<(Vec<Trainer>, TotalCount), DbError> {
+        let store = self.store.lock().unwrap();
+        let mut trainers: Vec<Trainer> = store
+            .trainers
+            .keys()
+            .filter_map(|&id| store.trainer(id, true))
+            .filter(|t| metadata.is_none_or(|m| contains(&t.metadata, m)))
+            .collect();
+        if sort.collation.is_some() {
+            trainers.sort_by(|a, b| a.name.cmp(&b.name).then(a.trainer_id.cmp(&b.trainer_id)));
+        }
+
+        Ok(page(trainers, paging))
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Trainer>, DbError> {
+        Ok(self.store.lock().unwrap().trainer(id, false))
+    }
+
+    async fn resolve(&self, key: IdOrUuid) -> Result<Option<i32>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(match key {
+            IdOrUuid::Id(id) => Some(id),
+            IdOrUuid::Uuid(uuid) => id_of(uuid).filter(|id| store.trainers.contains_key(id)),
+        })
+    }
+
+    async fn pokemon(&self, trainer_id: i32) -> Result<Vec<Pokemon>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .trainers
+            .get(&trainer_id)
+            .map(|t| store.team(t))
+            .unwrap_or_default())
+    }
+
+    async fn teams(&self, trainer_ids: &[i32]) -> Result<HashMap<i32, Vec<PokemonFull>>, DbError> {
+        self.team_loads.fetch_add(1, Ordering::Relaxed);
+        let store = self.store.lock().unwrap();
+        Ok(trainer_ids
+            .iter()
+            .filter_map(|&id| {
+                let mut ids = store.trainers.get(&id)?.team.clone();
+                ids.sort_unstable();
+                let team: Vec<PokemonFull> = ids
+                    .iter()
+                    .filter_map(|&id| store.pokemon_full(id))
+                    .collect();
+                (!team.is_empty()).then_some((id, team))
+            })
+            .collect())
+    }
+
+    async fn create(&self, name: &str, gym_leader: bool) -> Result<i32, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if store.name_taken(name, None) {
+            return Err(DbError::NameTaken);
+        }
+
+        Ok(store.insert_trainer(name, gym_leader, Vec::new()))
+    }
+
+    async fn create_many(&self, trainers: &[(&str, bool)]) -> Result<Vec<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let mut names: Vec<String> = trainers.iter().map(|t| t.0.to_lowercase()).collect();
+        names.sort_unstable();
+        names.dedup();
+        if names.len() < trainers.len() || names.iter().any(|n| store.name_taken(n, None)) {
+            return Err(DbError::NameTaken);
+        }
+
+        Ok(trainers
+            .iter()
+            .map(|&(name, gym_leader)| store.insert_trainer(name, gym_leader, Vec::new()))
+            .collect())
+    }
+
+    async fn create_ai(
+        &self,
+        name: &str,
+        strategy: Strategy,
+        difficulty: Difficulty,
+        team: Vec<i32>,
+    ) -> Result<CreateAi, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let unknown = store.unknown_pokemon(&team);
+        if !unknown.is_empty() {
+            return Ok(CreateAi::UnknownPokemon(unknown));
+        }
+        if store.name_taken(name, None) {
+            return Err(DbError::NameTaken);
+        }
+
+        let id = store.insert_trainer(name, false, team);
+        store.trainers.get_mut(&id).unwrap().ai = Some((strategy, difficulty));
+
+        Ok(CreateAi::Created(id))
+    }
+
+    async fn update(&self, id: i32, changes: TrainerChanges) -> Result<Update, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if !store.trainers.contains_key(&id) {
+            return Ok(Update::NotFound);
+        }
+        if let Some(team) = &changes.team {
+            let unknown = store.unknown_pokemon(team);
+            if !unknown.is_empty() {
+                return Ok(Update::UnknownPokemon(unknown));
+            }
+        }
+        if let Some(name) = &changes.name {
+            if store.name_taken(name, Some(id)) {
+                return Err(DbError::NameTaken);
+            }
+        }
+
+        let trainer = store.trainers.get_mut(&id).unwrap();
+        let old_name = trainer.name.clone();
+        if let Some(name) = changes.name {
+            trainer.name = name;
+        }
+        if let Some(gym_leader) = changes.gym_leader {
+            trainer.gym_leader = gym_leader;
+        }
+        if let Some(team) = changes.team {
+            trainer.team = team;
+        }
+        if let Some(metadata) = changes.metadata {
+            trainer.metadata = metadata;
+        }
+        if trainer.name != old_name {
+            let now = OffsetDateTime::now_utc();
+            store.past_names.push((id, old_name, now));
+        }
+        store.sync_instances();
+
+        Ok(Update::Updated)
+    }
+
+    async fn delete(&self, id: i32, release_team: bool) -> Result<Delete, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(trainer) = store.trainers.get(&id) else {
+            return Ok(Delete::NotFound);
+        };
+        if !trainer.team.is_empty() && !release_team {
+            return Ok(Delete::HasTeam(trainer.team.len() as i64));
+        }
+
+        let trainer = store.trainers.remove(&id).unwrap();
+        let now = OffsetDateTime::now_utc();
+        store.past_names.push((id, trainer.name, now));
+        store.sync_instances();
+        for (trainer_id, _) in store.users.values_mut() {
+            if *trainer_id == Some(id) {
+                *trainer_id = None;
+            }
+        }
+        Ok(Delete::Deleted)
+    }
+
+    async fn exists_with_pokemon(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store.trainers.contains_key(&trainer_id) && store.pokemon.contains_key(&pokemon_id))
+    }
+
+    async fn attach(
+        &self,
+        trainer_id: i32,
+        pokemon_id: i32,
+        max_team_size: usize,
+    ) -> Result<Attach, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(trainer) = store.trainers.get_mut(&trainer_id) else {
+            return Ok(Attach::TeamFull);
+        };
+        if trainer.team.contains(&pokemon_id) {
+            return Ok(Attach::AlreadyAttached);
+        }
+        if trainer.team.len() >= max_team_size {
+            return Ok(Attach::TeamFull);
+        }
+
+        trainer.team.push(pokemon_id);
+        store.sync_instances();
+        Ok(Attach::Attached)
+    }
+
+    async fn detach(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(trainer) = store.trainers.get_mut(&trainer_id) else {
+            return Ok(false);
+        };
+        let before = trainer.team.len();
+        trainer.team.retain(|&id| id != pokemon_id);
+        let detached = trainer.team.len() < before;
+        store.sync_instances();
+
+        Ok(detached)
+    }
+
+    async fn owned(&self, trainer_id: i32) -> Result<Vec<OwnedPokemon>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .instances
+            .iter()
+            .filter(|(_, i)| i.trainer_id == trainer_id)
+            .filter_map(|(&id, _)| store.owned(id))
+            .collect())
+    }
+
+    async fn instance(&self, instance_id: i32) -> Result<Option<OwnedPokemon>, DbError> {
+        Ok(self.store.lock().unwrap().owned(instance_id))
+    }
+
+    async fn set_nickname(
+        &self,
+        instance_id: i32,
+        nickname: Option<&str>,
+    ) -> Result<bool, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(instance) = store.instances.get_mut(&instance_id) else {
+            return Ok(false);
+        };
+        instance.nickname = nickname.map(str::to_string);
+
+        Ok(true)
+    }
+
+    async fn account(&self, user_id: i32) -> Result<Option<Account>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .users
+            .get(&user_id)
+            .map(|&(trainer_id, is_admin)| Account {
+                trainer_id,
+                is_admin,
+            }))
+    }
+
+    async fn past_names(&self, trainer_id: i32) -> Result<Vec<PastName>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .past_names
+            .iter()
+            .rev()
+            .filter(|(id, _, _)| *id == trainer_id)
+            .map(|(_, name, released_at)| PastName {
+                name: name.clone(),
+                released_at: *released_at,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl PokemonRepository for InMemoryRepository {
+    async fn list(
+        &self,
+        filter: &PokemonFilter,
+        sort: &SortParams,
+        paging: &PageParams,
+    ) -> Result<(Vec<PokemonFull>, TotalCount), DbError> {
+        let store = self.store.lock().unwrap();
+        let regions = filter
+            .generation
+            .map(regions_in_generation)
+            .unwrap_or_default();
+        let lower = |s: &Option<String>| s.as_deref().map(str::to_lowercase);
+        let (name, starts_with) = (lower(&filter.name), lower(&filter.starts_with));
+        let (region, ability) = (lower(&filter.region), lower(&filter.ability));
+
+        let mut pokemon: Vec<PokemonFull> = store
+            .pokemon
+            .keys()
+            .filter_map(|&id| store.pokemon_full(id))
+            .filter(|p| {
+                let name_lower = p.name.to_lowercase();
+                let region_lower = p.region.to_lowercase();
+                (filter.generation.is_none() || regions.contains(&region_lower))
+                    && name.as_ref().is_none_or(|n| name_lower.contains(n))
+                    && starts_with
+                        .as_ref()
+                        .is_none_or(|s| name_lower.starts_with(s))
+                    && region.as_ref().is_none_or(|r| &region_lower == r)
+                    && ability
+                        .as_ref()
+                        .is_none_or(|a| p.abilities.iter().any(|x| &x.name.to_lowercase() == a))
+                    && filter
+                        .metadata
+                        .as_ref()
+                        .is_none_or(|m| contains(&p.metadata, m))
+            })
+            .collect();
+        if sort.collation.is_some() {
+            pokemon.sort_by(|a, b| a.name.cmp(&b.name).then(a.pokemon_id.cmp(&b.pokemon_id)));
+        }
+
+        Ok(page(pokemon, paging))
+    }
+
+    async fn find(&self, key: &EntityKey) -> Result<Option<Pokemon>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(match key {
+            EntityKey::Id(id) => store.pokemon(*id),
+            EntityKey::Uuid(uuid) => id_of(*uuid).and_then(|id| store.pokemon(id)),
+            EntityKey::Slug(slug) => store
+                .pokemon
+                .keys()
+                .filter_map(|&id| store.pokemon(id))
+                .find(|p| &p.slug == slug),
+        })
+    }
+
+    async fn resolve_region(&self, region: &RegionRef) -> Result<Option<(i32, String)>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .regions
+            .iter()
+            .find(|(&id, name)| match region {
+                RegionRef::Id(wanted) => id == *wanted,
+                RegionRef::Name(wanted) => *name == wanted,
+            })
+            .map(|(&id, name)| (id, name.clone())))
+    }
+
+    async fn count_by_region(&self, regions: &[String]) -> Result<Vec<(String, i64)>, DbError> {
+        let store = self.store.lock().unwrap();
+        let mut counts: Vec<(String, i64)> = store
+            .regions
+            .iter()
+            .filter(|(_, name)| regions.contains(&name.to_lowercase()))
+            .map(|(&region_id, name)| {
+                let count = store
+                    .pokemon
+                    .iter()
+                    .filter(|(&id, p)| p.region_id == region_id && store.is_published(id))
+                    .count();
+                (name.clone(), count as i64)
+            })
+            .collect();
+        counts.sort();
+
+        Ok(counts)
+    }
+
+    async fn group_counts(&self, grouping: Grouping) -> Result<Vec<(String, i64)>, DbError> {
+        let store = self.store.lock().unwrap();
+        let published = store
+            .pokemon
+            .iter()
+            .filter(|(&id, _)| store.is_published(id))
+            .map(|(_, p)| p);
+        let counts = match grouping {
+            Grouping::Region => {
+                let mut counts: BTreeMap<String, i64> = BTreeMap::new();
+                for p in published {
+                    *counts
+                        .entry(store.regions[&p.region_id].clone())
+                        .or_default() += 1;
+                }
+                counts.into_iter().collect()
+            }
+            Grouping::AbilityCount => {
+                let mut counts: BTreeMap<usize, i64> = BTreeMap::new();
+                for p in published {
+                    *counts.entry(p.abilities.len()).or_default() += 1;
+                }
+                counts
+                    .into_iter()
+                    .map(|(n, count)| (n.to_string(), count))
+                    .collect()
+            }
+            Grouping::Damage(bucket) => {
+                let mut counts: BTreeMap<i32, i64> = BTreeMap::new();
+                for a in store.abilities.keys().filter_map(|&id| store.ability(id)) {
+                    *counts.entry(a.damage / bucket).or_default() += 1;
+                }
+                counts
+                    .into_iter()
+                    .map(|(n, count)| ((n * bucket).to_string(), count))
+                    .collect()
+            }
+        };
+
+        Ok(counts)
+    }
+
+    async fn unknown_abilities(&self, ids: &[i32]) -> Result<Vec<i32>, DbError> {
+        let store = self.store.lock().unwrap();
+        let mut unknown: Vec<i32> = ids
+            .iter()
+            .copied()
+            .filter(|id| !store.abilities.contains_key(id))
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+
+        Ok(unknown)
+    }
+
+    async fn create(&self, pokemon: NewPokemon) -> Result<Pokemon, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let id = store.insert_pokemon(pokemon);
+        Ok(store.stored_pokemon(id))
+    }
+
+    async fn create_many(&self, pokemon: Vec<NewPokemon>) -> Result<Vec<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        Ok(pokemon
+            .into_iter()
+            .map(|p| store.insert_pokemon(p))
+            .collect())
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        name: Option<&str>,
+        region_id: i32,
+        metadata: Option<&Metadata>,
+    ) -> Result<Option<Pokemon>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(p) = store.pokemon.get_mut(&id) else {
+            return Ok(None);
+        };
+        if let Some(name) = name {
+            p.name = name.to_string();
+        }
+        if let Some(metadata) = metadata {
+            p.metadata = metadata.clone();
+        }
+        p.region_id = region_id;
+        Ok(Some(store.stored_pokemon(id)))
+    }
+
+    async fn upsert(
+        &self,
+        name: &str,
+        region_id: i32,
+        metadata: Option<&Metadata>,
+    ) -> Result<(Pokemon, bool), DbError> {
+        let mut store = self.store.lock().unwrap();
+        let slug = slugify(name);
+        if let Some((&id, p)) = store.pokemon.iter_mut().find(|(_, p)| p.slug == slug) {
+            p.region_id = region_id;
+            if let Some(metadata) = metadata {
+                p.metadata = metadata.clone();
+            }
+            return Ok((store.stored_pokemon(id), false));
+        }
+
+        let id = store.insert_pokemon(NewPokemon {
+            name: name.to_string(),
+            region_id,
+            publish_at: None,
+            abilities: Vec::new(),
+            metadata: metadata.cloned().unwrap_or_default(),
+        });
+        Ok((store.stored_pokemon(id), true))
+    }
+
+    async fn delete(&self, key: &EntityKey) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let id = match key {
+            EntityKey::Id(id) => Some(*id),
+            EntityKey::Uuid(uuid) => id_of(*uuid),
+            EntityKey::Slug(slug) => store
+                .pokemon
+                .iter()
+                .find(|(_, p)| &p.slug == slug)
+                .map(|(&id, _)| id),
+        };
+        Ok(id.filter(|id| store.pokemon.remove(id).is_some()))
+    }
+
+    async fn publish_due(&self, now: OffsetDateTime) -> Result<Vec<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let mut published = Vec::new();
+        for (&id, p) in store.pokemon.iter_mut() {
+            if p.publish_at.is_some_and(|at| at <= now) {
+                p.publish_at = None;
+                published.push(id);
+            }
+        }
+
+        Ok(published)
+    }
+
+    async fn description(&self, key: &EntityKey) -> Result<Option<Description>, DbError> {
+        let Some(pokemon) = PokemonRepository::find(self, key).await? else {
+            return Ok(None);
+        };
+        let store = self.store.lock().unwrap();
+
+        Ok(Some(
+            store
+                .descriptions
+                .get(&pokemon.pokemon_id)
+                .cloned()
+                .unwrap_or_default(),
+        ))
+    }
+
+    async fn set_description(
+        &self,
+        key: &EntityKey,
+        description: &Description,
+    ) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let id = match key {
+            EntityKey::Id(id) => Some(*id),
+            EntityKey::Uuid(uuid) => id_of(*uuid),
+            EntityKey::Slug(slug) => store
+                .pokemon
+                .iter()
+                .find(|(_, p)| &p.slug == slug)
+                .map(|(&id, _)| id),
+        }
+        .filter(|id| store.pokemon.contains_key(id));
+        if let Some(id) = id {
+            store.descriptions.insert(id, description.clone());
+        }
+
+        Ok(id)
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, DbError> {
+        let store = self.store.lock().unwrap();
+        let hits = store
+            .pokemon
+            .keys()
+            .filter_map(|&id| store.pokemon(id))
+            .filter_map(|p| {
+                let rank = search_rank(query, &p.name, store.descriptions.get(&p.pokemon_id))?;
+                Some(SearchHit {
+                    kind: "pokemon",
+                    id: p.pokemon_id,
+                    name: p.name,
+                    rank,
+                })
+            })
+            .collect();
+
+        Ok(best_hits(hits, limit))
+    }
+
+    async fn references(&self, pokemon_id: i32) -> Result<Vec<ExternalReference>, DbError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .references_of(pokemon_id, "pokemon"))
+    }
+
+    async fn add_reference(
+        &self,
+        key: &EntityKey,
+        reference: &NewReference,
+    ) -> Result<AddReference, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let pokemon_id = store.pokemon_id(key);
+
+        Ok(store.add_reference(pokemon_id, reference, "pokemon"))
+    }
+
+    async fn delete_reference(
+        &self,
+        key: &EntityKey,
+        reference_id: i32,
+    ) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let pokemon_id = store.pokemon_id(key);
+
+        Ok(store.delete_reference(pokemon_id, reference_id))
+    }
+
+    async fn record_version(&self, pokemon_id: i32) -> Result<Option<Version>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if !store.pokemon.contains_key(&pokemon_id) {
+            return Ok(None);
+        }
+
+        Ok(store.record_version(pokemon_id))
+    }
+
+    async fn versions(&self, key: &EntityKey) -> Result<Option<(i32, Vec<Version>)>, DbError> {
+        let store = self.store.lock().unwrap();
+
+        Ok(store.pokemon_id(key).map(|id| {
+            let versions = store.versions.get(&id).cloned().unwrap_or_default();
+            (id, versions)
+        }))
+    }
+}
+
+#[async_trait]
+impl AbilityRepository for InMemoryRepository {
+    async fn apply(
+        &self,
+        name: &str,
+        damage: i32,
+        status_effect: &str,
+    ) -> Result<(Ability, bool), DbError> {
+        let mut store = self.store.lock().unwrap();
+        let existing = store.abilities.iter().find(|(_, a)| a.name == name);
+        let (id, went_live) = match existing {
+            Some((&id, a)) => (
+                id,
+                a.publish_at
+                    .is_some_and(|at| at > OffsetDateTime::now_utc()),
+            ),
+            None => (store.next_id(), true),
+        };
+        store.abilities.insert(
+            id,
+            StoredAbility {
+                name: name.to_string(),
+                damage,
+                status_effect: status_effect.to_string(),
+                publish_at: None,
+            },
+        );
+        store.ability_changes.remove(&id);
+
+        Ok((store.ability(id).unwrap(), went_live))
+    }
+
+    async fn stage(
+        &self,
+        name: &str,
+        damage: i32,
+        status_effect: &str,
+        publish_at: OffsetDateTime,
+    ) -> Result<i32, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if let Some((&id, _)) = store.abilities.iter().find(|(_, a)| a.name == name) {
+            store
+                .ability_changes
+                .insert(id, (damage, status_effect.to_string(), publish_at));
+            return Ok(id);
+        }
+
+        let id = store.next_id();
+        store.abilities.insert(
+            id,
+            StoredAbility {
+                name: name.to_string(),
+                damage,
+                status_effect: status_effect.to_string(),
+                publish_at: Some(publish_at),
+            },
+        );
+        Ok(id)
+    }
+
+    async fn publish_due(&self, now: OffsetDateTime) -> Result<PublishedAbilities, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let mut created = Vec::new();
+        for (&id, a) in store.abilities.iter_mut() {
+            if a.publish_at.is_some_and(|at| at <= now) {
+                a.publish_at = None;
+                created.push(id);
+            }
+        }
+
+        let due: Vec<i32> = store
+            .ability_changes
+            .iter()
+            .filter(|(_, (_, _, at))| *at <= now)
+            .map(|(&id, _)| id)
+            .collect();
+        for &id in &due {
+            let (damage, status_effect, _) = store.ability_changes.remove(&id).unwrap();
+            let a = store.abilities.get_mut(&id).unwrap();
+            a.damage = damage;
+            a.status_effect = status_effect;
+        }
+
+        Ok(PublishedAbilities {
+            created,
+            rebalanced: due,
+        })
+    }
+
+    async fn list(
+        &self,
+        tags: &[String],
+        locales: &[String],
+        paging: &PageParams,
+    ) -> Result<(Vec<TaggedAbility>, i64), DbError> {
+        let store = self.store.lock().unwrap();
+        let abilities: Vec<TaggedAbility> = store
+            .abilities
+            .keys()
+            .filter_map(|&id| store.ability(id))
+            .map(|a| {
+                let mut tag_ids = store
+                    .ability_tags
+                    .get(&a.ability_id)
+                    .cloned()
+                    .unwrap_or_default();
+                tag_ids.sort_unstable();
+                let (name, status_effect) = locales
+                    .iter()
+                    .find_map(|l| {
+                        store
+                            .ability_translations
+                            .get(&(a.ability_id, l.clone()))
+                            .cloned()
+                    })
+                    .unwrap_or((a.name, a.status_effect));
+                TaggedAbility {
+                    ability_id: a.ability_id,
+                    name,
+                    damage: a.damage,
+                    status_effect,
+                    tags: tag_ids.iter().map(|t| store.tags[t].0.clone()).collect(),
+                }
+            })
+            .filter(|a| tags.iter().all(|t| a.tags.contains(t)))
+            .collect();
+
+        let (page, total) = page(abilities, paging);
+        Ok((page, total.value.unwrap_or_default()))
+    }
+
+    async fn set_tags(&self, name: &str, tags: &[String]) -> Result<SetTags, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some((&ability_id, _)) = store.abilities.iter().find(|(_, a)| a.name == name) else {
+            return Ok(SetTags::NotFound);
+        };
+        let mut tag_ids = Vec::new();
+        let mut unknown = Vec::new();
+        for tag in tags {
+            match store.tags.iter().find(|(_, (n, _))| n == tag) {
+                Some((&id, _)) => tag_ids.push(id),
+                None => unknown.push(tag.clone()),
+            }
+        }
+        if !unknown.is_empty() {
+            unknown.sort();
+            unknown.dedup();
+            return Ok(SetTags::UnknownTags(unknown));
+        }
+
+        tag_ids.sort_unstable();
+        tag_ids.dedup();
+        store.ability_tags.insert(ability_id, tag_ids);
+        Ok(SetTags::Set(ability_id))
+    }
+
+    async fn tag_stats(&self) -> Result<Vec<TagStats>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .tags
+            .iter()
+            .map(|(&tag_id, (name, kind))| {
+                let damage: Vec<i32> = store
+                    .ability_tags
+                    .iter()
+                    .filter(|(_, tags)| tags.contains(&tag_id))
+                    .filter_map(|(&id, _)| store.ability(id))
+                    .map(|a| a.damage)
+                    .collect();
+                TagStats {
+                    name: name.clone(),
+                    kind: kind.clone(),
+                    ability_count: damage.len() as i64,
+                    average_damage: (!damage.is_empty()).then(|| {
+                        damage.iter().map(|&d| f64::from(d)).sum::<f64>() / damage.len() as f64
+                    }),
+                }
+            })
+            .collect())
+    }
+
+    async fn translations(&self, name: &str) -> Result<Option<Vec<AbilityTranslation>>, DbError> {
+        let store = self.store.lock().unwrap();
+        let Some((&ability_id, _)) = store.abilities.iter().find(|(_, a)| a.name == name) else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            store
+                .ability_translations
+                .iter()
+                .filter(|((id, _), _)| *id == ability_id)
+                .map(|((_, locale), (name, status_effect))| AbilityTranslation {
+                    locale: locale.clone(),
+                    name: name.clone(),
+                    status_effect: status_effect.clone(),
+                })
+                .collect(),
+        ))
+    }
+
+    async fn set_translation(
+        &self,
+        name: &str,
+        translation: &AbilityTranslation,
+    ) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some((&ability_id, _)) = store.abilities.iter().find(|(_, a)| a.name == name) else {
+            return Ok(None);
+        };
+        store.ability_translations.insert(
+            (ability_id, translation.locale.clone()),
+            (translation.name.clone(), translation.status_effect.clone()),
+        );
+
+        Ok(Some(ability_id))
+    }
+
+    async fn delete_translation(&self, name: &str, locale: &str) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some((&ability_id, _)) = store.abilities.iter().find(|(_, a)| a.name == name) else {
+            return Ok(None);
+        };
+
+        Ok(store
+            .ability_translations
+            .remove(&(ability_id, locale.to_string()))
+            .map(|_| ability_id))
+    }
+
+    async fn description(&self, name: &str) -> Result<Option<Description>, DbError> {
+        let store = self.store.lock().unwrap();
+        let ability_id = store
+            .abilities
+            .keys()
+            .filter_map(|&id| store.ability(id))
+            .find(|a| a.name == name)
+            .map(|a| a.ability_id);
+
+        Ok(ability_id.map(|id| store.descriptions.get(&id).cloned().unwrap_or_default()))
+    }
+
+    async fn set_description(
+        &self,
+        name: &str,
+        description: &Description,
+    ) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some((&ability_id, _)) = store.abilities.iter().find(|(_, a)| a.name == name) else {
+            return Ok(None);
+        };
+        store.descriptions.insert(ability_id, description.clone());
+
+        Ok(Some(ability_id))
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, DbError> {
+        let store = self.store.lock().unwrap();
+        let hits = store
+            .abilities
+            .keys()
+            .filter_map(|&id| store.ability(id))
+            .filter_map(|a| {
+                let rank = search_rank(query, &a.name, store.descriptions.get(&a.ability_id))?;
+                Some(SearchHit {
+                    kind: "ability",
+                    id: a.ability_id,
+                    name: a.name,
+                    rank,
+                })
+            })
+            .collect();
+
+        Ok(best_hits(hits, limit))
+    }
+
+    async fn find(&self, name: &str) -> Result<Option<Ability>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .abilities
+            .keys()
+            .filter_map(|&id| store.ability(id))
+            .find(|a| a.name == name))
+    }
+
+    async fn references(&self, ability_id: i32) -> Result<Vec<ExternalReference>, DbError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .references_of(ability_id, "ability"))
+    }
+
+    async fn add_reference(
+        &self,
+        name: &str,
+        reference: &NewReference,
+    ) -> Result<AddReference, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let ability_id = store.ability_id(name);
+
+        Ok(store.add_reference(ability_id, reference, "ability"))
+    }
+
+    async fn delete_reference(
+        &self,
+        name: &str,
+        reference_id: i32,
+    ) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let ability_id = store.ability_id(name);
+
+        Ok(store.delete_reference(ability_id, reference_id))
+    }
+
+    async fn record_version(&self, ability_id: i32) -> Result<Option<Version>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if !store.abilities.contains_key(&ability_id) {
+            return Ok(None);
+        }
+
+        Ok(store.record_version(ability_id))
+    }
+
+    async fn versions(&self, name: &str) -> Result<Option<(i32, Vec<Version>)>, DbError> {
+        let store = self.store.lock().unwrap();
+
+        Ok(store.ability_id(name).map(|id| {
+            let versions = store.versions.get(&id).cloned().unwrap_or_default();
+            (id, versions)
+        }))
+    }
+
+    async fn propose(
+        &self,
+        name: &str,
+        user_id: i32,
+        damage: i32,
+        status_effect: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<AbilityProposal>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(ability) = store
+            .abilities
+            .keys()
+            .filter_map(|&id| store.ability(id))
+            .find(|a| a.name == name)
+        else {
+            return Ok(None);
+        };
+        let proposal = AbilityProposal {
+            proposal_id: store.next_id(),
+            ability_id: ability.ability_id,
+            ability: ability.name,
+            proposed_by: user_id,
+            damage,
+            status_effect: status_effect.to_string(),
+            reason: reason.map(str::to_string),
+            proposed_at: OffsetDateTime::now_utc(),
+            status: ProposalStatus::Pending,
+            reviewed_by: None,
+            reviewed_at: None,
+            review_note: None,
+        };
+        store
+            .proposals
+            .insert(proposal.proposal_id, proposal.clone());
+
+        Ok(Some(proposal))
+    }
+
+    async fn proposals(
+        &self,
+        status: Option<ProposalStatus>,
+        proposed_by: Option<i32>,
+    ) -> Result<Vec<AbilityProposal>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .proposals
+            .values()
+            .filter(|p| status.is_none_or(|s| p.status == s))
+            .filter(|p| proposed_by.is_none_or(|u| p.proposed_by == u))
+            .cloned()
+            .collect())
+    }
+
+    async fn review(
+        &self,
+        proposal_id: i32,
+        reviewer: i32,
+        approve: bool,
+        note: Option<&str>,
+    ) -> Result<Review, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let Some(proposal) = store.proposals.get_mut(&proposal_id) else {
+            return Ok(Review::NotFound);
+        };
+        if proposal.status != ProposalStatus::Pending {
+            return Ok(Review::AlreadyReviewed(proposal.status));
+        }
+        proposal.status = if approve {
+            ProposalStatus::Approved
+        } else {
+            ProposalStatus::Rejected
+        };
+        proposal.reviewed_by = Some(reviewer);
+        proposal.reviewed_at = Some(OffsetDateTime::now_utc());
+        proposal.review_note = note.map(str::to_string);
+        let proposal = proposal.clone();
+        if approve {
+            if let Some(a) = store.abilities.get_mut(&proposal.ability_id) {
+                a.damage = proposal.damage;
+                a.status_effect = proposal.status_effect.clone();
+            }
+            store.ability_changes.remove(&proposal.ability_id);
+        }
+
+        Ok(Review::Reviewed(proposal))
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryRepository {
+    async fn create(&self, username: &str, _password_hash: &str) -> Result<Option<i32>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if store.usernames.values().any(|taken| taken == username) {
+            return Ok(None);
+        }
+
+        let id = store.next_id();
+        store.users.insert(id, (None, false));
+        store.usernames.insert(id, username.to_string());
+        Ok(Some(id))
+    }
+
+    async fn set_trainer(&self, user_id: i32, trainer_id: i32) -> Result<(), DbError> {
+        let mut store = self.store.lock().unwrap();
+        if let Some(user) = store.users.get_mut(&user_id) {
+            user.0 = Some(trainer_id);
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: i32) -> Result<(), DbError> {
+        let mut store = self.store.lock().unwrap();
+        store.users.remove(&user_id);
+        store.usernames.remove(&user_id);
+
+        Ok(())
+    }
+
+    async fn record_view(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+        keep: i64,
+    ) -> Result<(), DbError> {
+        let mut store = self.store.lock().unwrap();
+        let views = &mut store.recent_views;
+        views.retain(|&(u, k, id, _)| (u, k, id) != (user_id, kind, entity_id));
+        views.push((user_id, kind, entity_id, OffsetDateTime::now_utc()));
+        let kept = views.iter().filter(|(u, ..)| *u == user_id).count();
+        let mut excess = kept.saturating_sub(keep as usize);
+        views.retain(|(u, ..)| {
+            let drop = *u == user_id && excess > 0;
+            excess -= drop as usize;
+            !drop
+        });
+
+        Ok(())
+    }
+
+    async fn recent(&self, user_id: i32) -> Result<Vec<RecentView>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .recent_views
+            .iter()
+            .rev()
+            .filter(|(u, ..)| *u == user_id)
+            .filter_map(|&(_, kind, id, viewed_at)| {
+                let entry = store.entry(kind, id)?;
+                Some(RecentView { entry, viewed_at })
+            })
+            .collect())
+    }
+
+    async fn add_bookmark(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<AddBookmark, DbError> {
+        let mut store = self.store.lock().unwrap();
+        if store.entry(kind, entity_id).is_none() {
+            return Ok(AddBookmark::NotFound);
+        }
+        let bookmarks = &mut store.bookmarks;
+        if bookmarks
+            .iter()
+            .any(|&(u, k, id, _)| (u, k, id) == (user_id, kind, entity_id))
+        {
+            return Ok(AddBookmark::AlreadyAdded);
+        }
+        bookmarks.push((user_id, kind, entity_id, OffsetDateTime::now_utc()));
+
+        Ok(AddBookmark::Added)
+    }
+
+    async fn delete_bookmark(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<bool, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let before = store.bookmarks.len();
+        store
+            .bookmarks
+            .retain(|&(u, k, id, _)| (u, k, id) != (user_id, kind, entity_id));
+
+        Ok(store.bookmarks.len() < before)
+    }
+
+    async fn bookmarks(&self, user_id: i32) -> Result<Vec<Bookmark>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .bookmarks
+            .iter()
+            .rev()
+            .filter(|(u, ..)| *u == user_id)
+            .filter_map(|&(_, kind, id, bookmarked_at)| {
+                let entry = store.entry(kind, id)?;
+                Some(Bookmark {
+                    entry,
+                    bookmarked_at,
+                })
+            })
+            .collect())
+    }
+
+    async fn is_bookmarked(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<bool, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .bookmarks
+            .iter()
+            .any(|&(u, k, id, _)| (u, k, id) == (user_id, kind, entity_id)))
+    }
+
+    async fn record_exposure(
+        &self,
+        user_id: i32,
+        experiment: &str,
+        variant: &str,
+    ) -> Result<(), DbError> {
+        let mut store = self.store.lock().unwrap();
+        *store
+            .exposures
+            .entry((user_id, experiment.to_string(), variant.to_string()))
+            .or_default() += 1;
+
+        Ok(())
+    }
+
+    async fn exposures(&self, experiment: &str) -> Result<Vec<VariantExposures>, DbError> {
+        let store = self.store.lock().unwrap();
+        let mut by_variant: BTreeMap<&str, VariantExposures> = BTreeMap::new();
+        for ((_, e, variant), &exposures) in &store.exposures {
+            if e != experiment {
+                continue;
+            }
+            let counts = by_variant
+                .entry(variant)
+                .or_insert_with(|| VariantExposures {
+                    variant: variant.clone(),
+                    users: 0,
+                    exposures: 0,
+                });
+            counts.users += 1;
+            counts.exposures += exposures;
+        }
+
+        Ok(by_variant.into_values().collect())
+    }
+}
+
+impl Store {
+    /// The published pokemon as a fighter. Attributes aren't kept here, so
+    /// it has no types or weaknesses.
+    fn fighter(&self, pokemon_id: i32) -> Option<Fighter> {
+        if !self.is_published(pokemon_id) {
+            return None;
+        }
+        let mut abilities = self.pokemon[&pokemon_id].abilities.clone();
+        abilities.sort_unstable();
+
+        Some(Fighter {
+            pokemon_id,
+            types: Vec::new(),
+            weaknesses: Vec::new(),
+            moves: abilities
+                .into_iter()
+                .filter_map(|id| self.ability(id))
+                .map(|a| Move {
+                    name: a.name,
+                    damage: a.damage,
+                    status_effect: a.status_effect,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl BattleRepository for InMemoryRepository {
+    async fn fighters(
+        &self,
+        snapshot_id: Option<i32>,
+        ids: &[i32],
+    ) -> Result<Vec<Fighter>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(match snapshot_id {
+            Some(snapshot_id) => {
+                let pinned = store
+                    .snapshots
+                    .get(&snapshot_id)
+                    .map(|(_, fighters)| fighters.as_slice())
+                    .unwrap_or_default();
+                ids.iter()
+                    .filter_map(|&id| pinned.iter().find(|f| f.pokemon_id == id).cloned())
+                    .collect()
+            }
+            None => ids.iter().filter_map(|&id| store.fighter(id)).collect(),
+        })
+    }
+
+    async fn ai_trainer(&self, trainer_id: i32) -> Result<Option<AiTrainer>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store.trainers.get(&trainer_id).and_then(|t| {
+            let (strategy, difficulty) = t.ai?;
+            let mut team = t.team.clone();
+            team.sort_unstable();
+            Some(AiTrainer {
+                strategy: Some(strategy),
+                difficulty: Some(difficulty),
+                team,
+            })
+        }))
+    }
+
+    async fn store(&self, battle: NewBattle, per_hour: usize) -> Result<StoreBattle, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let now = OffsetDateTime::now_utc();
+        let hour = time::Duration::HOUR;
+        if per_hour > 0 {
+            let mut within_hour: Vec<OffsetDateTime> = store
+                .battles
+                .values()
+                .filter(|b| b.trainer_id == battle.trainer_id && b.fought_at > now - hour)
+                .map(|b| b.fought_at)
+                .collect();
+            within_hour.sort_unstable_by(|a, b| b.cmp(a));
+            if let Some(&oldest) = within_hour.get(per_hour - 1) {
+                let left = (oldest + hour - now).max(time::Duration::ZERO);
+                return Ok(StoreBattle::QuotaUsedUp(left.unsigned_abs()));
+            }
+        }
+
+        if let Some(reward) = battle.reward.filter(|&r| r > 0) {
+            if let Some(trainer) = store.trainers.get_mut(&battle.trainer_id) {
+                trainer.rewards += reward;
+            }
+        }
+        let id = store.next_id();
+        store.battles.insert(
+            id,
+            StoredBattleRow {
+                trainer_id: battle.trainer_id,
+                fought_at: now,
+                seed: battle.seed,
+                fighters: json!(battle.fighters),
+                winner: battle.winner,
+                turns: battle.turns.iter().map(|t| json!(t)).collect(),
+                ai_strategy: battle.ai_strategy,
+                ai_difficulty: battle.ai_difficulty,
+            },
+        );
+
+        Ok(StoreBattle::Stored(id))
+    }
+
+    async fn battle(&self, battle_id: i32) -> Result<Option<StoredBattle>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store.battles.get(&battle_id).map(|b| StoredBattle {
+            seed: b.seed,
+            fighters: serde_json::from_value(b.fighters.clone()).ok(),
+            winner: b.winner,
+            turns: b.turns.clone(),
+            ai_strategy: b.ai_strategy.map(|s| s.name().to_string()),
+            ai_difficulty: b.ai_difficulty.map(|d| d.name().to_string()),
+        }))
+    }
+
+    async fn take_snapshot(&self) -> Result<CatalogSnapshot, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let fighters: Vec<Fighter> = store
+            .pokemon
+            .keys()
+            .filter_map(|&id| store.fighter(id))
+            .collect();
+        let pokemon = fighters.len() as i64;
+        let snapshot_id = store.next_id();
+        let created_at = OffsetDateTime::now_utc();
+        store.snapshots.insert(snapshot_id, (created_at, fighters));
+
+        Ok(CatalogSnapshot {
+            snapshot_id,
+            created_at,
+            pokemon,
+        })
+    }
+
+    async fn snapshot(&self, snapshot_id: i32) -> Result<Option<CatalogSnapshot>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .snapshots
+            .get(&snapshot_id)
+            .map(|(created_at, fighters)| CatalogSnapshot {
+                snapshot_id,
+                created_at: *created_at,
+                pokemon: fighters.len() as i64,
+            }))
+    }
+}
+
+impl Store {
+    /// The published pokemon with a sprite, in id order, with the first
+    /// sprite of each.
+    fn quiz_pokemon(&self) -> Vec<(i32, String)> {
+        self.pokemon
+            .keys()
+            .filter(|&&id| self.is_published(id))
+            .filter_map(|&id| {
+                let (_, (_, _, sprite)) = self
+                    .references
+                    .iter()
+                    .find(|(_, (owner, kind, _))| *owner == id && *kind == ReferenceKind::Sprite)?;
+                Some((id, sprite.clone()))
+            })
+            .collect()
+    }
+
+    fn quiz_score(&self, trainer_id: i32) -> Option<QuizScore> {
+        let (correct, answered) = *self.quiz_scores.get(&trainer_id)?;
+        Some(QuizScore {
+            trainer_id,
+            name: self.trainers.get(&trainer_id)?.name.clone(),
+            correct,
+            answered,
+        })
+    }
+}
+
+#[async_trait]
+impl QuizRepository for InMemoryRepository {
+    async fn ask(&self, trainer_id: i32, pick: u64) -> Result<Option<NewQuestion>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let candidates = store.quiz_pokemon();
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let (pokemon_id, sprite_url) =
+            candidates[(pick % candidates.len() as u64) as usize].clone();
+        let question_id = store.next_id();
+        store
+            .quiz_questions
+            .insert(question_id, (trainer_id, pokemon_id, false));
+
+        Ok(Some(NewQuestion {
+            question_id,
+            name: store.pokemon[&pokemon_id].name.clone(),
+            sprite_url,
+        }))
+    }
+
+    async fn question(
+        &self,
+        question_id: i32,
+        trainer_id: i32,
+    ) -> Result<Option<AskedQuestion>, DbError> {
+        let store = self.store.lock().unwrap();
+        Ok(store
+            .quiz_questions
+            .get(&question_id)
+            .filter(|(asked, _, _)| *asked == trainer_id)
+            .and_then(|(_, pokemon_id, answered)| {
+                Some(AskedQuestion {
+                    name: store.pokemon.get(pokemon_id)?.name.clone(),
+                    answered: *answered,
+                })
+            }))
+    }
+
+    async fn answer(&self, question_id: i32, correct: bool) -> Result<Option<QuizScore>, DbError> {
+        let mut store = self.store.lock().unwrap();
+        let trainer_id = match store.quiz_questions.get_mut(&question_id) {
+            Some((trainer_id, _, answered @ false)) => {
+                *answered = true;
+                *trainer_id
+            }
+            _ => return Ok(None),
+        };
+        let score = store.quiz_scores.entry(trainer_id).or_default();
+        score.0 += i32::from(correct);
+        score.1 += 1;
+
+        Ok(store.quiz_score(trainer_id))
+    }
+
+    async fn leaderboard(&self, limit: i64) -> Result<Vec<QuizScore>, DbError> {
+        let store = self.store.lock().unwrap();
+        let mut scores: Vec<QuizScore> = store
+            .quiz_scores
+            .keys()
+            .filter_map(|&id| store.quiz_score(id))
+            .collect();
+        scores.sort_by_key(|s| (std::cmp::Reverse(s.correct), s.answered, s.trainer_id));
+        scores.truncate(limit as usize);
+
+        Ok(scores)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::db::{Db, DbError, DbTransaction};
+use crate::models::{
+    CountStrategy, ExternalReference, Metadata, ReferenceKind, TotalCount, Version,
+};
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use tokio_postgres::types::{Json, ToSql};
+use tokio_postgres::Row;
+
+mod ability;
+mod battle;
+#[cfg(test)]
+mod memory;
+mod pokemon;
+mod quiz;
+mod trainer;
+mod user;
+
+pub(crate) use ability::{AbilityRepository, PgAbilityRepository, Review, SetTags};
+pub(crate) use battle::{AiTrainer, BattleRepository, NewBattle, PgBattleRepository, StoreBattle};
+#[cfg(test)]
+pub(crate) use memory::InMemoryRepository;
+pub(crate) use pokemon::{
+    AddReference, Grouping, NewPokemon, PgPokemonRepository, PokemonRepository,
+};
+pub(crate) use quiz::{PgQuizRepository, QuizRepository};
+pub(crate) use trainer::{
+    Attach, CreateAi, Delete, PgTrainerRepository, TrainerChanges, TrainerRepository, Update,
+};
+pub(crate) use user::{AddBookmark, PgUserRepository, UserRepository};
+
+/// Runs `write` in a transaction on a connection of its own, committing if
+/// it returns `Ok` and rolling back otherwise, so that writes spanning
+/// several statements or tables happen entirely or not at all. The future
+/// may only borrow the transaction, so `write` captures its inputs by value.
+async fn in_transaction<T, F>(db: &Arc<Db>, write: F) -> Result<T, DbError>
+where
+    F: for<'t> FnOnce(&'t DbTransaction<'t>) -> BoxFuture<'t, Result<T, DbError>>,
+{
+    let mut db = db.conn().await?;
+    let tx = db.transaction().await?;
+    let value = write(&tx).await?;
+    tx.commit().await?;
+
+    Ok(value)
+}
+
+/// The `metadata` column at `idx` of `r`.
+fn metadata_at(r: &Row, idx: usize) -> Metadata {
+    r.get::<_, Json<Metadata>>(idx).0
+}
+
+/// A reference from the id, kind and value columns of `r`, attached to a
+/// PokeAPI `resource`.
+fn reference_from_row(r: &Row, resource: &str) -> ExternalReference {
+    ExternalReference::new(
+        r.get(0),
+        ReferenceKind::from_db(r.get(1)),
+        r.get(2),
+        resource,
+    )
+}
+
+/// A version from the version, snapshot and recording time columns of `r`.
+fn version_from_row(r: &Row) -> Version {
+    Version {
+        version: r.get(0),
+        snapshot: metadata_at(r, 1),
+        recorded_at: r.get(2),
+    }
+}
+
+/// Records row `id` of `table`, a pokemon or ability, in `{table}_version`
+/// as its next version, with `snapshot` as the JSON to keep. Nothing is
+/// recorded when the row is unchanged since its latest version. Returns the
+/// latest version, or `None` if there is no such row.
+async fn record_version(
+    db: &Arc<Db>,
+    table: &'static str,
+    snapshot: &'static str,
+    id: i32,
+) -> Result<Option<Version>, DbError> {
+    in_transaction(db, |tx| {
+        Box::pin(async move {
+            // Serializes recording per row, so that each version number is
+            // taken once and compared against the version before it.
+            tx.execute(
+                "SELECT pg_advisory_xact_lock(hashtext($1), $2)",
+                &[&table, &id],
+            )
+            .await?;
+            tx.execute(
+                &format!(
+                    "WITH latest AS (
+                         SELECT version, snapshot FROM {table}_version
+                         WHERE {table}_id = $1
+                         ORDER BY version DESC LIMIT 1
+                     )
+                     INSERT INTO {table}_version ({table}_id, version, snapshot)
+                     SELECT {table}_id, coalesce((SELECT version FROM latest), 0) + 1, s.snapshot
+                     FROM {table}, LATERAL (SELECT {snapshot} AS snapshot) s
+                     WHERE {table}_id = $1
+                       AND s.snapshot IS DISTINCT FROM (SELECT snapshot FROM latest)",
+                    table = table,
+                    snapshot = snapshot,
+                ),
+                &[&id],
+            )
+            .await?;
+            let rows = tx
+                .query(
+                    &format!(
+                        "SELECT version, snapshot, recorded_at FROM {table}_version
+                         WHERE {table}_id = $1
+                         ORDER BY version DESC LIMIT 1",
+                        table = table,
+                    ),
+                    &[&id],
+                )
+                .await?;
+
+            Ok(rows.first().map(version_from_row))
+        })
+    })
+    .await
+}
+
+/// Counts the rows of `from`, a `FROM ... [WHERE ...]` clause, as `strategy`
+/// asks. When `from` reads all of `table` unfiltered, the estimate comes
+/// straight from `pg_class.reltuples`; otherwise it is the planner's row
+/// estimate for the query.
+async fn count_rows(
+    db: &DbTransaction<'_>,
+    strategy: CountStrategy,
+    from: &str,
+    params: &[&(dyn ToSql + Sync)],
+    table: Option<&str>,
+) -> Result<TotalCount, tokio_postgres::Error> {
+    let estimate = match (strategy, table) {
+        (CountStrategy::None, _) => {
+            return Ok(TotalCount {
+                value: None,
+                approximate: false,
+            })
+        }
+        (CountStrategy::Exact, _) => None,
+        (CountStrategy::Estimate, Some(table)) => db
+            .query(
+                "SELECT reltuples::float8 FROM pg_class WHERE oid = $1::text::regclass",
+                &[&table],
+            )
+            .await?
+            .first()
+            .map(|r| r.get::<_, f64>(0))
+            // Tables that were never vacuumed or analyzed report -1.
+            .filter(|rows| *rows >= 0.0),
+        (CountStrategy::Estimate, None) => {
+            let rows = db
+                .query(&format!("EXPLAIN (FORMAT JSON) SELECT 1 {}", from), params)
+                .await?;
+            let plan: serde_json::Value = rows.first().unwrap().get(0);
+            plan[0]["Plan"]["Plan Rows"].as_f64()
+        }
+    };
+
+    match estimate {
+        Some(rows) => Ok(TotalCount {
+            value: Some(rows.round() as i64),
+            approximate: true,
+        }),
+        None => Ok(TotalCount {
+            value: Some(
+                db.query(&format!("SELECT count(*) {}", from), params)
+                    .await?
+                    .first()
+                    .unwrap()
+                    .get(0),
+            ),
+            approximate: false,
+        }),
+    }
+}
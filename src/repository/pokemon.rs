@@ -0,0 +1,785 @@
+use super::{
+    count_rows, in_transaction, metadata_at, record_version, reference_from_row, version_from_row,
+};
+use crate::db::{Db, DbError, DbTransaction};
+use crate::models::{
+    escape_like, generation_of, regions_in_generation, slugify, Ability, Attribute, Description,
+    EntityKey, ExternalReference, Metadata, NewReference, PageParams, Pokemon, PokemonFilter,
+    PokemonFull, RegionRef, SearchHit, SortParams, TotalCount, Version,
+};
+use axum::async_trait;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio_postgres::types::{Json, ToSql};
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+/// A pokemon to insert, along with the abilities it starts out with.
+pub(crate) struct NewPokemon {
+    pub(crate) name: String,
+    pub(crate) region_id: i32,
+    /// Keeps the pokemon hidden until this time.
+    pub(crate) publish_at: Option<OffsetDateTime>,
+    pub(crate) abilities: Vec<i32>,
+    pub(crate) metadata: Metadata,
+}
+
+/// What to count published pokemon, or their abilities, by.
+pub(crate) enum Grouping {
+    /// Pokemon per region name.
+    Region,
+    /// Pokemon per number of abilities they know.
+    AbilityCount,
+    /// Abilities per bucket of this many damage points, keyed by the bucket's
+    /// lowest damage.
+    Damage(i32),
+}
+
+#[async_trait]
+pub(crate) trait PokemonRepository: Send + Sync {
+    /// One page of pokemon matching `filter`, with their abilities and
+    /// attributes, and the total number of matches.
+    async fn list(
+        &self,
+        filter: &PokemonFilter,
+        sort: &SortParams,
+        paging: &PageParams,
+    ) -> Result<(Vec<PokemonFull>, TotalCount), DbError>;
+
+    async fn find(&self, key: &EntityKey) -> Result<Option<Pokemon>, DbError>;
+
+    /// The id and name of `region`, or `None` if it does not exist.
+    async fn resolve_region(&self, region: &RegionRef) -> Result<Option<(i32, String)>, DbError>;
+
+    /// Published pokemon per region for the regions named in `regions`, in
+    /// lowercase, ordered by region name. Regions without pokemon count zero.
+    async fn count_by_region(&self, regions: &[String]) -> Result<Vec<(String, i64)>, DbError>;
+
+    /// Counts per group, ordered by the group's region name or number.
+    async fn group_counts(&self, grouping: Grouping) -> Result<Vec<(String, i64)>, DbError>;
+
+    /// Of `ids`, the ones that are not abilities.
+    async fn unknown_abilities(&self, ids: &[i32]) -> Result<Vec<i32>, DbError>;
+
+    /// Inserts a pokemon with its abilities in one transaction and returns
+    /// it as stored. Its slug gets a numeric suffix if another pokemon
+    /// already has it. A duplicate name is an error.
+    async fn create(&self, pokemon: NewPokemon) -> Result<Pokemon, DbError>;
+
+    /// Inserts every pokemon with its abilities in one transaction and
+    /// returns their ids in order. Nothing is inserted if any of them fails.
+    async fn create_many(&self, pokemon: Vec<NewPokemon>) -> Result<Vec<i32>, DbError>;
+
+    /// Updates the pokemon, keeping its name or metadata when `name` or
+    /// `metadata` is `None`. The slug stays as it was. Returns the result, or
+    /// `None` if there is no such pokemon.
+    async fn update(
+        &self,
+        id: i32,
+        name: Option<&str>,
+        region_id: i32,
+        metadata: Option<&Metadata>,
+    ) -> Result<Option<Pokemon>, DbError>;
+
+    /// Moves the pokemon whose slug is that of `name` to `region_id`, or
+    /// inserts one named `name` if there is none. `metadata` replaces that of
+    /// the pokemon unless it is `None`. Returns it and whether it was
+    /// created.
+    async fn upsert(
+        &self,
+        name: &str,
+        region_id: i32,
+        metadata: Option<&Metadata>,
+    ) -> Result<(Pokemon, bool), DbError>;
+
+    /// Deletes the pokemon along with its ability and attribute links.
+    /// Returns its id, or `None` if it did not exist.
+    async fn delete(&self, key: &EntityKey) -> Result<Option<i32>, DbError>;
+
+    /// Clears the publish times that have passed by `now` and returns the
+    /// ids of the pokemon that went live.
+    async fn publish_due(&self, now: OffsetDateTime) -> Result<Vec<i32>, DbError>;
+
+    /// The description and lore of the pokemon, or `None` if it does not
+    /// exist or is not published yet.
+    async fn description(&self, key: &EntityKey) -> Result<Option<Description>, DbError>;
+
+    /// Replaces the description and lore of the pokemon, published or not.
+    /// Returns its id, or `None` if it does not exist.
+    async fn set_description(
+        &self,
+        key: &EntityKey,
+        description: &Description,
+    ) -> Result<Option<i32>, DbError>;
+
+    /// Up to `limit` published pokemon whose name, description or lore
+    /// matches `query`, best first.
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, DbError>;
+
+    /// The external references of the pokemon, oldest first.
+    async fn references(&self, pokemon_id: i32) -> Result<Vec<ExternalReference>, DbError>;
+
+    /// Attaches a reference to the pokemon, published or not.
+    async fn add_reference(
+        &self,
+        key: &EntityKey,
+        reference: &NewReference,
+    ) -> Result<AddReference, DbError>;
+
+    /// Detaches a reference from the pokemon. Returns the pokemon's id, or
+    /// `None` if it has no such reference.
+    async fn delete_reference(
+        &self,
+        key: &EntityKey,
+        reference_id: i32,
+    ) -> Result<Option<i32>, DbError>;
+
+    /// Records the pokemon as it now is as its next version, unless nothing
+    /// changed since the latest one. Returns the latest version, or `None`
+    /// if there is no such pokemon.
+    async fn record_version(&self, pokemon_id: i32) -> Result<Option<Version>, DbError>;
+
+    /// The id of the pokemon, published or not, and its versions, oldest
+    /// first. `None` if there is no such pokemon.
+    async fn versions(&self, key: &EntityKey) -> Result<Option<(i32, Vec<Version>)>, DbError>;
+}
+
+/// What a pokemon version keeps: the columns a rollback restores.
+const POKEMON_SNAPSHOT: &str = "jsonb_build_object(
+    'name', name, 'region_id', region_id, 'metadata', metadata,
+    'description', description, 'lore', lore
+)";
+
+pub(crate) enum AddReference {
+    /// The reference, and the id of the pokemon or ability it was attached
+    /// to.
+    Added(ExternalReference, i32),
+    NotFound,
+    /// The same link is already attached.
+    Duplicate,
+}
+
+pub(crate) struct PgPokemonRepository {
+    db: Arc<Db>,
+}
+
+impl PgPokemonRepository {
+    pub(crate) fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl PokemonRepository for PgPokemonRepository {
+    async fn list(
+        &self,
+        filter: &PokemonFilter,
+        sort: &SortParams,
+        paging: &PageParams,
+    ) -> Result<(Vec<PokemonFull>, TotalCount), DbError> {
+        let mut db = self.db.conn().await?;
+        // The count and the page see the same snapshot, so they agree.
+        let tx = db.read_only().await?;
+
+        let mut from =
+            "FROM published_pokemon p JOIN region r ON r.region_id = p.region_id".to_string();
+        let regions = filter
+            .generation
+            .map(regions_in_generation)
+            .unwrap_or_default();
+        let name_pattern = filter
+            .name
+            .as_deref()
+            .map(|name| format!("%{}%", escape_like(name)));
+        let prefix_pattern = filter
+            .starts_with
+            .as_deref()
+            .map(|prefix| format!("{}%", escape_like(prefix)));
+
+        let metadata = filter.metadata.as_ref().map(Json);
+
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        if filter.generation.is_some() {
+            params.push(&regions);
+            conditions.push(format!("lower(r.region_name) = ANY(${})", params.len()));
+        }
+        if let Some(pattern) = &name_pattern {
+            params.push(pattern);
+            conditions.push(format!("p.name ILIKE ${}", params.len()));
+        }
+        if let Some(pattern) = &prefix_pattern {
+            params.push(pattern);
+            conditions.push(format!("p.name ILIKE ${}", params.len()));
+        }
+        if let Some(region) = &filter.region {
+            params.push(region);
+            conditions.push(format!("lower(r.region_name) = lower(${})", params.len()));
+        }
+        if let Some(ability) = &filter.ability {
+            params.push(ability);
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM pokemonabilities pa
+                         JOIN published_ability a ON a.ability_id = pa.ability_id
+                         WHERE pa.pokemon_id = p.pokemon_id AND lower(a.name) = lower(${}))",
+                params.len()
+            ));
+        }
+        // Served by the GIN index on `metadata`.
+        if let Some(metadata) = &metadata {
+            params.push(metadata);
+            conditions.push(format!("p.metadata @> ${}", params.len()));
+        }
+        // Table statistics only describe the unfiltered catalog.
+        let table = if conditions.is_empty() {
+            Some("pokemon")
+        } else {
+            from.push_str(" WHERE ");
+            from.push_str(&conditions.join(" AND "));
+            None
+        };
+
+        let total_count = count_rows(&tx, paging.count(), &from, &params, table).await?;
+
+        // The id breaks ties so that pages never overlap.
+        let order = match sort.order_by("p.name") {
+            order if order.is_empty() => " ORDER BY p.pokemon_id".to_string(),
+            order => format!("{}, p.pokemon_id", order),
+        };
+        let (per_page, offset) = (paging.per_page(), paging.offset());
+        // Abilities and attributes are aggregated into JSON arrays by the
+        // same statement, so a page is a single round trip however many
+        // pokemon it holds.
+        let sql = format!(
+            "SELECT p.pokemon_id, p.name, p.slug, r.region_name,
+                    COALESCE((
+                        SELECT json_agg(json_build_object(
+                                   'ability_id', a.ability_id,
+                                   'name', a.name,
+                                   'damage', a.damage,
+                                   'status_effect', a.status_effect
+                               ) ORDER BY a.ability_id)
+                        FROM pokemonabilities pa
+                        JOIN published_ability a ON a.ability_id = pa.ability_id
+                        WHERE pa.pokemon_id = p.pokemon_id
+                    ), '[]'),
+                    COALESCE((
+                        SELECT json_agg(json_build_object(
+                                   'attribute_id', a.attribute_id,
+                                   'attribute_name', a.attribute_name,
+                                   'weakness', a.weakness
+                               ) ORDER BY a.attribute_id)
+                        FROM pokemonattributes pa
+                        JOIN attribute a ON a.attribute_id = pa.attribute_id
+                        WHERE pa.pokemon_id = p.pokemon_id
+                    ), '[]'),
+                    p.uuid, p.metadata
+             {}{} LIMIT ${} OFFSET ${}",
+            from,
+            order,
+            params.len() + 1,
+            params.len() + 2
+        );
+        params.push(&per_page);
+        params.push(&offset);
+
+        let rows = tx.query(&sql, &params).await?;
+        tx.commit().await?;
+
+        let pokemon = rows
+            .into_iter()
+            .map(|r| {
+                let region: String = r.get(3);
+                let Json(abilities): Json<Vec<Ability>> = r.get(4);
+                let Json(attributes): Json<Vec<Attribute>> = r.get(5);
+                PokemonFull {
+                    pokemon_id: r.get(0),
+                    uuid: r.get(6),
+                    name: r.get(1),
+                    slug: r.get(2),
+                    generation: generation_of(&region),
+                    region,
+                    abilities,
+                    attributes,
+                    metadata: metadata_at(&r, 7),
+                }
+            })
+            .collect();
+
+        Ok((pokemon, total_count))
+    }
+
+    async fn find(&self, key: &EntityKey) -> Result<Option<Pokemon>, DbError> {
+        let db = self.db.conn().await?;
+        let select = "SELECT p.pokemon_id, p.name, p.slug, r.region_name, p.uuid, p.metadata
+                      FROM published_pokemon p JOIN region r ON r.region_id = p.region_id";
+        let rows = match key {
+            EntityKey::Id(id) => {
+                db.query(&format!("{} WHERE p.pokemon_id = $1", select), &[id])
+                    .await?
+            }
+            EntityKey::Uuid(uuid) => {
+                db.query(&format!("{} WHERE p.uuid = $1", select), &[uuid])
+                    .await?
+            }
+            EntityKey::Slug(slug) => {
+                db.query(&format!("{} WHERE p.slug = $1", select), &[slug])
+                    .await?
+            }
+        };
+
+        Ok(rows.first().map(pokemon_from_row))
+    }
+
+    async fn resolve_region(&self, region: &RegionRef) -> Result<Option<(i32, String)>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = match region {
+            RegionRef::Id(id) => {
+                db.query(
+                    "SELECT region_id, region_name FROM region WHERE region_id = $1",
+                    &[id],
+                )
+                .await?
+            }
+            RegionRef::Name(name) => {
+                db.query(
+                    "SELECT region_id, region_name FROM region WHERE region_name = $1",
+                    &[name],
+                )
+                .await?
+            }
+        };
+
+        Ok(rows.first().map(|r| (r.get(0), r.get(1))))
+    }
+
+    async fn count_by_region(&self, regions: &[String]) -> Result<Vec<(String, i64)>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT r.region_name, count(p.pokemon_id)
+                 FROM region r LEFT JOIN published_pokemon p ON p.region_id = r.region_id
+                 WHERE lower(r.region_name) = ANY($1)
+                 GROUP BY r.region_name
+                 ORDER BY r.region_name",
+                &[&regions],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    async fn group_counts(&self, grouping: Grouping) -> Result<Vec<(String, i64)>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = match grouping {
+            Grouping::Region => {
+                db.query(
+                    "SELECT r.region_name, count(*)
+                     FROM published_pokemon p JOIN region r ON r.region_id = p.region_id
+                     GROUP BY r.region_name ORDER BY r.region_name",
+                    &[],
+                )
+                .await?
+            }
+            Grouping::AbilityCount => {
+                db.query(
+                    "SELECT n::text, count(*) FROM (
+                         SELECT count(pa.ability_id) AS n
+                         FROM published_pokemon p
+                         LEFT JOIN pokemonabilities pa ON pa.pokemon_id = p.pokemon_id
+                         GROUP BY p.pokemon_id
+                     ) t GROUP BY n ORDER BY n",
+                    &[],
+                )
+                .await?
+            }
+            Grouping::Damage(bucket) => {
+                db.query(
+                    "SELECT ((damage / $1) * $1)::text, count(*)
+                     FROM published_ability GROUP BY damage / $1 ORDER BY damage / $1",
+                    &[&bucket],
+                )
+                .await?
+            }
+        };
+
+        Ok(rows.iter().map(|r| (r.get(0), r.get(1))).collect())
+    }
+
+    async fn unknown_abilities(&self, ids: &[i32]) -> Result<Vec<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT DISTINCT id FROM unnest($1::int4[]) AS ids (id)
+                 WHERE NOT EXISTS (SELECT 1 FROM ability WHERE ability_id = id)
+                 ORDER BY id",
+                &[&ids],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|r| r.get(0)).collect())
+    }
+
+    async fn create(&self, pokemon: NewPokemon) -> Result<Pokemon, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move { insert_pokemon(tx, &pokemon).await })
+        })
+        .await
+    }
+
+    async fn create_many(&self, pokemon: Vec<NewPokemon>) -> Result<Vec<i32>, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                let mut ids = Vec::with_capacity(pokemon.len());
+                for p in &pokemon {
+                    ids.push(insert_pokemon(tx, p).await?.pokemon_id);
+                }
+
+                Ok(ids)
+            })
+        })
+        .await
+    }
+
+    async fn update(
+        &self,
+        id: i32,
+        name: Option<&str>,
+        region_id: i32,
+        metadata: Option<&Metadata>,
+    ) -> Result<Option<Pokemon>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "UPDATE pokemon p
+                 SET name = COALESCE($2, p.name), region_id = r.region_id,
+                     metadata = COALESCE($4, p.metadata)
+                 FROM region r
+                 WHERE p.pokemon_id = $1 AND r.region_id = $3
+                 RETURNING p.pokemon_id, p.name, p.slug, r.region_name, p.uuid, p.metadata",
+                &[&id, &name, &region_id, &metadata.map(Json)],
+            )
+            .await?;
+
+        Ok(rows.first().map(pokemon_from_row))
+    }
+
+    async fn upsert(
+        &self,
+        name: &str,
+        region_id: i32,
+        metadata: Option<&Metadata>,
+    ) -> Result<(Pokemon, bool), DbError> {
+        let db = self.db.conn().await?;
+        // Keyed by slug, so `pikachu` finds `Pikachu` rather than clashing
+        // with it.
+        let rows = db
+            .query(
+                "INSERT INTO pokemon AS p (name, slug, region_id, metadata)
+                 VALUES ($1, $2, $3, COALESCE($4, '{}'))
+                 ON CONFLICT (slug) DO UPDATE
+                 SET region_id = EXCLUDED.region_id, metadata = COALESCE($4, p.metadata)
+                 RETURNING p.pokemon_id, p.name, p.slug,
+                           (SELECT region_name FROM region WHERE region_id = p.region_id),
+                           p.uuid, p.metadata, p.xmax = 0",
+                &[&name, &slugify(name), &region_id, &metadata.map(Json)],
+            )
+            .await?;
+        let r = rows.first().unwrap();
+
+        // xmax is only zero for a freshly inserted row version.
+        Ok((pokemon_from_row(r), r.get(6)))
+    }
+
+    async fn delete(&self, key: &EntityKey) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let (id, uuid, slug) = key_columns(key);
+        let rows = db
+            .query(
+                "WITH target AS (
+                     SELECT pokemon_id FROM pokemon
+                     WHERE pokemon_id = $1 OR uuid = $2 OR slug = $3
+                 ),
+                 abilities AS (
+                     DELETE FROM pokemonabilities WHERE pokemon_id IN (SELECT * FROM target)
+                 ),
+                 attributes AS (
+                     DELETE FROM pokemonattributes WHERE pokemon_id IN (SELECT * FROM target)
+                 )
+                 DELETE FROM pokemon WHERE pokemon_id IN (SELECT * FROM target)
+                 RETURNING pokemon_id",
+                &[&id, &uuid, &slug],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn publish_due(&self, now: OffsetDateTime) -> Result<Vec<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "UPDATE pokemon SET publish_at = NULL WHERE publish_at <= $1
+                 RETURNING pokemon_id",
+                &[&now],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|r| r.get(0)).collect())
+    }
+
+    async fn description(&self, key: &EntityKey) -> Result<Option<Description>, DbError> {
+        let db = self.db.conn().await?;
+        let (id, uuid, slug) = key_columns(key);
+        let rows = db
+            .query(
+                "SELECT description, lore FROM published_pokemon
+                 WHERE pokemon_id = $1 OR uuid = $2 OR slug = $3",
+                &[&id, &uuid, &slug],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| Description {
+            description: r.get(0),
+            lore: r.get(1),
+        }))
+    }
+
+    async fn set_description(
+        &self,
+        key: &EntityKey,
+        description: &Description,
+    ) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let (id, uuid, slug) = key_columns(key);
+        let rows = db
+            .query(
+                "UPDATE pokemon SET description = $4, lore = $5
+                 WHERE pokemon_id = $1 OR uuid = $2 OR slug = $3
+                 RETURNING pokemon_id",
+                &[
+                    &id,
+                    &uuid,
+                    &slug,
+                    &description.description,
+                    &description.lore,
+                ],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, DbError> {
+        let db = self.db.conn().await?;
+        // The GIN index on `search` serves the match; only the hits are
+        // ranked.
+        let rows = db
+            .query(
+                "SELECT pokemon_id, name, ts_rank(search, q)
+                 FROM published_pokemon, websearch_to_tsquery('english', $1) q
+                 WHERE search @@ q
+                 ORDER BY 3 DESC, pokemon_id
+                 LIMIT $2",
+                &[&query, &limit],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| SearchHit {
+                kind: "pokemon",
+                id: r.get(0),
+                name: r.get(1),
+                rank: r.get(2),
+            })
+            .collect())
+    }
+
+    async fn references(&self, pokemon_id: i32) -> Result<Vec<ExternalReference>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT reference_id, kind, value FROM external_reference
+                 WHERE pokemon_id = $1
+                 ORDER BY reference_id",
+                &[&pokemon_id],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| reference_from_row(r, "pokemon"))
+            .collect())
+    }
+
+    async fn add_reference(
+        &self,
+        key: &EntityKey,
+        reference: &NewReference,
+    ) -> Result<AddReference, DbError> {
+        let db = self.db.conn().await?;
+        let (id, uuid, slug) = key_columns(key);
+        // A conflict inserts nothing, which leaves the pokemon without a
+        // reference id.
+        let rows = db
+            .query(
+                "WITH target AS (
+                     SELECT pokemon_id FROM pokemon
+                     WHERE pokemon_id = $1 OR uuid = $2 OR slug = $3
+                 ), inserted AS (
+                     INSERT INTO external_reference (pokemon_id, kind, value)
+                     SELECT pokemon_id, $4, $5 FROM target
+                     ON CONFLICT DO NOTHING
+                     RETURNING reference_id
+                 )
+                 SELECT t.pokemon_id, i.reference_id
+                 FROM target t LEFT JOIN inserted i ON true",
+                &[
+                    &id,
+                    &uuid,
+                    &slug,
+                    &reference.kind.as_str(),
+                    &reference.value,
+                ],
+            )
+            .await?;
+
+        Ok(match rows.first() {
+            None => AddReference::NotFound,
+            Some(r) => match r.get::<_, Option<i32>>(1) {
+                Some(reference_id) => AddReference::Added(
+                    ExternalReference::new(
+                        reference_id,
+                        reference.kind,
+                        reference.value.clone(),
+                        "pokemon",
+                    ),
+                    r.get(0),
+                ),
+                None => AddReference::Duplicate,
+            },
+        })
+    }
+
+    async fn delete_reference(
+        &self,
+        key: &EntityKey,
+        reference_id: i32,
+    ) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let (id, uuid, slug) = key_columns(key);
+        let rows = db
+            .query(
+                "DELETE FROM external_reference r USING pokemon p
+                 WHERE r.pokemon_id = p.pokemon_id AND r.reference_id = $4
+                   AND (p.pokemon_id = $1 OR p.uuid = $2 OR p.slug = $3)
+                 RETURNING p.pokemon_id",
+                &[&id, &uuid, &slug, &reference_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn record_version(&self, pokemon_id: i32) -> Result<Option<Version>, DbError> {
+        record_version(&self.db, "pokemon", POKEMON_SNAPSHOT, pokemon_id).await
+    }
+
+    async fn versions(&self, key: &EntityKey) -> Result<Option<(i32, Vec<Version>)>, DbError> {
+        let db = self.db.conn().await?;
+        let (id, uuid, slug) = key_columns(key);
+        // The pokemon is left joined, so that it is told apart from one
+        // without versions.
+        let rows = db
+            .query(
+                "SELECT v.version, v.snapshot, v.recorded_at, p.pokemon_id FROM pokemon p
+                 LEFT JOIN pokemon_version v ON v.pokemon_id = p.pokemon_id
+                 WHERE p.pokemon_id = $1 OR p.uuid = $2 OR p.slug = $3
+                 ORDER BY v.version",
+                &[&id, &uuid, &slug],
+            )
+            .await?;
+        let Some(pokemon_id) = rows.first().map(|r| r.get(3)) else {
+            return Ok(None);
+        };
+
+        Ok(Some((
+            pokemon_id,
+            rows.iter()
+                .filter(|r| r.get::<_, Option<i32>>(0).is_some())
+                .map(version_from_row)
+                .collect(),
+        )))
+    }
+}
+
+/// The id, UUID and slug to match a pokemon against for `key`, only one of
+/// them set.
+fn key_columns(key: &EntityKey) -> (Option<i32>, Option<&Uuid>, Option<&String>) {
+    match key {
+        EntityKey::Id(id) => (Some(*id), None, None),
+        EntityKey::Uuid(uuid) => (None, Some(uuid), None),
+        EntityKey::Slug(slug) => (None, None, Some(slug)),
+    }
+}
+
+/// A pokemon from the id, name, slug, region name, UUID and metadata
+/// columns of `r`.
+fn pokemon_from_row(r: &Row) -> Pokemon {
+    Pokemon {
+        pokemon_id: r.get(0),
+        uuid: r.get(4),
+        name: r.get(1),
+        slug: r.get(2),
+        region: r.get(3),
+        metadata: metadata_at(r, 5),
+    }
+}
+
+async fn insert_pokemon(tx: &DbTransaction<'_>, pokemon: &NewPokemon) -> Result<Pokemon, DbError> {
+    // Tries `slug`, `slug-2`, `slug-3`, ... until one is free. The unique
+    // index settles races, and a taken slug leaves the transaction usable.
+    let base = slugify(&pokemon.name);
+    let mut suffix = 1;
+    let stored = loop {
+        let slug = match suffix {
+            1 => base.clone(),
+            n => format!("{}-{}", base, n),
+        };
+        let rows = tx
+            .query(
+                "INSERT INTO pokemon AS p (name, slug, region_id, publish_at, metadata)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (slug) DO NOTHING
+                 RETURNING p.pokemon_id, p.name, p.slug,
+                           (SELECT region_name FROM region WHERE region_id = p.region_id),
+                           p.uuid, p.metadata",
+                &[
+                    &pokemon.name,
+                    &slug,
+                    &pokemon.region_id,
+                    &pokemon.publish_at,
+                    &Json(&pokemon.metadata),
+                ],
+            )
+            .await?;
+        match rows.first() {
+            Some(r) => break pokemon_from_row(r),
+            None => suffix += 1,
+        }
+    };
+    let pokemon_id = stored.pokemon_id;
+
+    if !pokemon.abilities.is_empty() {
+        tx.execute(
+            "INSERT INTO pokemonabilities (pokemon_id, ability_id)
+             SELECT $1, unnest($2::int4[])
+             ON CONFLICT DO NOTHING",
+            &[&pokemon_id, &pokemon.abilities],
+        )
+        .await?;
+    }
+
+    Ok(stored)
+}
@@ -0,0 +1,192 @@
+use super::in_transaction;
+use crate::db::{Db, DbError};
+use crate::models::QuizScore;
+use axum::async_trait;
+use std::sync::Arc;
+
+/// Questions of the "Who's that pokemon?" quiz and the scores they add up
+/// to.
+#[async_trait]
+pub(crate) trait QuizRepository: Send + Sync {
+    /// Asks `trainer_id` about one of the published pokemon with a sprite,
+    /// the `pick`th of them in id order, wrapping around. `None` if no
+    /// published pokemon has a sprite.
+    async fn ask(&self, trainer_id: i32, pick: u64) -> Result<Option<NewQuestion>, DbError>;
+
+    /// Question `question_id`, if it was asked of `trainer_id`.
+    async fn question(
+        &self,
+        question_id: i32,
+        trainer_id: i32,
+    ) -> Result<Option<AskedQuestion>, DbError>;
+
+    /// Marks the question answered and adds the answer to the score of the
+    /// trainer it was asked of, in one transaction. `None` if it was already
+    /// answered, so concurrent answers can't both count.
+    async fn answer(&self, question_id: i32, correct: bool) -> Result<Option<QuizScore>, DbError>;
+
+    /// The `limit` trainers with the most right answers, the fewer answers
+    /// the better among equals.
+    async fn leaderboard(&self, limit: i64) -> Result<Vec<QuizScore>, DbError>;
+}
+
+pub(crate) struct NewQuestion {
+    pub(crate) question_id: i32,
+    pub(crate) name: String,
+    pub(crate) sprite_url: String,
+}
+
+pub(crate) struct AskedQuestion {
+    /// The pokemon's name as it is now.
+    pub(crate) name: String,
+    pub(crate) answered: bool,
+}
+
+pub(crate) struct PgQuizRepository {
+    db: Arc<Db>,
+}
+
+impl PgQuizRepository {
+    pub(crate) fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+}
+
+/// Published pokemon with the first sprite attached to each, as columns
+/// `pokemon_id`, `name` and `sprite_url`.
+const QUIZ_POKEMON: &str = "SELECT p.pokemon_id, p.name, s.value AS sprite_url
+     FROM published_pokemon p
+     JOIN LATERAL (SELECT r.value FROM external_reference r
+                   WHERE r.pokemon_id = p.pokemon_id AND r.kind = 'sprite'
+                   ORDER BY r.reference_id LIMIT 1) s ON true";
+
+#[async_trait]
+impl QuizRepository for PgQuizRepository {
+    async fn ask(&self, trainer_id: i32, pick: u64) -> Result<Option<NewQuestion>, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                let count: i64 = tx
+                    .query_one(&format!("SELECT count(*) FROM ({}) q", QUIZ_POKEMON), &[])
+                    .await?
+                    .get(0);
+                if count == 0 {
+                    return Ok(None);
+                }
+
+                let offset = (pick % count as u64) as i64;
+                let row = tx
+                    .query_one(
+                        &format!(
+                            "WITH picked AS ({} ORDER BY p.pokemon_id OFFSET $2 LIMIT 1),
+                                  asked AS (
+                                      INSERT INTO quiz_question (trainer_id, pokemon_id)
+                                      SELECT $1, pokemon_id FROM picked
+                                      RETURNING question_id
+                                  )
+                             SELECT asked.question_id, picked.name, picked.sprite_url
+                             FROM asked, picked",
+                            QUIZ_POKEMON
+                        ),
+                        &[&trainer_id, &offset],
+                    )
+                    .await?;
+
+                Ok(Some(NewQuestion {
+                    question_id: row.get(0),
+                    name: row.get(1),
+                    sprite_url: row.get(2),
+                }))
+            })
+        })
+        .await
+    }
+
+    async fn question(
+        &self,
+        question_id: i32,
+        trainer_id: i32,
+    ) -> Result<Option<AskedQuestion>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT p.name, q.answered_at IS NOT NULL
+                 FROM quiz_question q
+                 JOIN pokemon p ON p.pokemon_id = q.pokemon_id
+                 WHERE q.question_id = $1 AND q.trainer_id = $2",
+                &[&question_id, &trainer_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| AskedQuestion {
+            name: r.get(0),
+            answered: r.get(1),
+        }))
+    }
+
+    async fn answer(&self, question_id: i32, correct: bool) -> Result<Option<QuizScore>, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                let rows = tx
+                    .query(
+                        "UPDATE quiz_question SET answered_at = now(), correct = $2
+                         WHERE question_id = $1 AND answered_at IS NULL
+                         RETURNING trainer_id",
+                        &[&question_id, &correct],
+                    )
+                    .await?;
+                let Some(r) = rows.first() else {
+                    return Ok(None);
+                };
+                let trainer_id: i32 = r.get(0);
+
+                let row = tx
+                    .query_one(
+                        "WITH scored AS (
+                             INSERT INTO quiz_score (trainer_id, answered, correct)
+                             VALUES ($1, 1, $2::bool::int)
+                             ON CONFLICT (trainer_id) DO UPDATE SET
+                                 answered = quiz_score.answered + 1,
+                                 correct = quiz_score.correct + EXCLUDED.correct
+                             RETURNING answered, correct
+                         )
+                         SELECT t.name, s.correct, s.answered
+                         FROM scored s, trainer t WHERE t.trainer_id = $1",
+                        &[&trainer_id, &correct],
+                    )
+                    .await?;
+
+                Ok(Some(QuizScore {
+                    trainer_id,
+                    name: row.get(0),
+                    correct: row.get(1),
+                    answered: row.get(2),
+                }))
+            })
+        })
+        .await
+    }
+
+    async fn leaderboard(&self, limit: i64) -> Result<Vec<QuizScore>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT s.trainer_id, t.name, s.correct, s.answered
+                 FROM quiz_score s
+                 JOIN trainer t ON t.trainer_id = s.trainer_id
+                 ORDER BY s.correct DESC, s.answered, s.trainer_id
+                 LIMIT $1",
+                &[&limit],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| QuizScore {
+                trainer_id: r.get(0),
+                name: r.get(1),
+                correct: r.get(2),
+                answered: r.get(3),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,721 @@
+use super::{count_rows, in_transaction, metadata_at};
+use crate::ai::{Difficulty, Strategy};
+use crate::db::{Db, DbError, DbTransaction};
+use crate::models::{
+    generation_of, Ability, Attribute, IdOrUuid, Ivs, Metadata, OwnedPokemon, PageParams, PastName,
+    Pokemon, PokemonFull, SortParams, TotalCount, Trainer,
+};
+use axum::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_postgres::types::{Json, ToSql};
+use tokio_postgres::Row;
+
+#[async_trait]
+pub(crate) trait TrainerRepository: Send + Sync {
+    /// One page of trainers with their pokemon, and the total trainer count.
+    /// With `metadata`, only the trainers whose metadata contains it count.
+    async fn list(
+        &self,
+        metadata: Option<&Metadata>,
+        sort: &SortParams,
+        paging: &PageParams,
+    ) -> Result<(Vec<Trainer>, TotalCount), DbError>;
+
+    /// The trainer without its pokemon.
+    async fn get(&self, id: i32) -> Result<Option<Trainer>, DbError>;
+
+    /// The id of the trainer `key` names. A numeric id is returned as is,
+    /// without checking that the trainer exists.
+    async fn resolve(&self, key: IdOrUuid) -> Result<Option<i32>, DbError>;
+
+    /// The trainer's published pokemon, by id.
+    async fn pokemon(&self, trainer_id: i32) -> Result<Vec<Pokemon>, DbError>;
+
+    /// Like `pokemon`, but with each pokemon's abilities and attributes.
+    async fn team(&self, trainer_id: i32) -> Result<Vec<PokemonFull>, DbError> {
+        let mut teams = self.teams(&[trainer_id]).await?;
+
+        Ok(teams.remove(&trainer_id).unwrap_or_default())
+    }
+
+    /// `team` for each of the trainers at once. Trainers without published
+    /// pokemon, or that don't exist, are left out.
+    async fn teams(&self, trainer_ids: &[i32]) -> Result<HashMap<i32, Vec<PokemonFull>>, DbError>;
+
+    /// Inserts a trainer and returns its id. Fails with `NameTaken` if
+    /// another trainer has the name, or gave it up in the last 30 days;
+    /// so do every other insert and rename.
+    async fn create(&self, name: &str, gym_leader: bool) -> Result<i32, DbError>;
+
+    /// Inserts every `(name, gym_leader)` and returns their ids in order.
+    /// Nothing is inserted if any of them fails.
+    async fn create_many(&self, trainers: &[(&str, bool)]) -> Result<Vec<i32>, DbError>;
+
+    /// Inserts a computer-controlled trainer along with its team, in one
+    /// transaction. Nothing is inserted if the team names a pokemon that
+    /// doesn't exist.
+    async fn create_ai(
+        &self,
+        name: &str,
+        strategy: Strategy,
+        difficulty: Difficulty,
+        team: Vec<i32>,
+    ) -> Result<CreateAi, DbError>;
+
+    /// Applies `changes` to the trainer in one transaction. Nothing changes
+    /// if the new team names a pokemon that doesn't exist.
+    async fn update(&self, id: i32, changes: TrainerChanges) -> Result<Update, DbError>;
+
+    /// Deletes the trainer. One that still has pokemon is only deleted with
+    /// `release_team`, which detaches them in the same transaction.
+    async fn delete(&self, id: i32, release_team: bool) -> Result<Delete, DbError>;
+
+    /// Whether both the trainer and the pokemon exist.
+    async fn exists_with_pokemon(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, DbError>;
+
+    /// Attaches the pokemon unless it already is, or the trainer already
+    /// has `max_team_size` pokemon.
+    async fn attach(
+        &self,
+        trainer_id: i32,
+        pokemon_id: i32,
+        max_team_size: usize,
+    ) -> Result<Attach, DbError>;
+
+    /// Returns `false` when the pokemon was not attached.
+    async fn detach(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, DbError>;
+
+    /// The trainer the user plays as and whether they're an admin, or
+    /// `None` for an unknown user.
+    async fn account(&self, user_id: i32) -> Result<Option<Account>, DbError>;
+
+    /// The trainer's published pokemon as the instances it owns, oldest
+    /// first.
+    async fn owned(&self, trainer_id: i32) -> Result<Vec<OwnedPokemon>, DbError>;
+
+    /// One owned pokemon, whoever owns it, unless its species is unpublished.
+    async fn instance(&self, instance_id: i32) -> Result<Option<OwnedPokemon>, DbError>;
+
+    /// Sets or clears an owned pokemon's nickname. Returns whether it exists.
+    async fn set_nickname(&self, instance_id: i32, nickname: Option<&str>)
+        -> Result<bool, DbError>;
+
+    /// The names the trainer went by before, latest first.
+    async fn past_names(&self, trainer_id: i32) -> Result<Vec<PastName>, DbError>;
+}
+
+pub(crate) struct Account {
+    /// `None` once the account's trainer was deleted.
+    pub(crate) trainer_id: Option<i32>,
+    pub(crate) is_admin: bool,
+}
+
+pub(crate) enum Delete {
+    Deleted,
+    NotFound,
+    /// The trainer still has this many pokemon and was kept.
+    HasTeam(i64),
+}
+
+/// What to change on a trainer; `None` keeps the current value.
+pub(crate) struct TrainerChanges {
+    pub(crate) name: Option<String>,
+    pub(crate) gym_leader: Option<bool>,
+    /// Replaces the whole team; an empty list releases every pokemon.
+    pub(crate) team: Option<Vec<i32>>,
+    /// Replaces the whole object.
+    pub(crate) metadata: Option<Metadata>,
+}
+
+pub(crate) enum Update {
+    Updated,
+    NotFound,
+    /// These pokemon of the new team don't exist, so nothing was changed.
+    UnknownPokemon(Vec<i32>),
+}
+
+pub(crate) enum CreateAi {
+    Created(i32),
+    /// These pokemon of the team don't exist.
+    UnknownPokemon(Vec<i32>),
+}
+
+pub(crate) enum Attach {
+    Attached,
+    AlreadyAttached,
+    TeamFull,
+}
+
+pub(crate) struct PgTrainerRepository {
+    db: Arc<Db>,
+}
+
+impl PgTrainerRepository {
+    pub(crate) fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl TrainerRepository for PgTrainerRepository {
+    async fn list(
+        &self,
+        metadata: Option<&Metadata>,
+        sort: &SortParams,
+        paging: &PageParams,
+    ) -> Result<(Vec<Trainer>, TotalCount), DbError> {
+        let mut db = self.db.conn().await?;
+        // The count and the page see the same snapshot, so they agree.
+        let tx = db.read_only().await?;
+
+        let metadata = metadata.map(Json);
+        let mut from = "FROM trainer t".to_string();
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        // Served by the GIN index on `metadata`.
+        if let Some(metadata) = &metadata {
+            params.push(metadata);
+            from.push_str(" WHERE t.metadata @> $1");
+        }
+        // Table statistics only describe the unfiltered table.
+        let table = metadata.is_none().then_some("trainer");
+
+        // Rows for the same trainer must be adjacent so they can be folded
+        // together, so the trainer id always follows the requested sort. The
+        // page is cut from the trainers alone, before joining their pokemon.
+        let order = match sort.order_by("t.name") {
+            order if order.is_empty() => " ORDER BY t.trainer_id".to_string(),
+            order => format!("{}, t.trainer_id", order),
+        };
+        let sql = format!(
+            "SELECT t.trainer_id, t.name, t.gym_leader, t.rewards, p.pokemon_id, p.name,
+                    p.slug, r.region_name, t.uuid, p.uuid, t.metadata, p.metadata
+             FROM (SELECT t.* {from}{order} LIMIT ${} OFFSET ${}) t
+             LEFT JOIN trainerspokemon tp ON tp.trainer_id = t.trainer_id
+             LEFT JOIN published_pokemon p ON p.pokemon_id = tp.pokemon_id
+             LEFT JOIN region r ON r.region_id = p.region_id{order}, p.pokemon_id",
+            params.len() + 1,
+            params.len() + 2
+        );
+
+        let total_count = count_rows(&tx, paging.count(), &from, &params, table).await?;
+
+        // The page bounds the trainers but not how many pokemon each has.
+        let (per_page, offset) = (paging.per_page(), paging.offset());
+        params.push(&per_page);
+        params.push(&offset);
+        let rows = tx.query_capped(&sql, &params).await?;
+        tx.commit().await?;
+        let mut trainers: Vec<Trainer> = Vec::new();
+        for r in rows {
+            let trainer_id: i32 = r.get(0);
+            if trainers.last().map(|t| t.trainer_id) != Some(trainer_id) {
+                trainers.push(Trainer {
+                    trainer_id,
+                    uuid: r.get(8),
+                    name: r.get(1),
+                    gym_leader: r.get(2),
+                    rewards: r.get(3),
+                    metadata: metadata_at(&r, 10),
+                    pokemon: Some(Vec::new()),
+                });
+            }
+
+            // A trainer without pokemon still yields one row of NULLs.
+            let Some(pokemon_id) = r.get::<_, Option<i32>>(4) else {
+                continue;
+            };
+            let trainer = trainers.last_mut().unwrap();
+            trainer.pokemon.get_or_insert_with(Vec::new).push(Pokemon {
+                pokemon_id,
+                uuid: r.get(9),
+                name: r.get(5),
+                slug: r.get(6),
+                region: r.get(7),
+                metadata: metadata_at(&r, 11),
+            });
+        }
+
+        Ok((trainers, total_count))
+    }
+
+    async fn get(&self, id: i32) -> Result<Option<Trainer>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT trainer_id, name, gym_leader, rewards, uuid, metadata FROM trainer
+                 WHERE trainer_id = $1",
+                &[&id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| Trainer {
+            trainer_id: r.get(0),
+            uuid: r.get(4),
+            name: r.get(1),
+            gym_leader: r.get(2),
+            rewards: r.get(3),
+            metadata: metadata_at(r, 5),
+            pokemon: None,
+        }))
+    }
+
+    async fn resolve(&self, key: IdOrUuid) -> Result<Option<i32>, DbError> {
+        let uuid = match key {
+            IdOrUuid::Id(id) => return Ok(Some(id)),
+            IdOrUuid::Uuid(uuid) => uuid,
+        };
+        let db = self.db.conn().await?;
+        let rows = db
+            .query("SELECT trainer_id FROM trainer WHERE uuid = $1", &[&uuid])
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn pokemon(&self, trainer_id: i32) -> Result<Vec<Pokemon>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query_capped(
+                "SELECT p.pokemon_id, p.name, p.slug, r.region_name, p.uuid, p.metadata
+                 FROM trainerspokemon tp
+                 JOIN published_pokemon p ON p.pokemon_id = tp.pokemon_id
+                 JOIN region r ON r.region_id = p.region_id
+                 WHERE tp.trainer_id = $1
+                 ORDER BY p.pokemon_id",
+                &[&trainer_id],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| Pokemon {
+                pokemon_id: r.get(0),
+                uuid: r.get(4),
+                name: r.get(1),
+                slug: r.get(2),
+                region: r.get(3),
+                metadata: metadata_at(r, 5),
+            })
+            .collect())
+    }
+
+    async fn teams(&self, trainer_ids: &[i32]) -> Result<HashMap<i32, Vec<PokemonFull>>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query_capped(
+                "SELECT tp.trainer_id, p.pokemon_id, p.name, p.slug, r.region_name,
+                        COALESCE((
+                            SELECT json_agg(json_build_object(
+                                       'ability_id', a.ability_id,
+                                       'name', a.name,
+                                       'damage', a.damage,
+                                       'status_effect', a.status_effect
+                                   ) ORDER BY a.ability_id)
+                            FROM pokemonabilities pa
+                            JOIN published_ability a ON a.ability_id = pa.ability_id
+                            WHERE pa.pokemon_id = p.pokemon_id
+                        ), '[]'),
+                        COALESCE((
+                            SELECT json_agg(json_build_object(
+                                       'attribute_id', a.attribute_id,
+                                       'attribute_name', a.attribute_name,
+                                       'weakness', a.weakness
+                                   ) ORDER BY a.attribute_id)
+                            FROM pokemonattributes pa
+                            JOIN attribute a ON a.attribute_id = pa.attribute_id
+                            WHERE pa.pokemon_id = p.pokemon_id
+                        ), '[]'),
+                        p.uuid, p.metadata
+                 FROM trainerspokemon tp
+                 JOIN published_pokemon p ON p.pokemon_id = tp.pokemon_id
+                 JOIN region r ON r.region_id = p.region_id
+                 WHERE tp.trainer_id = ANY($1)
+                 ORDER BY p.pokemon_id",
+                &[&trainer_ids],
+            )
+            .await?;
+
+        let mut teams: HashMap<i32, Vec<PokemonFull>> = HashMap::new();
+        for r in rows {
+            let region: String = r.get(4);
+            let Json(abilities): Json<Vec<Ability>> = r.get(5);
+            let Json(attributes): Json<Vec<Attribute>> = r.get(6);
+            teams.entry(r.get(0)).or_default().push(PokemonFull {
+                pokemon_id: r.get(1),
+                uuid: r.get(7),
+                name: r.get(2),
+                slug: r.get(3),
+                generation: generation_of(&region),
+                region,
+                abilities,
+                attributes,
+                metadata: metadata_at(&r, 8),
+            });
+        }
+
+        Ok(teams)
+    }
+
+    async fn create(&self, name: &str, gym_leader: bool) -> Result<i32, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "INSERT INTO trainer (name, gym_leader) VALUES ($1, $2) RETURNING trainer_id",
+                &[&name, &gym_leader],
+            )
+            .await?;
+
+        Ok(rows.first().unwrap().get(0))
+    }
+
+    async fn create_many(&self, trainers: &[(&str, bool)]) -> Result<Vec<i32>, DbError> {
+        let (names, gym_leaders): (Vec<&str>, Vec<bool>) = trainers.iter().copied().unzip();
+
+        // One statement, so it is all or nothing by itself. Ids are drawn in
+        // `ORDER BY n` order, so sorting them puts them in the input's order.
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "INSERT INTO trainer (name, gym_leader)
+                 SELECT name, gym_leader
+                 FROM unnest($1::text[], $2::bool[]) WITH ORDINALITY AS t (name, gym_leader, n)
+                 ORDER BY n
+                 RETURNING trainer_id",
+                &[&names, &gym_leaders],
+            )
+            .await?;
+        let mut ids: Vec<i32> = rows.iter().map(|r| r.get(0)).collect();
+        ids.sort_unstable();
+
+        Ok(ids)
+    }
+
+    async fn create_ai(
+        &self,
+        name: &str,
+        strategy: Strategy,
+        difficulty: Difficulty,
+        team: Vec<i32>,
+    ) -> Result<CreateAi, DbError> {
+        let name = name.to_string();
+
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                let unknown = unknown_pokemon(tx, &team).await?;
+                if !unknown.is_empty() {
+                    return Ok(CreateAi::UnknownPokemon(unknown));
+                }
+
+                let row = tx
+                    .query_one(
+                        "INSERT INTO trainer (name, gym_leader, ai_strategy, ai_difficulty)
+                         VALUES ($1, false, $2, $3) RETURNING trainer_id",
+                        &[&name, &strategy.name(), &difficulty.name()],
+                    )
+                    .await?;
+                let id = row.get(0);
+                replace_team(tx, id, &team).await?;
+
+                Ok(CreateAi::Created(id))
+            })
+        })
+        .await
+    }
+
+    async fn update(&self, id: i32, changes: TrainerChanges) -> Result<Update, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                // The lock keeps the team from being attached to while it is
+                // replaced.
+                let rows = tx
+                    .query(
+                        "SELECT 1 FROM trainer WHERE trainer_id = $1 FOR UPDATE",
+                        &[&id],
+                    )
+                    .await?;
+                if rows.is_empty() {
+                    return Ok(Update::NotFound);
+                }
+
+                if let Some(team) = &changes.team {
+                    let unknown = unknown_pokemon(tx, team).await?;
+                    if !unknown.is_empty() {
+                        return Ok(Update::UnknownPokemon(unknown));
+                    }
+                }
+
+                tx.execute(
+                    "UPDATE trainer
+                     SET name = COALESCE($2, name), gym_leader = COALESCE($3, gym_leader),
+                         metadata = COALESCE($4, metadata)
+                     WHERE trainer_id = $1",
+                    &[
+                        &id,
+                        &changes.name,
+                        &changes.gym_leader,
+                        &changes.metadata.as_ref().map(Json),
+                    ],
+                )
+                .await?;
+
+                if let Some(team) = &changes.team {
+                    replace_team(tx, id, team).await?;
+                }
+
+                Ok(Update::Updated)
+            })
+        })
+        .await
+    }
+
+    async fn delete(&self, id: i32, release_team: bool) -> Result<Delete, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                // The lock keeps pokemon from being attached between the
+                // check and the delete.
+                let rows = tx
+                    .query(
+                        "SELECT (SELECT count(*) FROM trainerspokemon WHERE trainer_id = $1)
+                         FROM trainer WHERE trainer_id = $1 FOR UPDATE",
+                        &[&id],
+                    )
+                    .await?;
+                let Some(row) = rows.first() else {
+                    return Ok(Delete::NotFound);
+                };
+                let team_size: i64 = row.get(0);
+                if team_size > 0 && !release_team {
+                    return Ok(Delete::HasTeam(team_size));
+                }
+
+                tx.execute("DELETE FROM trainerspokemon WHERE trainer_id = $1", &[&id])
+                    .await?;
+                tx.execute("DELETE FROM trainer WHERE trainer_id = $1", &[&id])
+                    .await?;
+
+                Ok(Delete::Deleted)
+            })
+        })
+        .await
+    }
+
+    async fn exists_with_pokemon(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT EXISTS (SELECT 1 FROM trainer WHERE trainer_id = $1)
+                        AND EXISTS (SELECT 1 FROM published_pokemon WHERE pokemon_id = $2)",
+                &[&trainer_id, &pokemon_id],
+            )
+            .await?;
+
+        Ok(rows.first().unwrap().get(0))
+    }
+
+    async fn attach(
+        &self,
+        trainer_id: i32,
+        pokemon_id: i32,
+        max_team_size: usize,
+    ) -> Result<Attach, DbError> {
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                // Locking the trainer serializes concurrent attaches, so two
+                // of them can't both see room for one more pokemon. Each
+                // statement below reads the team as it is once the lock is
+                // held.
+                tx.execute(
+                    "SELECT 1 FROM trainer WHERE trainer_id = $1 FOR UPDATE",
+                    &[&trainer_id],
+                )
+                .await?;
+                let rows = tx
+                    .query(
+                        "SELECT count(*), bool_or(pokemon_id = $2)
+                         FROM trainerspokemon WHERE trainer_id = $1",
+                        &[&trainer_id, &pokemon_id],
+                    )
+                    .await?;
+                let team_size: i64 = rows[0].get(0);
+                if rows[0].get::<_, Option<bool>>(1) == Some(true) {
+                    return Ok(Attach::AlreadyAttached);
+                }
+                if team_size >= max_team_size as i64 {
+                    return Ok(Attach::TeamFull);
+                }
+
+                tx.execute(
+                    "INSERT INTO trainerspokemon (trainer_id, pokemon_id) VALUES ($1, $2)",
+                    &[&trainer_id, &pokemon_id],
+                )
+                .await?;
+
+                Ok(Attach::Attached)
+            })
+        })
+        .await
+    }
+
+    async fn detach(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, DbError> {
+        let db = self.db.conn().await?;
+        let deleted = db
+            .execute(
+                "DELETE FROM trainerspokemon WHERE trainer_id = $1 AND pokemon_id = $2",
+                &[&trainer_id, &pokemon_id],
+            )
+            .await?;
+
+        Ok(deleted > 0)
+    }
+
+    async fn account(&self, user_id: i32) -> Result<Option<Account>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT trainer_id, is_admin FROM users WHERE user_id = $1",
+                &[&user_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| Account {
+            trainer_id: r.get(0),
+            is_admin: r.get(1),
+        }))
+    }
+
+    async fn owned(&self, trainer_id: i32) -> Result<Vec<OwnedPokemon>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query_capped(
+                &format!(
+                    "{} WHERE tp.trainer_id = $1 ORDER BY tp.instance_id",
+                    SELECT_OWNED
+                ),
+                &[&trainer_id],
+            )
+            .await?;
+
+        Ok(rows.iter().map(owned_from_row).collect())
+    }
+
+    async fn instance(&self, instance_id: i32) -> Result<Option<OwnedPokemon>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                &format!("{} WHERE tp.instance_id = $1", SELECT_OWNED),
+                &[&instance_id],
+            )
+            .await?;
+
+        Ok(rows.first().map(owned_from_row))
+    }
+
+    async fn set_nickname(
+        &self,
+        instance_id: i32,
+        nickname: Option<&str>,
+    ) -> Result<bool, DbError> {
+        let db = self.db.conn().await?;
+        let updated = db
+            .execute(
+                "UPDATE trainerspokemon SET nickname = $2 WHERE instance_id = $1",
+                &[&instance_id, &nickname],
+            )
+            .await?;
+
+        Ok(updated > 0)
+    }
+
+    async fn past_names(&self, trainer_id: i32) -> Result<Vec<PastName>, DbError> {
+        let db = self.db.conn().await?;
+        // Renames and deletes are recorded by triggers; see V22.
+        let rows = db
+            .query_capped(
+                "SELECT name, released_at FROM trainer_name_history
+                 WHERE trainer_id = $1
+                 ORDER BY released_at DESC, history_id DESC",
+                &[&trainer_id],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| PastName {
+                name: r.get(0),
+                released_at: r.get(1),
+            })
+            .collect())
+    }
+}
+
+/// The ids in `team` that aren't published pokemon, in order.
+async fn unknown_pokemon(tx: &DbTransaction<'_>, team: &[i32]) -> Result<Vec<i32>, DbError> {
+    let rows = tx
+        .query(
+            "SELECT DISTINCT id FROM unnest($1::int4[]) AS ids (id)
+             WHERE NOT EXISTS (SELECT 1 FROM published_pokemon WHERE pokemon_id = id)
+             ORDER BY id",
+            &[&team],
+        )
+        .await?;
+
+    Ok(rows.iter().map(|r| r.get(0)).collect())
+}
+
+/// Owned pokemon joined with their published species, for `owned_from_row`.
+const SELECT_OWNED: &str = "SELECT tp.instance_id, tp.trainer_id, tp.nickname, tp.level,
+           tp.iv_hp, tp.iv_attack, tp.iv_defense, tp.iv_special_attack,
+           tp.iv_special_defense, tp.iv_speed,
+           p.pokemon_id, p.name, p.slug, r.region_name, p.uuid, p.metadata
+    FROM trainerspokemon tp
+    JOIN published_pokemon p ON p.pokemon_id = tp.pokemon_id
+    JOIN region r ON r.region_id = p.region_id";
+
+fn owned_from_row(r: &Row) -> OwnedPokemon {
+    OwnedPokemon {
+        instance_id: r.get(0),
+        trainer_id: r.get(1),
+        nickname: r.get(2),
+        level: r.get(3),
+        ivs: Ivs {
+            hp: r.get(4),
+            attack: r.get(5),
+            defense: r.get(6),
+            special_attack: r.get(7),
+            special_defense: r.get(8),
+            speed: r.get(9),
+        },
+        species: Pokemon {
+            pokemon_id: r.get(10),
+            name: r.get(11),
+            slug: r.get(12),
+            region: r.get(13),
+            uuid: r.get(14),
+            metadata: metadata_at(r, 15),
+        },
+    }
+}
+
+/// Releases the pokemon not in `team` and catches the new ones. Those the
+/// trainer keeps stay the same instances, nickname and all.
+async fn replace_team(
+    tx: &DbTransaction<'_>,
+    trainer_id: i32,
+    team: &[i32],
+) -> Result<(), DbError> {
+    tx.execute(
+        "DELETE FROM trainerspokemon WHERE trainer_id = $1 AND pokemon_id <> ALL($2)",
+        &[&trainer_id, &team],
+    )
+    .await?;
+    tx.execute(
+        "INSERT INTO trainerspokemon (trainer_id, pokemon_id)
+         SELECT $1, id FROM unnest($2::int4[]) AS ids (id)
+         ON CONFLICT DO NOTHING",
+        &[&trainer_id, &team],
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,344 @@
+use super::{in_transaction, metadata_at};
+use crate::db::{Db, DbError};
+use crate::models::{
+    Ability, Bookmark, CatalogEntry, CatalogKind, Pokemon, RecentView, VariantExposures,
+};
+use axum::async_trait;
+use std::sync::Arc;
+use time::OffsetDateTime;
+
+/// What each signed-in user keeps for themselves, apart from their trainer.
+#[async_trait]
+pub(crate) trait UserRepository: Send + Sync {
+    /// Inserts an account without a trainer and returns its id, or `None`
+    /// if the username is taken.
+    async fn create(&self, username: &str, password_hash: &str) -> Result<Option<i32>, DbError>;
+
+    /// Makes the user play as the trainer.
+    async fn set_trainer(&self, user_id: i32, trainer_id: i32) -> Result<(), DbError>;
+
+    /// Deletes an account nothing was kept for yet, such as one whose
+    /// trainer couldn't be created.
+    async fn delete(&self, user_id: i32) -> Result<(), DbError>;
+
+    /// Notes that the user just viewed the pokemon or ability, moving it to
+    /// the front of their recent views. Only the newest `keep` are kept.
+    async fn record_view(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+        keep: i64,
+    ) -> Result<(), DbError>;
+
+    /// What the user viewed, newest first. Entities deleted or unpublished
+    /// since are left out.
+    async fn recent(&self, user_id: i32) -> Result<Vec<RecentView>, DbError>;
+
+    /// Bookmarks the published pokemon or ability for the user.
+    async fn add_bookmark(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<AddBookmark, DbError>;
+
+    /// Returns whether the user had bookmarked the entity.
+    async fn delete_bookmark(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<bool, DbError>;
+
+    /// What the user bookmarked, newest first. Entities deleted or
+    /// unpublished since are left out.
+    async fn bookmarks(&self, user_id: i32) -> Result<Vec<Bookmark>, DbError>;
+
+    /// Whether the user bookmarked the entity, even if it was deleted since.
+    async fn is_bookmarked(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<bool, DbError>;
+
+    /// Notes that the user was shown the variant of the experiment.
+    async fn record_exposure(
+        &self,
+        user_id: i32,
+        experiment: &str,
+        variant: &str,
+    ) -> Result<(), DbError>;
+
+    /// Users and exposures of each variant of the experiment that was shown
+    /// at all, by variant.
+    async fn exposures(&self, experiment: &str) -> Result<Vec<VariantExposures>, DbError>;
+}
+
+pub(crate) enum AddBookmark {
+    Added,
+    AlreadyAdded,
+    /// No such published pokemon or ability.
+    NotFound,
+}
+
+pub(crate) struct PgUserRepository {
+    db: Arc<Db>,
+}
+
+impl PgUserRepository {
+    pub(crate) fn new(db: Arc<Db>) -> Self {
+        Self { db }
+    }
+
+    /// The user's rows of `table`, `recent_view` or `bookmark`, newest first
+    /// by the `at` column, with the pokemon or ability each names. Those no
+    /// longer published are left out.
+    async fn entries(
+        &self,
+        table: &str,
+        at: &str,
+        user_id: i32,
+    ) -> Result<Vec<(CatalogEntry, OffsetDateTime)>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                &format!(
+                    "SELECT e.{at},
+                            p.pokemon_id, p.name, p.slug, r.region_name, p.uuid, p.metadata,
+                            a.ability_id, a.name, a.damage, a.status_effect
+                     FROM {table} e
+                     LEFT JOIN published_pokemon p
+                         ON e.kind = 'pokemon' AND p.pokemon_id = e.entity_id
+                     LEFT JOIN region r ON r.region_id = p.region_id
+                     LEFT JOIN published_ability a
+                         ON e.kind = 'ability' AND a.ability_id = e.entity_id
+                     WHERE e.user_id = $1
+                       AND (p.pokemon_id IS NOT NULL OR a.ability_id IS NOT NULL)
+                     ORDER BY e.{at} DESC",
+                    at = at,
+                    table = table,
+                ),
+                &[&user_id],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| {
+                let entry = match r.get::<_, Option<i32>>(1) {
+                    Some(pokemon_id) => CatalogEntry::Pokemon(Pokemon {
+                        pokemon_id,
+                        name: r.get(2),
+                        slug: r.get(3),
+                        region: r.get(4),
+                        uuid: r.get(5),
+                        metadata: metadata_at(r, 6),
+                    }),
+                    None => CatalogEntry::Ability(Ability {
+                        ability_id: r.get(7),
+                        name: r.get(8),
+                        damage: r.get(9),
+                        status_effect: r.get(10),
+                    }),
+                };
+
+                (entry, r.get(0))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgUserRepository {
+    async fn create(&self, username: &str, password_hash: &str) -> Result<Option<i32>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "INSERT INTO users (username, password_hash) VALUES ($1, $2)
+                 ON CONFLICT (username) DO NOTHING
+                 RETURNING user_id",
+                &[&username, &password_hash],
+            )
+            .await?;
+
+        Ok(rows.first().map(|r| r.get(0)))
+    }
+
+    async fn set_trainer(&self, user_id: i32, trainer_id: i32) -> Result<(), DbError> {
+        let db = self.db.conn().await?;
+        db.execute(
+            "UPDATE users SET trainer_id = $2 WHERE user_id = $1",
+            &[&user_id, &trainer_id],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: i32) -> Result<(), DbError> {
+        let db = self.db.conn().await?;
+        db.execute("DELETE FROM users WHERE user_id = $1", &[&user_id])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_view(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+        keep: i64,
+    ) -> Result<(), DbError> {
+        let kind = kind.as_str();
+        in_transaction(&self.db, |tx| {
+            Box::pin(async move {
+                tx.execute(
+                    "INSERT INTO recent_view (user_id, kind, entity_id) VALUES ($1, $2, $3)
+                     ON CONFLICT (user_id, kind, entity_id) DO UPDATE SET viewed_at = now()",
+                    &[&user_id, &kind, &entity_id],
+                )
+                .await?;
+                tx.execute(
+                    "DELETE FROM recent_view WHERE user_id = $1 AND (kind, entity_id) NOT IN (
+                         SELECT kind, entity_id FROM recent_view WHERE user_id = $1
+                         ORDER BY viewed_at DESC LIMIT $2
+                     )",
+                    &[&user_id, &keep],
+                )
+                .await?;
+
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    async fn recent(&self, user_id: i32) -> Result<Vec<RecentView>, DbError> {
+        let entries = self.entries("recent_view", "viewed_at", user_id).await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(entry, viewed_at)| RecentView { entry, viewed_at })
+            .collect())
+    }
+
+    async fn add_bookmark(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<AddBookmark, DbError> {
+        let db = self.db.conn().await?;
+        let kind = kind.as_str();
+        let rows = db
+            .query(
+                &format!(
+                    "WITH entity AS (
+                         SELECT {kind}_id FROM published_{kind} WHERE {kind}_id = $3
+                     ), added AS (
+                         INSERT INTO bookmark (user_id, kind, entity_id)
+                         SELECT $1, $2, {kind}_id FROM entity
+                         ON CONFLICT DO NOTHING
+                         RETURNING 1
+                     )
+                     SELECT EXISTS (SELECT 1 FROM entity), EXISTS (SELECT 1 FROM added)",
+                    kind = kind,
+                ),
+                &[&user_id, &kind, &entity_id],
+            )
+            .await?;
+
+        Ok(match (rows[0].get(0), rows[0].get(1)) {
+            (false, _) => AddBookmark::NotFound,
+            (true, true) => AddBookmark::Added,
+            (true, false) => AddBookmark::AlreadyAdded,
+        })
+    }
+
+    async fn delete_bookmark(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<bool, DbError> {
+        let db = self.db.conn().await?;
+        let deleted = db
+            .execute(
+                "DELETE FROM bookmark WHERE user_id = $1 AND kind = $2 AND entity_id = $3",
+                &[&user_id, &kind.as_str(), &entity_id],
+            )
+            .await?;
+
+        Ok(deleted > 0)
+    }
+
+    async fn bookmarks(&self, user_id: i32) -> Result<Vec<Bookmark>, DbError> {
+        let entries = self.entries("bookmark", "bookmarked_at", user_id).await?;
+
+        Ok(entries
+            .into_iter()
+            .map(|(entry, bookmarked_at)| Bookmark {
+                entry,
+                bookmarked_at,
+            })
+            .collect())
+    }
+
+    async fn is_bookmarked(
+        &self,
+        user_id: i32,
+        kind: CatalogKind,
+        entity_id: i32,
+    ) -> Result<bool, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT 1 FROM bookmark WHERE user_id = $1 AND kind = $2 AND entity_id = $3",
+                &[&user_id, &kind.as_str(), &entity_id],
+            )
+            .await?;
+
+        Ok(!rows.is_empty())
+    }
+
+    async fn record_exposure(
+        &self,
+        user_id: i32,
+        experiment: &str,
+        variant: &str,
+    ) -> Result<(), DbError> {
+        let db = self.db.conn().await?;
+        db.execute(
+            "INSERT INTO experiment_exposure (user_id, experiment, variant) VALUES ($1, $2, $3)
+             ON CONFLICT (user_id, experiment, variant) DO UPDATE
+             SET exposures = experiment_exposure.exposures + 1, last_exposed_at = now()",
+            &[&user_id, &experiment, &variant],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn exposures(&self, experiment: &str) -> Result<Vec<VariantExposures>, DbError> {
+        let db = self.db.conn().await?;
+        let rows = db
+            .query(
+                "SELECT variant, count(*), sum(exposures)::BIGINT FROM experiment_exposure
+                 WHERE experiment = $1 GROUP BY variant ORDER BY variant",
+                &[&experiment],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| VariantExposures {
+                variant: r.get(0),
+                users: r.get(1),
+                exposures: r.get(2),
+            })
+            .collect())
+    }
+}
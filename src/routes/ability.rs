@@ -0,0 +1,1518 @@
+use crate::auth::{AdminClaims, AuthClaims};
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::extract::{AcceptLanguage, Path, Query};
+use crate::models::{
+    escape_like, Ability, AbilityDetail, AbilityProposal, AbilityTranslation, CatalogKind,
+    Description, DiffParams, ExternalReference, FieldChange, NewReference, PageParams,
+    ProposalParams, ProposalStatus, ReferenceKind, Render, RenderParams, TagStats, TaggedAbility,
+    Version, VersionDiff,
+};
+use crate::repository::{AddReference, Review, SetTags};
+use crate::routes::me::record_view;
+use crate::service::{version_diff, CatalogHistory};
+use crate::validation::{
+    FieldErrors, Valid, Validate, MAX_NAME_LEN, MAX_NOTE_LEN, MAX_STATUS_EFFECT_LEN,
+};
+use crate::AppState;
+use axum::{
+    extract::{RawQuery, State},
+    http::{
+        header::{LINK, VARY},
+        HeaderName, HeaderValue, StatusCode,
+    },
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/ability", get(get_abilities))
+        .route("/ability/suggest", get(suggest_abilities))
+        .route("/ability/stats", get(get_ability_stats))
+        .route("/ability/proposals", get(get_proposals))
+        .route(
+            "/ability/proposals/:proposal_id/approve",
+            post(approve_proposal),
+        )
+        .route(
+            "/ability/proposals/:proposal_id/reject",
+            post(reject_proposal),
+        )
+        .route(
+            "/ability/:name",
+            get(get_ability_by_name).put(upsert_ability),
+        )
+        .route("/ability/:name/tags", put(put_ability_tags))
+        .route(
+            "/ability/:name/description",
+            get(get_ability_description).put(put_ability_description),
+        )
+        .route("/ability/:name/references", post(add_ability_reference))
+        .route(
+            "/ability/:name/references/:reference_id",
+            delete(delete_ability_reference),
+        )
+        .route("/ability/:name/proposals", post(propose_change))
+        .route("/ability/:name/versions", get(get_ability_versions))
+        .route(
+            "/ability/:name/versions/diff",
+            get(get_ability_version_diff),
+        )
+        .route(
+            "/ability/:name/versions/:version/rollback",
+            post(rollback_ability),
+        )
+        .route("/ability/:name/translations", get(get_translations))
+        .route(
+            "/ability/:name/translations/:locale",
+            put(put_translation).delete(delete_translation),
+        )
+        .route("/pokemon-abilities/:id", get(get_ability))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_abilities,
+        get_ability,
+        suggest_abilities,
+        get_ability_stats,
+        upsert_ability,
+        put_ability_tags,
+        get_ability_by_name,
+        get_ability_description,
+        put_ability_description,
+        add_ability_reference,
+        delete_ability_reference,
+        get_ability_versions,
+        get_ability_version_diff,
+        rollback_ability,
+        propose_change,
+        get_proposals,
+        approve_proposal,
+        reject_proposal,
+        get_translations,
+        put_translation,
+        delete_translation
+    ),
+    components(schemas(
+        Ability,
+        TaggedAbility,
+        TagStats,
+        GetAbilitiesResponse,
+        GetAbilityResponse,
+        SuggestResponse,
+        GetAbilityStatsResponse,
+        UpsertAbilityRequest,
+        Version,
+        FieldChange,
+        VersionDiff,
+        GetAbilityVersionsResponse,
+        ProposalStatus,
+        AbilityProposal,
+        ProposeChangeRequest,
+        GetProposalsResponse,
+        ReviewProposalRequest,
+        AbilityTags,
+        AbilityDetail,
+        ExternalReference,
+        NewReference,
+        ReferenceKind,
+        Description,
+        Render,
+        AbilityTranslation,
+        GetTranslationsResponse,
+        PutTranslationRequest
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+/// Most tags an ability may carry.
+const MAX_TAGS: usize = 16;
+
+/// `?tag=&page=&per_page=` for `/ability`. The total is always exact.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct AbilitiesParams {
+    /// Comma-separated tags, such as `special,water`. Only abilities that
+    /// carry every one of them are listed.
+    tag: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetAbilitiesResponse {
+    abilities: Vec<TaggedAbility>,
+    total_count: i64,
+    page: i64,
+    per_page: i64,
+}
+
+/// Tags are matched in lowercase, with blanks around them ignored.
+fn parse_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    tags.into_iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability",
+    tag = "ability",
+    params(
+        AbilitiesParams,
+        ("Accept-Language" = Option<String>, Header, description = "Locales to name the abilities in; English when none has a translation")
+    ),
+    responses((status = 200, description = "A page of published abilities with their tags, by id, with `Link` headers to the others", body = GetAbilitiesResponse))
+)]
+async fn get_abilities(
+    State(state): State<Arc<AppState>>,
+    RawQuery(query): RawQuery,
+    AcceptLanguage(locales): AcceptLanguage,
+    Query(params): Query<AbilitiesParams>,
+) -> Result<([(HeaderName, HeaderValue); 2], Json<GetAbilitiesResponse>), ApiError> {
+    let tags = parse_tags(params.tag.as_deref().unwrap_or_default().split(','));
+    let paging = PageParams {
+        page: params.page,
+        per_page: params.per_page,
+        count: None,
+    };
+
+    match state.abilities.list(&tags, &locales, &paging).await {
+        Ok((abilities, total_count)) => {
+            let links = paging.links(
+                "/ability",
+                query.as_deref(),
+                abilities.len(),
+                Some(total_count),
+            );
+            Ok((
+                [
+                    (LINK, links),
+                    (VARY, HeaderValue::from_static("accept-language")),
+                ],
+                Json(GetAbilitiesResponse {
+                    abilities,
+                    total_count,
+                    page: paging.page(),
+                    per_page: paging.per_page(),
+                }),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch abilities: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetAbilityStatsResponse {
+    /// Every tag, categories first.
+    tags: Vec<TagStats>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/stats",
+    tag = "ability",
+    responses((status = 200, description = "How many published abilities carry each tag", body = GetAbilityStatsResponse))
+)]
+async fn get_ability_stats(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetAbilityStatsResponse>, ApiError> {
+    match state.abilities.tag_stats().await {
+        Ok(tags) => Ok(Json(GetAbilityStatsResponse { tags })),
+        Err(e) => {
+            tracing::error!("Failed to fetch tag stats: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetAbilityResponse {
+    ability: Vec<Ability>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon-abilities/{id}",
+    tag = "ability",
+    params(
+        ("id" = i32, Path, description = "Pokemon id"),
+        ("Accept-Language" = Option<String>, Header, description = "Locales to name the abilities in; English when none has a translation")
+    ),
+    responses((status = 200, description = "Abilities of the pokemon", body = GetAbilityResponse))
+)]
+async fn get_ability(
+    db: DbConn,
+    Path(id): Path<i32>,
+    AcceptLanguage(locales): AcceptLanguage,
+) -> Result<([(HeaderName, HeaderValue); 1], Json<GetAbilityResponse>), ApiError> {
+    match db
+        .query_capped(
+            "SELECT a.ability_id, coalesce(tr.name, a.name), a.damage,
+                    coalesce(tr.status_effect, a.status_effect)
+             FROM pokemonabilities pa
+             JOIN published_pokemon p ON p.pokemon_id = pa.pokemon_id
+             JOIN published_ability a ON a.ability_id = pa.ability_id
+             LEFT JOIN LATERAL (
+                 SELECT tr.name, tr.status_effect FROM ability_translation tr
+                 WHERE tr.ability_id = a.ability_id AND tr.locale = ANY($2)
+                 ORDER BY array_position($2, tr.locale)
+                 LIMIT 1
+             ) tr ON true
+             WHERE pa.pokemon_id = $1",
+            &[&id, &locales],
+        )
+        .await
+    {
+        Ok(rows) => {
+            let abilities: Vec<Ability> = rows
+                .iter()
+                .map(|r| Ability {
+                    ability_id: r.get(0),
+                    name: r.get(1),
+                    damage: r.get(2),
+                    status_effect: r.get(3),
+                })
+                .collect();
+
+            tracing::debug!(
+                pokemon_id = id,
+                count = abilities.len(),
+                "Fetched abilities"
+            );
+
+            Ok((
+                [(VARY, HeaderValue::from_static("accept-language"))],
+                Json(GetAbilityResponse { ability: abilities }),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch abilities: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SuggestParams {
+    q: String,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SuggestResponse {
+    suggestions: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/suggest",
+    tag = "ability",
+    params(SuggestParams),
+    responses((status = 200, description = "Ability names matching the query", body = SuggestResponse))
+)]
+async fn suggest_abilities(
+    db: DbConn,
+    Query(params): Query<SuggestParams>,
+) -> Result<Json<SuggestResponse>, ApiError> {
+    let q = params.q.trim();
+    if q.is_empty() {
+        return Ok(Json(SuggestResponse {
+            suggestions: Vec::new(),
+        }));
+    }
+    let limit = params.limit.unwrap_or(10).clamp(1, 25);
+
+    // The trigram index on `ability.name` serves the ILIKE. Prefix matches
+    // rank above substring matches, then shorter names first.
+    match db
+        .query(
+            "SELECT name FROM published_ability
+             WHERE name ILIKE '%' || $1 || '%'
+             ORDER BY name ILIKE $1 || '%' DESC, length(name), name
+             LIMIT $2",
+            &[&escape_like(q), &limit],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(SuggestResponse {
+            suggestions: rows.iter().map(|r| r.get(0)).collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to suggest abilities: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UpsertAbilityRequest {
+    /// Between 0 and 10000.
+    damage: i32,
+    status_effect: String,
+    /// Stages the change instead of applying it right away. A new ability
+    /// stays hidden until then; a rebalance of a live one is applied by the
+    /// publisher at that time.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    publish_at: Option<OffsetDateTime>,
+}
+
+impl Validate for UpsertAbilityRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.damage("damage", self.damage);
+        errors.name("status_effect", &self.status_effect, MAX_STATUS_EFFECT_LEN);
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/ability/{name}",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    request_body = UpsertAbilityRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Ability created or updated", body = Ability),
+        (status = 202, description = "Change staged until its publish time", body = Ability),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 422, description = "Invalid name or fields", body = ErrorBody)
+    )
+)]
+async fn upsert_ability(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Valid(payload): Valid<UpsertAbilityRequest>,
+) -> Result<(StatusCode, Json<Ability>), ApiError> {
+    // The name comes from the path rather than the body, so `Valid` never
+    // saw it.
+    let mut errors = FieldErrors::default();
+    errors.name("name", &name, MAX_NAME_LEN);
+    errors.into_result()?;
+
+    let UpsertAbilityRequest {
+        damage,
+        status_effect,
+        publish_at,
+    } = payload;
+
+    // A staged change is invisible until `publish_at`, so it answers 202
+    // and no change hooks fire yet.
+    if let Some(at) = publish_at.filter(|at| *at > state.clock.now()) {
+        return match state
+            .abilities
+            .stage(&name, damage, &status_effect, at)
+            .await
+        {
+            Ok(ability_id) => Ok((
+                StatusCode::ACCEPTED,
+                Json(Ability {
+                    ability_id,
+                    name,
+                    damage,
+                    status_effect,
+                }),
+            )),
+            Err(e) => {
+                tracing::error!("Failed to stage ability: {}", e);
+
+                Err(e.into())
+            }
+        };
+    }
+
+    match state.abilities.apply(&name, damage, &status_effect).await {
+        Ok((ability, went_live)) => {
+            CatalogHistory::new(&state)
+                .record_ability(ability.ability_id)
+                .await;
+            if went_live {
+                state.created(Entity::Ability, ability.ability_id);
+            } else {
+                state.updated(Entity::Ability, ability.ability_id);
+            }
+
+            Ok((StatusCode::OK, Json(ability)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to upsert ability: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct AbilityTags {
+    /// Replaces the ability's tags; `[]` clears them.
+    tags: Vec<String>,
+}
+
+impl Validate for AbilityTags {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if self.tags.len() > MAX_TAGS {
+            errors.add("tags", format!("must have at most {} tags", MAX_TAGS));
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/ability/{name}/tags",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    request_body = AbilityTags,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The ability's tags", body = AbilityTags),
+        (status = 400, description = "Unknown tags", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability", body = ErrorBody),
+        (status = 422, description = "Too many tags", body = ErrorBody)
+    )
+)]
+async fn put_ability_tags(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Valid(payload): Valid<AbilityTags>,
+) -> Result<Json<AbilityTags>, ApiError> {
+    let mut tags = parse_tags(payload.tags.iter().map(String::as_str));
+    tags.sort();
+    tags.dedup();
+
+    match state.abilities.set_tags(&name, &tags).await {
+        Ok(SetTags::Set(ability_id)) => {
+            state.updated(Entity::Ability, ability_id);
+
+            Ok(Json(AbilityTags { tags }))
+        }
+        Ok(SetTags::NotFound) => Err(ApiError::NotFound),
+        Ok(SetTags::UnknownTags(unknown)) => Err(ApiError::BadRequest(format!(
+            "unknown tags: {}",
+            unknown.join(", ")
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to tag ability: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// The ability is named in English, as in the path, whatever
+/// `Accept-Language` asks for.
+#[utoipa::path(
+    get,
+    path = "/ability/{name}",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    responses(
+        (status = 200, description = "The ability with its external references", body = AbilityDetail),
+        (status = 404, description = "No such published ability", body = ErrorBody)
+    )
+)]
+async fn get_ability_by_name(
+    claims: Option<AuthClaims>,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<AbilityDetail>, ApiError> {
+    let ability = match state.abilities.find(&name).await {
+        Ok(Some(ability)) => ability,
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch ability: {}", e);
+
+            return Err(e.into());
+        }
+    };
+    record_view(&state, claims, CatalogKind::Ability, ability.ability_id).await;
+
+    match state.abilities.references(ability.ability_id).await {
+        Ok(references) => Ok(Json(AbilityDetail {
+            ability,
+            references,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch ability references: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/ability/{name}/references",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    request_body = NewReference,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Reference attached", body = ExternalReference),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability", body = ErrorBody),
+        (status = 409, description = "The ability already has this reference", body = ErrorBody),
+        (status = 422, description = "Not an https URL, a Bulbapedia URL or a PokeAPI id as the kind asks", body = ErrorBody)
+    )
+)]
+async fn add_ability_reference(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Valid(payload): Valid<NewReference>,
+) -> Result<(StatusCode, Json<ExternalReference>), ApiError> {
+    match state.abilities.add_reference(&name, &payload).await {
+        Ok(AddReference::Added(reference, ability_id)) => {
+            state.updated(Entity::Ability, ability_id);
+
+            Ok((StatusCode::CREATED, Json(reference)))
+        }
+        Ok(AddReference::NotFound) => Err(ApiError::NotFound),
+        Ok(AddReference::Duplicate) => Err(ApiError::Conflict(
+            "the ability already has this reference".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to attach ability reference: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/ability/{name}/references/{reference_id}",
+    tag = "ability",
+    params(
+        ("name" = String, Path, description = "Ability name"),
+        ("reference_id" = i32, Path, description = "Reference id")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Reference detached"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability or reference", body = ErrorBody)
+    )
+)]
+async fn delete_ability_reference(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path((name, reference_id)): Path<(String, i32)>,
+) -> Result<StatusCode, ApiError> {
+    match state.abilities.delete_reference(&name, reference_id).await {
+        Ok(Some(ability_id)) => {
+            state.updated(Entity::Ability, ability_id);
+
+            Ok(StatusCode::OK)
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to detach ability reference: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/{name}/description",
+    tag = "ability",
+    params(
+        ("name" = String, Path, description = "Ability name"),
+        RenderParams
+    ),
+    responses(
+        (status = 200, description = "The ability's description and lore, as markdown or sanitized HTML", body = Description),
+        (status = 404, description = "No such published ability", body = ErrorBody)
+    )
+)]
+async fn get_ability_description(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<RenderParams>,
+) -> Result<Json<Description>, ApiError> {
+    match state.abilities.description(&name).await {
+        Ok(Some(description)) => Ok(Json(description.render(params.render.unwrap_or_default()))),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch ability description: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Both fields are replaced; one left out is cleared.
+#[utoipa::path(
+    put,
+    path = "/ability/{name}/description",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    request_body = Description,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The description and lore as stored", body = Description),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability", body = ErrorBody),
+        (status = 422, description = "Text too long", body = ErrorBody)
+    )
+)]
+async fn put_ability_description(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Valid(payload): Valid<Description>,
+) -> Result<Json<Description>, ApiError> {
+    match state.abilities.set_description(&name, &payload).await {
+        Ok(Some(ability_id)) => {
+            CatalogHistory::new(&state).record_ability(ability_id).await;
+            state.updated(Entity::Ability, ability_id);
+
+            Ok(Json(payload))
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to describe ability: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetAbilityVersionsResponse {
+    ability_id: i32,
+    /// Oldest first.
+    versions: Vec<Version>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/{name}/versions",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Every version of the ability, published or not", body = GetAbilityVersionsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability", body = ErrorBody)
+    )
+)]
+async fn get_ability_versions(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<GetAbilityVersionsResponse>, ApiError> {
+    let (ability_id, versions) = CatalogHistory::new(&state).ability_versions(&name).await?;
+
+    Ok(Json(GetAbilityVersionsResponse {
+        ability_id,
+        versions,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/{name}/versions/diff",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name"), DiffParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The fields that differ between the two versions", body = VersionDiff),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability or version", body = ErrorBody)
+    )
+)]
+async fn get_ability_version_diff(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<DiffParams>,
+) -> Result<Json<VersionDiff>, ApiError> {
+    let (_, versions) = CatalogHistory::new(&state).ability_versions(&name).await?;
+
+    version_diff(&versions, params.from, params.to).map(Json)
+}
+
+/// Writes the damage, status effect, description and lore of an earlier
+/// version back as a new version. A staged rebalance is dropped.
+#[utoipa::path(
+    post,
+    path = "/ability/{name}/versions/{version}/rollback",
+    tag = "ability",
+    params(
+        ("name" = String, Path, description = "Ability name"),
+        ("version" = i32, Path, description = "Version to restore")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The version recording the rollback", body = Version),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability or version", body = ErrorBody),
+        (status = 409, description = "The version can't be restored", body = ErrorBody)
+    )
+)]
+async fn rollback_ability(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path((name, version)): Path<(String, i32)>,
+) -> Result<Json<Version>, ApiError> {
+    CatalogHistory::new(&state)
+        .rollback_ability(&name, version)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ProposeChangeRequest {
+    damage: i32,
+    status_effect: String,
+    /// Why the ability should change, for the admin reviewing it.
+    reason: Option<String>,
+}
+
+impl Validate for ProposeChangeRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.damage("damage", self.damage);
+        errors.name("status_effect", &self.status_effect, MAX_STATUS_EFFECT_LEN);
+        if let Some(reason) = &self.reason {
+            errors.max_len("reason", reason, MAX_NOTE_LEN);
+        }
+    }
+}
+
+/// Proposes a rebalance for an admin to approve or reject. Nothing changes
+/// until one approves it.
+#[utoipa::path(
+    post,
+    path = "/ability/{name}/proposals",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name")),
+    request_body = ProposeChangeRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Proposal filed for review", body = AbilityProposal),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No such published ability", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn propose_change(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Valid(payload): Valid<ProposeChangeRequest>,
+) -> Result<(StatusCode, Json<AbilityProposal>), ApiError> {
+    match state
+        .abilities
+        .propose(
+            &name,
+            claims.sub,
+            payload.damage,
+            &payload.status_effect,
+            payload.reason.as_deref(),
+        )
+        .await
+    {
+        Ok(Some(proposal)) => Ok((StatusCode::CREATED, Json(proposal))),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to file ability proposal: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct GetProposalsResponse {
+    /// Oldest first.
+    pub(crate) proposals: Vec<AbilityProposal>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/proposals",
+    tag = "ability",
+    params(ProposalParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Proposals from every user", body = GetProposalsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_proposals(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ProposalParams>,
+) -> Result<Json<GetProposalsResponse>, ApiError> {
+    match state.abilities.proposals(params.status, None).await {
+        Ok(proposals) => Ok(Json(GetProposalsResponse { proposals })),
+        Err(e) => {
+            tracing::error!("Failed to fetch ability proposals: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ReviewProposalRequest {
+    /// Shown to the user who proposed it.
+    note: Option<String>,
+}
+
+impl Validate for ReviewProposalRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let Some(note) = &self.note {
+            errors.max_len("note", note, MAX_NOTE_LEN);
+        }
+    }
+}
+
+/// Approves or rejects the proposal, and applies an approved one.
+async fn review_proposal(
+    state: &AppState,
+    admin: AdminClaims,
+    proposal_id: i32,
+    approve: bool,
+    note: Option<String>,
+) -> Result<Json<AbilityProposal>, ApiError> {
+    let reviewed = state
+        .abilities
+        .review(proposal_id, admin.0.sub, approve, note.as_deref())
+        .await;
+    match reviewed {
+        Ok(Review::Reviewed(proposal)) => {
+            tracing::info!(
+                admin = %admin.0.username,
+                proposal_id,
+                ability_id = proposal.ability_id,
+                status = proposal.status.as_str(),
+                "Reviewed ability proposal"
+            );
+            if approve {
+                CatalogHistory::new(state)
+                    .record_ability(proposal.ability_id)
+                    .await;
+                state.updated(Entity::Ability, proposal.ability_id);
+            }
+
+            Ok(Json(proposal))
+        }
+        Ok(Review::NotFound) => Err(ApiError::NotFound),
+        Ok(Review::AlreadyReviewed(status)) => Err(ApiError::Conflict(format!(
+            "the proposal was already {}",
+            status.as_str()
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to review ability proposal: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Applies the proposed rebalance right away, dropping any staged one.
+#[utoipa::path(
+    post,
+    path = "/ability/proposals/{proposal_id}/approve",
+    tag = "ability",
+    params(("proposal_id" = i32, Path, description = "Proposal id")),
+    request_body = ReviewProposalRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Proposal approved and applied", body = AbilityProposal),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such proposal", body = ErrorBody),
+        (status = 409, description = "Already approved or rejected", body = ErrorBody),
+        (status = 422, description = "Note too long", body = ErrorBody)
+    )
+)]
+async fn approve_proposal(
+    admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(proposal_id): Path<i32>,
+    Valid(payload): Valid<ReviewProposalRequest>,
+) -> Result<Json<AbilityProposal>, ApiError> {
+    review_proposal(&state, admin, proposal_id, true, payload.note).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/ability/proposals/{proposal_id}/reject",
+    tag = "ability",
+    params(("proposal_id" = i32, Path, description = "Proposal id")),
+    request_body = ReviewProposalRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Proposal rejected", body = AbilityProposal),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such proposal", body = ErrorBody),
+        (status = 409, description = "Already approved or rejected", body = ErrorBody),
+        (status = 422, description = "Note too long", body = ErrorBody)
+    )
+)]
+async fn reject_proposal(
+    admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(proposal_id): Path<i32>,
+    Valid(payload): Valid<ReviewProposalRequest>,
+) -> Result<Json<AbilityProposal>, ApiError> {
+    review_proposal(&state, admin, proposal_id, false, payload.note).await
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetTranslationsResponse {
+    /// By locale.
+    translations: Vec<AbilityTranslation>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ability/{name}/translations",
+    tag = "ability",
+    params(("name" = String, Path, description = "Ability name, in English")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The ability's translations, published or not", body = GetTranslationsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability", body = ErrorBody)
+    )
+)]
+async fn get_translations(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<GetTranslationsResponse>, ApiError> {
+    match state.abilities.translations(&name).await {
+        Ok(Some(translations)) => Ok(Json(GetTranslationsResponse { translations })),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch ability translations: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct PutTranslationRequest {
+    name: String,
+    status_effect: String,
+}
+
+impl Validate for PutTranslationRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.name("name", &self.name, MAX_NAME_LEN);
+        errors.name("status_effect", &self.status_effect, MAX_STATUS_EFFECT_LEN);
+    }
+}
+
+/// Locales in the path are matched in lowercase, with `_` read as `-`.
+fn parse_locale(locale: &str) -> Result<String, ApiError> {
+    let locale = locale.trim().to_lowercase().replace('_', "-");
+    let mut errors = FieldErrors::default();
+    errors.locale("locale", &locale);
+    errors.into_result()?;
+
+    Ok(locale)
+}
+
+#[utoipa::path(
+    put,
+    path = "/ability/{name}/translations/{locale}",
+    tag = "ability",
+    params(
+        ("name" = String, Path, description = "Ability name, in English"),
+        ("locale" = String, Path, description = "Language tag, such as `fr` or `pt-br`")
+    ),
+    request_body = PutTranslationRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Translation added or replaced", body = AbilityTranslation),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability", body = ErrorBody),
+        (status = 422, description = "Invalid locale or fields", body = ErrorBody)
+    )
+)]
+async fn put_translation(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path((name, locale)): Path<(String, String)>,
+    Valid(payload): Valid<PutTranslationRequest>,
+) -> Result<Json<AbilityTranslation>, ApiError> {
+    let translation = AbilityTranslation {
+        locale: parse_locale(&locale)?,
+        name: payload.name,
+        status_effect: payload.status_effect,
+    };
+
+    match state.abilities.set_translation(&name, &translation).await {
+        Ok(Some(ability_id)) => {
+            state.updated(Entity::Ability, ability_id);
+
+            Ok(Json(translation))
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to translate ability: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/ability/{name}/translations/{locale}",
+    tag = "ability",
+    params(
+        ("name" = String, Path, description = "Ability name, in English"),
+        ("locale" = String, Path, description = "Language tag, such as `fr` or `pt-br`")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Translation removed; the locale falls back to English"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such ability or translation", body = ErrorBody),
+        (status = 422, description = "Invalid locale", body = ErrorBody)
+    )
+)]
+async fn delete_translation(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path((name, locale)): Path<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    let locale = parse_locale(&locale)?;
+
+    match state.abilities.delete_translation(&name, &locale).await {
+        Ok(Some(ability_id)) => {
+            state.updated(Entity::Ability, ability_id);
+
+            Ok(StatusCode::OK)
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to delete ability translation: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::jobs::publish_due;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, send_request, state, token, TestClock};
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use time::format_description::well_known::Rfc3339;
+    use time::OffsetDateTime;
+
+    #[tokio::test]
+    async fn an_immediate_update_drops_the_staged_rebalance() {
+        let now = OffsetDateTime::now_utc();
+        let clock = TestClock::at(now);
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let tackle = repo.add_ability("Tackle", 40, "none");
+        let state = state(repo.clone()).with_clock(clock.clone());
+        let token = token(&state, admin);
+        let app = build_app(state.clone());
+        let in_an_hour = (now + Duration::from_secs(3600)).format(&Rfc3339).unwrap();
+
+        let (status, _) = send(
+            &app,
+            Method::PUT,
+            "/ability/Tackle",
+            Some(&token),
+            Some(json!({"damage": 90, "status_effect": "none", "publish_at": in_an_hour})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let (status, body) = send(
+            &app,
+            Method::PUT,
+            "/ability/Tackle",
+            Some(&token),
+            Some(json!({"damage": 50, "status_effect": "none"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["damage"], 50);
+
+        clock.advance(Duration::from_secs(7200));
+        publish_due(&state).await.unwrap();
+        assert_eq!(repo.ability(tackle).unwrap().damage, 50);
+    }
+
+    #[tokio::test]
+    async fn abilities_are_filtered_and_counted_by_tag() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        repo.add_ability("Surf", 90, "none");
+        repo.add_ability("Bubble", 20, "none");
+        repo.add_ability("Tackle", 40, "none");
+        repo.add_tag("special", "category");
+        repo.add_tag("water", "type");
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        for (name, tags) in [
+            ("Surf", json!(["water", "Special"])),
+            ("Bubble", json!(["water"])),
+        ] {
+            let uri = format!("/ability/{}/tags", name);
+            let body = json!({"tags": tags});
+            let (status, _) = send(&app, Method::PUT, &uri, Some(&token), Some(body)).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let body = json!({"tags": ["shadow"]});
+        let (status, body) = send(
+            &app,
+            Method::PUT,
+            "/ability/Tackle/tags",
+            Some(&token),
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "unknown tags: shadow");
+
+        let (_, body) = send(&app, Method::GET, "/ability?tag=water", None, None).await;
+        assert_eq!(body["total_count"], 2);
+        let (_, body) = send(&app, Method::GET, "/ability?tag=water,special", None, None).await;
+        assert_eq!(body["total_count"], 1);
+        assert_eq!(body["abilities"][0]["name"], "Surf");
+        assert_eq!(body["abilities"][0]["tags"], json!(["special", "water"]));
+
+        let (_, body) = send(&app, Method::GET, "/ability/stats", None, None).await;
+        assert_eq!(body["tags"][0]["name"], "special");
+        assert_eq!(body["tags"][0]["ability_count"], 1);
+        assert_eq!(body["tags"][1]["ability_count"], 2);
+        assert_eq!(body["tags"][1]["average_damage"], 55.0);
+    }
+
+    #[tokio::test]
+    async fn damage_is_capped() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        let (status, body) = send(
+            &app,
+            Method::PUT,
+            "/ability/Psystrike",
+            Some(&token),
+            Some(json!({"damage": i32::MAX, "status_effect": "none"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "damage");
+    }
+
+    #[tokio::test]
+    async fn the_name_in_the_path_is_validated() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+        let long = format!("/ability/{}", "a".repeat(65));
+
+        for uri in ["/ability/%20%20", long.as_str()] {
+            let (status, body) = send(
+                &app,
+                Method::PUT,
+                uri,
+                Some(&token),
+                Some(json!({"damage": 40, "status_effect": "none"})),
+            )
+            .await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+            assert_eq!(body["fields"][0]["field"], "name");
+        }
+    }
+
+    #[tokio::test]
+    async fn abilities_are_named_in_the_best_translated_locale() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        repo.add_ability("Surf", 90, "none");
+        repo.add_ability("Tackle", 40, "none");
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        for (uri, name) in [
+            ("/ability/Surf/translations/FR", "Surf (fr)"),
+            ("/ability/Surf/translations/fr_ca", "Surf (fr-ca)"),
+            ("/ability/Tackle/translations/de", "Tackle (de)"),
+        ] {
+            let body = json!({"name": name, "status_effect": "aucun"});
+            let (status, _) = send(&app, Method::PUT, uri, Some(&token), Some(body)).await;
+            assert_eq!(status, StatusCode::OK, "{}", uri);
+        }
+        let body = json!({"name": "Surf", "status_effect": "none"});
+        let (status, body) = send(
+            &app,
+            Method::PUT,
+            "/ability/Surf/translations/en-gb",
+            Some(&token),
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "locale");
+
+        let localized = |accept: &str| {
+            Request::get("/ability")
+                .header(header::ACCEPT_LANGUAGE, accept)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (_, body) = send_request(&app, localized("fr-CH, de;q=0.5")).await;
+        assert_eq!(body["abilities"][0]["name"], "Surf (fr)");
+        assert_eq!(body["abilities"][0]["status_effect"], "aucun");
+        assert_eq!(body["abilities"][1]["name"], "Tackle (de)");
+        let (_, body) = send_request(&app, localized("en, de")).await;
+        assert_eq!(body["abilities"][1]["name"], "Tackle");
+        let (_, body) = send(&app, Method::GET, "/ability", None, None).await;
+        assert_eq!(body["abilities"][0]["name"], "Surf");
+
+        let uri = "/ability/Surf/translations/fr-ca";
+        let (status, _) = send(&app, Method::DELETE, uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::DELETE, uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let uri = "/ability/Surf/translations";
+        let (_, body) = send(&app, Method::GET, uri, Some(&token), None).await;
+        assert_eq!(
+            body["translations"],
+            json!([{"locale": "fr", "name": "Surf (fr)", "status_effect": "aucun"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn versions_are_compared_and_rolled_back() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let trainer = repo.add_user(None, false);
+        let state = state(repo.clone());
+        let admin = token(&state, admin);
+        let trainer = token(&state, trainer);
+        let app = build_app(state);
+
+        for damage in [40, 40, 60] {
+            let body = json!({"damage": damage, "status_effect": "none"});
+            let (status, _) = send(
+                &app,
+                Method::PUT,
+                "/ability/Tackle",
+                Some(&admin),
+                Some(body),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let lore = json!({"lore": "Charges *head first*."});
+        let (status, _) = send(
+            &app,
+            Method::PUT,
+            "/ability/Tackle/description",
+            Some(&admin),
+            Some(lore),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // The repeated write changed nothing, so it recorded nothing.
+        let (status, _) = send(
+            &app,
+            Method::GET,
+            "/ability/Tackle/versions",
+            Some(&trainer),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send(
+            &app,
+            Method::GET,
+            "/ability/Tackle/versions",
+            Some(&admin),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["versions"].as_array().unwrap().len(), 3);
+
+        let (status, body) = send(
+            &app,
+            Method::GET,
+            "/ability/Tackle/versions/diff?from=1&to=3",
+            Some(&admin),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["changes"],
+            json!([
+                {"field": "damage", "from": 40, "to": 60},
+                {"field": "lore", "from": null, "to": "Charges *head first*."}
+            ])
+        );
+        let (status, _) = send(
+            &app,
+            Method::GET,
+            "/ability/Tackle/versions/diff?from=1&to=9",
+            Some(&admin),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            "/ability/Tackle/versions/1/rollback",
+            Some(&admin),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["version"], 4);
+        assert_eq!(body["snapshot"]["damage"], 40);
+        assert_eq!(body["snapshot"]["lore"], json!(null));
+        let (_, body) = send(&app, Method::GET, "/ability/Tackle", None, None).await;
+        assert_eq!(body["damage"], 40);
+    }
+
+    #[tokio::test]
+    async fn proposals_are_applied_once_an_admin_approves_them() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let misty = repo.add_user(None, false);
+        let brock = repo.add_user(None, false);
+        let state = state(repo.clone());
+        let admin = token(&state, admin);
+        let misty = token(&state, misty);
+        let brock = token(&state, brock);
+        let app = build_app(state);
+
+        let body = json!({"damage": 40, "status_effect": "none"});
+        let (status, _) = send(
+            &app,
+            Method::PUT,
+            "/ability/Tackle",
+            Some(&admin),
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let proposal =
+            json!({"damage": 55, "status_effect": "none", "reason": "Falls off late game."});
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/ability/Surf/proposals",
+            Some(&misty),
+            Some(proposal.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            "/ability/Tackle/proposals",
+            Some(&misty),
+            Some(proposal),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["status"], "pending");
+        let approve = format!("/ability/proposals/{}/approve", body["proposal_id"]);
+
+        // Nothing changes until an admin approves it.
+        let (_, body) = send(&app, Method::GET, "/ability/Tackle", None, None).await;
+        assert_eq!(body["damage"], 40);
+        let (status, _) = send(&app, Method::POST, &approve, Some(&brock), Some(json!({}))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send(
+            &app,
+            Method::GET,
+            "/ability/proposals?status=pending",
+            Some(&admin),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["proposals"][0]["reason"], "Falls off late game.");
+
+        let note = json!({"note": "Fair."});
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            &approve,
+            Some(&admin),
+            Some(note.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "approved");
+        let (_, body) = send(&app, Method::GET, "/ability/Tackle", None, None).await;
+        assert_eq!(body["damage"], 55);
+        let (status, _) = send(&app, Method::POST, &approve, Some(&admin), Some(note)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (_, body) = send(&app, Method::GET, "/me/proposals", Some(&misty), None).await;
+        assert_eq!(body["proposals"][0]["review_note"], "Fair.");
+        let (_, body) = send(&app, Method::GET, "/me/proposals", Some(&brock), None).await;
+        assert_eq!(body["proposals"], json!([]));
+    }
+}
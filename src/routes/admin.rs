@@ -0,0 +1,825 @@
+use crate::ai::{Difficulty, Strategy};
+use crate::auth::AdminClaims;
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::experiments::EXPERIMENTS;
+use crate::extract::Query;
+use crate::migrations;
+use crate::models::{CountStrategy, VariantExposures};
+use crate::recorder::RecordedRequest;
+use crate::repository::CreateAi;
+use crate::slo::{now_minute, SloTargets, ROUTE_METRICS_MINUTES};
+use crate::validation::{FieldErrors, Valid, Validate, MAX_NAME_LEN};
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .route("/admin/recent-requests", get(get_recent_requests))
+        .route("/admin/slo", get(get_slo))
+        .route("/admin/scheduled", get(get_scheduled))
+        .route("/admin/impersonations", get(get_impersonations))
+        .route("/admin/reset", post(reset))
+        .route("/admin/usage", get(get_usage))
+        .route("/admin/ai-trainer", post(create_ai_trainer))
+        .route("/admin/experiments", get(get_experiments))
+}
+
+/// Admin reports that scan whole tables, which run under the heavy
+/// concurrency budget.
+pub(crate) fn heavy_router() -> Router<Arc<AppState>> {
+    Router::new().route("/admin/tables", get(get_tables))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_metrics,
+        get_recent_requests,
+        get_slo,
+        get_scheduled,
+        get_impersonations,
+        reset,
+        get_tables,
+        get_usage,
+        create_ai_trainer,
+        get_experiments
+    ),
+    components(schemas(
+        RecordedRequest,
+        SloTargets,
+        GetRecentRequestsResponse,
+        SloWindow,
+        RouteSlo,
+        GetSloResponse,
+        ScheduledChange,
+        GetScheduledResponse,
+        Impersonation,
+        GetImpersonationsResponse,
+        ResetResponse,
+        TableStats,
+        GetTablesResponse,
+        ClientUsage,
+        GetUsageResponse,
+        Strategy,
+        Difficulty,
+        CreateAiTrainerRequest,
+        CreateAiTrainerResponse,
+        VariantExposures,
+        ExperimentResults,
+        GetExperimentsResponse
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+struct GetRecentRequestsResponse {
+    enabled: bool,
+    requests: Vec<RecordedRequest>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/recent-requests",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Most recent requests first", body = GetRecentRequestsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_recent_requests(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetRecentRequestsResponse>, ApiError> {
+    let requests = match &state.request_log {
+        Some(log) => log.entries.lock().unwrap().iter().rev().cloned().collect(),
+        None => Vec::new(),
+    };
+
+    Ok(Json(GetRecentRequestsResponse {
+        enabled: state.request_log.is_some(),
+        requests,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+struct SloWindow {
+    window: &'static str,
+    requests: u64,
+    availability: f64,
+    latency: f64,
+    /// How fast the error budget is being spent; 1.0 exhausts it exactly at
+    /// the end of the SLO period, anything above burns it early.
+    availability_burn_rate: f64,
+    latency_burn_rate: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct RouteSlo {
+    route: String,
+    windows: Vec<SloWindow>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetSloResponse {
+    targets: SloTargets,
+    routes: Vec<RouteSlo>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/slo",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "SLO attainment per route", body = GetSloResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_slo(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetSloResponse>, ApiError> {
+    let now = now_minute();
+    let slo = state.slo;
+
+    let mut routes: Vec<RouteSlo> = Vec::new();
+    for (window, minutes) in [("5m", 5), ("1h", ROUTE_METRICS_MINUTES)] {
+        for (route, total) in state.route_metrics.window(now, minutes) {
+            let ratio = |bad: u64| {
+                if total.requests == 0 {
+                    0.0
+                } else {
+                    bad as f64 / total.requests as f64
+                }
+            };
+            let error_ratio = ratio(total.errors);
+            let slow_ratio = ratio(total.slow);
+
+            let entry = SloWindow {
+                window,
+                requests: total.requests,
+                availability: 1.0 - error_ratio,
+                latency: 1.0 - slow_ratio,
+                availability_burn_rate: error_ratio / (1.0 - slo.availability),
+                latency_burn_rate: slow_ratio / (1.0 - slo.latency),
+            };
+
+            match routes.iter_mut().find(|r| r.route == route) {
+                Some(r) => r.windows.push(entry),
+                None => routes.push(RouteSlo {
+                    route,
+                    windows: vec![entry],
+                }),
+            }
+        }
+    }
+
+    Ok(Json(GetSloResponse {
+        targets: slo,
+        routes,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+struct ScheduledChange {
+    /// `pokemon`, `ability`, or `ability_change` for a staged rebalance.
+    kind: String,
+    id: i32,
+    name: String,
+    #[serde(with = "time::serde::rfc3339")]
+    publish_at: OffsetDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetScheduledResponse {
+    scheduled: Vec<ScheduledChange>,
+}
+
+/// Lists staged catalog changes that are not public yet, soonest first.
+#[utoipa::path(
+    get,
+    path = "/admin/scheduled",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Staged changes", body = GetScheduledResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_scheduled(
+    _admin: AdminClaims,
+    db: DbConn,
+) -> Result<Json<GetScheduledResponse>, ApiError> {
+    match db
+        .query(
+            "SELECT 'pokemon', pokemon_id, name, publish_at FROM pokemon
+             WHERE publish_at > now()
+             UNION ALL
+             SELECT 'ability', ability_id, name, publish_at FROM ability
+             WHERE publish_at > now()
+             UNION ALL
+             SELECT 'ability_change', c.ability_id, a.name, c.publish_at
+             FROM ability_changes c JOIN ability a ON a.ability_id = c.ability_id
+             ORDER BY 4",
+            &[],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetScheduledResponse {
+            scheduled: rows
+                .iter()
+                .map(|r| ScheduledChange {
+                    kind: r.get(0),
+                    id: r.get(1),
+                    name: r.get(2),
+                    publish_at: r.get(3),
+                })
+                .collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to list scheduled changes: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Impersonation {
+    admin: String,
+    trainer_id: i32,
+    method: String,
+    path: String,
+    request_id: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    at: OffsetDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetImpersonationsResponse {
+    impersonations: Vec<Impersonation>,
+}
+
+/// The audit trail of requests admins made as other trainers, newest first.
+#[utoipa::path(
+    get,
+    path = "/admin/impersonations",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Most recent impersonated requests", body = GetImpersonationsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_impersonations(
+    _admin: AdminClaims,
+    db: DbConn,
+) -> Result<Json<GetImpersonationsResponse>, ApiError> {
+    match db
+        .query(
+            "SELECT u.username, i.trainer_id, i.method, i.path, i.request_id, i.at
+             FROM impersonation_audit i JOIN users u ON u.user_id = i.admin_user_id
+             ORDER BY i.audit_id DESC
+             LIMIT 200",
+            &[],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetImpersonationsResponse {
+            impersonations: rows
+                .iter()
+                .map(|r| Impersonation {
+                    admin: r.get(0),
+                    trainer_id: r.get(1),
+                    method: r.get(2),
+                    path: r.get(3),
+                    request_id: r.get(4),
+                    at: r.get(5),
+                })
+                .collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to list impersonations: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_metrics(_admin: AdminClaims, State(state): State<Arc<AppState>>) -> String {
+    let metrics = &state.db.metrics;
+    let pool = state.db.pool.status();
+
+    format!(
+        "db_queries_total {}\n\
+         db_slow_queries_total {}\n\
+         db_query_duration_seconds_sum {}\n\
+         db_pool_checkouts_total {}\n\
+         db_pool_wait_seconds_sum {}\n\
+         db_rows_total {}\n\
+         db_row_bytes_total {}\n\
+         db_request_row_bytes_max {}\n\
+         db_pool_max_size {}\n\
+         db_pool_size {}\n\
+         db_pool_available {}\n\
+         db_pool_waiting {}\n",
+        metrics.queries.load(Ordering::Relaxed),
+        metrics.slow_queries.load(Ordering::Relaxed),
+        metrics.query_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        metrics.checkouts.load(Ordering::Relaxed),
+        metrics.checkout_wait_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        metrics.rows.load(Ordering::Relaxed),
+        metrics.row_bytes.load(Ordering::Relaxed),
+        metrics.max_request_row_bytes.load(Ordering::Relaxed),
+        pool.max_size,
+        pool.size,
+        pool.available,
+        pool.waiting,
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ResetParams {
+    /// Loads the sample catalog once the tables are empty.
+    #[serde(default)]
+    seed: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ResetResponse {
+    truncated: Vec<String>,
+    seeded: bool,
+}
+
+/// Wipes every table for a clean test run. Only available to admins, and
+/// only when `APP_ENV` is `dev` or `test`.
+#[utoipa::path(
+    post,
+    path = "/admin/reset",
+    tag = "admin",
+    params(ResetParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Tables truncated", body = ResetResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin, or not a dev or test environment", body = ErrorBody)
+    )
+)]
+async fn reset(
+    AdminClaims(admin): AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ResetParams>,
+) -> Result<Json<ResetResponse>, ApiError> {
+    if !state.reset_enabled {
+        return Err(ApiError::Forbidden);
+    }
+
+    // Checked out only now, so that the refusal above never waits on the
+    // database.
+    let mut db = state.db.conn().await.map_err(|e| {
+        tracing::error!("Failed to check out a connection: {}", e);
+
+        ApiError::from(e)
+    })?;
+    match migrations::reset(&mut db, params.seed).await {
+        Ok(truncated) => {
+            tracing::warn!(
+                admin = admin.username,
+                seeded = params.seed,
+                "Database reset"
+            );
+            state.catalog_cache.invalidate();
+
+            Ok(Json(ResetResponse {
+                truncated,
+                seeded: params.seed,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to reset database: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TablesParams {
+    /// How row counts are worked out. Defaults to `exact`, which scans
+    /// every table.
+    count: Option<CountStrategy>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TableStats {
+    name: String,
+    /// `null` when the request asked for `count=none`, or for an estimate
+    /// of a table that was never analyzed.
+    rows: Option<i64>,
+    /// Whether `rows` is a planner estimate rather than an exact count.
+    approximate: bool,
+    /// On-disk size including indexes and TOAST data.
+    size_bytes: i64,
+    /// When a statement last wrote to the table; `null` if none has since
+    /// this was first tracked.
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_modified: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetTablesResponse {
+    tables: Vec<TableStats>,
+}
+
+/// Row count, size and last write of every table, for checking the state of
+/// a database at a glance.
+#[utoipa::path(
+    get,
+    path = "/admin/tables",
+    tag = "admin",
+    params(TablesParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Every table, by name", body = GetTablesResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_tables(
+    _admin: AdminClaims,
+    db: DbConn,
+    Query(params): Query<TablesParams>,
+) -> Result<Json<GetTablesResponse>, ApiError> {
+    let count = params.count.unwrap_or(CountStrategy::Exact);
+    let exact = matches!(count, CountStrategy::Exact);
+
+    // query_to_xml runs the count for each table inside this one statement.
+    match db
+        .query(
+            "SELECT c.relname,
+                    CASE WHEN $1 THEN
+                        (xpath('/row/count/text()', query_to_xml(
+                            format('SELECT count(*) FROM %I.%I', n.nspname, c.relname),
+                            false, true, ''
+                        )))[1]::text::int8
+                    END,
+                    c.reltuples::float8,
+                    pg_total_relation_size(c.oid),
+                    m.modified_at
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             LEFT JOIN table_modifications m ON m.table_name = c.relname
+             WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p')
+             ORDER BY c.relname",
+            &[&exact],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetTablesResponse {
+            tables: rows
+                .iter()
+                .map(|r| {
+                    let estimate: f64 = r.get(2);
+                    let (rows, approximate) = match count {
+                        CountStrategy::Exact => (r.get(1), false),
+                        // Tables that were never vacuumed or analyzed report -1.
+                        CountStrategy::Estimate => {
+                            ((estimate >= 0.0).then(|| estimate.round() as i64), true)
+                        }
+                        CountStrategy::None => (None, false),
+                    };
+                    TableStats {
+                        name: r.get(0),
+                        rows,
+                        approximate,
+                        size_bytes: r.get(3),
+                        last_modified: r.get(4),
+                    }
+                })
+                .collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch table stats: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Longest window `GET /admin/usage` reports on.
+const MAX_USAGE_DAYS: i32 = 90;
+
+#[derive(Deserialize, IntoParams)]
+struct UsageParams {
+    /// Only this client: `user:<username>`, or `anonymous` for requests
+    /// without a valid bearer token. All clients when omitted.
+    key: Option<String>,
+    /// How many days back to report, including today (UTC). Defaults to 7,
+    /// at most 90.
+    days: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ClientUsage {
+    key: String,
+    /// UTC date, as `YYYY-MM-DD`.
+    day: String,
+    requests: i64,
+    /// Queries the client's requests ran, leaving out those inside
+    /// transactions.
+    queries: i64,
+    /// Total time those queries took.
+    query_ms: f64,
+    /// Total time spent handling the requests, queries included.
+    request_ms: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetUsageResponse {
+    usage: Vec<ClientUsage>,
+}
+
+/// Database cost of each client's requests per day, to see which client is
+/// loading the server. Clients are told apart by the user they sign in as.
+/// Usage is written every `USAGE_FLUSH_SECS`, so the last moments of it
+/// don't show yet.
+#[utoipa::path(
+    get,
+    path = "/admin/usage",
+    tag = "admin",
+    params(UsageParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Newest day first, then most query time first", body = GetUsageResponse),
+        (status = 400, description = "days out of range", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_usage(
+    _admin: AdminClaims,
+    db: DbConn,
+    Query(params): Query<UsageParams>,
+) -> Result<Json<GetUsageResponse>, ApiError> {
+    let days = params.days.unwrap_or(7);
+    if !(1..=MAX_USAGE_DAYS).contains(&days) {
+        return Err(ApiError::BadRequest(format!(
+            "days must be between 1 and {}",
+            MAX_USAGE_DAYS
+        )));
+    }
+
+    match db
+        .query(
+            "SELECT client, day::text, requests, queries, query_micros, request_micros
+             FROM api_usage
+             WHERE day > (now() AT TIME ZONE 'UTC')::date - $1::int4
+               AND ($2::text IS NULL OR client = $2)
+             ORDER BY day DESC, query_micros DESC, client",
+            &[&days, &params.key],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetUsageResponse {
+            usage: rows
+                .iter()
+                .map(|r| ClientUsage {
+                    key: r.get(0),
+                    day: r.get(1),
+                    requests: r.get(2),
+                    queries: r.get(3),
+                    query_ms: r.get::<_, i64>(4) as f64 / 1000.0,
+                    request_ms: r.get::<_, i64>(5) as f64 / 1000.0,
+                })
+                .collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch API usage: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateAiTrainerRequest {
+    name: String,
+    strategy: Strategy,
+    /// Defaults to `normal`.
+    #[serde(default)]
+    difficulty: Difficulty,
+    /// Ids of the pokemon on the trainer's team, which the strategy picks
+    /// from when challenged.
+    pokemon: Vec<i32>,
+}
+
+impl Validate for CreateAiTrainerRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.name("name", &self.name, MAX_NAME_LEN);
+        if self.pokemon.is_empty() {
+            errors.add("pokemon", "must not be empty");
+        }
+        let mut ids = self.pokemon.clone();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() < self.pokemon.len() {
+            errors.add("pokemon", "must not list a pokemon twice");
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct CreateAiTrainerResponse {
+    trainer_id: i32,
+}
+
+/// Creates a computer-controlled trainer that challengers can battle through
+/// `POST /battle/ai`.
+#[utoipa::path(
+    post,
+    path = "/admin/ai-trainer",
+    tag = "admin",
+    request_body = CreateAiTrainerRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Trainer created", body = CreateAiTrainerResponse),
+        (status = 400, description = "The team lists pokemon that don't exist", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 422, description = "Invalid fields, or too many pokemon", body = ErrorBody)
+    )
+)]
+async fn create_ai_trainer(
+    AdminClaims(admin): AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Valid(payload): Valid<CreateAiTrainerRequest>,
+) -> Result<(StatusCode, Json<CreateAiTrainerResponse>), ApiError> {
+    let max_team_size = state.limits.max_team_size;
+    if payload.pokemon.len() > max_team_size {
+        let mut errors = FieldErrors::default();
+        errors.add(
+            "pokemon",
+            format!("must have at most {} pokemon", max_team_size),
+        );
+        errors.into_result()?;
+    }
+
+    match state
+        .trainers
+        .create_ai(
+            &payload.name,
+            payload.strategy,
+            payload.difficulty,
+            payload.pokemon,
+        )
+        .await
+    {
+        Ok(CreateAi::Created(trainer_id)) => {
+            tracing::info!(admin = admin.username, trainer_id, "Created AI trainer");
+            state.created(Entity::Trainer, trainer_id);
+
+            Ok((
+                StatusCode::CREATED,
+                Json(CreateAiTrainerResponse { trainer_id }),
+            ))
+        }
+        Ok(CreateAi::UnknownPokemon(unknown)) => Err(ApiError::BadRequest(format!(
+            "unknown pokemon: {}",
+            unknown
+                .iter()
+                .map(i32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to create AI trainer: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct ExperimentResults {
+    experiment: String,
+    /// Every variant, shown to anyone or not.
+    variants: Vec<VariantExposures>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetExperimentsResponse {
+    experiments: Vec<ExperimentResults>,
+}
+
+/// How many users were shown each variant of each running experiment, to
+/// compare them. Results come from the exposures clients record, not from
+/// assignments.
+#[utoipa::path(
+    get,
+    path = "/admin/experiments",
+    tag = "admin",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Exposures of each variant of each experiment", body = GetExperimentsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn get_experiments(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetExperimentsResponse>, ApiError> {
+    let mut experiments = Vec::new();
+    for experiment in EXPERIMENTS {
+        let mut shown = match state.users.exposures(experiment.key).await {
+            Ok(shown) => shown,
+            Err(e) => {
+                tracing::error!("Failed to fetch experiment exposures: {}", e);
+
+                return Err(e.into());
+            }
+        };
+        let variants = experiment
+            .variants
+            .iter()
+            .map(
+                |&variant| match shown.iter().position(|v| v.variant == variant) {
+                    Some(i) => shown.swap_remove(i),
+                    None => VariantExposures {
+                        variant: variant.to_string(),
+                        users: 0,
+                        exposures: 0,
+                    },
+                },
+            )
+            .collect();
+        experiments.push(ExperimentResults {
+            experiment: experiment.key.to_string(),
+            variants,
+        });
+    }
+
+    Ok(Json(GetExperimentsResponse { experiments }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn reset_is_refused_outside_test_environments_and_to_non_admins() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let user = repo.add_user(None, false);
+
+        // As in production, where the endpoint is never enabled.
+        let production = state(repo.clone());
+        let admin = token(&production, admin);
+        let app = build_app(production);
+        let (status, _) = send(&app, Method::POST, "/admin/reset", Some(&admin), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let test = state(repo).with_reset_endpoint();
+        let user = token(&test, user);
+        let app = build_app(test);
+        let (status, _) = send(&app, Method::POST, "/admin/reset", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, Method::POST, "/admin/reset", Some(&user), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::extract::JsonBody;
+use crate::service::AccountService;
+use crate::validation::{FieldErrors, Valid, Validate, MAX_NAME_LEN};
+use crate::AppState;
+use argon2::password_hash::{rand_core::OsRng, SaltString};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock};
+use utoipa::{OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/auth/register", post(register))
+        .route("/auth/login", post(login))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(register, login),
+    components(schemas(Credentials, LoginResponse))
+)]
+pub(crate) struct ApiDoc;
+
+/// What logins for unknown usernames are checked against, so that they take
+/// as long as a wrong password and can't be told apart by timing.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(b"not anyone's password", &salt)
+        .expect("hashing a fixed password cannot fail")
+        .to_string()
+});
+
+const MIN_PASSWORD_LEN: usize = 8;
+/// Keeps the cost of hashing a single registration bounded.
+const MAX_PASSWORD_LEN: usize = 1024;
+
+#[derive(Deserialize, ToSchema)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+/// Only applied on registration; a login with a malformed password simply
+/// fails with 401.
+impl Validate for Credentials {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.name("username", &self.username, MAX_NAME_LEN);
+        if self.password.chars().count() < MIN_PASSWORD_LEN {
+            errors.add(
+                "password",
+                format!("must be at least {} characters", MIN_PASSWORD_LEN),
+            );
+        }
+        errors.max_len("password", &self.password, MAX_PASSWORD_LEN);
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "Account and its trainer created"),
+        (status = 422, description = "Username taken or out of bounds, or password out of bounds", body = ErrorBody)
+    )
+)]
+/// Creates the account together with the trainer it plays as, named after
+/// the account. A username another trainer goes by is taken too.
+async fn register(
+    State(state): State<Arc<AppState>>,
+    Valid(payload): Valid<Credentials>,
+) -> Result<StatusCode, ApiError> {
+    // Argon2 is deliberately slow, so it stays off the async workers.
+    let password_hash = tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(payload.password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .expect("password hashing panicked")
+    .map_err(|e| {
+        tracing::error!("Failed to hash password: {}", e);
+
+        ApiError::DatabaseError
+    })?;
+
+    AccountService::new(&state)
+        .register(&payload.username, &password_hash)
+        .await?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[derive(Serialize, ToSchema)]
+struct LoginResponse {
+    token: String,
+    token_type: &'static str,
+    /// Seconds until the token expires.
+    expires_in: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "Bearer token for the account", body = LoginResponse),
+        (status = 401, description = "Wrong username or password", body = ErrorBody)
+    )
+)]
+async fn login(
+    State(state): State<Arc<AppState>>,
+    db: DbConn,
+    JsonBody(payload): JsonBody<Credentials>,
+) -> Result<Json<LoginResponse>, ApiError> {
+    let rows = match db
+        .query(
+            "SELECT user_id, password_hash FROM users WHERE username = $1",
+            &[&payload.username],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::error!("Failed to look up user: {}", e);
+
+            return Err(e.into());
+        }
+    };
+    let (user_id, password_hash): (Option<i32>, Option<String>) =
+        rows.first().map(|row| (row.get(0), row.get(1))).unzip();
+
+    // An unknown username is still put through argon2, and gets the same
+    // answer as a wrong password.
+    let password = payload.password;
+    let valid = tokio::task::spawn_blocking(move || {
+        let password_hash = password_hash.as_deref().unwrap_or(&DUMMY_HASH);
+        PasswordHash::new(password_hash)
+            .map(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    })
+    .await
+    .expect("password verification panicked");
+    let Some(user_id) = user_id.filter(|_| valid) else {
+        return Err(ApiError::Unauthorized);
+    };
+
+    Ok(Json(LoginResponse {
+        token: state
+            .auth
+            .issue(user_id, payload.username, state.clock.now()),
+        token_type: "Bearer",
+        expires_in: state.auth.token_ttl.as_secs(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state};
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn taken_usernames_are_rejected_on_the_username_field() {
+        let repo = Arc::new(InMemoryRepository::default());
+        repo.add_trainer("Misty", &[]);
+        let app = build_app(state(repo));
+        let register = |username: &str| json!({"username": username, "password": "pikapika"});
+
+        let (status, _) = send(
+            &app,
+            Method::POST,
+            "/auth/register",
+            None,
+            Some(register("Ash")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        for username in ["Ash", "Misty"] {
+            let (status, body) = send(
+                &app,
+                Method::POST,
+                "/auth/register",
+                None,
+                Some(register(username)),
+            )
+            .await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", username);
+            assert_eq!(body["fields"][0]["field"], "username", "{}", username);
+        }
+    }
+}
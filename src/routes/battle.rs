@@ -0,0 +1,283 @@
+use crate::ai::Difficulty;
+use crate::auth::{AdminClaims, CurrentTrainer};
+use crate::battle::{Action, Status, Turn};
+use crate::error::ApiError;
+use crate::extract::{JsonBody, Path};
+use crate::models::CatalogSnapshot;
+use crate::service::{Battle, BattleService};
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/battle", post(simulate_battle))
+        .route("/battle/ai", post(simulate_ai_battle))
+        .route("/battle/:id/verify", post(verify_battle))
+        .route("/battle/snapshots", post(create_snapshot))
+        .route("/battle/snapshots/:id", get(get_snapshot))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        simulate_battle,
+        simulate_ai_battle,
+        verify_battle,
+        create_snapshot,
+        get_snapshot
+    ),
+    components(schemas(
+        BattleRequest,
+        AiBattleRequest,
+        BattleResponse,
+        Difficulty,
+        Turn,
+        Action,
+        Status,
+        CatalogSnapshot
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Deserialize, ToSchema)]
+struct BattleRequest {
+    pokemon_id: i32,
+    opponent_id: i32,
+    /// Replays the battle from an earlier response. A random seed is used
+    /// when omitted.
+    seed: Option<u64>,
+    /// Fights with the types and abilities pinned in this catalog snapshot
+    /// instead of the live catalog, so that rebalances don't change the
+    /// result. Replays need the same snapshot as well as the same seed.
+    snapshot_id: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BattleResponse {
+    /// Id of the stored battle, for `POST /battle/{id}/verify`.
+    battle_id: i32,
+    pokemon_id: i32,
+    opponent_id: i32,
+    seed: u64,
+    /// The catalog snapshot the battle was fought with, or `null` for the
+    /// live catalog.
+    snapshot_id: Option<i32>,
+    /// The computer-controlled trainer that sent out `opponent_id`, or
+    /// `null` when both pokemon used random abilities.
+    ai_trainer_id: Option<i32>,
+    /// The difficulty the AI trainer played at, or `null` without one.
+    difficulty: Option<Difficulty>,
+    /// What the challenger earned for beating the AI trainer, now added to
+    /// their trainer's `rewards`: 0 unless they won, and `null` without an
+    /// AI trainer.
+    reward: Option<i32>,
+    /// The pokemon left standing, or `null` for a draw.
+    winner: Option<i32>,
+    turns: Vec<Turn>,
+}
+
+impl BattleResponse {
+    fn new(battle: Battle, snapshot_id: Option<i32>, ai_trainer_id: Option<i32>) -> Self {
+        Self {
+            battle_id: battle.battle_id,
+            pokemon_id: battle.pokemon_id,
+            opponent_id: battle.opponent_id,
+            seed: battle.seed,
+            snapshot_id,
+            ai_trainer_id,
+            difficulty: battle.difficulty,
+            reward: battle.reward,
+            winner: battle.outcome.winner,
+            turns: battle.outcome.turns,
+        }
+    }
+}
+
+/// Simulates a battle between two pokemon on behalf of the signed-in
+/// user's trainer, which counts against its hourly battle quota.
+#[utoipa::path(
+    post,
+    path = "/battle",
+    tag = "battle",
+    request_body = BattleRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Turn-by-turn log and winner", body = BattleResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No such pokemon or snapshot, or the account has no trainer", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody),
+        (status = 429, description = "Battle quota used up; Retry-After says when it frees up", body = ErrorBody)
+    )
+)]
+async fn simulate_battle(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    JsonBody(payload): JsonBody<BattleRequest>,
+) -> Result<Json<BattleResponse>, ApiError> {
+    let battle = BattleService::new(&state)
+        .fight(
+            me.trainer_id,
+            payload.pokemon_id,
+            payload.opponent_id,
+            payload.seed,
+            payload.snapshot_id,
+        )
+        .await?;
+
+    Ok(Json(BattleResponse::new(battle, payload.snapshot_id, None)))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct AiBattleRequest {
+    pokemon_id: i32,
+    /// A trainer created through `POST /admin/ai-trainer`.
+    ai_trainer_id: i32,
+    /// Replays the battle from an earlier response, as long as the AI
+    /// trainer's team hasn't changed since. A random seed is used when
+    /// omitted.
+    seed: Option<u64>,
+    /// As for `POST /battle`.
+    snapshot_id: Option<i32>,
+}
+
+/// Battles a computer-controlled trainer. Its strategy picks which of its
+/// pokemon to send out against `pokemon_id` and which abilities that pokemon
+/// uses; the challenger's pokemon uses random abilities as in
+/// `POST /battle`.
+///
+/// The trainer's difficulty scales the damage of its pokemon's abilities to
+/// a share of the strength of the challenger's whole team, `pokemon_id`
+/// included, as the team analyzer scores them against each other, and
+/// decides how often the strategy gets a choice wrong. Harder trainers give
+/// bigger rewards.
+///
+/// The signed-in user's trainer is the challenger, and the battle counts
+/// against its hourly battle quota as in `POST /battle`.
+#[utoipa::path(
+    post,
+    path = "/battle/ai",
+    tag = "battle",
+    request_body = AiBattleRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Turn-by-turn log and winner", body = BattleResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No such pokemon, AI trainer or snapshot, or the account has no trainer", body = ErrorBody),
+        (status = 409, description = "The AI trainer has no pokemon to send out", body = ErrorBody),
+        (status = 429, description = "Battle quota used up; Retry-After says when it frees up", body = ErrorBody)
+    )
+)]
+async fn simulate_ai_battle(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    JsonBody(payload): JsonBody<AiBattleRequest>,
+) -> Result<Json<BattleResponse>, ApiError> {
+    let battle = BattleService::new(&state)
+        .fight_ai(
+            me.trainer_id,
+            payload.pokemon_id,
+            payload.ai_trainer_id,
+            payload.seed,
+            payload.snapshot_id,
+        )
+        .await?;
+
+    Ok(Json(BattleResponse::new(
+        battle,
+        payload.snapshot_id,
+        Some(payload.ai_trainer_id),
+    )))
+}
+
+#[derive(Serialize, ToSchema)]
+struct VerifyResponse {
+    battle_id: i32,
+    /// Whether the replay came out exactly as stored.
+    consistent: bool,
+    /// The stored winner, or `null` for a draw.
+    winner: Option<i32>,
+    replayed_winner: Option<i32>,
+    /// The first turn that came out differently, or `null` when all of them
+    /// matched.
+    first_mismatch: Option<u32>,
+}
+
+/// Replays a stored battle from its stored seed and fighters, and compares
+/// the result with the stored one. The replay doesn't read the catalog, so
+/// a mismatch means either the stored battle was altered or the simulator
+/// no longer plays out the same.
+#[utoipa::path(
+    post,
+    path = "/battle/{id}/verify",
+    tag = "battle",
+    params(("id" = i32, Path, description = "Battle id")),
+    responses(
+        (status = 200, description = "How the replay compares with the stored battle", body = VerifyResponse),
+        (status = 404, description = "No such battle", body = ErrorBody)
+    )
+)]
+async fn verify_battle(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    let verification = BattleService::new(&state).verify(id).await?;
+
+    Ok(Json(VerifyResponse {
+        battle_id: id,
+        consistent: verification.consistent,
+        winner: verification.winner,
+        replayed_winner: verification.replayed_winner,
+        first_mismatch: verification.first_mismatch,
+    }))
+}
+
+/// Pins the types, weaknesses and ability values of every published pokemon,
+/// for battles that must not change with the catalog, such as those of a
+/// tournament. Take one when the tournament starts and pass its id with each
+/// battle.
+#[utoipa::path(
+    post,
+    path = "/battle/snapshots",
+    tag = "battle",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Snapshot taken", body = CatalogSnapshot),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody)
+    )
+)]
+async fn create_snapshot(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<CatalogSnapshot>), ApiError> {
+    let snapshot = BattleService::new(&state).take_snapshot().await?;
+
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/battle/snapshots/{id}",
+    tag = "battle",
+    params(("id" = i32, Path, description = "Snapshot id")),
+    responses(
+        (status = 200, description = "The snapshot", body = CatalogSnapshot),
+        (status = 404, description = "No such snapshot", body = ErrorBody)
+    )
+)]
+async fn get_snapshot(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<CatalogSnapshot>, ApiError> {
+    let snapshot = BattleService::new(&state).snapshot(id).await?;
+
+    Ok(Json(snapshot))
+}
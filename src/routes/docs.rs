@@ -0,0 +1,105 @@
+use crate::case::{camel_case_schemas, FieldCase};
+use crate::error::ErrorBody;
+use crate::routes::{
+    ability, admin, auth, battle, me, owned, pokemon, quiz, region, reports, search, stats, system,
+    team, trainer,
+};
+use crate::validation::FieldError;
+use crate::AppState;
+use axum::{extract::State, response::Html, routing::get, Json, Router};
+use std::sync::Arc;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/openapi.json", get(get_openapi))
+        .route("/docs", get(get_docs))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Pokedex API",
+        description = "Trainers, pokemon and their abilities."
+    ),
+    components(schemas(ErrorBody, FieldError)),
+    modifiers(&BearerAuth)
+)]
+struct ApiDoc;
+
+/// Declares the `bearer_auth` scheme that mutating routes list under
+/// `security`, so Swagger UI can send the token from `/auth/login`.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
+            );
+        }
+    }
+}
+
+/// The full spec. Each route module documents its own handlers and DTOs, so
+/// the spec sits next to the code it describes.
+pub(crate) fn openapi() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    for api in [
+        auth::ApiDoc::openapi(),
+        me::ApiDoc::openapi(),
+        trainer::ApiDoc::openapi(),
+        owned::ApiDoc::openapi(),
+        pokemon::ApiDoc::openapi(),
+        quiz::ApiDoc::openapi(),
+        ability::ApiDoc::openapi(),
+        region::ApiDoc::openapi(),
+        team::ApiDoc::openapi(),
+        search::ApiDoc::openapi(),
+        stats::ApiDoc::openapi(),
+        battle::ApiDoc::openapi(),
+        reports::ApiDoc::openapi(),
+        admin::ApiDoc::openapi(),
+        system::ApiDoc::openapi(),
+    ] {
+        doc.merge(api);
+    }
+    doc
+}
+
+async fn get_openapi(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let mut spec = serde_json::to_value(openapi()).expect("the spec always serializes");
+    if state.field_case == FieldCase::Camel {
+        camel_case_schemas(&mut spec);
+    }
+    Json(spec)
+}
+
+/// Swagger UI is loaded from a CDN rather than bundled into the binary.
+const SWAGGER_UI: &str = r##"<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>Pokedex API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+async fn get_docs() -> Html<&'static str> {
+    Html(SWAGGER_UI)
+}
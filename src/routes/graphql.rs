@@ -0,0 +1,793 @@
+use crate::auth::AuthClaims;
+use crate::db::DbError;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::models::{
+    Ability, CountStrategy, EntityKey, PageParams, Pokemon, PokemonFilter, PokemonFull, Region,
+    RegionRef, SortParams,
+};
+use crate::repository::{NewPokemon, TrainerChanges, TrainerRepository};
+use crate::routes::pokemon::{require_abilities, require_region};
+use crate::service::{CatalogHistory, TrainerService};
+use crate::validation::{FieldErrors, MAX_NAME_LEN, MAX_STATUS_EFFECT_LEN};
+use crate::AppState;
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{ComplexObject, Context, EmptySubscription, Object, Schema, SimpleObject};
+use axum::{extract::State, response::Html, routing::get, Extension, Json, Router};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Deepest selection a query may make; trainer -> pokemon -> abilities is 3.
+const MAX_DEPTH: usize = 8;
+/// Fields a single query may select in total, counting list items once.
+const MAX_COMPLEXITY: usize = 500;
+
+type ApiSchema = Schema<Query, Mutation, EmptySubscription>;
+
+/// `POST /graphql` runs queries and mutations against the same data as the
+/// REST routes; `GET /graphql` serves the GraphiQL playground. Mutations
+/// need a bearer token, like their REST counterparts: catalog writes an
+/// admin's, and trainer writes that of the trainer's own user.
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    let schema = Schema::build(Query, Mutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish();
+
+    Router::new()
+        .route("/graphql", get(graphiql).post(execute))
+        .layer(Extension(schema))
+}
+
+async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+async fn execute(
+    State(state): State<Arc<AppState>>,
+    Extension(schema): Extension<ApiSchema>,
+    claims: Option<AuthClaims>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    // A loader per request, so that nothing loaded outlives the request.
+    let teams = DataLoader::new(TeamLoader(state.trainers.clone()), tokio::spawn);
+    let mut request = request.data(state).data(teams);
+    if let Some(claims) = claims {
+        request = request.data(claims);
+    }
+
+    Json(schema.execute(request).await)
+}
+
+fn state<'a>(ctx: &Context<'a>) -> &'a Arc<AppState> {
+    ctx.data_unchecked::<Arc<AppState>>()
+}
+
+/// Answers like an admin-only REST route would: 401 without a valid bearer
+/// token and 403 for users who aren't admins.
+async fn require_admin(ctx: &Context<'_>) -> Result<(), ApiError> {
+    let claims = ctx.data_opt::<AuthClaims>().ok_or(ApiError::Unauthorized)?;
+    match state(ctx).trainers.account(claims.sub).await {
+        Ok(Some(account)) if account.is_admin => Ok(()),
+        Ok(_) => Err(ApiError::Forbidden),
+        Err(e) => Err(db_error("look up the user", e)),
+    }
+}
+
+/// Answers 401 without a valid bearer token and 403 unless `trainer_id` is
+/// the caller's own trainer. There is no impersonation here; admins acting
+/// as a trainer go through the audited REST routes.
+async fn require_owner(ctx: &Context<'_>, trainer_id: i32) -> Result<(), ApiError> {
+    let claims = ctx.data_opt::<AuthClaims>().ok_or(ApiError::Unauthorized)?;
+    match state(ctx).trainers.account(claims.sub).await {
+        Ok(Some(account)) if account.trainer_id == Some(trainer_id) => Ok(()),
+        Ok(_) => Err(ApiError::Forbidden),
+        Err(e) => Err(db_error("look up the user's trainer", e)),
+    }
+}
+
+fn db_error(action: &str, e: DbError) -> ApiError {
+    tracing::error!("Failed to {}: {}", action, e);
+
+    e.into()
+}
+
+/// Loads the teams of every trainer a query selects in one go, instead of
+/// one query per trainer.
+struct TeamLoader(Arc<dyn TrainerRepository>);
+
+impl Loader<i32> for TeamLoader {
+    type Value = Vec<PokemonFull>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, trainer_ids: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        self.0
+            .teams(trainer_ids)
+            .await
+            .map_err(|e| db_error("fetch teams", e).into())
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct Trainer {
+    trainer_id: i32,
+    uuid: Uuid,
+    name: String,
+    gym_leader: bool,
+    /// What the trainer has earned beating AI trainers.
+    rewards: i32,
+}
+
+#[ComplexObject]
+impl Trainer {
+    /// The trainer's published pokemon with their abilities and attributes.
+    async fn pokemon(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PokemonFull>> {
+        let teams = ctx.data_unchecked::<DataLoader<TeamLoader>>();
+
+        Ok(teams.load_one(self.trainer_id).await?.unwrap_or_default())
+    }
+}
+
+impl From<crate::models::Trainer> for Trainer {
+    fn from(t: crate::models::Trainer) -> Self {
+        Self {
+            trainer_id: t.trainer_id,
+            uuid: t.uuid,
+            name: t.name,
+            gym_leader: t.gym_leader,
+            rewards: t.rewards,
+        }
+    }
+}
+
+fn paging(page: Option<i64>, per_page: Option<i64>) -> PageParams {
+    PageParams {
+        page,
+        per_page,
+        // Lists are plain arrays here, with no total to report.
+        count: Some(CountStrategy::None),
+    }
+}
+
+pub(crate) struct Query;
+
+#[Object]
+impl Query {
+    async fn trainers(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i64>,
+        per_page: Option<i64>,
+    ) -> async_graphql::Result<Vec<Trainer>> {
+        let paging = paging(page, per_page);
+        match state(ctx)
+            .trainers
+            .list(None, &SortParams::default(), &paging)
+            .await
+        {
+            Ok((trainers, _)) => Ok(trainers.into_iter().map(Trainer::from).collect()),
+            Err(e) => Err(db_error("fetch trainers", e).into()),
+        }
+    }
+
+    async fn trainer(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Trainer>> {
+        match state(ctx).trainers.get(id).await {
+            Ok(trainer) => Ok(trainer.map(Trainer::from)),
+            Err(e) => Err(db_error("fetch trainer", e).into()),
+        }
+    }
+
+    /// Published pokemon, filtered like `GET /pokemon`.
+    #[allow(clippy::too_many_arguments)]
+    async fn pokemon(
+        &self,
+        ctx: &Context<'_>,
+        page: Option<i64>,
+        per_page: Option<i64>,
+        name: Option<String>,
+        region: Option<String>,
+        ability: Option<String>,
+        generation: Option<i32>,
+    ) -> async_graphql::Result<Vec<PokemonFull>> {
+        let filter = PokemonFilter {
+            name,
+            region,
+            ability,
+            generation,
+            ..PokemonFilter::default()
+        };
+        let paging = paging(page, per_page);
+        match state(ctx)
+            .pokemon
+            .list(&filter, &SortParams::default(), &paging)
+            .await
+        {
+            Ok((pokemon, _)) => Ok(pokemon),
+            Err(e) => Err(db_error("fetch pokemon", e).into()),
+        }
+    }
+
+    async fn regions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Region>> {
+        let db = state(ctx)
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("fetch regions", e.into()))?;
+        match db
+            .query_capped(
+                "SELECT region_id, region_name FROM region ORDER BY region_id",
+                &[],
+            )
+            .await
+        {
+            Ok(rows) => Ok(rows
+                .iter()
+                .map(|r| Region {
+                    region_id: r.get(0),
+                    region_name: r.get(1),
+                })
+                .collect()),
+            Err(e) => Err(db_error("fetch regions", e).into()),
+        }
+    }
+
+    /// Published abilities, by id.
+    async fn abilities(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Ability>> {
+        let db = state(ctx)
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("fetch abilities", e.into()))?;
+        match db
+            .query_capped(
+                "SELECT ability_id, name, damage, status_effect
+                 FROM published_ability ORDER BY ability_id",
+                &[],
+            )
+            .await
+        {
+            Ok(rows) => Ok(rows
+                .iter()
+                .map(|r| Ability {
+                    ability_id: r.get(0),
+                    name: r.get(1),
+                    damage: r.get(2),
+                    status_effect: r.get(3),
+                })
+                .collect()),
+            Err(e) => Err(db_error("fetch abilities", e).into()),
+        }
+    }
+}
+
+pub(crate) struct Mutation;
+
+#[Object]
+impl Mutation {
+    /// Returns the new trainer's id.
+    async fn create_trainer(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        gym_leader: bool,
+    ) -> async_graphql::Result<i32> {
+        require_admin(ctx).await?;
+
+        Ok(TrainerService::new(state(ctx))
+            .create(&name, gym_leader)
+            .await?)
+    }
+
+    /// Changes the given fields, like `PATCH /trainer/:id`. `pokemon`
+    /// replaces the whole team, and `[]` releases every pokemon.
+    async fn update_trainer(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        name: Option<String>,
+        gym_leader: Option<bool>,
+        pokemon: Option<Vec<i32>>,
+    ) -> async_graphql::Result<Trainer> {
+        require_owner(ctx, id).await?;
+
+        let state = state(ctx);
+        let changes = TrainerChanges {
+            name,
+            gym_leader,
+            team: pokemon,
+            metadata: None,
+        };
+        TrainerService::new(state).update(id, changes).await?;
+
+        match state.trainers.get(id).await {
+            Ok(Some(trainer)) => Ok(trainer.into()),
+            Ok(None) => Err(ApiError::NotFound.into()),
+            Err(e) => Err(db_error("fetch trainer", e).into()),
+        }
+    }
+
+    /// Returns whether the trainer existed. Fails like `DELETE /trainer/:id`
+    /// when the trainer still has pokemon, unless `force` releases them.
+    async fn delete_trainer(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        #[graphql(default)] force: bool,
+    ) -> async_graphql::Result<bool> {
+        require_owner(ctx, id).await?;
+
+        Ok(TrainerService::new(state(ctx)).delete(id, force).await?)
+    }
+
+    /// Fails like `POST /trainer/:id/pokemon/:pokemon_id` when the pokemon
+    /// is already attached or the team is full.
+    async fn attach_pokemon(
+        &self,
+        ctx: &Context<'_>,
+        trainer_id: i32,
+        pokemon_id: i32,
+    ) -> async_graphql::Result<bool> {
+        require_owner(ctx, trainer_id).await?;
+
+        TrainerService::new(state(ctx))
+            .attach(trainer_id, pokemon_id)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Returns whether the pokemon was attached.
+    async fn detach_pokemon(
+        &self,
+        ctx: &Context<'_>,
+        trainer_id: i32,
+        pokemon_id: i32,
+    ) -> async_graphql::Result<bool> {
+        require_owner(ctx, trainer_id).await?;
+
+        Ok(TrainerService::new(state(ctx))
+            .detach(trainer_id, pokemon_id)
+            .await?)
+    }
+
+    /// Creates a pokemon that goes live right away, like `POST /pokemon`
+    /// without `publish_at`.
+    async fn create_pokemon(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        region_id: i32,
+        #[graphql(default)] abilities: Vec<i32>,
+    ) -> async_graphql::Result<Pokemon> {
+        require_admin(ctx).await?;
+        let mut errors = FieldErrors::default();
+        errors.slug_name("name", &name, MAX_NAME_LEN);
+        errors.into_result()?;
+
+        let state = state(ctx);
+        let (region_id, _) = require_region(state, &RegionRef::Id(region_id)).await?;
+        require_abilities(state, &abilities).await?;
+
+        let created = state
+            .pokemon
+            .create(NewPokemon {
+                name,
+                region_id,
+                publish_at: None,
+                abilities,
+                metadata: Default::default(),
+            })
+            .await;
+        match created {
+            Ok(pokemon) => {
+                CatalogHistory::new(state)
+                    .record_pokemon(pokemon.pokemon_id)
+                    .await;
+                state.created(Entity::Pokemon, pokemon.pokemon_id);
+
+                Ok(pokemon)
+            }
+            Err(e) => Err(db_error("create pokemon", e).into()),
+        }
+    }
+
+    /// Moves the pokemon to another region, and renames it when given a
+    /// `name`, like `PUT /pokemon/:id`.
+    async fn update_pokemon(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        name: Option<String>,
+        region_id: i32,
+    ) -> async_graphql::Result<Pokemon> {
+        require_admin(ctx).await?;
+        let mut errors = FieldErrors::default();
+        if let Some(name) = &name {
+            errors.name("name", name, MAX_NAME_LEN);
+        }
+        errors.into_result()?;
+
+        let state = state(ctx);
+        let (region_id, _) = require_region(state, &RegionRef::Id(region_id)).await?;
+
+        match state
+            .pokemon
+            .update(id, name.as_deref(), region_id, None)
+            .await
+        {
+            Ok(Some(pokemon)) => {
+                CatalogHistory::new(state).record_pokemon(id).await;
+                state.updated(Entity::Pokemon, id);
+
+                Ok(pokemon)
+            }
+            Ok(None) => Err(ApiError::NotFound.into()),
+            Err(e) => Err(db_error("update pokemon", e).into()),
+        }
+    }
+
+    /// Returns whether the pokemon existed. Fails like `DELETE /pokemon/:key`
+    /// while a trainer still owns it.
+    async fn delete_pokemon(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<bool> {
+        require_admin(ctx).await?;
+
+        let state = state(ctx);
+        match state.pokemon.delete(&EntityKey::Id(id)).await {
+            Ok(Some(_)) => {
+                state.deleted(Entity::Pokemon, id);
+
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => Err(db_error("delete pokemon", e).into()),
+        }
+    }
+
+    /// Creates an ability that goes live right away. Fails with 409 when the
+    /// name is taken; `PUT /ability/:name` upserts instead.
+    async fn create_ability(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        damage: i32,
+        status_effect: String,
+    ) -> async_graphql::Result<Ability> {
+        require_admin(ctx).await?;
+        let mut errors = ability_errors(damage, &status_effect);
+        errors.name("name", &name, MAX_NAME_LEN);
+        errors.into_result()?;
+
+        let state = state(ctx);
+        let db = state
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("create ability", e.into()))?;
+        match db
+            .query(
+                "INSERT INTO ability (name, damage, status_effect) VALUES ($1, $2, $3)
+                 RETURNING ability_id",
+                &[&name, &damage, &status_effect],
+            )
+            .await
+        {
+            Ok(rows) => {
+                let ability_id = rows.first().unwrap().get(0);
+                CatalogHistory::new(state).record_ability(ability_id).await;
+                state.created(Entity::Ability, ability_id);
+
+                Ok(Ability {
+                    ability_id,
+                    name,
+                    damage,
+                    status_effect,
+                })
+            }
+            Err(e) => Err(db_error("create ability", e.into()).into()),
+        }
+    }
+
+    /// Rebalances the ability right away.
+    async fn update_ability(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        damage: i32,
+        status_effect: String,
+    ) -> async_graphql::Result<Ability> {
+        require_admin(ctx).await?;
+        ability_errors(damage, &status_effect).into_result()?;
+
+        let state = state(ctx);
+        let db = state
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("update ability", e.into()))?;
+        match db
+            .query(
+                "UPDATE ability SET damage = $2, status_effect = $3 WHERE ability_id = $1
+                 RETURNING name",
+                &[&id, &damage, &status_effect],
+            )
+            .await
+        {
+            Ok(rows) => match rows.first() {
+                Some(r) => {
+                    CatalogHistory::new(state).record_ability(id).await;
+                    state.updated(Entity::Ability, id);
+
+                    Ok(Ability {
+                        ability_id: id,
+                        name: r.get(0),
+                        damage,
+                        status_effect,
+                    })
+                }
+                None => Err(ApiError::NotFound.into()),
+            },
+            Err(e) => Err(db_error("update ability", e.into()).into()),
+        }
+    }
+
+    /// Returns whether the ability existed, dropping any rebalance staged
+    /// for it. Fails with 409 while a pokemon still has the ability.
+    async fn delete_ability(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<bool> {
+        require_admin(ctx).await?;
+
+        let state = state(ctx);
+        let db = state
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("delete ability", e.into()))?;
+        // Foreign keys are checked at the end of the statement, after the
+        // staged change is gone.
+        match db
+            .execute(
+                "WITH unstaged AS (DELETE FROM ability_changes WHERE ability_id = $1)
+                 DELETE FROM ability WHERE ability_id = $1",
+                &[&id],
+            )
+            .await
+        {
+            Ok(0) => Ok(false),
+            Ok(_) => {
+                state.deleted(Entity::Ability, id);
+
+                Ok(true)
+            }
+            Err(e) => Err(db_error("delete ability", e.into()).into()),
+        }
+    }
+
+    async fn create_region(
+        &self,
+        ctx: &Context<'_>,
+        region_name: String,
+    ) -> async_graphql::Result<Region> {
+        require_admin(ctx).await?;
+        let mut errors = FieldErrors::default();
+        errors.name("region_name", &region_name, MAX_NAME_LEN);
+        errors.into_result()?;
+
+        let state = state(ctx);
+        let db = state
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("create region", e.into()))?;
+        match db
+            .query(
+                "INSERT INTO region (region_name) VALUES ($1) RETURNING region_id",
+                &[&region_name],
+            )
+            .await
+        {
+            Ok(rows) => {
+                let region_id = rows.first().unwrap().get(0);
+                state.created(Entity::Region, region_id);
+
+                Ok(Region {
+                    region_id,
+                    region_name,
+                })
+            }
+            Err(e) => Err(db_error("create region", e.into()).into()),
+        }
+    }
+
+    async fn update_region(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        region_name: String,
+    ) -> async_graphql::Result<Region> {
+        require_admin(ctx).await?;
+        let mut errors = FieldErrors::default();
+        errors.name("region_name", &region_name, MAX_NAME_LEN);
+        errors.into_result()?;
+
+        let state = state(ctx);
+        let db = state
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("update region", e.into()))?;
+        match db
+            .execute(
+                "UPDATE region SET region_name = $2 WHERE region_id = $1",
+                &[&id, &region_name],
+            )
+            .await
+        {
+            Ok(0) => Err(ApiError::NotFound.into()),
+            Ok(_) => {
+                state.updated(Entity::Region, id);
+
+                Ok(Region {
+                    region_id: id,
+                    region_name,
+                })
+            }
+            Err(e) => Err(db_error("update region", e.into()).into()),
+        }
+    }
+
+    /// Returns whether the region existed. Fails like `DELETE /region/:id`
+    /// while pokemon still belong to it.
+    async fn delete_region(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<bool> {
+        require_admin(ctx).await?;
+
+        let state = state(ctx);
+        let db = state
+            .db
+            .conn()
+            .await
+            .map_err(|e| db_error("delete region", e.into()))?;
+        match db
+            .query("SELECT 1 FROM pokemon WHERE region_id = $1 LIMIT 1", &[&id])
+            .await
+        {
+            Ok(rows) if !rows.is_empty() => {
+                return Err(
+                    ApiError::Conflict("region is still referenced by pokemon".to_string()).into(),
+                )
+            }
+            Ok(_) => {}
+            Err(e) => return Err(db_error("check region references", e.into()).into()),
+        }
+
+        match db
+            .execute("DELETE FROM region WHERE region_id = $1", &[&id])
+            .await
+        {
+            Ok(0) => Ok(false),
+            Ok(_) => {
+                state.deleted(Entity::Region, id);
+
+                Ok(true)
+            }
+            Err(e) => Err(db_error("delete region", e.into()).into()),
+        }
+    }
+}
+
+/// The checks `PUT /ability/:name` makes of its body.
+fn ability_errors(damage: i32, status_effect: &str) -> FieldErrors {
+    let mut errors = FieldErrors::default();
+    errors.damage("damage", damage);
+    errors.name("status_effect", status_effect, MAX_STATUS_EFFECT_LEN);
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn query(query: &str) -> Option<Value> {
+        Some(json!({ "query": query }))
+    }
+
+    #[tokio::test]
+    async fn teams_of_listed_trainers_load_in_one_batch() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let staryu = repo.add_pokemon("Staryu", kanto, &[]);
+        repo.add_trainer("Ash", &[pikachu]);
+        repo.add_trainer("Misty", &[staryu]);
+        repo.add_trainer("Brock", &[]);
+        let app = build_app(state(repo.clone()));
+
+        let body = query("{ trainers { name pokemon { name } } }");
+        let (status, body) = send(&app, Method::POST, "/graphql", None, body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["data"]["trainers"],
+            json!([
+                {"name": "Ash", "pokemon": [{"name": "Pikachu"}]},
+                {"name": "Misty", "pokemon": [{"name": "Staryu"}]},
+                {"name": "Brock", "pokemon": []},
+            ])
+        );
+        assert_eq!(repo.team_loads(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_trainer_requires_the_owner_and_replaces_the_team() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let eevee = repo.add_pokemon("Eevee", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let owner = repo.add_user(Some(ash), false);
+        let admin = repo.add_user(None, true);
+        let state = state(repo.clone());
+        let (token, admin) = (token(&state, owner), token(&state, admin));
+        let app = build_app(state);
+        let mutation = query(&format!(
+            "mutation {{ updateTrainer(id: {}, name: \"Red\", pokemon: [{}]) {{
+                 name pokemon {{ name }}
+             }} }}",
+            ash, eevee
+        ));
+
+        let (_, body) = send(&app, Method::POST, "/graphql", None, mutation.clone()).await;
+        assert_eq!(body["errors"][0]["extensions"]["code"], 401);
+        let (_, body) = send(
+            &app,
+            Method::POST,
+            "/graphql",
+            Some(&admin),
+            mutation.clone(),
+        )
+        .await;
+        assert_eq!(body["errors"][0]["extensions"]["code"], 403);
+        assert_eq!(repo.team_of(ash), Some(vec![pikachu]));
+
+        let (_, body) = send(&app, Method::POST, "/graphql", Some(&token), mutation).await;
+        assert_eq!(
+            body["data"]["updateTrainer"],
+            json!({"name": "Red", "pokemon": [{"name": "Eevee"}]})
+        );
+        assert_eq!(repo.team_of(ash), Some(vec![eevee]));
+    }
+
+    #[tokio::test]
+    async fn catalog_mutations_require_an_admin() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let ash = repo.add_trainer("Ash", &[]);
+        let player = repo.add_user(Some(ash), false);
+        let admin = repo.add_user(None, true);
+        let state = state(repo);
+        let (player, admin) = (token(&state, player), token(&state, admin));
+        let app = build_app(state);
+        let mutation = query(&format!(
+            "mutation {{ createPokemon(name: \"Pikachu\", regionId: {}) {{ slug }} }}",
+            kanto
+        ));
+
+        let (_, body) = send(
+            &app,
+            Method::POST,
+            "/graphql",
+            Some(&player),
+            mutation.clone(),
+        )
+        .await;
+        assert_eq!(body["errors"][0]["extensions"]["code"], 403);
+
+        let (_, body) = send(&app, Method::POST, "/graphql", Some(&admin), mutation).await;
+        assert_eq!(body["data"]["createPokemon"]["slug"], "pikachu");
+    }
+}
@@ -0,0 +1,859 @@
+use crate::auth::{AuthClaims, CurrentTrainer};
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::experiments::{self, EXPERIMENTS};
+use crate::extract::{Path, Query};
+use crate::models::{
+    Bookmark, CatalogEntry, CatalogKind, ExperimentAssignment, OwnedPokemon, PageParams, Pokemon,
+    ProposalParams, RecentView, Trainer,
+};
+use crate::repository::AddBookmark;
+use crate::routes::ability::GetProposalsResponse;
+use crate::service::TrainerService;
+use crate::AppState;
+use axum::{
+    extract::{RawQuery, State},
+    http::{header::LINK, HeaderName, HeaderValue, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+/// Routes acting on the signed-in user's own trainer, so clients never pass
+/// a trainer id and cannot act on anyone else's. Admins may act as another
+/// trainer; see `CurrentTrainer`.
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/me", get(get_me))
+        .route("/me/pokemon", get(get_my_pokemon))
+        .route("/me/battles", get(get_my_battles))
+        .route(
+            "/me/pokemon/:pokemon_id",
+            post(attach_my_pokemon).delete(detach_my_pokemon),
+        )
+        .route("/me/inventory", get(get_my_inventory))
+        .route("/me/recent", get(get_my_recent))
+        .route("/me/bookmarks", get(get_my_bookmarks))
+        .route("/me/proposals", get(get_my_proposals))
+        .route("/me/experiments", get(get_my_experiments))
+        .route(
+            "/me/experiments/:experiment/exposures",
+            post(record_my_exposure),
+        )
+        .route(
+            "/me/bookmarks/:kind/:id",
+            put(add_my_bookmark).delete(delete_my_bookmark),
+        )
+}
+
+/// Streams that stay open, merged apart from the concurrency limits.
+pub(crate) fn streams() -> Router<Arc<AppState>> {
+    Router::new().route("/me/bookmarks/events", get(get_my_bookmark_events))
+}
+
+/// Views kept for each user's `/me/recent`; older ones drop off.
+const MAX_RECENT_VIEWS: i64 = 20;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_me,
+        get_my_pokemon,
+        attach_my_pokemon,
+        detach_my_pokemon,
+        get_my_battles,
+        get_my_inventory,
+        get_my_recent,
+        get_my_bookmarks,
+        add_my_bookmark,
+        delete_my_bookmark,
+        get_my_bookmark_events,
+        get_my_proposals,
+        get_my_experiments,
+        record_my_exposure
+    ),
+    components(schemas(
+        GetMeResponse,
+        GetMyPokemonResponse,
+        MyBattle,
+        GetMyBattlesResponse,
+        GetMyInventoryResponse,
+        CatalogEntry,
+        RecentView,
+        GetMyRecentResponse,
+        CatalogKind,
+        Bookmark,
+        GetMyBookmarksResponse,
+        ExperimentAssignment,
+        GetMyExperimentsResponse
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+struct GetMeResponse {
+    user_id: i32,
+    username: String,
+    trainer: Trainer,
+    /// Whether an admin is acting as someone else's trainer through
+    /// `X-Impersonate-Trainer`.
+    impersonated: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The signed-in user and their trainer", body = GetMeResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "The account has no trainer", body = ErrorBody)
+    )
+)]
+async fn get_me(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetMeResponse>, ApiError> {
+    match state.trainers.get(me.trainer_id).await {
+        Ok(Some(trainer)) => Ok(Json(GetMeResponse {
+            user_id: me.claims.sub,
+            username: me.claims.username,
+            trainer,
+            impersonated: me.impersonated,
+        })),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetMyPokemonResponse {
+    pokemon: Vec<Pokemon>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/pokemon",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pokemon of the signed-in user's trainer", body = GetMyPokemonResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "The account has no trainer", body = ErrorBody)
+    )
+)]
+async fn get_my_pokemon(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetMyPokemonResponse>, ApiError> {
+    match state.trainers.pokemon(me.trainer_id).await {
+        Ok(pokemon) => Ok(Json(GetMyPokemonResponse { pokemon })),
+        Err(e) => {
+            tracing::error!("Failed to fetch the trainer's pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/pokemon/{pokemon_id}",
+    tag = "me",
+    params(("pokemon_id" = i32, Path, description = "Pokemon id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Pokemon attached to the signed-in user's trainer"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon, or the account has no trainer", body = ErrorBody),
+        (status = 409, description = "Pokemon is already attached, or the team is full", body = ErrorBody)
+    )
+)]
+async fn attach_my_pokemon(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path(pokemon_id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    TrainerService::new(&state)
+        .attach(me.trainer_id, pokemon_id)
+        .await?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/pokemon/{pokemon_id}",
+    tag = "me",
+    params(("pokemon_id" = i32, Path, description = "Pokemon id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pokemon detached from the signed-in user's trainer"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "Pokemon is not attached, or the account has no trainer", body = ErrorBody)
+    )
+)]
+async fn detach_my_pokemon(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path(pokemon_id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    match TrainerService::new(&state)
+        .detach(me.trainer_id, pokemon_id)
+        .await?
+    {
+        true => Ok(StatusCode::OK),
+        false => Err(ApiError::NotFound),
+    }
+}
+
+/// `?page=&per_page=` for `/me/battles`. The total is always exact, as it
+/// only counts one trainer's battles.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct MyBattlesParams {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct MyBattle {
+    /// For `POST /battle/{id}/verify`.
+    battle_id: i32,
+    pokemon_id: i32,
+    opponent_id: i32,
+    /// The winning pokemon's id, or `null` for a draw.
+    winner: Option<i32>,
+    /// The computer-controlled trainer fought through `POST /battle/ai`.
+    ai_trainer_id: Option<i32>,
+    /// Credited for beating `ai_trainer_id`.
+    reward: Option<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    fought_at: OffsetDateTime,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetMyBattlesResponse {
+    battles: Vec<MyBattle>,
+    total_count: i64,
+    page: i64,
+    per_page: i64,
+}
+
+/// Battles the signed-in user's trainer asked for, newest first. Battles
+/// fought before they were attributed to a trainer are not listed.
+#[utoipa::path(
+    get,
+    path = "/me/battles",
+    tag = "me",
+    params(MyBattlesParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "A page of the signed-in user's battles, with `Link` headers to the others", body = GetMyBattlesResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "The account has no trainer", body = ErrorBody)
+    )
+)]
+async fn get_my_battles(
+    me: CurrentTrainer,
+    db: DbConn,
+    RawQuery(query): RawQuery,
+    Query(params): Query<MyBattlesParams>,
+) -> Result<([(HeaderName, HeaderValue); 1], Json<GetMyBattlesResponse>), ApiError> {
+    let paging = PageParams {
+        page: params.page,
+        per_page: params.per_page,
+        count: None,
+    };
+
+    // The count and the page come from one statement, so they always agree.
+    match db
+        .query(
+            "SELECT c.total, b.battle_id, b.pokemon_id, b.opponent_id, b.winner,
+                    b.ai_trainer_id, b.reward, b.fought_at
+             FROM (SELECT count(*) AS total FROM battle WHERE trainer_id = $1) c
+             LEFT JOIN LATERAL (
+                 SELECT * FROM battle WHERE trainer_id = $1
+                 ORDER BY fought_at DESC, battle_id DESC
+                 LIMIT $2 OFFSET $3
+             ) b ON true",
+            &[&me.trainer_id, &paging.per_page(), &paging.offset()],
+        )
+        .await
+    {
+        Ok(rows) => {
+            let total_count = rows.first().map_or(0, |r| r.get(0));
+            let battles = rows
+                .iter()
+                .filter(|r| r.get::<_, Option<i32>>(1).is_some())
+                .map(|r| MyBattle {
+                    battle_id: r.get(1),
+                    pokemon_id: r.get(2),
+                    opponent_id: r.get(3),
+                    winner: r.get(4),
+                    ai_trainer_id: r.get(5),
+                    reward: r.get(6),
+                    fought_at: r.get(7),
+                })
+                .collect::<Vec<_>>();
+
+            let links = paging.links(
+                "/me/battles",
+                query.as_deref(),
+                battles.len(),
+                Some(total_count),
+            );
+            Ok((
+                [(LINK, links)],
+                Json(GetMyBattlesResponse {
+                    battles,
+                    total_count,
+                    page: paging.page(),
+                    per_page: paging.per_page(),
+                }),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch the trainer's battles: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetMyInventoryResponse {
+    /// Rewards won in battles against AI trainers.
+    rewards: i32,
+    /// The pokemon the trainer owns, oldest first.
+    pokemon: Vec<OwnedPokemon>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/inventory",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "What the signed-in user's trainer holds: its rewards and the pokemon it owns", body = GetMyInventoryResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "The account has no trainer", body = ErrorBody)
+    )
+)]
+async fn get_my_inventory(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetMyInventoryResponse>, ApiError> {
+    let trainer = match state.trainers.get(me.trainer_id).await {
+        Ok(Some(trainer)) => trainer,
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer: {}", e);
+
+            return Err(e.into());
+        }
+    };
+
+    match state.trainers.owned(me.trainer_id).await {
+        Ok(pokemon) => Ok(Json(GetMyInventoryResponse {
+            rewards: trainer.rewards,
+            pokemon,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch owned pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Notes that the caller viewed the pokemon or ability, when they are
+/// signed in. The view is incidental to the read, so a failure is logged
+/// rather than returned.
+pub(crate) async fn record_view(
+    state: &AppState,
+    claims: Option<AuthClaims>,
+    kind: CatalogKind,
+    entity_id: i32,
+) {
+    let Some(claims) = claims else {
+        return;
+    };
+    if let Err(e) = state
+        .users
+        .record_view(claims.sub, kind, entity_id, MAX_RECENT_VIEWS)
+        .await
+    {
+        tracing::error!("Failed to record view: {}", e);
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetMyRecentResponse {
+    /// Newest first, each entity once.
+    recent: Vec<RecentView>,
+}
+
+/// The pokemon and abilities the signed-in user last opened through
+/// `GET /pokemon/{key}` and `GET /ability/{name}`, on any device.
+#[utoipa::path(
+    get,
+    path = "/me/recent",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The signed-in user's last 20 viewed pokemon and abilities that are still published", body = GetMyRecentResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody)
+    )
+)]
+async fn get_my_recent(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetMyRecentResponse>, ApiError> {
+    match state.users.recent(claims.sub).await {
+        Ok(recent) => Ok(Json(GetMyRecentResponse { recent })),
+        Err(e) => {
+            tracing::error!("Failed to fetch recent views: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetMyBookmarksResponse {
+    /// Newest first.
+    bookmarks: Vec<Bookmark>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/bookmarks",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The pokemon and abilities the signed-in user bookmarked that are still published", body = GetMyBookmarksResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody)
+    )
+)]
+async fn get_my_bookmarks(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<GetMyBookmarksResponse>, ApiError> {
+    match state.users.bookmarks(claims.sub).await {
+        Ok(bookmarks) => Ok(Json(GetMyBookmarksResponse { bookmarks })),
+        Err(e) => {
+            tracing::error!("Failed to fetch bookmarks: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/me/bookmarks/{kind}/{id}",
+    tag = "me",
+    params(
+        ("kind" = CatalogKind, Path, description = "`pokemon` or `ability`"),
+        ("id" = i32, Path, description = "Pokemon or ability id")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Bookmarked"),
+        (status = 200, description = "Already bookmarked"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No such published pokemon or ability", body = ErrorBody)
+    )
+)]
+async fn add_my_bookmark(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+    Path((kind, id)): Path<(CatalogKind, i32)>,
+) -> Result<StatusCode, ApiError> {
+    match state.users.add_bookmark(claims.sub, kind, id).await {
+        Ok(AddBookmark::Added) => Ok(StatusCode::CREATED),
+        Ok(AddBookmark::AlreadyAdded) => Ok(StatusCode::OK),
+        Ok(AddBookmark::NotFound) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to add bookmark: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me/bookmarks/{kind}/{id}",
+    tag = "me",
+    params(
+        ("kind" = CatalogKind, Path, description = "`pokemon` or `ability`"),
+        ("id" = i32, Path, description = "Pokemon or ability id")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Bookmark removed"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "Not bookmarked", body = ErrorBody)
+    )
+)]
+async fn delete_my_bookmark(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+    Path((kind, id)): Path<(CatalogKind, i32)>,
+) -> Result<StatusCode, ApiError> {
+    match state.users.delete_bookmark(claims.sub, kind, id).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to delete bookmark: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// The `PokemonUpdated`, `PokemonDeleted`, `AbilityUpdated` and
+/// `AbilityDeleted` events of `/events`, for the entities the signed-in
+/// user has bookmarked at the time of the change.
+#[utoipa::path(
+    get,
+    path = "/me/bookmarks/events",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Server-sent stream of changes to the signed-in user's bookmarks", content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody)
+    )
+)]
+async fn get_my_bookmark_events(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let user_id = claims.sub;
+    let users = state.users.clone();
+    // A lagging subscriber just misses the events it fell behind on.
+    let changes = BroadcastStream::new(state.events.subscribe())
+        .filter_map(|event| event.ok())
+        .filter_map(|event| Some((event.catalog_change()?, event)));
+    let stream = futures_util::StreamExt::filter_map(changes, move |((kind, id), event)| {
+        let users = users.clone();
+        async move {
+            match users.is_bookmarked(user_id, kind, id).await {
+                Ok(true) => Event::default().json_data(event).ok(),
+                Ok(false) => None,
+                Err(e) => {
+                    tracing::error!("Failed to check bookmark: {}", e);
+
+                    None
+                }
+            }
+        }
+    })
+    .map(Ok);
+    // Like `/events`, the stream ends when the server shuts down.
+    let stream = futures_util::StreamExt::take_until(stream, state.shutting_down());
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/proposals",
+    tag = "me",
+    params(ProposalParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The ability changes the signed-in user proposed, and how they were reviewed", body = GetProposalsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody)
+    )
+)]
+async fn get_my_proposals(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ProposalParams>,
+) -> Result<Json<GetProposalsResponse>, ApiError> {
+    match state
+        .abilities
+        .proposals(params.status, Some(claims.sub))
+        .await
+    {
+        Ok(proposals) => Ok(Json(GetProposalsResponse { proposals })),
+        Err(e) => {
+            tracing::error!("Failed to fetch the user's proposals: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetMyExperimentsResponse {
+    experiments: Vec<ExperimentAssignment>,
+}
+
+/// The variant of each running experiment the signed-in user sees. It is
+/// the same on every call and every device.
+#[utoipa::path(
+    get,
+    path = "/me/experiments",
+    tag = "me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The signed-in user's variant of each experiment", body = GetMyExperimentsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody)
+    )
+)]
+async fn get_my_experiments(claims: AuthClaims) -> Json<GetMyExperimentsResponse> {
+    let experiments = EXPERIMENTS
+        .iter()
+        .map(|e| ExperimentAssignment {
+            experiment: e.key.to_string(),
+            variant: e.assign(claims.sub).to_string(),
+        })
+        .collect();
+
+    Json(GetMyExperimentsResponse { experiments })
+}
+
+/// Records that the signed-in user was just shown their variant. Clients
+/// call it each time they render it, so only users who actually saw a
+/// variant count towards its results.
+#[utoipa::path(
+    post,
+    path = "/me/experiments/{experiment}/exposures",
+    tag = "me",
+    params(("experiment" = String, Path, description = "Experiment key")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Exposure recorded for the user's variant", body = ExperimentAssignment),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No such experiment", body = ErrorBody)
+    )
+)]
+async fn record_my_exposure(
+    claims: AuthClaims,
+    State(state): State<Arc<AppState>>,
+    Path(experiment): Path<String>,
+) -> Result<Json<ExperimentAssignment>, ApiError> {
+    let experiment = experiments::find(&experiment).ok_or(ApiError::NotFound)?;
+    let variant = experiment.assign(claims.sub);
+    match state
+        .users
+        .record_exposure(claims.sub, experiment.key, variant)
+        .await
+    {
+        Ok(()) => Ok(Json(ExperimentAssignment {
+            experiment: experiment.key.to_string(),
+            variant: variant.to_string(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to record experiment exposure: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::timeout;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn the_inventory_holds_rewards_and_owned_pokemon() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        repo.add_rewards(ash, 100);
+        let state = state(repo.clone());
+        let token = token(&state, repo.add_user(Some(ash), false));
+        let app = build_app(state);
+
+        let (status, body) = send(&app, Method::GET, "/me/inventory", Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["rewards"], 100);
+        assert_eq!(body["pokemon"][0]["species"]["name"], "Pikachu");
+        assert_eq!(body["pokemon"][0]["trainer_id"], ash);
+    }
+
+    #[tokio::test]
+    async fn recent_views_are_kept_per_user_once_each() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        repo.add_ability("Tackle", 40, "none");
+        let ash = repo.add_user(None, false);
+        let gary = repo.add_user(None, false);
+        let state = state(repo);
+        let ash = token(&state, ash);
+        let gary = token(&state, gary);
+        let app = build_app(state);
+
+        let pikachu = format!("/pokemon/{}", pikachu);
+        for uri in [pikachu.as_str(), "/ability/Tackle", pikachu.as_str()] {
+            let (status, _) = send(&app, Method::GET, uri, Some(&ash), None).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        // Anonymous views aren't anyone's.
+        send(&app, Method::GET, "/ability/Tackle", None, None).await;
+
+        let (status, body) = send(&app, Method::GET, "/me/recent", Some(&ash), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let recent = body["recent"].as_array().unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0]["kind"], "pokemon");
+        assert_eq!(recent[0]["name"], "Pikachu");
+        assert_eq!(recent[1]["kind"], "ability");
+        assert_eq!(recent[1]["damage"], 40);
+        let (_, body) = send(&app, Method::GET, "/me/recent", Some(&gary), None).await;
+        assert_eq!(body["recent"], json!([]));
+        let (status, _) = send(&app, Method::GET, "/me/recent", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn changes_to_bookmarks_are_streamed() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let eevee = repo.add_pokemon("Eevee", kanto, &[]);
+        let tackle = repo.add_ability("Tackle", 40, "none");
+        let admin = repo.add_user(None, true);
+        let ash = repo.add_user(None, false);
+        let state = state(repo);
+        let admin = token(&state, admin);
+        let ash = token(&state, ash);
+        let app = build_app(state);
+
+        let pikachu_bookmark = format!("/me/bookmarks/pokemon/{}", pikachu);
+        let (status, _) = send(&app, Method::PUT, &pikachu_bookmark, Some(&ash), None).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, Method::PUT, &pikachu_bookmark, Some(&ash), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let uri = format!("/me/bookmarks/ability/{}", tackle);
+        let (status, _) = send(&app, Method::PUT, &uri, Some(&ash), None).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(
+            &app,
+            Method::PUT,
+            "/me/bookmarks/ability/999",
+            Some(&ash),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = send(&app, Method::GET, "/me/bookmarks", Some(&ash), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["bookmarks"][0]["kind"], "ability");
+        assert_eq!(body["bookmarks"][0]["name"], "Tackle");
+        assert_eq!(body["bookmarks"][1]["name"], "Pikachu");
+
+        let request = Request::get("/me/bookmarks/events")
+            .header(header::AUTHORIZATION, format!("Bearer {}", ash))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut events = response.into_body().into_data_stream();
+
+        // Eevee isn't bookmarked, so only Pikachu's change comes through.
+        for id in [eevee, pikachu] {
+            let uri = format!("/pokemon/{}/description", id);
+            let lore = json!({"lore": "Sleeps a lot."});
+            let (status, _) = send(&app, Method::PUT, &uri, Some(&admin), Some(lore)).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let frame = timeout(Duration::from_secs(1), events.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let expected = json!({"type": "PokemonUpdated", "pokemon_id": pikachu});
+        assert_eq!(frame, format!("data: {}\n\n", expected));
+
+        let (status, _) = send(&app, Method::DELETE, &pikachu_bookmark, Some(&ash), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::DELETE, &pikachu_bookmark, Some(&ash), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn experiment_exposures_count_towards_the_users_variant() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let misty = repo.add_user(None, false);
+        let brock = repo.add_user(None, false);
+        let state = state(repo.clone());
+        let admin = token(&state, admin);
+        let misty = token(&state, misty);
+        let brock = token(&state, brock);
+        let app = build_app(state);
+
+        let (status, body) = send(&app, Method::GET, "/me/experiments", Some(&misty), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["experiments"][0]["experiment"], "pokedex_layout");
+        let variant = body["experiments"][0]["variant"].clone();
+        let (_, again) = send(&app, Method::GET, "/me/experiments", Some(&misty), None).await;
+        assert_eq!(again["experiments"][0]["variant"], variant);
+
+        let uri = "/me/experiments/pokedex_layout/exposures";
+        for user in [&misty, &misty, &brock] {
+            let (status, body) = send(&app, Method::POST, uri, Some(user), None).await;
+            assert_eq!(status, StatusCode::OK);
+            if user == &misty {
+                assert_eq!(body["variant"], variant);
+            }
+        }
+        let uri = "/me/experiments/battle_music/exposures";
+        let (status, _) = send(&app, Method::POST, uri, Some(&misty), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _) = send(&app, Method::GET, "/admin/experiments", Some(&misty), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) =
+            send(&app, Method::GET, "/admin/experiments", Some(&admin), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let variants = body["experiments"][0]["variants"].as_array().unwrap();
+        assert_eq!(variants.len(), 2);
+        let total = |field: &str| {
+            variants
+                .iter()
+                .map(|v| v[field].as_i64().unwrap())
+                .sum::<i64>()
+        };
+        assert_eq!((total("users"), total("exposures")), (2, 3));
+        let shown = variants.iter().find(|v| v["variant"] == variant).unwrap();
+        assert!(shown["exposures"].as_i64().unwrap() >= 2);
+    }
+}
@@ -0,0 +1,17 @@
+pub(crate) mod ability;
+pub(crate) mod admin;
+pub(crate) mod auth;
+pub(crate) mod battle;
+pub(crate) mod docs;
+pub(crate) mod graphql;
+pub(crate) mod me;
+pub(crate) mod owned;
+pub(crate) mod pokemon;
+pub(crate) mod quiz;
+pub(crate) mod region;
+pub(crate) mod reports;
+pub(crate) mod search;
+pub(crate) mod stats;
+pub(crate) mod system;
+pub(crate) mod team;
+pub(crate) mod trainer;
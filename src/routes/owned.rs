@@ -0,0 +1,227 @@
+use crate::auth::CurrentTrainer;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::extract::Path;
+use crate::models::{IdOrUuid, Ivs, OwnedPokemon, Patch};
+use crate::routes::trainer::resolve_trainer;
+use crate::validation::{FieldErrors, Valid, Validate, MAX_NAME_LEN};
+use crate::AppState;
+use axum::{extract::State, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+/// Pokemon as trainers own them, as opposed to the species in `/pokemon`.
+/// They are caught and released through `/trainer/{id}/pokemon` and
+/// `/me/pokemon`.
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/trainer/:id/owned", get(get_owned))
+        .route(
+            "/owned/:instance_id",
+            get(get_instance).patch(patch_instance),
+        )
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_owned, get_instance, patch_instance),
+    components(schemas(OwnedPokemon, Ivs, GetOwnedResponse, PatchInstanceRequest))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+struct GetOwnedResponse {
+    trainer_id: i32,
+    pokemon: Vec<OwnedPokemon>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/trainer/{id}/owned",
+    tag = "owned",
+    params(("id" = String, Path, description = "Trainer id or UUID")),
+    responses(
+        (status = 200, description = "The pokemon the trainer owns, oldest first", body = GetOwnedResponse),
+        (status = 404, description = "No such trainer", body = ErrorBody)
+    )
+)]
+async fn get_owned(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<IdOrUuid>,
+) -> Result<Json<GetOwnedResponse>, ApiError> {
+    let trainer_id = resolve_trainer(&state, key).await?;
+    match state.trainers.get(trainer_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer: {}", e);
+
+            return Err(e.into());
+        }
+    }
+
+    match state.trainers.owned(trainer_id).await {
+        Ok(pokemon) => Ok(Json(GetOwnedResponse {
+            trainer_id,
+            pokemon,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch owned pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+async fn find_instance(state: &AppState, instance_id: i32) -> Result<OwnedPokemon, ApiError> {
+    match state.trainers.instance(instance_id).await {
+        Ok(Some(instance)) => Ok(instance),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch owned pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/owned/{instance_id}",
+    tag = "owned",
+    params(("instance_id" = i32, Path, description = "Owned pokemon id")),
+    responses(
+        (status = 200, description = "The owned pokemon", body = OwnedPokemon),
+        (status = 404, description = "No such owned pokemon", body = ErrorBody)
+    )
+)]
+async fn get_instance(
+    State(state): State<Arc<AppState>>,
+    Path(instance_id): Path<i32>,
+) -> Result<Json<OwnedPokemon>, ApiError> {
+    find_instance(&state, instance_id).await.map(Json)
+}
+
+/// Fields left out are kept as they are.
+#[derive(Deserialize, ToSchema)]
+struct PatchInstanceRequest {
+    /// `null` clears it.
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    nickname: Patch<String>,
+}
+
+impl Validate for PatchInstanceRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let Patch::Value(nickname) = &self.nickname {
+            errors.name("nickname", nickname, MAX_NAME_LEN);
+        }
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/owned/{instance_id}",
+    tag = "owned",
+    params(("instance_id" = i32, Path, description = "Owned pokemon id")),
+    request_body = PatchInstanceRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The updated owned pokemon", body = OwnedPokemon),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not owned by the caller's trainer, or X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "No such owned pokemon", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn patch_instance(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path(instance_id): Path<i32>,
+    Valid(payload): Valid<PatchInstanceRequest>,
+) -> Result<Json<OwnedPokemon>, ApiError> {
+    let instance = find_instance(&state, instance_id).await?;
+    me.require_owner(instance.trainer_id)?;
+
+    if let Some(nickname) = payload.nickname.into_change() {
+        match state
+            .trainers
+            .set_nickname(instance_id, nickname.as_deref())
+            .await
+        {
+            Ok(true) => state.updated(Entity::Trainer, instance.trainer_id),
+            Ok(false) => return Err(ApiError::NotFound),
+            Err(e) => {
+                tracing::error!("Failed to rename owned pokemon: {}", e);
+
+                return Err(e.into());
+            }
+        }
+    }
+
+    find_instance(&state, instance_id).await.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn owned_pokemon_keep_their_nickname_across_team_changes() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let eevee = repo.add_pokemon("Eevee", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let gary = repo.add_trainer("Gary", &[]);
+        let state = state(repo.clone());
+        let ash_token = token(&state, repo.add_user(Some(ash), false));
+        let gary_token = token(&state, repo.add_user(Some(gary), false));
+        let app = build_app(state);
+
+        let uri = format!("/trainer/{}/owned", ash);
+        let (status, body) = send(&app, Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["pokemon"][0]["species"]["name"], "Pikachu");
+        assert_eq!(body["pokemon"][0]["level"], 5);
+        let instance = format!("/owned/{}", body["pokemon"][0]["instance_id"]);
+
+        let sparky = json!({"nickname": "Sparky"});
+        let (status, _) = send(
+            &app,
+            Method::PATCH,
+            &instance,
+            Some(&gary_token),
+            Some(sparky.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) = send(
+            &app,
+            Method::PATCH,
+            &instance,
+            Some(&ash_token),
+            Some(sparky),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["nickname"], "Sparky");
+
+        // Catching another pokemon leaves Pikachu the same instance.
+        let team = json!({"pokemon": [pikachu, eevee]});
+        let trainer = format!("/trainer/{}", ash);
+        let (status, _) = send(&app, Method::PATCH, &trainer, Some(&ash_token), Some(team)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, Method::GET, &instance, None, None).await;
+        assert_eq!(body["nickname"], "Sparky");
+        let (_, body) = send(&app, Method::GET, &uri, None, None).await;
+        assert_eq!(body["pokemon"][1]["species"]["name"], "Eevee");
+        assert_eq!(body["pokemon"][1]["nickname"], json!(null));
+    }
+}
@@ -0,0 +1,1631 @@
+use crate::auth::{AdminClaims, AuthClaims};
+use crate::cache::{json_bytes, to_json_bytes};
+use crate::db::{DbConn, DbError};
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::extract::{Path, Query};
+use crate::models::{
+    generation_of, metadata_filter, regions_in_generation, Ability, Attribute, BulkCreateResponse,
+    CatalogKind, CountStrategy, Description, DiffParams, EntityKey, ExternalReference, FieldChange,
+    Metadata, NameCollation, NewReference, PageParams, Pokemon, PokemonDetail, PokemonFilter,
+    PokemonFull, ReferenceKind, RegionRef, Render, RenderParams, SortParams, Version, VersionDiff,
+};
+use crate::repository::{AddReference, Grouping, NewPokemon};
+use crate::routes::me::record_view;
+use crate::service::{version_diff, CatalogHistory};
+use crate::validation::{FieldErrors, Valid, Validate, MAX_NAME_LEN};
+use crate::AppState;
+use axum::{
+    body::Bytes,
+    extract::{RawQuery, State},
+    http::{header::LINK, HeaderValue, StatusCode},
+    response::Response,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use uuid::Uuid;
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/pokemon", get(get_pokemon).post(create_pokemon))
+        .route("/pokemon/bulk", post(create_pokemon_bulk))
+        .route(
+            "/pokemon/:key",
+            get(get_pokemon_by_key)
+                .put(put_pokemon)
+                .delete(delete_pokemon),
+        )
+        .route(
+            "/pokemon/:key/description",
+            get(get_pokemon_description).put(put_pokemon_description),
+        )
+        .route("/pokemon/:key/references", post(add_pokemon_reference))
+        .route("/pokemon/:key/versions", get(get_pokemon_versions))
+        .route("/pokemon/:key/versions/diff", get(get_pokemon_version_diff))
+        .route(
+            "/pokemon/:key/versions/:version/rollback",
+            post(rollback_pokemon),
+        )
+        .route(
+            "/pokemon/:key/references/:reference_id",
+            delete(delete_pokemon_reference),
+        )
+        .route("/generation/:n", get(get_generation))
+        .route("/pokemon-attributes/:id", get(get_attribute))
+}
+
+/// Aggregations over the whole catalog, which run under the heavy
+/// concurrency budget.
+pub(crate) fn heavy_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/pokemon/group-by", get(get_pokemon_groups))
+        .route("/pokemon/index", get(get_pokemon_index))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_pokemon,
+        create_pokemon,
+        create_pokemon_bulk,
+        get_pokemon_by_key,
+        put_pokemon,
+        delete_pokemon,
+        get_pokemon_description,
+        put_pokemon_description,
+        add_pokemon_reference,
+        delete_pokemon_reference,
+        get_pokemon_versions,
+        get_pokemon_version_diff,
+        rollback_pokemon,
+        get_generation,
+        get_attribute,
+        get_pokemon_groups,
+        get_pokemon_index
+    ),
+    components(schemas(
+        Ability,
+        Attribute,
+        CountStrategy,
+        Description,
+        NameCollation,
+        Pokemon,
+        PokemonDetail,
+        PokemonFull,
+        ExternalReference,
+        NewReference,
+        ReferenceKind,
+        RegionRef,
+        Render,
+        GetAttributeResponse,
+        GetPokemonResponse,
+        IndexLetter,
+        GetIndexResponse,
+        CreatePokemonRequest,
+        BulkCreateResponse,
+        PutPokemonRequest,
+        RegionCount,
+        GetGenerationResponse,
+        GroupField,
+        Group,
+        GetGroupsResponse,
+        Version,
+        FieldChange,
+        VersionDiff,
+        GetPokemonVersionsResponse
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+struct GetAttributeResponse {
+    attributes: Vec<Attribute>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon-attributes/{id}",
+    tag = "pokemon",
+    params(("id" = i32, Path, description = "Pokemon id")),
+    responses((status = 200, description = "Attributes of the pokemon", body = GetAttributeResponse))
+)]
+async fn get_attribute(
+    db: DbConn,
+    Path(id): Path<i32>,
+) -> Result<Json<GetAttributeResponse>, ApiError> {
+    match db
+        .query_capped(
+            "SELECT a.attribute_id, a.attribute_name, a.weakness
+             FROM pokemonattributes pa
+             JOIN published_pokemon p ON p.pokemon_id = pa.pokemon_id
+             JOIN attribute a ON a.attribute_id = pa.attribute_id
+             WHERE pa.pokemon_id = $1",
+            &[&id],
+        )
+        .await
+    {
+        Ok(rows) => {
+            let attributes: Vec<Attribute> = rows
+                .iter()
+                .map(|r| Attribute {
+                    attribute_id: r.get(0),
+                    attribute_name: r.get(1),
+                    weakness: r.get(2),
+                })
+                .collect();
+
+            tracing::debug!(
+                pokemon_id = id,
+                count = attributes.len(),
+                "Fetched attributes"
+            );
+
+            Ok(Json(GetAttributeResponse { attributes }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch attributes: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetPokemonResponse {
+    pokemons: Vec<PokemonFull>,
+    /// `null` when the request asked for `count=none`.
+    total_count: Option<i64>,
+    /// Whether `total_count` is a planner estimate rather than an exact count.
+    approximate: bool,
+    page: i64,
+    per_page: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon",
+    tag = "pokemon",
+    params(
+        SortParams,
+        PokemonFilter,
+        PageParams,
+        ("meta.{key}" = Option<String>, Query, description = "Only pokemon whose metadata has this string value under `key`")
+    ),
+    responses(
+        (status = 200, description = "A page of matching pokemon, with `Link` headers to the others", body = GetPokemonResponse),
+        (status = 422, description = "Invalid query parameters, such as an unknown collation", body = ErrorBody)
+    )
+)]
+async fn get_pokemon(
+    State(state): State<Arc<AppState>>,
+    RawQuery(query): RawQuery,
+    Query(sort): Query<SortParams>,
+    Query(mut filter): Query<PokemonFilter>,
+    Query(paging): Query<PageParams>,
+) -> Result<Response, ApiError> {
+    filter.metadata = metadata_filter(query.as_deref());
+
+    // The catalog changes rarely and is read constantly, so pages are served
+    // from their serialized bytes until the next write.
+    let key = catalog_key(&filter, &sort, &paging);
+    if let Some(body) = state.catalog_cache.get(&key).await {
+        let links = cached_page_links(&paging, query.as_deref(), &body);
+        let mut response = json_bytes(body);
+        if let Some(links) = links {
+            response.headers_mut().insert(LINK, links);
+        }
+        return Ok(response);
+    }
+    let generation = state.catalog_cache.generation();
+
+    match state.pokemon.list(&filter, &sort, &paging).await {
+        Ok((pokemons, total_count)) => {
+            tracing::debug!(count = pokemons.len(), "Fetched pokemon");
+
+            let links = paging.links(
+                "/pokemon",
+                query.as_deref(),
+                pokemons.len(),
+                total_count.value,
+            );
+            let response = GetPokemonResponse {
+                pokemons,
+                total_count: total_count.value,
+                approximate: total_count.approximate,
+                page: paging.page(),
+                per_page: paging.per_page(),
+            };
+            let body = to_json_bytes(&response);
+            state
+                .catalog_cache
+                .insert(key, generation, body.clone())
+                .await;
+
+            let mut response = json_bytes(body);
+            response.headers_mut().insert(LINK, links);
+            Ok(response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch pokemon: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// The `Link` header of a cached `/pokemon` page, from the count and size
+/// recorded in its body. `None` if the body isn't such a page.
+fn cached_page_links(
+    paging: &PageParams,
+    query: Option<&str>,
+    body: &Bytes,
+) -> Option<HeaderValue> {
+    #[derive(Deserialize)]
+    struct CachedPage {
+        total_count: Option<i64>,
+        pokemons: Vec<IgnoredAny>,
+    }
+
+    let page: CachedPage = serde_json::from_slice(body).ok()?;
+    Some(paging.links("/pokemon", query, page.pokemons.len(), page.total_count))
+}
+
+/// Caches the page most clients open with: `/pokemon` without parameters.
+pub(crate) async fn warm_catalog(state: &AppState) -> Result<(), DbError> {
+    let generation = state.catalog_cache.generation();
+    let paging = PageParams::default();
+    let (pokemons, total_count) = state
+        .pokemon
+        .list(&PokemonFilter::default(), &SortParams::default(), &paging)
+        .await?;
+
+    let response = GetPokemonResponse {
+        pokemons,
+        total_count: total_count.value,
+        approximate: total_count.approximate,
+        page: paging.page(),
+        per_page: paging.per_page(),
+    };
+    state
+        .catalog_cache
+        .insert(
+            catalog_key(&PokemonFilter::default(), &SortParams::default(), &paging),
+            generation,
+            to_json_bytes(&response),
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Cache key of the `/pokemon` page the parameters select. It is built from
+/// the parsed parameters rather than the query string, so a page is cached
+/// once however its query is written: parameters in any order, defaults
+/// spelled out or left off, filters in any case, and unknown parameters
+/// ignored.
+fn catalog_key(filter: &PokemonFilter, sort: &SortParams, paging: &PageParams) -> String {
+    let lower = |s: &Option<String>| s.as_deref().map(str::to_lowercase);
+    let mut metadata: Vec<(&String, &serde_json::Value)> =
+        filter.metadata.iter().flatten().collect();
+    metadata.sort_unstable_by_key(|&(key, _)| key);
+    let count = match paging.count() {
+        CountStrategy::Exact => "exact",
+        CountStrategy::Estimate => "estimate",
+        CountStrategy::None => "none",
+    };
+
+    format!(
+        "/pokemon?{}",
+        json!({
+            "generation": filter.generation,
+            "name": lower(&filter.name),
+            "region": lower(&filter.region),
+            "ability": lower(&filter.ability),
+            "starts_with": lower(&filter.starts_with),
+            "metadata": metadata,
+            "collation": sort.collation.map(NameCollation::query_value),
+            "page": paging.page(),
+            "per_page": paging.per_page(),
+            "count": count,
+        })
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct IndexParams {
+    collation: Option<NameCollation>,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct IndexLetter {
+    letter: String,
+    count: i64,
+    /// Page of the name-ordered list on which this letter starts.
+    page: i64,
+    first_page: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetIndexResponse {
+    letters: Vec<IndexLetter>,
+}
+
+/// Counts pokemon by the first letter of their name and works out where
+/// each letter starts in `/pokemon` ordered by name with the same collation
+/// and page size, so an A–Z bar can jump straight to it.
+#[utoipa::path(
+    get,
+    path = "/pokemon/index",
+    tag = "pokemon",
+    params(IndexParams),
+    responses((status = 200, description = "Pokemon counts per first letter", body = GetIndexResponse))
+)]
+async fn get_pokemon_index(
+    db: DbConn,
+    Query(params): Query<IndexParams>,
+) -> Result<Json<GetIndexResponse>, ApiError> {
+    let collation = params.collation.unwrap_or(NameCollation::Icu);
+    let paging = PageParams {
+        page: None,
+        per_page: params.per_page,
+        count: None,
+    };
+    let per_page = paging.per_page();
+
+    let sql = format!(
+        "WITH letters AS (
+             SELECT upper(left(name, 1)) AS letter, count(*) AS count,
+                    min(name COLLATE \"{0}\") AS first_name
+             FROM published_pokemon GROUP BY 1
+         )
+         SELECT letter, count,
+                (SELECT count(*) FROM published_pokemon p WHERE p.name COLLATE \"{0}\" < l.first_name)
+         FROM letters l ORDER BY first_name",
+        collation.sql_name()
+    );
+
+    match db.query(&sql, &[]).await {
+        Ok(rows) => {
+            let letters = rows
+                .iter()
+                .map(|r| {
+                    let offset: i64 = r.get(2);
+                    let page = offset / per_page + 1;
+                    IndexLetter {
+                        letter: r.get(0),
+                        count: r.get(1),
+                        page,
+                        first_page: format!(
+                            "/pokemon?collation={}&per_page={}&page={}",
+                            collation.query_value(),
+                            per_page,
+                            page
+                        ),
+                    }
+                })
+                .collect();
+
+            Ok(Json(GetIndexResponse { letters }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to build pokemon index: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon/{key}",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id, UUID or slug")),
+    responses(
+        (status = 200, description = "The pokemon with its external references", body = PokemonDetail),
+        (status = 404, description = "No such pokemon", body = ErrorBody)
+    )
+)]
+async fn get_pokemon_by_key(
+    claims: Option<AuthClaims>,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+) -> Result<Json<PokemonDetail>, ApiError> {
+    let pokemon = match state.pokemon.find(&key).await {
+        Ok(Some(pokemon)) => pokemon,
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch pokemon: {:?}", e);
+
+            return Err(e.into());
+        }
+    };
+    record_view(&state, claims, CatalogKind::Pokemon, pokemon.pokemon_id).await;
+
+    match state.pokemon.references(pokemon.pokemon_id).await {
+        Ok(references) => Ok(Json(PokemonDetail {
+            pokemon,
+            references,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch pokemon references: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/pokemon/{key}/references",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id, UUID or slug")),
+    request_body = NewReference,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Reference attached", body = ExternalReference),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon", body = ErrorBody),
+        (status = 409, description = "The pokemon already has this reference", body = ErrorBody),
+        (status = 422, description = "Not an https URL, a Bulbapedia URL or a PokeAPI id as the kind asks", body = ErrorBody)
+    )
+)]
+async fn add_pokemon_reference(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+    Valid(payload): Valid<NewReference>,
+) -> Result<(StatusCode, Json<ExternalReference>), ApiError> {
+    match state.pokemon.add_reference(&key, &payload).await {
+        Ok(AddReference::Added(reference, pokemon_id)) => {
+            state.updated(Entity::Pokemon, pokemon_id);
+
+            Ok((StatusCode::CREATED, Json(reference)))
+        }
+        Ok(AddReference::NotFound) => Err(ApiError::NotFound),
+        Ok(AddReference::Duplicate) => Err(ApiError::Conflict(
+            "the pokemon already has this reference".to_string(),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to attach pokemon reference: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/pokemon/{key}/references/{reference_id}",
+    tag = "pokemon",
+    params(
+        ("key" = String, Path, description = "Pokemon id, UUID or slug"),
+        ("reference_id" = i32, Path, description = "Reference id")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Reference detached"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon or reference", body = ErrorBody)
+    )
+)]
+async fn delete_pokemon_reference(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path((key, reference_id)): Path<(EntityKey, i32)>,
+) -> Result<StatusCode, ApiError> {
+    match state.pokemon.delete_reference(&key, reference_id).await {
+        Ok(Some(pokemon_id)) => {
+            state.updated(Entity::Pokemon, pokemon_id);
+
+            Ok(StatusCode::OK)
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to detach pokemon reference: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Answers 400 listing any of `ids` that are not abilities.
+pub(crate) async fn require_abilities(state: &AppState, ids: &[i32]) -> Result<(), ApiError> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    match state.pokemon.unknown_abilities(ids).await {
+        Ok(unknown) if unknown.is_empty() => Ok(()),
+        Ok(unknown) => Err(ApiError::BadRequest(format!(
+            "unknown abilities: {}",
+            unknown
+                .iter()
+                .map(i32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to look up abilities: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Looks up `region`, answering 400 if it does not exist.
+pub(crate) async fn require_region(
+    state: &AppState,
+    region: &RegionRef,
+) -> Result<(i32, String), ApiError> {
+    match state.pokemon.resolve_region(region).await {
+        Ok(Some(region)) => Ok(region),
+        Ok(None) => Err(ApiError::BadRequest("unknown region".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to resolve region: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreatePokemonRequest {
+    name: String,
+    region: RegionRef,
+    /// Stages the pokemon so that it only appears on the public endpoints
+    /// from this time on.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    publish_at: Option<OffsetDateTime>,
+    /// Ids of the abilities the pokemon starts out with.
+    #[serde(default)]
+    abilities: Vec<i32>,
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    metadata: Metadata,
+}
+
+impl Validate for CreatePokemonRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.slug_name("name", &self.name, MAX_NAME_LEN);
+        errors.metadata("metadata", &self.metadata);
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/pokemon",
+    tag = "pokemon",
+    request_body = CreatePokemonRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Pokemon created", body = Pokemon),
+        (status = 400, description = "Unknown region or ability", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 409, description = "Name already taken", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn create_pokemon(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Valid(payload): Valid<CreatePokemonRequest>,
+) -> Result<(StatusCode, Json<Pokemon>), ApiError> {
+    let (region_id, _) = require_region(&state, &payload.region).await?;
+    require_abilities(&state, &payload.abilities).await?;
+
+    // A publish time that has already passed is the same as none at all.
+    let publish_at = payload.publish_at.filter(|at| *at > state.clock.now());
+
+    // A duplicate name fails the unique constraint and maps to 409.
+    let created = state
+        .pokemon
+        .create(NewPokemon {
+            name: payload.name,
+            region_id,
+            publish_at,
+            abilities: payload.abilities,
+            metadata: payload.metadata,
+        })
+        .await;
+    match created {
+        Ok(pokemon) => {
+            CatalogHistory::new(&state)
+                .record_pokemon(pokemon.pokemon_id)
+                .await;
+            // Staged pokemon are announced by the publisher once they go live.
+            if publish_at.is_none() {
+                state.created(Entity::Pokemon, pokemon.pokemon_id);
+            }
+
+            Ok((StatusCode::CREATED, Json(pokemon)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/pokemon/bulk",
+    tag = "pokemon",
+    request_body = Vec<CreatePokemonRequest>,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "All pokemon created", body = BulkCreateResponse),
+        (status = 400, description = "Unknown region or ability", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 409, description = "Name already taken", body = ErrorBody),
+        (status = 422, description = "Invalid fields or too many items", body = ErrorBody)
+    )
+)]
+async fn create_pokemon_bulk(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Valid(payload): Valid<Vec<CreatePokemonRequest>>,
+) -> Result<(StatusCode, Json<BulkCreateResponse>), ApiError> {
+    let now = state.clock.now();
+    let mut names = HashSet::new();
+    let mut region_ids = Vec::with_capacity(payload.len());
+    for (i, item) in payload.iter().enumerate() {
+        // The database only sees one insert at a time, so clashes between
+        // items are caught here.
+        if !names.insert(&item.name) {
+            return Err(ApiError::Conflict("name repeats an earlier item".to_string()).at_item(i));
+        }
+        let (region_id, _) = require_region(&state, &item.region)
+            .await
+            .map_err(|e| e.at_item(i))?;
+        require_abilities(&state, &item.abilities)
+            .await
+            .map_err(|e| e.at_item(i))?;
+        region_ids.push(region_id);
+    }
+
+    let pokemon: Vec<NewPokemon> = payload
+        .into_iter()
+        .zip(region_ids)
+        .map(|(item, region_id)| NewPokemon {
+            name: item.name,
+            region_id,
+            publish_at: item.publish_at.filter(|at| *at > now),
+            abilities: item.abilities,
+            metadata: item.metadata,
+        })
+        .collect();
+    let live: Vec<bool> = pokemon.iter().map(|p| p.publish_at.is_none()).collect();
+
+    match state.pokemon.create_many(pokemon).await {
+        Ok(ids) => {
+            let history = CatalogHistory::new(&state);
+            for (&pokemon_id, live) in ids.iter().zip(live) {
+                history.record_pokemon(pokemon_id).await;
+                if live {
+                    state.created(Entity::Pokemon, pokemon_id);
+                }
+            }
+
+            Ok((StatusCode::CREATED, Json(BulkCreateResponse { ids })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to bulk create pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct PutPokemonRequest {
+    /// Renames the pokemon when updating by id. Ignored for upserts by name.
+    name: Option<String>,
+    region: RegionRef,
+    /// Replaces the pokemon's metadata. Left out, it is kept as it is.
+    #[schema(value_type = Option<Object>)]
+    metadata: Option<Metadata>,
+}
+
+impl Validate for PutPokemonRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let Some(name) = &self.name {
+            errors.name("name", name, MAX_NAME_LEN);
+        }
+        if let Some(metadata) = &self.metadata {
+            errors.metadata("metadata", metadata);
+        }
+    }
+}
+
+/// `PUT /pokemon/:key` updates by id when the key is numeric. Otherwise it
+/// moves the pokemon with that slug, or creates one named after the key.
+#[utoipa::path(
+    put,
+    path = "/pokemon/{key}",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id or UUID to update, or slug or name to upsert")),
+    request_body = PutPokemonRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pokemon updated or created", body = Pokemon),
+        (status = 400, description = "Unknown region", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon", body = ErrorBody),
+        (status = 409, description = "Name already taken", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn put_pokemon(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    Valid(payload): Valid<PutPokemonRequest>,
+) -> Result<Json<Pokemon>, ApiError> {
+    if let Ok(uuid) = Uuid::parse_str(&key) {
+        return match state.pokemon.find(&EntityKey::Uuid(uuid)).await {
+            Ok(Some(pokemon)) => update_pokemon(&state, pokemon.pokemon_id, payload).await,
+            Ok(None) => Err(ApiError::NotFound),
+            Err(e) => {
+                tracing::error!("Failed to fetch pokemon: {:?}", e);
+
+                Err(e.into())
+            }
+        };
+    }
+
+    match key.parse() {
+        Ok(id) => update_pokemon(&state, id, payload).await,
+        Err(_) => {
+            // The name comes from the path rather than the body, so `Valid`
+            // never saw it.
+            let mut errors = FieldErrors::default();
+            errors.slug_name("key", &key, MAX_NAME_LEN);
+            errors.into_result()?;
+
+            upsert_pokemon(&state, key, payload).await
+        }
+    }
+}
+
+async fn update_pokemon(
+    state: &AppState,
+    id: i32,
+    payload: PutPokemonRequest,
+) -> Result<Json<Pokemon>, ApiError> {
+    let (region_id, _) = require_region(state, &payload.region).await?;
+
+    match state
+        .pokemon
+        .update(
+            id,
+            payload.name.as_deref(),
+            region_id,
+            payload.metadata.as_ref(),
+        )
+        .await
+    {
+        Ok(Some(pokemon)) => {
+            CatalogHistory::new(state).record_pokemon(id).await;
+            state.updated(Entity::Pokemon, id);
+
+            Ok(Json(pokemon))
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to update pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+async fn upsert_pokemon(
+    state: &AppState,
+    name: String,
+    payload: PutPokemonRequest,
+) -> Result<Json<Pokemon>, ApiError> {
+    let (region_id, _) = require_region(state, &payload.region).await?;
+
+    match state
+        .pokemon
+        .upsert(&name, region_id, payload.metadata.as_ref())
+        .await
+    {
+        Ok((pokemon, created)) => {
+            CatalogHistory::new(state)
+                .record_pokemon(pokemon.pokemon_id)
+                .await;
+            if created {
+                state.created(Entity::Pokemon, pokemon.pokemon_id);
+            } else {
+                state.updated(Entity::Pokemon, pokemon.pokemon_id);
+            }
+
+            Ok(Json(pokemon))
+        }
+        Err(e) => {
+            tracing::error!("Failed to upsert pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Deletes the pokemon along with its ability and attribute links. A pokemon
+/// still owned by a trainer trips the foreign key and returns 409.
+#[utoipa::path(
+    delete,
+    path = "/pokemon/{key}",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id, UUID or slug")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pokemon deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon", body = ErrorBody),
+        (status = 409, description = "A trainer still owns the pokemon", body = ErrorBody)
+    )
+)]
+async fn delete_pokemon(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+) -> Result<StatusCode, ApiError> {
+    match state.pokemon.delete(&key).await {
+        Ok(None) => Err(ApiError::NotFound),
+        Ok(Some(id)) => {
+            state.deleted(Entity::Pokemon, id);
+
+            Ok(StatusCode::OK)
+        }
+        Err(e) => {
+            tracing::error!("Failed to delete pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon/{key}/description",
+    tag = "pokemon",
+    params(
+        ("key" = String, Path, description = "Pokemon id, UUID or slug"),
+        RenderParams
+    ),
+    responses(
+        (status = 200, description = "The pokemon's description and lore, as markdown or sanitized HTML", body = Description),
+        (status = 404, description = "No such pokemon", body = ErrorBody)
+    )
+)]
+async fn get_pokemon_description(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+    Query(params): Query<RenderParams>,
+) -> Result<Json<Description>, ApiError> {
+    match state.pokemon.description(&key).await {
+        Ok(Some(description)) => Ok(Json(description.render(params.render.unwrap_or_default()))),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch pokemon description: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Both fields are replaced; one left out is cleared.
+#[utoipa::path(
+    put,
+    path = "/pokemon/{key}/description",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id, UUID or slug")),
+    request_body = Description,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The description and lore as stored", body = Description),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon", body = ErrorBody),
+        (status = 422, description = "Text too long", body = ErrorBody)
+    )
+)]
+async fn put_pokemon_description(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+    Valid(payload): Valid<Description>,
+) -> Result<Json<Description>, ApiError> {
+    match state.pokemon.set_description(&key, &payload).await {
+        Ok(Some(id)) => {
+            CatalogHistory::new(&state).record_pokemon(id).await;
+            state.updated(Entity::Pokemon, id);
+
+            Ok(Json(payload))
+        }
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to describe pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetPokemonVersionsResponse {
+    pokemon_id: i32,
+    /// Oldest first.
+    versions: Vec<Version>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon/{key}/versions",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id, UUID or slug")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Every version of the pokemon, published or not", body = GetPokemonVersionsResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon", body = ErrorBody)
+    )
+)]
+async fn get_pokemon_versions(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+) -> Result<Json<GetPokemonVersionsResponse>, ApiError> {
+    let (pokemon_id, versions) = CatalogHistory::new(&state).pokemon_versions(&key).await?;
+
+    Ok(Json(GetPokemonVersionsResponse {
+        pokemon_id,
+        versions,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon/{key}/versions/diff",
+    tag = "pokemon",
+    params(("key" = String, Path, description = "Pokemon id, UUID or slug"), DiffParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The fields that differ between the two versions", body = VersionDiff),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon or version", body = ErrorBody)
+    )
+)]
+async fn get_pokemon_version_diff(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<EntityKey>,
+    Query(params): Query<DiffParams>,
+) -> Result<Json<VersionDiff>, ApiError> {
+    let (_, versions) = CatalogHistory::new(&state).pokemon_versions(&key).await?;
+
+    version_diff(&versions, params.from, params.to).map(Json)
+}
+
+/// Writes the name, region, metadata, description and lore of an earlier
+/// version back as a new version.
+#[utoipa::path(
+    post,
+    path = "/pokemon/{key}/versions/{version}/rollback",
+    tag = "pokemon",
+    params(
+        ("key" = String, Path, description = "Pokemon id, UUID or slug"),
+        ("version" = i32, Path, description = "Version to restore")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The version recording the rollback", body = Version),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such pokemon or version", body = ErrorBody),
+        (status = 409, description = "The name is taken, the region is gone or the version can't be restored", body = ErrorBody)
+    )
+)]
+async fn rollback_pokemon(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Path((key, version)): Path<(EntityKey, i32)>,
+) -> Result<Json<Version>, ApiError> {
+    CatalogHistory::new(&state)
+        .rollback_pokemon(&key, version)
+        .await
+        .map(Json)
+}
+
+#[derive(Serialize, ToSchema)]
+struct RegionCount {
+    region: String,
+    pokemon_count: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetGenerationResponse {
+    generation: i32,
+    pokemon_count: i64,
+    regions: Vec<RegionCount>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/generation/{n}",
+    tag = "pokemon",
+    params(("n" = i32, Path, description = "Generation number")),
+    responses(
+        (status = 200, description = "Pokemon counts per region", body = GetGenerationResponse),
+        (status = 404, description = "No such generation", body = ErrorBody)
+    )
+)]
+async fn get_generation(
+    State(state): State<Arc<AppState>>,
+    Path(generation): Path<i32>,
+) -> Result<Json<GetGenerationResponse>, ApiError> {
+    let regions = regions_in_generation(generation);
+    if regions.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+
+    match state.pokemon.count_by_region(&regions).await {
+        Ok(counts) => {
+            let regions: Vec<RegionCount> = counts
+                .into_iter()
+                .map(|(region, pokemon_count)| RegionCount {
+                    region,
+                    pokemon_count,
+                })
+                .collect();
+
+            Ok(Json(GetGenerationResponse {
+                generation,
+                pokemon_count: regions.iter().map(|r| r.pokemon_count).sum(),
+                regions,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch generation summary: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum GroupField {
+    Region,
+    Generation,
+    /// Number of pokemon per count of abilities they know.
+    AbilityCount,
+    /// Histogram of ability damage, in buckets of `bucket` points.
+    Damage,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GroupByParams {
+    field: GroupField,
+    bucket: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Group {
+    key: String,
+    count: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetGroupsResponse {
+    field: GroupField,
+    groups: Vec<Group>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/pokemon/group-by",
+    tag = "pokemon",
+    params(GroupByParams),
+    responses((status = 200, description = "Pokemon counts per group", body = GetGroupsResponse))
+)]
+async fn get_pokemon_groups(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GroupByParams>,
+) -> Result<Json<GetGroupsResponse>, ApiError> {
+    let bucket = params.bucket.unwrap_or(25).max(1);
+    let grouping = match params.field {
+        GroupField::Region | GroupField::Generation => Grouping::Region,
+        GroupField::AbilityCount => Grouping::AbilityCount,
+        GroupField::Damage => Grouping::Damage(bucket),
+    };
+
+    let counts = match state.pokemon.group_counts(grouping).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            tracing::error!("Failed to group pokemon: {:?}", e);
+
+            return Err(e.into());
+        }
+    };
+
+    let mut groups: Vec<Group> = counts
+        .into_iter()
+        .map(|(key, count)| Group { key, count })
+        .collect();
+
+    match params.field {
+        GroupField::Generation => {
+            // Generations are derived from region names in code, so fold the
+            // per-region counts here instead of in SQL.
+            let mut by_generation: Vec<Group> = Vec::new();
+            for g in groups {
+                let key = generation_of(&g.key)
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                match by_generation.iter_mut().find(|b| b.key == key) {
+                    Some(b) => b.count += g.count,
+                    None => by_generation.push(Group {
+                        key,
+                        count: g.count,
+                    }),
+                }
+            }
+            by_generation.sort_by(|a, b| a.key.cmp(&b.key));
+            groups = by_generation;
+        }
+        GroupField::Damage => {
+            for g in &mut groups {
+                let low: i32 = g.key.parse().unwrap_or_default();
+                g.key = format!("{}-{}", low, low + bucket - 1);
+            }
+        }
+        _ => {}
+    }
+
+    Ok(Json(GetGroupsResponse {
+        field: params.field,
+        groups,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn get_pokemon_filters_by_ability() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let thunderbolt = repo.add_ability("Thunderbolt", 90, "paralyze");
+        let tackle = repo.add_ability("Tackle", 40, "none");
+        repo.add_pokemon("Pikachu", kanto, &[thunderbolt, tackle]);
+        repo.add_pokemon("Eevee", kanto, &[tackle]);
+        let app = build_app(state(repo));
+
+        let (status, body) = send(
+            &app,
+            Method::GET,
+            "/pokemon?ability=thunderbolt",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_count"], 1);
+        assert_eq!(body["pokemons"][0]["name"], "Pikachu");
+        assert_eq!(body["pokemons"][0]["generation"], 1);
+        assert_eq!(body["pokemons"][0]["abilities"][0]["damage"], 90);
+
+        let (_, body) = send(&app, Method::GET, "/pokemon?ability=tackle", None, None).await;
+        assert_eq!(body["total_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn slugs_are_suffixed_on_collision_and_address_the_pokemon() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        repo.add_region("Johto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let admin = repo.add_user(None, true);
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        let body = json!({"name": "Pikachu!", "region": "Kanto"});
+        let (status, body) = send(&app, Method::POST, "/pokemon", Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["slug"], "pikachu-2");
+
+        // The key is read as a slug, so this moves the existing Pikachu.
+        let body = json!({"region": "Johto"});
+        let (status, body) = send(
+            &app,
+            Method::PUT,
+            "/pokemon/pikachu",
+            Some(&token),
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["pokemon_id"], pikachu);
+        assert_eq!(body["name"], "Pikachu");
+        assert_eq!(body["region"], "Johto");
+
+        // Renaming keeps the slug.
+        let uri = format!("/pokemon/{}", pikachu);
+        let body = json!({"name": "Raichu", "region": "Johto"});
+        let (_, body) = send(&app, Method::PUT, &uri, Some(&token), Some(body)).await;
+        assert_eq!(body["slug"], "pikachu");
+
+        let (status, _) = send(
+            &app,
+            Method::DELETE,
+            "/pokemon/pikachu-2",
+            Some(&token),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, Method::GET, "/pokemon/pikachu-2", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = send(&app, Method::GET, "/pokemon/pikachu", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "Raichu");
+    }
+
+    #[tokio::test]
+    async fn names_without_a_letter_are_rejected() {
+        let repo = Arc::new(InMemoryRepository::default());
+        repo.add_region("Kanto");
+        let admin = repo.add_user(None, true);
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        // One would get an empty slug, the other one read as an id.
+        for name in ["!!!", "151"] {
+            let body = json!({"name": name, "region": "Kanto"});
+            let (status, body) =
+                send(&app, Method::POST, "/pokemon", Some(&token), Some(body)).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", name);
+            assert_eq!(body["fields"][0]["field"], "name");
+        }
+
+        let body = json!({"region": "Kanto"});
+        let (status, body) = send(
+            &app,
+            Method::PUT,
+            "/pokemon/%3F%3F",
+            Some(&token),
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "key");
+    }
+
+    #[tokio::test]
+    async fn cached_pages_keep_their_links() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        for name in ["Bulbasaur", "Charmander", "Squirtle"] {
+            repo.add_pokemon(name, kanto, &[]);
+        }
+        let app = build_app(state(repo));
+
+        let links = || async {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::get("/pokemon?per_page=2")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            response.headers()[header::LINK].clone()
+        };
+
+        let fetched = links().await;
+        assert_eq!(
+            fetched,
+            "</pokemon?per_page=2&page=1>; rel=\"first\", \
+             </pokemon?per_page=2&page=2>; rel=\"next\", \
+             </pokemon?per_page=2&page=2>; rel=\"last\""
+        );
+        assert_eq!(links().await, fetched);
+    }
+
+    #[tokio::test]
+    async fn lists_are_ordered_by_name_only_when_a_collation_is_asked_for() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        for name in ["Zubat", "Abra", "Mew"] {
+            repo.add_pokemon(name, kanto, &[]);
+        }
+        let app = build_app(state(repo));
+        let names = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let (status, body) = send(&app, Method::GET, uri, None, None).await;
+                assert_eq!(status, StatusCode::OK, "{}", uri);
+                body["pokemons"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| p["name"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(names("/pokemon").await, ["Zubat", "Abra", "Mew"]);
+        assert_eq!(
+            names("/pokemon?collation=binary").await,
+            ["Abra", "Mew", "Zubat"]
+        );
+
+        let (status, body) =
+            send(&app, Method::GET, "/pokemon?collation=klingon", None, None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "collation");
+    }
+
+    #[tokio::test]
+    async fn pages_are_cached_by_what_they_select() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        for name in ["Charmander", "Charmeleon", "Squirtle"] {
+            repo.add_pokemon(name, kanto, &[]);
+        }
+        let app = build_app(state(repo.clone()));
+        let total = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let (status, body) = send(&app, Method::GET, uri, None, None).await;
+                assert_eq!(status, StatusCode::OK, "{}", uri);
+                body["total_count"].clone()
+            }
+        };
+
+        assert_eq!(total("/pokemon?per_page=2&name=char").await, 2);
+        // Added behind the cache's back, so only a fresh page counts it.
+        repo.add_pokemon("Charizard", kanto, &[]);
+
+        for uri in [
+            "/pokemon?name=char&per_page=2",
+            "/pokemon?name=CHAR&per_page=2&page=1&count=exact",
+            "/pokemon?per_page=2&name=char&utm_source=mail",
+        ] {
+            assert_eq!(total(uri).await, 2, "{}", uri);
+        }
+        assert_eq!(total("/pokemon?per_page=3&name=char").await, 3);
+    }
+
+    #[tokio::test]
+    async fn references_are_checked_and_shown_on_the_detail() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let kanto = repo.add_region("Kanto");
+        repo.add_ability("Static", 0, "paralysis");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        let uri = format!("/pokemon/{}/references", pikachu);
+        for (kind, value) in [
+            (
+                "bulbapedia",
+                "http://bulbapedia.bulbagarden.net/wiki/Pikachu",
+            ),
+            ("bulbapedia", "https://example.com/wiki/Pikachu"),
+            ("sprite", "javascript:alert(1)"),
+            ("sprite", "https://user@sprites.example/25.png"),
+            ("pokeapi", "025"),
+        ] {
+            let body = json!({"kind": kind, "value": value});
+            let (status, body) = send(&app, Method::POST, &uri, Some(&token), Some(body)).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", value);
+            assert_eq!(body["fields"][0]["field"], "value");
+        }
+
+        let wiki = json!({
+            "kind": "bulbapedia",
+            "value": "https://bulbapedia.bulbagarden.net/wiki/Pikachu_(Pok%C3%A9mon)"
+        });
+        let (status, _) = send(&app, Method::POST, &uri, Some(&token), Some(wiki.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send(&app, Method::POST, &uri, Some(&token), Some(wiki)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let pokeapi = json!({"kind": "pokeapi", "value": "25"});
+        let (status, body) = send(&app, Method::POST, &uri, Some(&token), Some(pokeapi)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["url"], "https://pokeapi.co/api/v2/pokemon/25/");
+        let pokeapi_id = body["reference_id"].clone();
+
+        let (_, body) = send(&app, Method::GET, "/pokemon/pikachu", None, None).await;
+        assert_eq!(body["name"], "Pikachu");
+        assert_eq!(body["references"][0]["kind"], "bulbapedia");
+        assert_eq!(body["references"][1]["value"], "25");
+
+        let uri = format!("/pokemon/pikachu/references/{}", pokeapi_id);
+        let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, Method::GET, "/pokemon/pikachu", None, None).await;
+        assert_eq!(body["references"].as_array().unwrap().len(), 1);
+
+        let sprite = json!({"kind": "sprite", "value": "https://sprites.example/static.png"});
+        let uri = "/ability/Static/references";
+        let (status, _) = send(&app, Method::POST, uri, Some(&token), Some(sprite)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (_, body) = send(&app, Method::GET, "/ability/Static", None, None).await;
+        assert_eq!(body["status_effect"], "paralysis");
+        assert_eq!(
+            body["references"][0]["url"],
+            "https://sprites.example/static.png"
+        );
+        let (status, _) = send(&app, Method::GET, "/ability/Surf", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn generations_filter_the_catalog_and_summarise_their_regions() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let galar = repo.add_region("Galar");
+        repo.add_region("Hisui");
+        repo.add_pokemon("Pikachu", kanto, &[]);
+        repo.add_pokemon("Eevee", kanto, &[]);
+        repo.add_pokemon("Wooloo", galar, &[]);
+        let app = build_app(state(repo));
+
+        let (status, body) = send(&app, Method::GET, "/pokemon?generation=1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_count"], 2);
+        for pokemon in body["pokemons"].as_array().unwrap() {
+            assert_eq!(pokemon["generation"], 1);
+        }
+
+        let (status, body) = send(&app, Method::GET, "/generation/8", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({
+                "generation": 8,
+                "pokemon_count": 1,
+                "regions": [
+                    {"region": "Galar", "pokemon_count": 1},
+                    {"region": "Hisui", "pokemon_count": 0}
+                ]
+            })
+        );
+
+        let (status, _) = send(&app, Method::GET, "/generation/99", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn group_by_counts_pokemon_and_abilities() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let galar = repo.add_region("Galar");
+        let hisui = repo.add_region("Hisui");
+        let thunderbolt = repo.add_ability("Thunderbolt", 90, "paralyze");
+        let tackle = repo.add_ability("Tackle", 40, "none");
+        repo.add_ability("Scratch", 40, "none");
+        repo.add_ability("Growl", 0, "none");
+        repo.add_pokemon("Pikachu", kanto, &[thunderbolt, tackle]);
+        repo.add_pokemon("Eevee", kanto, &[tackle]);
+        repo.add_pokemon("Wooloo", galar, &[tackle]);
+        repo.add_pokemon("Zorua", hisui, &[]);
+        let app = build_app(state(repo));
+        let groups = |body: serde_json::Value| body["groups"].clone();
+
+        let (status, body) = send(
+            &app,
+            Method::GET,
+            "/pokemon/group-by?field=region",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["field"], "region");
+        assert_eq!(
+            groups(body),
+            json!([
+                {"key": "Galar", "count": 1},
+                {"key": "Hisui", "count": 1},
+                {"key": "Kanto", "count": 2}
+            ])
+        );
+
+        // Galar and Hisui are both generation 8.
+        let (_, body) = send(
+            &app,
+            Method::GET,
+            "/pokemon/group-by?field=generation",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(
+            groups(body),
+            json!([{"key": "1", "count": 2}, {"key": "8", "count": 2}])
+        );
+
+        let (_, body) = send(
+            &app,
+            Method::GET,
+            "/pokemon/group-by?field=ability_count",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(
+            groups(body),
+            json!([
+                {"key": "0", "count": 1},
+                {"key": "1", "count": 2},
+                {"key": "2", "count": 1}
+            ])
+        );
+
+        let (_, body) = send(
+            &app,
+            Method::GET,
+            "/pokemon/group-by?field=damage",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(
+            groups(body),
+            json!([
+                {"key": "0-24", "count": 1},
+                {"key": "25-49", "count": 2},
+                {"key": "75-99", "count": 1}
+            ])
+        );
+
+        let (_, body) = send(
+            &app,
+            Method::GET,
+            "/pokemon/group-by?field=damage&bucket=50",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(
+            groups(body),
+            json!([{"key": "0-49", "count": 3}, {"key": "50-99", "count": 1}])
+        );
+    }
+}
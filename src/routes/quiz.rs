@@ -0,0 +1,194 @@
+use crate::auth::CurrentTrainer;
+use crate::error::ApiError;
+use crate::extract::{JsonBody, Path, Query};
+use crate::models::{QuizAnswer, QuizQuestion, QuizScore};
+use crate::service::QuizService;
+use crate::AppState;
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/quiz/next", get(next_question))
+        .route("/quiz/:id/answer", post(answer_question))
+        .route("/quiz/leaderboard", get(get_leaderboard))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(next_question, answer_question, get_leaderboard),
+    components(schemas(
+        QuizQuestion,
+        AnswerRequest,
+        QuizAnswer,
+        QuizScore,
+        GetLeaderboardResponse
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+/// A "Who's that pokemon?" question for the signed-in user's trainer: the
+/// sprite of a random published pokemon, to show as a silhouette, and its
+/// name with the letters blanked out. Only pokemon with a `sprite` reference
+/// are asked about.
+#[utoipa::path(
+    get,
+    path = "/quiz/next",
+    tag = "quiz",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "A new question", body = QuizQuestion),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No pokemon has a sprite, or the account has no trainer", body = ErrorBody)
+    )
+)]
+async fn next_question(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<QuizQuestion>, ApiError> {
+    let question = QuizService::new(&state).next(me.trainer_id).await?;
+
+    Ok(Json(question))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct AnswerRequest {
+    /// The pokemon's name. Case, spaces and punctuation are ignored, and a
+    /// typo is forgiven in names of five letters or more, two in names of
+    /// nine or more.
+    answer: String,
+}
+
+/// Answers a question the signed-in user's trainer was asked. Each
+/// question counts once towards the trainer's score, right or wrong.
+#[utoipa::path(
+    post,
+    path = "/quiz/{id}/answer",
+    tag = "quiz",
+    params(("id" = i32, Path, description = "Question id")),
+    request_body = AnswerRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Whether the answer was right, and the new score", body = QuizAnswer),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 404, description = "No such question for this trainer, or the account has no trainer", body = ErrorBody),
+        (status = 409, description = "The question was already answered", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn answer_question(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    JsonBody(payload): JsonBody<AnswerRequest>,
+) -> Result<Json<QuizAnswer>, ApiError> {
+    let answer = QuizService::new(&state)
+        .answer(me.trainer_id, id, &payload.answer)
+        .await?;
+
+    Ok(Json(answer))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LeaderboardParams {
+    /// Defaults to 10; clamped to 1..=100.
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetLeaderboardResponse {
+    /// Most right answers first; fewer answers first among equals.
+    scores: Vec<QuizScore>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/quiz/leaderboard",
+    tag = "quiz",
+    params(LeaderboardParams),
+    responses(
+        (status = 200, description = "The best quiz scores", body = GetLeaderboardResponse)
+    )
+)]
+async fn get_leaderboard(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LeaderboardParams>,
+) -> Result<Json<GetLeaderboardResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(10).clamp(1, 100);
+    let scores = QuizService::new(&state).leaderboard(limit).await?;
+
+    Ok(Json(GetLeaderboardResponse { scores }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::models::{EntityKey, NewReference, ReferenceKind};
+    use crate::repository::{InMemoryRepository, PokemonRepository};
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn answers_are_scored_once_on_the_leaderboard() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        repo.add_pokemon("Missingno", kanto, &[]);
+        let sprite = NewReference {
+            kind: ReferenceKind::Sprite,
+            value: "https://img.example/25.png".to_string(),
+        };
+        let key = EntityKey::Id(pikachu);
+        repo.add_reference(&key, &sprite).await.unwrap();
+        let ash = repo.add_trainer("Ash", &[]);
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo);
+        let ash_token = token(&state, user);
+        let app = build_app(state);
+
+        let mut asked = Vec::new();
+        for _ in 0..2 {
+            let (status, body) =
+                send(&app, Method::GET, "/quiz/next", Some(&ash_token), None).await;
+            assert_eq!(status, StatusCode::OK);
+            // Missingno has no sprite, so only Pikachu is asked about.
+            assert_eq!(body["masked_name"], "P______");
+            assert_eq!(body["sprite_url"], "https://img.example/25.png");
+            asked.push(body["question_id"].as_i64().unwrap());
+        }
+
+        let uri = format!("/quiz/{}/answer", asked[0]);
+        let answer = json!({"answer": "pikachuu"});
+        let (status, body) = send(&app, Method::POST, &uri, Some(&ash_token), Some(answer)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["correct"], true);
+        assert_eq!(body["pokemon"], "Pikachu");
+        assert_eq!(body["score"]["correct"], 1);
+
+        let again = json!({"answer": "Pikachu"});
+        let (status, _) = send(&app, Method::POST, &uri, Some(&ash_token), Some(again)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let uri = format!("/quiz/{}/answer", asked[1]);
+        let wrong = json!({"answer": "Raichu"});
+        let (status, body) = send(&app, Method::POST, &uri, Some(&ash_token), Some(wrong)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["correct"], false);
+
+        let (status, body) = send(&app, Method::GET, "/quiz/leaderboard", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["scores"],
+            json!([{"trainer_id": ash, "name": "Ash", "correct": 1, "answered": 2}])
+        );
+    }
+}
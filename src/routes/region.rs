@@ -0,0 +1,227 @@
+use crate::auth::AdminClaims;
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::extract::Path;
+use crate::models::Region;
+use crate::validation::{FieldErrors, Valid, Validate, MAX_NAME_LEN};
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/region", get(get_regions).post(create_region))
+        .route("/region/:id", put(update_region).delete(delete_region))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_regions, create_region, update_region, delete_region),
+    components(schemas(Region, GetRegionsResponse, RegionRequest))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+struct GetRegionsResponse {
+    regions: Vec<Region>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/region",
+    tag = "region",
+    responses((status = 200, description = "All regions", body = GetRegionsResponse))
+)]
+async fn get_regions(db: DbConn) -> Result<Json<GetRegionsResponse>, ApiError> {
+    match db
+        .query_capped(
+            "SELECT region_id, region_name FROM region ORDER BY region_id",
+            &[],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetRegionsResponse {
+            regions: rows
+                .iter()
+                .map(|r| Region {
+                    region_id: r.get(0),
+                    region_name: r.get(1),
+                })
+                .collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch regions: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct RegionRequest {
+    region_name: String,
+}
+
+impl Validate for RegionRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.name("region_name", &self.region_name, MAX_NAME_LEN);
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/region",
+    tag = "region",
+    request_body = RegionRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Region created", body = Region),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 409, description = "Region name already taken", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn create_region(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    db: DbConn,
+    Valid(payload): Valid<RegionRequest>,
+) -> Result<(StatusCode, Json<Region>), ApiError> {
+    match db
+        .query(
+            "INSERT INTO region (region_name) VALUES ($1) RETURNING region_id",
+            &[&payload.region_name],
+        )
+        .await
+    {
+        Ok(rows) => {
+            let region_id = rows.first().unwrap().get(0);
+            state.created(Entity::Region, region_id);
+
+            Ok((
+                StatusCode::CREATED,
+                Json(Region {
+                    region_id,
+                    region_name: payload.region_name,
+                }),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create region: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/region/{id}",
+    tag = "region",
+    params(("id" = i32, Path, description = "Region id")),
+    request_body = RegionRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Region renamed", body = Region),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such region", body = ErrorBody),
+        (status = 409, description = "Region name already taken", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn update_region(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    db: DbConn,
+    Path(id): Path<i32>,
+    Valid(payload): Valid<RegionRequest>,
+) -> Result<Json<Region>, ApiError> {
+    match db
+        .execute(
+            "UPDATE region SET region_name = $2 WHERE region_id = $1",
+            &[&id, &payload.region_name],
+        )
+        .await
+    {
+        Ok(0) => Err(ApiError::NotFound),
+        Ok(_) => {
+            state.updated(Entity::Region, id);
+
+            Ok(Json(Region {
+                region_id: id,
+                region_name: payload.region_name,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to update region: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/region/{id}",
+    tag = "region",
+    params(("id" = i32, Path, description = "Region id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Region deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 404, description = "No such region", body = ErrorBody),
+        (status = 409, description = "Pokemon still belong to the region", body = ErrorBody)
+    )
+)]
+async fn delete_region(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    db: DbConn,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    // Checked up front so the answer doesn't depend on a foreign key.
+    match db
+        .query("SELECT 1 FROM pokemon WHERE region_id = $1 LIMIT 1", &[&id])
+        .await
+    {
+        Ok(rows) if !rows.is_empty() => {
+            return Err(ApiError::Conflict(
+                "region is still referenced by pokemon".to_string(),
+            ))
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("Failed to check region references: {}", e);
+
+            return Err(e.into());
+        }
+    }
+
+    match db
+        .execute("DELETE FROM region WHERE region_id = $1", &[&id])
+        .await
+    {
+        Ok(0) => Err(ApiError::NotFound),
+        Ok(_) => {
+            state.deleted(Entity::Region, id);
+
+            Ok(StatusCode::OK)
+        }
+        Err(e) => {
+            tracing::error!("Failed to delete region: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::extract::{Path, Query};
+use crate::AppState;
+use axum::{routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/reports", get(get_reports))
+        .route("/reports/:view_name", get(get_report))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_reports, get_report),
+    components(schemas(GetReportsResponse, ReportColumn, GetReportResponse))
+)]
+pub(crate) struct ApiDoc;
+
+/// Every view in the `reports` schema is exposed read-only under
+/// `/reports/:view_name`, so adding a report only takes a `CREATE VIEW`.
+const REPORTS_SCHEMA: &str = "reports";
+
+#[derive(Serialize, ToSchema)]
+struct GetReportsResponse {
+    reports: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports",
+    tag = "reports",
+    responses((status = 200, description = "Names of the available reports", body = GetReportsResponse))
+)]
+async fn get_reports(db: DbConn) -> Result<Json<GetReportsResponse>, ApiError> {
+    match db
+        .query(
+            "SELECT table_name::text FROM information_schema.views
+             WHERE table_schema = $1 ORDER BY table_name",
+            &[&REPORTS_SCHEMA],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetReportsResponse {
+            reports: rows.iter().map(|r| r.get(0)).collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to list reports: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReportParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReportColumn {
+    name: String,
+    data_type: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetReportResponse {
+    view: String,
+    columns: Vec<ReportColumn>,
+    #[schema(value_type = Vec<Object>)]
+    rows: Vec<serde_json::Value>,
+    limit: i64,
+    offset: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/{view_name}",
+    tag = "reports",
+    params(("view_name" = String, Path, description = "Report name"), ReportParams),
+    responses(
+        (status = 200, description = "Rows of the report", body = GetReportResponse),
+        (status = 404, description = "No such report", body = ErrorBody)
+    )
+)]
+async fn get_report(
+    db: DbConn,
+    Path(view_name): Path<String>,
+    Query(params): Query<ReportParams>,
+) -> Result<Json<GetReportResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    // Looking the view up in the catalog first means only existing report
+    // views ever get interpolated into the query below.
+    let columns = match db
+        .query(
+            "SELECT c.column_name::text, c.data_type::text
+             FROM information_schema.columns c
+             JOIN information_schema.views v
+               ON v.table_schema = c.table_schema AND v.table_name = c.table_name
+             WHERE c.table_schema = $1 AND c.table_name = $2
+             ORDER BY c.ordinal_position",
+            &[&REPORTS_SCHEMA, &view_name],
+        )
+        .await
+    {
+        Ok(rows) => rows
+            .iter()
+            .map(|r| ReportColumn {
+                name: r.get(0),
+                data_type: r.get(1),
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            tracing::error!("Failed to fetch report columns: {:?}", e);
+
+            return Err(e.into());
+        }
+    };
+
+    if columns.is_empty() {
+        return Err(ApiError::NotFound);
+    }
+
+    let sql = format!(
+        "SELECT row_to_json(v) FROM {}.\"{}\" v LIMIT $1 OFFSET $2",
+        REPORTS_SCHEMA,
+        view_name.replace('"', "\"\"")
+    );
+    match db.query(&sql, &[&limit, &offset]).await {
+        Ok(rows) => Ok(Json(GetReportResponse {
+            view: view_name,
+            columns,
+            rows: rows.iter().map(|r| r.get(0)).collect(),
+            limit,
+            offset,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch report: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
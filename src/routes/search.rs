@@ -0,0 +1,136 @@
+use crate::error::ApiError;
+use crate::extract::Query;
+use crate::models::SearchHit;
+use crate::AppState;
+use axum::{extract::State, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/search", get(search))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(search), components(schemas(SearchHit, SearchResponse)))]
+pub(crate) struct ApiDoc;
+
+/// `?q=&limit=` for `/search`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchParams {
+    /// Words to look for, as in a web search: `"quoted phrases"`, `or`,
+    /// and `-word` to leave out matches of a word.
+    q: String,
+    /// Between 1 and 50; 20 by default.
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct SearchResponse {
+    /// Pokemon and abilities together, best match first.
+    results: Vec<SearchHit>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "search",
+    params(SearchParams),
+    responses((status = 200, description = "Published pokemon and abilities whose name, description or lore match", body = SearchResponse))
+)]
+async fn search(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let q = params.q.trim();
+    if q.is_empty() {
+        return Ok(Json(SearchResponse {
+            results: Vec::new(),
+        }));
+    }
+    let limit = params.limit.unwrap_or(20).clamp(1, 50);
+
+    let pokemon = state.pokemon.search(q, limit).await;
+    let abilities = state.abilities.search(q, limit).await;
+    match pokemon.and_then(|p| abilities.map(|a| (p, a))) {
+        Ok((pokemon, abilities)) => {
+            let mut results: Vec<SearchHit> = pokemon.into_iter().chain(abilities).collect();
+            results.sort_by(|a, b| b.rank.total_cmp(&a.rank));
+            results.truncate(limit as usize);
+
+            Ok(Json(SearchResponse { results }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to search: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn descriptions_are_rendered_safely_and_searched() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let admin = repo.add_user(None, true);
+        let kanto = repo.add_region("Kanto");
+        let surf = repo.add_ability("Surf", 90, "none");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[surf]);
+        repo.add_pokemon("Lapras", kanto, &[surf]);
+        let state = state(repo);
+        let token = token(&state, admin);
+        let app = build_app(state);
+
+        let lore = json!({
+            "description": "A **surfing** mouse <script>alert(1)</script>",
+            "lore": "Seen riding waves off Seafoam Islands."
+        });
+        let uri = format!("/pokemon/{}/description", pikachu);
+        let (status, _) = send(&app, Method::PUT, &uri, None, Some(lore.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = send(&app, Method::PUT, &uri, Some(&token), Some(lore)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["lore"], "Seen riding waves off Seafoam Islands.");
+        let big = json!({"description": "a".repeat(2001)});
+        let (status, body) = send(&app, Method::PUT, &uri, Some(&token), Some(big)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "description");
+
+        let (_, body) = send(&app, Method::GET, &uri, None, None).await;
+        assert!(body["description"].as_str().unwrap().contains("<script>"));
+        let html = format!("{}?render=html", uri);
+        let (_, body) = send(&app, Method::GET, &html, None, None).await;
+        assert_eq!(
+            body["description"],
+            "<p>A <strong>surfing</strong> mouse </p>\n"
+        );
+
+        let wave = json!({"description": "Rides a huge wave."});
+        let uri = "/ability/Surf/description";
+        let (status, _) = send(&app, Method::PUT, uri, Some(&token), Some(wave)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, Method::GET, uri, None, None).await;
+        assert_eq!(
+            body,
+            json!({"description": "Rides a huge wave.", "lore": null})
+        );
+
+        let (_, body) = send(&app, Method::GET, "/search?q=waves", None, None).await;
+        assert_eq!(body["results"].as_array().unwrap().len(), 1);
+        assert_eq!(body["results"][0]["name"], "Pikachu");
+        let (_, body) = send(&app, Method::GET, "/search?q=wave", None, None).await;
+        assert_eq!(body["results"][0]["kind"], "ability");
+        assert_eq!(body["results"][1]["kind"], "pokemon");
+        let (_, body) = send(&app, Method::GET, "/search?q=lapras", None, None).await;
+        assert_eq!(body["results"][0]["name"], "Lapras");
+    }
+}
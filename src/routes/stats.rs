@@ -0,0 +1,182 @@
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::extract::Query;
+use crate::AppState;
+use axum::{routing::get, Json, Router};
+use serde::de::{Deserializer, Error as _};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/stats/history", get(get_history))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_history),
+    components(schemas(Metric, StatPoint, GetHistoryResponse))
+)]
+pub(crate) struct ApiDoc;
+
+/// Longest window `GET /stats/history` reports on.
+const MAX_RANGE_DAYS: i32 = 365;
+
+/// A count the stats job records once a day.
+#[derive(Clone, Copy, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum Metric {
+    /// Trainers people play.
+    Trainers,
+    /// Pokemon those trainers caught.
+    Catches,
+    /// Battles fought, against AI trainers too.
+    Battles,
+}
+
+impl Metric {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Trainers => "trainers",
+            Self::Catches => "catches",
+            Self::Battles => "battles",
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HistoryParams {
+    metric: Metric,
+    /// How many days back to report, including today (UTC), as `<n>d`.
+    /// Defaults to `30d`, at most `365d`.
+    #[serde(default)]
+    #[param(value_type = Option<String>)]
+    range: Range,
+}
+
+/// A number of days, written `<n>d`.
+struct Range(i32);
+
+impl Default for Range {
+    fn default() -> Self {
+        Self(30)
+    }
+}
+
+impl<'de> Deserialize<'de> for Range {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let range = String::deserialize(deserializer)?;
+        match range_days(&range) {
+            Some(days) if (1..=MAX_RANGE_DAYS).contains(&days) => Ok(Self(days)),
+            _ => Err(D::Error::custom(format!(
+                "must be a number of days from 1d to {}d",
+                MAX_RANGE_DAYS
+            ))),
+        }
+    }
+}
+
+/// Days in a range such as `30d`, or `None` if it isn't one.
+fn range_days(range: &str) -> Option<i32> {
+    let days = range.strip_suffix('d')?;
+    if !days.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    days.parse().ok()
+}
+
+#[derive(Serialize, ToSchema)]
+struct StatPoint {
+    /// UTC date, as `YYYY-MM-DD`.
+    day: String,
+    value: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetHistoryResponse {
+    metric: Metric,
+    /// Oldest day first. Days the stats job didn't run on are missing.
+    points: Vec<StatPoint>,
+}
+
+/// A key count as it stood at the end of each day, for trend charts. The
+/// stats job records the counts every `STATS_INTERVAL_SECS`, so today's
+/// value is as of its last run.
+#[utoipa::path(
+    get,
+    path = "/stats/history",
+    tag = "stats",
+    params(HistoryParams),
+    responses(
+        (status = 200, description = "The metric per day", body = GetHistoryResponse),
+        (status = 422, description = "Unknown metric, or range not a number of days up to 365", body = ErrorBody)
+    )
+)]
+async fn get_history(
+    Query(params): Query<HistoryParams>,
+    db: DbConn,
+) -> Result<Json<GetHistoryResponse>, ApiError> {
+    match db
+        .query(
+            "SELECT day::text, value FROM daily_stat
+             WHERE metric = $1 AND day > (now() AT TIME ZONE 'UTC')::date - $2::int4
+             ORDER BY day",
+            &[&params.metric.name(), &params.range.0],
+        )
+        .await
+    {
+        Ok(rows) => Ok(Json(GetHistoryResponse {
+            metric: params.metric,
+            points: rows
+                .iter()
+                .map(|r| StatPoint {
+                    day: r.get(0),
+                    value: r.get(1),
+                })
+                .collect(),
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch stats history: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::range_days;
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state};
+    use axum::http::{Method, StatusCode};
+    use std::sync::Arc;
+
+    #[test]
+    fn ranges_are_counted_in_days() {
+        assert_eq!(range_days("30d"), Some(30));
+        assert_eq!(range_days("365d"), Some(365));
+        assert_eq!(range_days("30"), None);
+        assert_eq!(range_days("-1d"), None);
+        assert_eq!(range_days("4w"), None);
+    }
+
+    #[tokio::test]
+    async fn bad_metrics_and_ranges_are_rejected() {
+        let app = build_app(state(Arc::new(InMemoryRepository::default())));
+
+        for uri in [
+            "/stats/history",
+            "/stats/history?metric=pokemon",
+            "/stats/history?metric=catches&range=0d",
+            "/stats/history?metric=catches&range=366d",
+            "/stats/history?metric=catches&range=month",
+        ] {
+            let (status, body) = send(&app, Method::GET, uri, None, None).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", uri);
+            assert!(body["fields"].is_array(), "{}", uri);
+        }
+    }
+}
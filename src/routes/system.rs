@@ -0,0 +1,94 @@
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use utoipa::OpenApi;
+
+/// Liveness and readiness probes.
+pub(crate) fn probes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+}
+
+pub(crate) fn streams() -> Router<Arc<AppState>> {
+    Router::new().route("/events", get(get_events))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(health, ready, get_events))]
+pub(crate) struct ApiDoc;
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "system",
+    responses((status = 200, description = "The process is up"))
+)]
+async fn health() -> StatusCode {
+    StatusCode::OK
+}
+
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "system",
+    responses(
+        (status = 200, description = "The database is reachable"),
+        (status = 503, description = "The server is warming up or shutting down, or the database is unreachable")
+    )
+)]
+async fn ready(State(state): State<Arc<AppState>>) -> StatusCode {
+    // Fail while draining so load balancers stop sending new requests, and
+    // until warm-up finishes so they don't start too early.
+    if state.is_shutting_down() || !state.is_warmed_up() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    let db = match state.db.conn().await {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("Readiness check failed: {}", e);
+
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+    };
+
+    match db.query("SELECT 1", &[]).await {
+        Ok(_) => StatusCode::OK,
+        Err(e) => {
+            tracing::error!("Readiness check failed: {}", e);
+
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "system",
+    responses((status = 200, description = "Server-sent stream of domain events: catalog changes, catches and battles", content_type = "text/event-stream"))
+)]
+async fn get_events(
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // A lagging subscriber just misses the events it fell behind on.
+    let stream = BroadcastStream::new(state.events.subscribe())
+        .filter_map(|event| event.ok())
+        .filter_map(|event| Event::default().json_data(event).ok())
+        .map(Ok);
+    // Streams never finish on their own, so end them when the server shuts
+    // down; otherwise draining would wait on them forever.
+    let stream = futures_util::StreamExt::take_until(stream, state.shutting_down());
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,159 @@
+use crate::db::DbConn;
+use crate::error::ApiError;
+use crate::team::{self, Recommendation};
+use crate::validation::{FieldErrors, Valid, Validate};
+use crate::AppState;
+use axum::{extract::State, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/teams/recommend", post(recommend_team))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(recommend_team),
+    components(schemas(Recommendation, RecommendTeamRequest, RecommendTeamResponse))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Deserialize, ToSchema)]
+struct RecommendTeamRequest {
+    trainer_id: i32,
+    opponent_trainer_id: i32,
+    team_size: Option<usize>,
+}
+
+impl Validate for RecommendTeamRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if self.team_size == Some(0) {
+            errors.add("team_size", "must be at least 1");
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct RecommendTeamResponse {
+    trainer_id: i32,
+    opponent_trainer_id: i32,
+    team: Vec<Recommendation>,
+}
+
+async fn load_combatants(
+    db: &DbConn,
+    trainer_id: i32,
+) -> Result<Vec<team::Combatant>, tokio_postgres::Error> {
+    let rows = db
+        .query(
+            "SELECT p.pokemon_id, p.name,
+                    COALESCE(array_agg(DISTINCT a.attribute_name)
+                             FILTER (WHERE a.attribute_id IS NOT NULL), '{}'),
+                    COALESCE(array_agg(DISTINCT a.weakness)
+                             FILTER (WHERE a.attribute_id IS NOT NULL), '{}'),
+                    COALESCE(max(ab.damage), 0)
+             FROM trainerspokemon tp
+             JOIN published_pokemon p ON p.pokemon_id = tp.pokemon_id
+             LEFT JOIN pokemonattributes pat ON pat.pokemon_id = p.pokemon_id
+             LEFT JOIN attribute a ON a.attribute_id = pat.attribute_id
+             LEFT JOIN pokemonabilities pab ON pab.pokemon_id = p.pokemon_id
+             LEFT JOIN published_ability ab ON ab.ability_id = pab.ability_id
+             WHERE tp.trainer_id = $1
+             GROUP BY p.pokemon_id, p.name",
+            &[&trainer_id],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|r| team::Combatant {
+            pokemon_id: r.get(0),
+            name: r.get(1),
+            types: r.get(2),
+            weaknesses: r.get(3),
+            best_damage: r.get(4),
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/teams/recommend",
+    tag = "team",
+    request_body = RecommendTeamRequest,
+    responses(
+        (status = 200, description = "Best team against the opponent", body = RecommendTeamResponse),
+        (status = 404, description = "No such trainer", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn recommend_team(
+    State(state): State<Arc<AppState>>,
+    db: DbConn,
+    Valid(payload): Valid<RecommendTeamRequest>,
+) -> Result<Json<RecommendTeamResponse>, ApiError> {
+    let trainer_ids = [payload.trainer_id, payload.opponent_trainer_id];
+    match db
+        .query(
+            "SELECT count(*) FROM trainer WHERE trainer_id = ANY($1)",
+            &[&trainer_ids.as_slice()],
+        )
+        .await
+    {
+        Ok(rows) => {
+            let found: i64 = rows.first().unwrap().get(0);
+            let expected = if payload.trainer_id == payload.opponent_trainer_id {
+                1
+            } else {
+                2
+            };
+            if found < expected {
+                return Err(ApiError::NotFound);
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch trainers: {:?}", e);
+
+            return Err(e.into());
+        }
+    }
+
+    // Each roster is loaded on its own connection, so this one goes back to
+    // the pool rather than sitting idle while they run.
+    drop(db);
+
+    let mut rosters = match state
+        .db
+        .lookup_all(
+            trainer_ids,
+            state.limits.lookup_concurrency,
+            |db, trainer_id| async move { load_combatants(&db, trainer_id).await },
+        )
+        .await
+    {
+        Ok(rosters) => rosters,
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer pokemon: {}", e);
+
+            return Err(e.into());
+        }
+    };
+    // Both ids may be the same trainer, in which case there are two rosters
+    // for it and each side takes one.
+    let mut take = |trainer_id: i32| {
+        let i = rosters
+            .iter()
+            .position(|(id, _)| *id == trainer_id)
+            .unwrap();
+        rosters.swap_remove(i).1
+    };
+    let collection = take(payload.trainer_id);
+    let opponents = take(payload.opponent_trainer_id);
+
+    Ok(Json(RecommendTeamResponse {
+        trainer_id: payload.trainer_id,
+        opponent_trainer_id: payload.opponent_trainer_id,
+        team: team::recommend(collection, &opponents, payload.team_size.unwrap_or(6)),
+    }))
+}
@@ -0,0 +1,966 @@
+use crate::auth::{AdminClaims, CurrentTrainer};
+use crate::deprecation::Deprecated;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::extract::{Path, Query};
+use crate::models::{
+    metadata_filter, BulkCreateResponse, CountStrategy, IdOrUuid, Metadata, PageParams, PastName,
+    Patch, Pokemon, PokemonFull, SortParams, Trainer,
+};
+use crate::repository::TrainerChanges;
+use crate::service::TrainerService;
+use crate::validation::{FieldErrors, Valid, Validate, MAX_NAME_LEN};
+use crate::AppState;
+use axum::{
+    extract::{RawQuery, State},
+    http::{header::LINK, HeaderName, HeaderValue, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+pub(crate) fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/trainer", get(get_trainers).post(create_trainer))
+        .route("/trainer/bulk", post(create_trainer_bulk))
+        .route(
+            "/trainer/:id",
+            get(get_trainer).patch(patch_trainer).delete(delete_trainer),
+        )
+        .route("/trainer/:id/team", get(get_team))
+        .route("/trainer/:id/name-history", get(get_name_history))
+        .route(
+            "/trainer/:id/pokemon/:pokemon_id",
+            post(attach_pokemon).delete(detach_pokemon),
+        )
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        get_trainers,
+        get_trainer,
+        get_team,
+        get_name_history,
+        create_trainer,
+        create_trainer_bulk,
+        patch_trainer,
+        delete_trainer,
+        attach_pokemon,
+        detach_pokemon
+    ),
+    components(schemas(
+        CountStrategy,
+        Trainer,
+        Pokemon,
+        GetTrainersResponse,
+        GetTrainerResponse,
+        GetTeamResponse,
+        PastName,
+        GetNameHistoryResponse,
+        CreateUserRequest,
+        PatchTrainerRequest,
+        BulkCreateResponse
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+#[derive(Serialize, ToSchema)]
+struct GetTrainersResponse {
+    trainers: Vec<Trainer>,
+    /// `null` when the request asked for `count=none`.
+    total_count: Option<i64>,
+    /// Whether `total_count` is a planner estimate rather than an exact count.
+    approximate: bool,
+    page: i64,
+    per_page: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/trainer",
+    tag = "trainer",
+    params(
+        SortParams,
+        PageParams,
+        ("meta.{key}" = Option<String>, Query, description = "Only trainers whose metadata has this string value under `key`")
+    ),
+    responses((status = 200, description = "A page of trainers, with `Link` headers to the others", body = GetTrainersResponse))
+)]
+async fn get_trainers(
+    State(state): State<Arc<AppState>>,
+    RawQuery(query): RawQuery,
+    Query(sort): Query<SortParams>,
+    Query(paging): Query<PageParams>,
+) -> Result<([(HeaderName, HeaderValue); 1], Json<GetTrainersResponse>), ApiError> {
+    let metadata = metadata_filter(query.as_deref());
+    match state.trainers.list(metadata.as_ref(), &sort, &paging).await {
+        Ok((trainers, total_count)) => {
+            tracing::debug!(count = trainers.len(), "Fetched trainers");
+
+            let links = paging.links(
+                "/trainer",
+                query.as_deref(),
+                trainers.len(),
+                total_count.value,
+            );
+            Ok((
+                [(LINK, links)],
+                Json(GetTrainersResponse {
+                    trainers,
+                    total_count: total_count.value,
+                    approximate: total_count.approximate,
+                    page: paging.page(),
+                    per_page: paging.per_page(),
+                }),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch trainers: {:?}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// The single trainer used to come wrapped in a one-element `trainers`
+/// array; `trainer` replaces it.
+const TRAINER_ARRAY: Deprecated = Deprecated {
+    // 2026-10-16
+    since: 1792108800,
+    // 2027-04-01
+    sunset: 1806537600,
+    note: "GET /trainer/{id}: use `trainer` instead of `trainers`",
+};
+
+#[derive(Serialize, ToSchema)]
+struct GetTrainerResponse {
+    trainer: Trainer,
+    /// `trainer` again, as a one-element array.
+    #[schema(deprecated)]
+    trainers: [Trainer; 1],
+}
+
+#[utoipa::path(
+    get,
+    path = "/trainer/{id}",
+    tag = "trainer",
+    params(("id" = String, Path, description = "Trainer id or UUID")),
+    responses(
+        (status = 200, description = "The trainer with their pokemon", body = GetTrainerResponse,
+            headers(
+                ("Deprecation" = String, description = "When `trainers` was deprecated, as `@<unix time>`"),
+                ("Sunset" = String, description = "HTTP date after which `trainers` is no longer sent"),
+                ("Warning" = String, description = "What replaces the deprecated fields")
+            )
+        ),
+        (status = 404, description = "No such trainer", body = ErrorBody),
+        (status = 422, description = "id is neither an id nor a UUID", body = ErrorBody)
+    )
+)]
+async fn get_trainer(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<IdOrUuid>,
+) -> Result<(Deprecated, Json<GetTrainerResponse>), ApiError> {
+    let id = resolve_trainer(&state, key).await?;
+    let mut trainer = match state.trainers.get(id).await {
+        Ok(Some(trainer)) => trainer,
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainers: {:?}", e);
+
+            return Err(e.into());
+        }
+    };
+
+    // Same as a trainer in `GET /trainer`.
+    match state.trainers.pokemon(id).await {
+        Ok(pokemon) => {
+            tracing::debug!(trainer_id = id, "Fetched trainer");
+            trainer.pokemon = Some(pokemon);
+
+            Ok((
+                TRAINER_ARRAY,
+                Json(GetTrainerResponse {
+                    trainers: [trainer.clone()],
+                    trainer,
+                }),
+            ))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch the trainer's pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetTeamResponse {
+    trainer_id: i32,
+    /// Most pokemon the team may hold.
+    max_size: usize,
+    pokemon: Vec<PokemonFull>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/trainer/{id}/team",
+    tag = "trainer",
+    params(("id" = String, Path, description = "Trainer id or UUID")),
+    responses(
+        (status = 200, description = "The trainer's pokemon with their abilities and attributes", body = GetTeamResponse),
+        (status = 404, description = "No such trainer", body = ErrorBody)
+    )
+)]
+async fn get_team(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<IdOrUuid>,
+) -> Result<Json<GetTeamResponse>, ApiError> {
+    let id = resolve_trainer(&state, key).await?;
+    match state.trainers.get(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer: {}", e);
+
+            return Err(e.into());
+        }
+    }
+
+    match state.trainers.team(id).await {
+        Ok(pokemon) => Ok(Json(GetTeamResponse {
+            trainer_id: id,
+            max_size: state.limits.max_team_size,
+            pokemon,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch team: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct GetNameHistoryResponse {
+    trainer_id: i32,
+    /// The name the trainer goes by now.
+    name: String,
+    past_names: Vec<PastName>,
+}
+
+/// Lists the names a trainer gave up by renaming. No other trainer may take
+/// one of them until 30 days after it was released.
+#[utoipa::path(
+    get,
+    path = "/trainer/{id}/name-history",
+    tag = "trainer",
+    params(("id" = String, Path, description = "Trainer id or UUID")),
+    responses(
+        (status = 200, description = "The trainer's past names, latest first", body = GetNameHistoryResponse),
+        (status = 404, description = "No such trainer", body = ErrorBody)
+    )
+)]
+async fn get_name_history(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<IdOrUuid>,
+) -> Result<Json<GetNameHistoryResponse>, ApiError> {
+    let id = resolve_trainer(&state, key).await?;
+    let name = match state.trainers.get(id).await {
+        Ok(Some(trainer)) => trainer.name,
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer: {}", e);
+
+            return Err(e.into());
+        }
+    };
+
+    match state.trainers.past_names(id).await {
+        Ok(past_names) => Ok(Json(GetNameHistoryResponse {
+            trainer_id: id,
+            name,
+            past_names,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch past names: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CreateUserRequest {
+    name: String,
+    gym_leader: bool,
+}
+
+impl Validate for CreateUserRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.name("name", &self.name, MAX_NAME_LEN);
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/trainer",
+    tag = "trainer",
+    request_body = CreateUserRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Trainer created"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 409, description = "Name held by another trainer now or in the last 30 days", body = ErrorBody),
+        (status = 422, description = "Invalid fields", body = ErrorBody)
+    )
+)]
+async fn create_trainer(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Valid(payload): Valid<CreateUserRequest>,
+) -> Result<StatusCode, ApiError> {
+    TrainerService::new(&state)
+        .create(&payload.name, payload.gym_leader)
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/trainer/bulk",
+    tag = "trainer",
+    request_body = Vec<CreateUserRequest>,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "All trainers created", body = BulkCreateResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not an admin", body = ErrorBody),
+        (status = 409, description = "A name is held by another trainer now or in the last 30 days, or repeats", body = ErrorBody),
+        (status = 422, description = "Invalid fields or too many items", body = ErrorBody)
+    )
+)]
+async fn create_trainer_bulk(
+    _admin: AdminClaims,
+    State(state): State<Arc<AppState>>,
+    Valid(payload): Valid<Vec<CreateUserRequest>>,
+) -> Result<(StatusCode, Json<BulkCreateResponse>), ApiError> {
+    // Names are compared without case, like the database does.
+    let mut names = HashSet::new();
+    for (i, item) in payload.iter().enumerate() {
+        if !names.insert(item.name.to_lowercase()) {
+            return Err(ApiError::Conflict("name repeats an earlier item".to_string()).at_item(i));
+        }
+    }
+
+    let rows: Vec<_> = payload
+        .iter()
+        .map(|t| (t.name.as_str(), t.gym_leader))
+        .collect();
+
+    match state.trainers.create_many(&rows).await {
+        Ok(ids) => {
+            for &trainer_id in &ids {
+                state.created(Entity::Trainer, trainer_id);
+            }
+
+            Ok((StatusCode::CREATED, Json(BulkCreateResponse { ids })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to bulk create trainers: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+/// Fields left out are kept as they are.
+#[derive(Deserialize, ToSchema)]
+struct PatchTrainerRequest {
+    /// May not be `null`.
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    name: Patch<String>,
+    /// May not be `null`.
+    #[serde(default)]
+    #[schema(value_type = Option<bool>)]
+    gym_leader: Patch<bool>,
+    /// Ids of the pokemon that make up the new team, replacing the old one.
+    /// `null` releases every pokemon, as does `[]`.
+    #[serde(default)]
+    #[schema(value_type = Option<Vec<i32>>)]
+    pokemon: Patch<Vec<i32>>,
+    /// Replaces the trainer's metadata. `null` clears it, as does `{}`.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    metadata: Patch<Metadata>,
+}
+
+/// The values themselves are checked by `TrainerService::update`.
+impl Validate for PatchTrainerRequest {
+    fn validate(&self, errors: &mut FieldErrors) {
+        errors.not_null("name", &self.name);
+        errors.not_null("gym_leader", &self.gym_leader);
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/trainer/{id}",
+    tag = "trainer",
+    params(("id" = String, Path, description = "Trainer id or UUID")),
+    request_body = PatchTrainerRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The updated trainer with their pokemon", body = Trainer),
+        (status = 400, description = "The new team lists pokemon that don't exist", body = ErrorBody),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not the caller's trainer, or X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "No such trainer", body = ErrorBody),
+        (status = 409, description = "New name held by another trainer now or in the last 30 days", body = ErrorBody),
+        (status = 422, description = "Invalid fields, or too many pokemon", body = ErrorBody)
+    )
+)]
+async fn patch_trainer(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<IdOrUuid>,
+    Valid(payload): Valid<PatchTrainerRequest>,
+) -> Result<Json<Trainer>, ApiError> {
+    let id = resolve_trainer(&state, key).await?;
+    me.require_owner(id)?;
+
+    let changes = TrainerChanges {
+        name: payload.name.into_change().flatten(),
+        gym_leader: payload.gym_leader.into_change().flatten(),
+        team: payload
+            .pokemon
+            .into_change()
+            .map(|team| team.unwrap_or_default()),
+        metadata: payload
+            .metadata
+            .into_change()
+            .map(|metadata| metadata.unwrap_or_default()),
+    };
+    TrainerService::new(&state).update(id, changes).await?;
+
+    let trainer = match state.trainers.get(id).await {
+        Ok(Some(trainer)) => trainer,
+        Ok(None) => return Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer: {}", e);
+
+            return Err(e.into());
+        }
+    };
+    match state.trainers.pokemon(id).await {
+        Ok(pokemon) => Ok(Json(Trainer {
+            pokemon: Some(pokemon),
+            ..trainer
+        })),
+        Err(e) => {
+            tracing::error!("Failed to fetch trainer's pokemon: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+struct DeleteTrainerParams {
+    /// Also release the trainer's pokemon. Without it, a trainer that still
+    /// has pokemon is kept and the request answers 409.
+    #[serde(default)]
+    force: bool,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trainer/{id}",
+    tag = "trainer",
+    params(("id" = String, Path, description = "Trainer id or UUID"), DeleteTrainerParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Trainer deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not the caller's trainer, or X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "No such trainer", body = ErrorBody),
+        (status = 409, description = "The trainer still has pokemon and `force` was not set", body = ErrorBody),
+        (status = 422, description = "force is not a boolean", body = ErrorBody)
+    )
+)]
+async fn delete_trainer(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<IdOrUuid>,
+    Query(params): Query<DeleteTrainerParams>,
+) -> Result<StatusCode, ApiError> {
+    let id = resolve_trainer(&state, key).await?;
+    me.require_owner(id)?;
+
+    match TrainerService::new(&state).delete(id, params.force).await? {
+        true => Ok(StatusCode::OK),
+        false => Err(ApiError::NotFound),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/trainer/{id}/pokemon/{pokemon_id}",
+    tag = "trainer",
+    params(
+        ("id" = String, Path, description = "Trainer id or UUID"),
+        ("pokemon_id" = i32, Path, description = "Pokemon id")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Pokemon attached to the trainer"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not the caller's trainer, or X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "No such trainer or pokemon", body = ErrorBody),
+        (status = 409, description = "Pokemon is already attached, or the team is full", body = ErrorBody)
+    )
+)]
+async fn attach_pokemon(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path((key, pokemon_id)): Path<(IdOrUuid, i32)>,
+) -> Result<StatusCode, ApiError> {
+    let trainer_id = resolve_trainer(&state, key).await?;
+    me.require_owner(trainer_id)?;
+
+    TrainerService::new(&state)
+        .attach(trainer_id, pokemon_id)
+        .await?;
+
+    Ok(StatusCode::CREATED)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trainer/{id}/pokemon/{pokemon_id}",
+    tag = "trainer",
+    params(
+        ("id" = String, Path, description = "Trainer id or UUID"),
+        ("pokemon_id" = i32, Path, description = "Pokemon id")
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Pokemon detached from the trainer"),
+        (status = 401, description = "Missing or invalid bearer token", body = ErrorBody),
+        (status = 403, description = "Not the caller's trainer, or X-Impersonate-Trainer sent by a non-admin", body = ErrorBody),
+        (status = 404, description = "Pokemon is not attached to the trainer", body = ErrorBody)
+    )
+)]
+async fn detach_pokemon(
+    me: CurrentTrainer,
+    State(state): State<Arc<AppState>>,
+    Path((key, pokemon_id)): Path<(IdOrUuid, i32)>,
+) -> Result<StatusCode, ApiError> {
+    let trainer_id = resolve_trainer(&state, key).await?;
+    me.require_owner(trainer_id)?;
+
+    match TrainerService::new(&state)
+        .detach(trainer_id, pokemon_id)
+        .await?
+    {
+        true => Ok(StatusCode::OK),
+        false => Err(ApiError::NotFound),
+    }
+}
+
+/// The id of the trainer `key` names, or 404.
+pub(crate) async fn resolve_trainer(state: &AppState, key: IdOrUuid) -> Result<i32, ApiError> {
+    match state.trainers.resolve(key).await {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(ApiError::NotFound),
+        Err(e) => {
+            tracing::error!("Failed to look up trainer: {}", e);
+
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_app;
+    use crate::case::FieldCase;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, send_in, state, token};
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    const CAMEL: FieldCase = FieldCase::Camel;
+
+    /// A trainer with one pokemon, and a second pokemon to move to.
+    fn repo() -> (Arc<InMemoryRepository>, i32, [i32; 2]) {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let eevee = repo.add_pokemon("Eevee", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+
+        (repo, ash, [pikachu, eevee])
+    }
+
+    #[tokio::test]
+    async fn get_trainer_answers_404_for_unknown_ids() {
+        let (repo, ash, _) = repo();
+        let app = build_app(state(repo));
+
+        let (status, body) =
+            send(&app, Method::GET, &format!("/trainer/{}", ash), None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["trainer"]["name"], "Ash");
+        assert_eq!(body["trainer"]["pokemon"][0]["name"], "Pikachu");
+
+        let (status, body) = send(&app, Method::GET, "/trainer/999", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], 404);
+    }
+
+    #[tokio::test]
+    async fn trainers_and_pokemon_are_found_by_uuid() {
+        let (repo, ash, [pikachu, _]) = repo();
+        let app = build_app(state(repo));
+
+        let (_, body) = send(&app, Method::GET, &format!("/trainer/{}", ash), None, None).await;
+        let trainer_uuid = body["trainer"]["uuid"].as_str().unwrap().to_string();
+        let pokemon_uuid = body["trainer"]["pokemon"][0]["uuid"].as_str().unwrap();
+
+        let uri = format!("/trainer/{}/team", trainer_uuid);
+        let (status, body) = send(&app, Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["trainer_id"], ash);
+        let uri = format!("/pokemon/{}", pokemon_uuid);
+        let (status, body) = send(&app, Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["pokemon_id"], pikachu);
+
+        let unknown = format!("/trainer/{}", uuid::Uuid::nil());
+        let (status, _) = send(&app, Method::GET, &unknown, None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn create_trainer_requires_an_admin_token() {
+        let (repo, ash, _) = repo();
+        let admin = repo.add_user(None, true);
+        let player = repo.add_user(Some(ash), false);
+        let state = state(repo);
+        let (admin, player) = (token(&state, admin), token(&state, player));
+        let app = build_app(state);
+        let misty = json!({"name": "Misty", "gym_leader": true});
+
+        let (status, _) = send(&app, Method::POST, "/trainer", None, Some(misty.clone())).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            "/trainer",
+            Some(&player),
+            Some(misty.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], 403);
+
+        let (status, _) = send(&app, Method::POST, "/trainer", Some(&admin), Some(misty)).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&app, Method::GET, "/trainer", None, None).await;
+        assert_eq!(body["total_count"], 2);
+        assert_eq!(body["trainers"][1]["name"], "Misty");
+    }
+
+    #[tokio::test]
+    async fn patch_trainer_replaces_the_team() {
+        let (repo, ash, [_, eevee]) = repo();
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo.clone());
+        let token = token(&state, user);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let body = json!({"pokemon": [eevee]});
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "Ash");
+        assert_eq!(body["pokemon"][0]["name"], "Eevee");
+        assert_eq!(repo.team_of(ash), Some(vec![eevee]));
+    }
+
+    #[tokio::test]
+    async fn trainers_are_filtered_by_metadata() {
+        let (repo, ash, _) = repo();
+        repo.add_trainer("Misty", &[]);
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo.clone());
+        let token = token(&state, user);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let metadata = json!({"metadata": {"league": "kanto", "badges": 8}});
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(metadata)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["metadata"]["badges"], 8);
+
+        let (_, body) = send(&app, Method::GET, "/trainer?meta.league=kanto", None, None).await;
+        assert_eq!(body["total_count"], 1);
+        assert_eq!(body["trainers"][0]["name"], "Ash");
+        let (_, body) = send(&app, Method::GET, "/trainer?meta.league=johto", None, None).await;
+        assert_eq!(body["total_count"], 0);
+
+        let cleared = json!({"metadata": null});
+        let (_, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(cleared)).await;
+        assert_eq!(body["metadata"], json!({}));
+
+        let too_many: serde_json::Map<_, _> = (0..33).map(|i| (i.to_string(), json!(i))).collect();
+        let body = json!({"metadata": too_many});
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "metadata");
+    }
+
+    #[tokio::test]
+    async fn metadata_keys_keep_their_case_for_camel_case_clients() {
+        let (repo, ash, _) = repo();
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo.clone()).with_field_case(CAMEL);
+        let token = token(&state, user);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let metadata = json!({"metadata": {"favoriteColor": "red", "home_town": "Pallet"}});
+        let (status, body) = send_in(
+            CAMEL,
+            &app,
+            Method::PATCH,
+            &uri,
+            Some(&token),
+            Some(metadata),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let stored = json!({"favoriteColor": "red", "home_town": "Pallet"});
+        assert_eq!(body["metadata"], stored);
+        let (_, body) = send_in(CAMEL, &app, Method::GET, &uri, None, None).await;
+        assert_eq!(body["trainer"]["metadata"], stored);
+
+        for filter in ["meta.favoriteColor=red", "meta.home_town=Pallet"] {
+            let uri = format!("/trainer?{}", filter);
+            let (_, body) = send_in(CAMEL, &app, Method::GET, &uri, None, None).await;
+            assert_eq!(body["totalCount"], 1, "{}", filter);
+        }
+        let uri = "/trainer?meta.favorite_color=red";
+        let (_, body) = send_in(CAMEL, &app, Method::GET, uri, None, None).await;
+        assert_eq!(body["totalCount"], 0);
+    }
+
+    #[tokio::test]
+    async fn released_names_are_kept_for_their_trainer() {
+        let (repo, ash, _) = repo();
+        let misty = repo.add_trainer("Misty", &[]);
+        let state = state(repo.clone());
+        let ash_token = token(&state, repo.add_user(Some(ash), false));
+        let misty_token = token(&state, repo.add_user(Some(misty), false));
+        let admin = token(&state, repo.add_user(None, true));
+        let app = build_app(state);
+        let (ash_uri, misty_uri) = (format!("/trainer/{}", ash), format!("/trainer/{}", misty));
+
+        let red = json!({"name": "Red"});
+        let (status, _) = send(&app, Method::PATCH, &ash_uri, Some(&ash_token), Some(red)).await;
+        assert_eq!(status, StatusCode::OK);
+        let uri = format!("{}/name-history", ash_uri);
+        let (status, body) = send(&app, Method::GET, &uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["name"], "Red");
+        assert_eq!(body["past_names"][0]["name"], "Ash");
+
+        // Held against everyone else, whatever the case, but not Ash.
+        let taken = json!({"name": "ASH"});
+        let (status, _) = send(
+            &app,
+            Method::PATCH,
+            &misty_uri,
+            Some(&misty_token),
+            Some(taken),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let ash_again = json!({"name": "Ash"});
+        let (status, _) = send(
+            &app,
+            Method::PATCH,
+            &ash_uri,
+            Some(&ash_token),
+            Some(ash_again),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let new_red = json!({"name": "red", "gym_leader": false});
+        let (status, _) = send(&app, Method::POST, "/trainer", Some(&admin), Some(new_red)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let twice =
+            json!([{"name": "Gary", "gym_leader": false}, {"name": "gary", "gym_leader": false}]);
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            "/trainer/bulk",
+            Some(&admin),
+            Some(twice),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"], "[1]: name repeats an earlier item");
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_may_change_a_trainer() {
+        let (repo, ash, [pikachu, eevee]) = repo();
+        let gary = repo.add_trainer("Gary", &[]);
+        let rival = repo.add_user(Some(gary), false);
+        let state = state(repo.clone());
+        let token = token(&state, rival);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let body = json!({"pokemon": [eevee]});
+        let (status, _) = send(&app, Method::PATCH, &uri, Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let attach = format!("{}/pokemon/{}", uri, eevee);
+        let (status, _) = send(&app, Method::POST, &attach, Some(&token), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(repo.team_of(ash), Some(vec![pikachu]));
+    }
+
+    #[tokio::test]
+    async fn patch_trainer_rejects_unknown_pokemon() {
+        let (repo, ash, [pikachu, _]) = repo();
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo.clone());
+        let token = token(&state, user);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let body = json!({"name": "Red", "pokemon": [999]});
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "unknown pokemon: 999");
+        assert_eq!(repo.team_of(ash), Some(vec![pikachu]));
+    }
+
+    #[tokio::test]
+    async fn malformed_requests_name_the_field_at_fault() {
+        let (repo, ash, _) = repo();
+        let user = repo.add_user(Some(ash), true);
+        let state = state(repo);
+        let token = token(&state, user);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let (status, body) = send(&app, Method::GET, "/trainer/abc", None, None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], 422);
+        assert_eq!(body["fields"][0]["field"], "id");
+
+        let body = json!({"pokemon": ["pikachu"]});
+        let (status, body) = send(&app, Method::PATCH, &uri, Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "pokemon[0]");
+
+        let body = json!({"gym_leader": true});
+        let (status, body) = send(&app, Method::POST, "/trainer", Some(&token), Some(body)).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body["fields"],
+            json!([{"field": "name", "message": "is required"}])
+        );
+
+        let forced = format!("{}?force=yes", uri);
+        let (status, body) = send(&app, Method::DELETE, &forced, Some(&token), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["fields"][0]["field"], "force");
+    }
+
+    #[tokio::test]
+    async fn delete_trainer_keeps_a_trainer_with_a_team_unless_forced() {
+        let (repo, ash, _) = repo();
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo.clone());
+        let token = token(&state, user);
+        let app = build_app(state);
+        let uri = format!("/trainer/{}", ash);
+
+        let (status, _) = send(&app, Method::DELETE, &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(repo.team_of(ash).is_some());
+
+        let forced = format!("{}?force=true", uri);
+        let (status, _) = send(&app, Method::DELETE, &forced, Some(&token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(repo.team_of(ash), None);
+    }
+
+    #[tokio::test]
+    async fn attaching_a_pokemon_publishes_pokemon_caught() {
+        let (repo, ash, [pikachu, eevee]) = repo();
+        let user = repo.add_user(Some(ash), false);
+        let state = state(repo.clone());
+        let token = token(&state, user);
+        let mut events = state.events.subscribe();
+        let app = build_app(state);
+
+        let uri = format!("/trainer/{}/pokemon/{}", ash, pikachu);
+        let (status, _) = send(&app, Method::POST, &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let uri = format!("/trainer/{}/pokemon/{}", ash, eevee);
+        let (status, _) = send(&app, Method::POST, &uri, Some(&token), None).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let event = serde_json::to_value(events.try_recv().unwrap()).unwrap();
+        assert_eq!(
+            event,
+            json!({"type": "PokemonCaught", "trainer_id": ash, "pokemon_id": eevee})
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn trainer_pages_link_to_each_other() {
+        let repo = Arc::new(InMemoryRepository::default());
+        for name in ["Ash", "Brock", "Misty"] {
+            repo.add_trainer(name, &[]);
+        }
+        let app = build_app(state(repo));
+
+        let response = app
+            .oneshot(
+                Request::get("/trainer?per_page=1&page=2")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::LINK],
+            "</trainer?per_page=1&page=1>; rel=\"first\", \
+             </trainer?per_page=1&page=1>; rel=\"prev\", \
+             </trainer?per_page=1&page=3>; rel=\"next\", \
+             </trainer?per_page=1&page=3>; rel=\"last\""
+        );
+    }
+}
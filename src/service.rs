@@ -0,0 +1,1020 @@
+use crate::ai::{Difficulty, Strategy};
+use crate::battle::{self, Fighter, Outcome, RandomAgent, Rng};
+use crate::db::DbError;
+use crate::error::ApiError;
+use crate::events::Entity;
+use crate::models::{
+    CatalogSnapshot, Description, EntityKey, Metadata, QuizAnswer, QuizQuestion, QuizScore,
+    Version, VersionDiff,
+};
+use crate::quiz::{is_match, mask_name};
+use crate::repository::{
+    AiTrainer, Attach, Delete, NewBattle, StoreBattle, TrainerChanges, Update,
+};
+use crate::validation::{FieldErrors, MAX_NAME_LEN};
+use crate::AppState;
+use serde::Deserialize;
+
+/// Trainer writes as every API makes them. Checks a change against the
+/// business rules, applies it through `AppState::trainers` and announces
+/// it, so the REST and GraphQL handlers only extract, authorize and
+/// respond.
+pub(crate) struct TrainerService<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> TrainerService<'a> {
+    pub(crate) fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    /// Inserts a trainer and returns its id.
+    pub(crate) async fn create(&self, name: &str, gym_leader: bool) -> Result<i32, ApiError> {
+        let mut errors = FieldErrors::default();
+        errors.name("name", name, MAX_NAME_LEN);
+        errors.into_result()?;
+
+        let trainer_id = self
+            .state
+            .trainers
+            .create(name, gym_leader)
+            .await
+            .map_err(|e| failed("create trainer", e))?;
+        self.state.created(Entity::Trainer, trainer_id);
+
+        Ok(trainer_id)
+    }
+
+    /// Applies `changes` to the trainer. A new team may not list a pokemon
+    /// twice or hold more than `Limits::max_team_size` of them.
+    pub(crate) async fn update(&self, id: i32, changes: TrainerChanges) -> Result<(), ApiError> {
+        let max_team_size = self.state.limits.max_team_size;
+        let mut errors = FieldErrors::default();
+        if let Some(name) = &changes.name {
+            errors.name("name", name, MAX_NAME_LEN);
+        }
+        if let Some(team) = &changes.team {
+            let mut ids = team.clone();
+            ids.sort_unstable();
+            ids.dedup();
+            if ids.len() < team.len() {
+                errors.add("pokemon", "must not list a pokemon twice");
+            }
+            if team.len() > max_team_size {
+                errors.add(
+                    "pokemon",
+                    format!("must have at most {} pokemon", max_team_size),
+                );
+            }
+        }
+        if let Some(metadata) = &changes.metadata {
+            errors.metadata("metadata", metadata);
+        }
+        errors.into_result()?;
+
+        match self.state.trainers.update(id, changes).await {
+            Ok(Update::Updated) => {
+                self.state.updated(Entity::Trainer, id);
+
+                Ok(())
+            }
+            Ok(Update::NotFound) => Err(ApiError::NotFound),
+            Ok(Update::UnknownPokemon(unknown)) => Err(ApiError::BadRequest(format!(
+                "unknown pokemon: {}",
+                unknown
+                    .iter()
+                    .map(i32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+            Err(e) => Err(failed("update trainer", e)),
+        }
+    }
+
+    /// Deletes the trainer, releasing its pokemon only when `force` is set.
+    /// Returns whether it existed.
+    pub(crate) async fn delete(&self, id: i32, force: bool) -> Result<bool, ApiError> {
+        match self.state.trainers.delete(id, force).await {
+            Ok(Delete::Deleted) => {
+                self.state.deleted(Entity::Trainer, id);
+
+                Ok(true)
+            }
+            Ok(Delete::NotFound) => Ok(false),
+            Ok(Delete::HasTeam(team_size)) => Err(ApiError::Conflict(format!(
+                "trainer still has {} pokemon; delete with force to release them",
+                team_size
+            ))),
+            Err(e) => Err(failed("delete trainer", e)),
+        }
+    }
+
+    /// Adds the pokemon to the trainer's team, unless it is already on it
+    /// or the team is full.
+    pub(crate) async fn attach(&self, trainer_id: i32, pokemon_id: i32) -> Result<(), ApiError> {
+        match self
+            .state
+            .trainers
+            .exists_with_pokemon(trainer_id, pokemon_id)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => return Err(ApiError::NotFound),
+            Err(e) => return Err(failed("look up trainer and pokemon", e)),
+        }
+
+        let max_team_size = self.state.limits.max_team_size;
+        match self
+            .state
+            .trainers
+            .attach(trainer_id, pokemon_id, max_team_size)
+            .await
+        {
+            Ok(Attach::Attached) => {
+                self.state.caught(trainer_id, pokemon_id);
+
+                Ok(())
+            }
+            Ok(Attach::AlreadyAttached) => Err(ApiError::Conflict(
+                "pokemon is already attached to this trainer".to_string(),
+            )),
+            Ok(Attach::TeamFull) => Err(ApiError::Conflict(format!(
+                "team is full; a trainer may have at most {} pokemon",
+                max_team_size
+            ))),
+            Err(e) => Err(failed("attach pokemon", e)),
+        }
+    }
+
+    /// Releases the pokemon. Returns whether it was on the team.
+    pub(crate) async fn detach(&self, trainer_id: i32, pokemon_id: i32) -> Result<bool, ApiError> {
+        self.state
+            .trainers
+            .detach(trainer_id, pokemon_id)
+            .await
+            .map_err(|e| failed("detach pokemon", e))
+    }
+}
+
+/// The versions of pokemon and abilities. Each write that may change what a
+/// version keeps records one afterwards; a rollback writes an old version
+/// back as the newest.
+pub(crate) struct CatalogHistory<'a> {
+    state: &'a AppState,
+}
+
+/// What a pokemon version keeps.
+#[derive(Deserialize)]
+struct PokemonSnapshot {
+    name: String,
+    region_id: i32,
+    metadata: Metadata,
+    description: Option<String>,
+    lore: Option<String>,
+}
+
+/// What an ability version keeps.
+#[derive(Deserialize)]
+struct AbilitySnapshot {
+    damage: i32,
+    status_effect: String,
+    description: Option<String>,
+    lore: Option<String>,
+}
+
+impl<'a> CatalogHistory<'a> {
+    pub(crate) fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    /// Records the pokemon's next version if it changed. The write before it
+    /// already went through, so a failure is logged rather than returned.
+    pub(crate) async fn record_pokemon(&self, pokemon_id: i32) {
+        if let Err(e) = self.state.pokemon.record_version(pokemon_id).await {
+            tracing::error!("Failed to record pokemon version: {}", e);
+        }
+    }
+
+    /// Records the ability's next version if it changed, as `record_pokemon`
+    /// does.
+    pub(crate) async fn record_ability(&self, ability_id: i32) {
+        if let Err(e) = self.state.abilities.record_version(ability_id).await {
+            tracing::error!("Failed to record ability version: {}", e);
+        }
+    }
+
+    /// The versions of the pokemon, oldest first, after its id.
+    pub(crate) async fn pokemon_versions(
+        &self,
+        key: &EntityKey,
+    ) -> Result<(i32, Vec<Version>), ApiError> {
+        match self.state.pokemon.versions(key).await {
+            Ok(Some(versions)) => Ok(versions),
+            Ok(None) => Err(ApiError::NotFound),
+            Err(e) => Err(failed("fetch pokemon versions", e)),
+        }
+    }
+
+    /// The versions of the ability, oldest first, after its id.
+    pub(crate) async fn ability_versions(
+        &self,
+        name: &str,
+    ) -> Result<(i32, Vec<Version>), ApiError> {
+        match self.state.abilities.versions(name).await {
+            Ok(Some(versions)) => Ok(versions),
+            Ok(None) => Err(ApiError::NotFound),
+            Err(e) => Err(failed("fetch ability versions", e)),
+        }
+    }
+
+    /// Restores the pokemon's name, region, metadata, description and lore
+    /// as of `version`, and returns the version that records it.
+    pub(crate) async fn rollback_pokemon(
+        &self,
+        key: &EntityKey,
+        version: i32,
+    ) -> Result<Version, ApiError> {
+        let (pokemon_id, versions) = self.pokemon_versions(key).await?;
+        let snapshot: PokemonSnapshot = restorable(find_version(&versions, version)?)?;
+
+        // A region deleted since then trips the foreign key and maps to 409.
+        match self
+            .state
+            .pokemon
+            .update(
+                pokemon_id,
+                Some(&snapshot.name),
+                snapshot.region_id,
+                Some(&snapshot.metadata),
+            )
+            .await
+        {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(ApiError::NotFound),
+            Err(e) => return Err(failed("roll back pokemon", e)),
+        }
+        let description = Description {
+            description: snapshot.description,
+            lore: snapshot.lore,
+        };
+        self.state
+            .pokemon
+            .set_description(&EntityKey::Id(pokemon_id), &description)
+            .await
+            .map_err(|e| failed("roll back pokemon description", e))?;
+        self.state.updated(Entity::Pokemon, pokemon_id);
+
+        match self.state.pokemon.record_version(pokemon_id).await {
+            Ok(Some(latest)) => Ok(latest),
+            Ok(None) => Err(ApiError::NotFound),
+            Err(e) => Err(failed("record pokemon version", e)),
+        }
+    }
+
+    /// Restores the ability's damage, status effect, description and lore
+    /// as of `version`, and returns the version that records it. A staged
+    /// rebalance is dropped, as an immediate update drops it.
+    pub(crate) async fn rollback_ability(
+        &self,
+        name: &str,
+        version: i32,
+    ) -> Result<Version, ApiError> {
+        let (ability_id, versions) = self.ability_versions(name).await?;
+        let snapshot: AbilitySnapshot = restorable(find_version(&versions, version)?)?;
+
+        self.state
+            .abilities
+            .apply(name, snapshot.damage, &snapshot.status_effect)
+            .await
+            .map_err(|e| failed("roll back ability", e))?;
+        let description = Description {
+            description: snapshot.description,
+            lore: snapshot.lore,
+        };
+        self.state
+            .abilities
+            .set_description(name, &description)
+            .await
+            .map_err(|e| failed("roll back ability description", e))?;
+        self.state.updated(Entity::Ability, ability_id);
+
+        match self.state.abilities.record_version(ability_id).await {
+            Ok(Some(latest)) => Ok(latest),
+            Ok(None) => Err(ApiError::NotFound),
+            Err(e) => Err(failed("record ability version", e)),
+        }
+    }
+}
+
+/// The changes from version `from` to version `to` of `versions`.
+pub(crate) fn version_diff(
+    versions: &[Version],
+    from: i32,
+    to: i32,
+) -> Result<VersionDiff, ApiError> {
+    let changes = find_version(versions, from)?.diff(find_version(versions, to)?);
+
+    Ok(VersionDiff { from, to, changes })
+}
+
+fn find_version(versions: &[Version], version: i32) -> Result<&Version, ApiError> {
+    versions
+        .iter()
+        .find(|v| v.version == version)
+        .ok_or(ApiError::NotFound)
+}
+
+/// The snapshot of `version` as what it restores. Versions recorded before
+/// a column was added to the snapshot lack it and can't be restored.
+fn restorable<T: for<'de> Deserialize<'de>>(version: &Version) -> Result<T, ApiError> {
+    serde_json::from_value(serde_json::Value::Object(version.snapshot.clone())).map_err(|_| {
+        ApiError::Conflict(format!(
+            "version {} predates fields a rollback restores",
+            version.version
+        ))
+    })
+}
+
+/// Battles as every API fights them. Loads the fighters, plays the battle
+/// out, stores it against the trainer's hourly quota and announces it.
+pub(crate) struct BattleService<'a> {
+    state: &'a AppState,
+}
+
+/// A battle as fought and stored.
+pub(crate) struct Battle {
+    pub(crate) battle_id: i32,
+    pub(crate) pokemon_id: i32,
+    pub(crate) opponent_id: i32,
+    pub(crate) seed: u64,
+    /// The AI trainer's difficulty, or `None` without one.
+    pub(crate) difficulty: Option<Difficulty>,
+    /// What the challenger earned, or `None` without an AI trainer.
+    pub(crate) reward: Option<i32>,
+    pub(crate) outcome: Outcome,
+}
+
+/// How a replayed battle compares with the stored one.
+pub(crate) struct Verification {
+    pub(crate) consistent: bool,
+    pub(crate) winner: Option<i32>,
+    pub(crate) replayed_winner: Option<i32>,
+    /// The first turn, counted from 1, that came out differently.
+    pub(crate) first_mismatch: Option<u32>,
+}
+
+impl<'a> BattleService<'a> {
+    pub(crate) fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    /// Battles `pokemon_id` against `opponent_id`, both using random
+    /// abilities, on behalf of `trainer_id`.
+    pub(crate) async fn fight(
+        &self,
+        trainer_id: i32,
+        pokemon_id: i32,
+        opponent_id: i32,
+        seed: Option<u64>,
+        snapshot_id: Option<i32>,
+    ) -> Result<Battle, ApiError> {
+        let mut errors = FieldErrors::default();
+        if pokemon_id == opponent_id {
+            errors.add("opponent_id", "must differ from pokemon_id");
+        }
+        errors.into_result()?;
+
+        let fighters: [Fighter; 2] = self
+            .fighters(snapshot_id, &[pokemon_id, opponent_id])
+            .await?
+            .try_into()
+            .map_err(|_| ApiError::NotFound)?;
+
+        let seed = seed.unwrap_or_else(|| self.state.seeds.seed());
+        let [a, b] = &fighters;
+        let outcome = battle::simulate(a, b, &mut Rng::new(seed));
+
+        let battle = NewBattle {
+            trainer_id,
+            seed,
+            snapshot_id,
+            ai_trainer_id: None,
+            ai_strategy: None,
+            ai_difficulty: None,
+            reward: None,
+            fighters,
+            winner: outcome.winner,
+            turns: outcome.turns.clone(),
+        };
+        let battle_id = self.store(battle).await?;
+
+        Ok(Battle {
+            battle_id,
+            pokemon_id,
+            opponent_id,
+            seed,
+            difficulty: None,
+            reward: None,
+            outcome,
+        })
+    }
+
+    /// Battles `pokemon_id` against computer-controlled trainer
+    /// `ai_trainer_id`, on behalf of `trainer_id`. The AI trainer's strategy
+    /// picks the pokemon it sends out, its difficulty scales that pokemon's
+    /// abilities against `trainer_id`'s whole team, and a win credits the
+    /// difficulty's reward to `trainer_id`.
+    pub(crate) async fn fight_ai(
+        &self,
+        trainer_id: i32,
+        pokemon_id: i32,
+        ai_trainer_id: i32,
+        seed: Option<u64>,
+        snapshot_id: Option<i32>,
+    ) -> Result<Battle, ApiError> {
+        let (strategy, difficulty, team) = match self
+            .state
+            .battles
+            .ai_trainer(ai_trainer_id)
+            .await
+            .map_err(|e| failed("fetch AI trainer", e))?
+        {
+            Some(AiTrainer {
+                strategy: Some(strategy),
+                difficulty: Some(difficulty),
+                team,
+            }) => (strategy, difficulty, team),
+            Some(_) => {
+                tracing::error!(
+                    trainer_id = ai_trainer_id,
+                    "AI trainer has an unknown strategy or difficulty"
+                );
+
+                return Err(ApiError::DatabaseError);
+            }
+            None => return Err(ApiError::NotFound),
+        };
+
+        let mut ids = vec![pokemon_id];
+        ids.extend(team.iter().filter(|&&id| id != pokemon_id));
+        let mut fighters = self.fighters(snapshot_id, &ids).await?;
+        if fighters.first().map(|f| f.pokemon_id) != Some(pokemon_id) {
+            return Err(ApiError::NotFound);
+        }
+        let mut team = fighters.split_off(1);
+        if team.is_empty() {
+            return Err(ApiError::Conflict(
+                "the AI trainer has no pokemon to send out".to_string(),
+            ));
+        }
+
+        let seed = seed.unwrap_or_else(|| self.state.seeds.seed());
+        // Picked with its own stream so that the battle draws the same numbers
+        // from `seed` as any other, and replays the same in verify.
+        let pick = difficulty.choose_fighter(strategy, &team, &fighters[0], &mut Rng::new(!seed));
+        let challenger = fighters.swap_remove(0);
+        let mut opponent = team.swap_remove(pick);
+        // The scaled abilities are stored with the battle, so verify replays
+        // with them.
+        let challengers = self
+            .team_of(trainer_id, challenger.clone(), snapshot_id)
+            .await?;
+        let power = difficulty.scale(&mut opponent, &challengers);
+        tracing::debug!(
+            trainer_id = ai_trainer_id,
+            pokemon_id = opponent.pokemon_id,
+            power,
+            "Scaled AI pokemon"
+        );
+
+        let opponent_id = opponent.pokemon_id;
+        let fighters = [challenger, opponent];
+        let [a, b] = &fighters;
+        let outcome = battle::simulate_with(
+            [(a, &RandomAgent), (b, &difficulty.agent(strategy))],
+            &mut Rng::new(seed),
+        );
+        let reward = difficulty.reward(outcome.winner == Some(pokemon_id));
+
+        let battle = NewBattle {
+            trainer_id,
+            seed,
+            snapshot_id,
+            ai_trainer_id: Some(ai_trainer_id),
+            ai_strategy: Some(strategy),
+            ai_difficulty: Some(difficulty),
+            reward: Some(reward),
+            fighters,
+            winner: outcome.winner,
+            turns: outcome.turns.clone(),
+        };
+        let battle_id = self.store(battle).await?;
+
+        Ok(Battle {
+            battle_id,
+            pokemon_id,
+            opponent_id,
+            seed,
+            difficulty: Some(difficulty),
+            reward: Some(reward),
+            outcome,
+        })
+    }
+
+    /// Replays stored battle `battle_id` from its stored seed and fighters.
+    pub(crate) async fn verify(&self, battle_id: i32) -> Result<Verification, ApiError> {
+        let stored = self
+            .state
+            .battles
+            .battle(battle_id)
+            .await
+            .map_err(|e| failed("fetch battle", e))?
+            .ok_or(ApiError::NotFound)?;
+
+        // The opponent of an AI trainer plays by the strategy and difficulty
+        // stored with the battle. Without them it picked at random, and before
+        // difficulties it played the bare strategy, which is how `Hard` plays.
+        let strategy = match stored.ai_strategy.as_deref() {
+            Some(name) => Strategy::from_name(name),
+            None => Some(Strategy::Random),
+        };
+        let difficulty = match stored.ai_difficulty.as_deref() {
+            Some(name) => Difficulty::from_name(name),
+            None => Some(Difficulty::Hard),
+        };
+        let agent = strategy.zip(difficulty).map(|(s, d)| d.agent(s));
+
+        // Fighters or a strategy that no longer parse count as altered, like any
+        // other field.
+        let outcome = match (stored.fighters, agent) {
+            (Some([a, b]), Some(agent)) => Some(battle::simulate_with(
+                [(&a, &RandomAgent), (&b, &agent)],
+                &mut Rng::new(stored.seed),
+            )),
+            _ => None,
+        };
+        let replayed_winner = outcome.as_ref().and_then(|o| o.winner);
+        let replayed_turns: Vec<serde_json::Value> = outcome
+            .map(|o| {
+                o.turns
+                    .iter()
+                    .map(|t| serde_json::to_value(t).expect("a turn serializes to JSON"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let stored_turns = &stored.turns;
+        let first_mismatch = (0..stored_turns.len().max(replayed_turns.len()))
+            .find(|&i| stored_turns.get(i) != replayed_turns.get(i))
+            .map(|i| i as u32 + 1);
+        let consistent = first_mismatch.is_none() && stored.winner == replayed_winner;
+        if !consistent {
+            tracing::warn!(battle_id, ?first_mismatch, "Battle failed to verify");
+        }
+
+        Ok(Verification {
+            consistent,
+            winner: stored.winner,
+            replayed_winner,
+            first_mismatch,
+        })
+    }
+
+    /// Pins every published pokemon as it is now.
+    pub(crate) async fn take_snapshot(&self) -> Result<CatalogSnapshot, ApiError> {
+        let snapshot = self
+            .state
+            .battles
+            .take_snapshot()
+            .await
+            .map_err(|e| failed("take catalog snapshot", e))?;
+        tracing::info!(
+            snapshot_id = snapshot.snapshot_id,
+            pokemon = snapshot.pokemon,
+            "Took catalog snapshot"
+        );
+
+        Ok(snapshot)
+    }
+
+    pub(crate) async fn snapshot(&self, snapshot_id: i32) -> Result<CatalogSnapshot, ApiError> {
+        self.state
+            .battles
+            .snapshot(snapshot_id)
+            .await
+            .map_err(|e| failed("fetch catalog snapshot", e))?
+            .ok_or(ApiError::NotFound)
+    }
+
+    async fn fighters(
+        &self,
+        snapshot_id: Option<i32>,
+        ids: &[i32],
+    ) -> Result<Vec<Fighter>, ApiError> {
+        self.state
+            .battles
+            .fighters(snapshot_id, ids)
+            .await
+            .map_err(|e| failed("fetch battle pokemon", e))
+    }
+
+    /// `trainer_id`'s team as fighters, led by `challenger`, which counts
+    /// towards it whether or not the trainer has it.
+    async fn team_of(
+        &self,
+        trainer_id: i32,
+        challenger: Fighter,
+        snapshot_id: Option<i32>,
+    ) -> Result<Vec<Fighter>, ApiError> {
+        let mut teams = self
+            .state
+            .trainers
+            .teams(&[trainer_id])
+            .await
+            .map_err(|e| failed("fetch team", e))?;
+        let ids: Vec<i32> = teams
+            .remove(&trainer_id)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| p.pokemon_id)
+            .filter(|&id| id != challenger.pokemon_id)
+            .collect();
+
+        let mut team = vec![challenger];
+        team.extend(self.fighters(snapshot_id, &ids).await?);
+        Ok(team)
+    }
+
+    /// Stores `battle` and announces it, or answers 429 once its trainer has
+    /// fought `Limits::battles_per_hour` battles within the last hour, with a
+    /// `Retry-After` of when the oldest of them stops counting.
+    async fn store(&self, battle: NewBattle) -> Result<i32, ApiError> {
+        let trainer_id = battle.trainer_id;
+        let winner = battle.winner;
+        let per_hour = self.state.limits.battles_per_hour;
+        match self.state.battles.store(battle, per_hour).await {
+            Ok(StoreBattle::Stored(battle_id)) => {
+                self.state.battle_finished(battle_id, trainer_id, winner);
+
+                Ok(battle_id)
+            }
+            Ok(StoreBattle::QuotaUsedUp(wait)) => {
+                tracing::info!(trainer_id, per_hour, "Battle quota used up");
+
+                Err(ApiError::TooManyRequests(wait))
+            }
+            Err(e) => Err(failed("store battle", e)),
+        }
+    }
+}
+
+/// The "Who's that pokemon?" quiz. Questions are kept server-side, so the
+/// answer is checked against the pokemon the trainer was actually shown.
+pub(crate) struct QuizService<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> QuizService<'a> {
+    pub(crate) fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    /// Asks `trainer_id` about a published pokemon with a sprite, picked at
+    /// random.
+    pub(crate) async fn next(&self, trainer_id: i32) -> Result<QuizQuestion, ApiError> {
+        let question = self
+            .state
+            .quiz
+            .ask(trainer_id, self.state.seeds.seed())
+            .await
+            .map_err(|e| failed("ask quiz question", e))?
+            .ok_or(ApiError::NotFound)?;
+
+        Ok(QuizQuestion {
+            question_id: question.question_id,
+            masked_name: mask_name(&question.name),
+            sprite_url: question.sprite_url,
+        })
+    }
+
+    /// Checks `answer` to question `question_id`, which must have been asked
+    /// of `trainer_id` and not answered yet, and scores it.
+    pub(crate) async fn answer(
+        &self,
+        trainer_id: i32,
+        question_id: i32,
+        answer: &str,
+    ) -> Result<QuizAnswer, ApiError> {
+        let mut errors = FieldErrors::default();
+        if answer.trim().is_empty() {
+            errors.add("answer", "must not be empty");
+        }
+        errors.into_result()?;
+
+        let question = self
+            .state
+            .quiz
+            .question(question_id, trainer_id)
+            .await
+            .map_err(|e| failed("fetch quiz question", e))?
+            .ok_or(ApiError::NotFound)?;
+        let already_answered =
+            || ApiError::Conflict("the question was already answered".to_string());
+        if question.answered {
+            return Err(already_answered());
+        }
+
+        let correct = is_match(answer, &question.name);
+        let score = self
+            .state
+            .quiz
+            .answer(question_id, correct)
+            .await
+            .map_err(|e| failed("record quiz answer", e))?
+            .ok_or_else(already_answered)?;
+
+        Ok(QuizAnswer {
+            correct,
+            pokemon: question.name,
+            score,
+        })
+    }
+
+    pub(crate) async fn leaderboard(&self, limit: i64) -> Result<Vec<QuizScore>, ApiError> {
+        self.state
+            .quiz
+            .leaderboard(limit)
+            .await
+            .map_err(|e| failed("fetch quiz leaderboard", e))
+    }
+}
+
+/// Accounts that sign in through `/auth`, each playing as a trainer of the
+/// same name.
+pub(crate) struct AccountService<'a> {
+    state: &'a AppState,
+}
+
+impl<'a> AccountService<'a> {
+    pub(crate) fn new(state: &'a AppState) -> Self {
+        Self { state }
+    }
+
+    /// Creates the account and the trainer it plays as, and returns the
+    /// account's id. A username that is taken, or that a trainer goes by,
+    /// is rejected as a `username` error and leaves nothing behind.
+    pub(crate) async fn register(
+        &self,
+        username: &str,
+        password_hash: &str,
+    ) -> Result<i32, ApiError> {
+        let taken = |message: &str| {
+            let mut errors = FieldErrors::default();
+            errors.add("username", message);
+            errors.into_result().unwrap_err()
+        };
+
+        let user_id = self
+            .state
+            .users
+            .create(username, password_hash)
+            .await
+            .map_err(|e| failed("create user", e))?
+            .ok_or_else(|| taken("is taken"))?;
+        let trainer_id = match TrainerService::new(self.state)
+            .create(username, false)
+            .await
+        {
+            Ok(trainer_id) => trainer_id,
+            Err(e) => {
+                if let Err(e) = self.state.users.delete(user_id).await {
+                    tracing::error!(
+                        "Failed to delete user {} left without a trainer: {}",
+                        user_id,
+                        e
+                    );
+                }
+
+                return Err(match e {
+                    // The only conflict creating a trainer runs into.
+                    ApiError::Conflict(_) => {
+                        taken("belongs to another trainer, or did within the last 30 days")
+                    }
+                    e => e,
+                });
+            }
+        };
+        self.state
+            .users
+            .set_trainer(user_id, trainer_id)
+            .await
+            .map_err(|e| failed("give user a trainer", e))?;
+
+        Ok(user_id)
+    }
+}
+
+fn failed(action: &str, e: DbError) -> ApiError {
+    tracing::error!("Failed to {}: {}", action, e);
+
+    e.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BattleService, TrainerService};
+    use crate::ai::{Difficulty, Strategy};
+    use crate::error::ApiError;
+    use crate::repository::{CreateAi, InMemoryRepository, TrainerChanges};
+    use crate::testing::state;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn team(team: Vec<i32>) -> TrainerChanges {
+        TrainerChanges {
+            name: None,
+            gym_leader: None,
+            team: Some(team),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn teams_are_checked_before_anything_changes() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let state = state(repo.clone());
+        let mut events = state.events.subscribe();
+        let trainers = TrainerService::new(&state);
+
+        let twice = trainers.update(ash, team(vec![pikachu, pikachu])).await;
+        assert!(matches!(twice, Err(ApiError::Validation(_))));
+        let too_many = trainers.update(ash, team((0..7).collect())).await;
+        assert!(matches!(too_many, Err(ApiError::Validation(_))));
+        let unknown = trainers.update(ash, team(vec![999])).await;
+        assert!(matches!(unknown, Err(ApiError::BadRequest(_))));
+        assert_eq!(repo.team_of(ash), Some(vec![pikachu]));
+        assert!(events.try_recv().is_err());
+
+        trainers.update(ash, team(Vec::new())).await.unwrap();
+        assert_eq!(repo.team_of(ash), Some(Vec::new()));
+        assert!(events.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn only_trainers_without_a_team_are_deleted_unforced() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let state = state(repo.clone());
+        let trainers = TrainerService::new(&state);
+
+        let kept = trainers.delete(ash, false).await;
+        assert!(matches!(kept, Err(ApiError::Conflict(_))));
+        assert!(trainers.delete(ash, true).await.unwrap());
+        assert!(!trainers.delete(ash, true).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn battles_beyond_the_hourly_quota_are_refused() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[]);
+        let eevee = repo.add_pokemon("Eevee", kanto, &[]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let misty = repo.add_trainer("Misty", &[]);
+        let mut state = state(repo);
+        state.limits.battles_per_hour = 2;
+        let mut events = state.events.subscribe();
+        let battles = BattleService::new(&state);
+
+        let same = battles.fight(ash, pikachu, pikachu, None, None).await;
+        assert!(matches!(same, Err(ApiError::Validation(_))));
+        for seed in 0..2 {
+            let fought = battles.fight(ash, pikachu, eevee, Some(seed), None).await;
+            assert!(fought.is_ok());
+            assert!(events.try_recv().is_ok());
+        }
+
+        let refused = battles.fight(ash, pikachu, eevee, Some(2), None).await;
+        let Err(ApiError::TooManyRequests(wait)) = refused else {
+            panic!("the third battle within the hour was not refused");
+        };
+        assert!(wait > Duration::from_secs(59 * 60) && wait <= Duration::from_secs(60 * 60));
+        assert!(events.try_recv().is_err());
+        // The quota is per trainer.
+        let other = battles.fight(misty, pikachu, eevee, Some(2), None).await;
+        assert!(other.is_ok());
+    }
+
+    #[tokio::test]
+    async fn only_a_win_against_an_ai_trainer_is_rewarded() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let thunder = repo.add_ability("Thunder", 150, "none");
+        let quake = repo.add_ability("Earthquake", 150, "none");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[thunder]);
+        let magikarp = repo.add_pokemon("Magikarp", kanto, &[]);
+        let onix = repo.add_pokemon("Onix", kanto, &[]);
+        let rhydon = repo.add_pokemon("Rhydon", kanto, &[quake]);
+        let ash = repo.add_trainer("Ash", &[pikachu, magikarp]);
+        let state = state(repo);
+        let ai = |name: &'static str, team: Vec<i32>| {
+            let trainers = state.trainers.clone();
+            async move {
+                match trainers
+                    .create_ai(name, Strategy::Greedy, Difficulty::Normal, team)
+                    .await
+                {
+                    Ok(CreateAi::Created(id)) => id,
+                    _ => panic!("{} was not created", name),
+                }
+            }
+        };
+        let brock = ai("Brock", vec![onix]).await;
+        let giovanni = ai("Giovanni", vec![rhydon]).await;
+        let battles = BattleService::new(&state);
+        let rewards = || async { state.trainers.get(ash).await.unwrap().unwrap().rewards };
+
+        // Onix can only struggle against Pikachu's Thunder.
+        let won = battles.fight_ai(ash, pikachu, brock, Some(1), None).await;
+        let won = won.unwrap();
+        assert_eq!(won.outcome.winner, Some(pikachu));
+        assert_eq!(won.reward, Some(Difficulty::Normal.reward(true)));
+        assert_eq!(rewards().await, Difficulty::Normal.reward(true));
+
+        // Magikarp can only struggle against Rhydon, even with its
+        // Earthquake scaled down.
+        let lost = battles
+            .fight_ai(ash, magikarp, giovanni, Some(1), None)
+            .await;
+        let lost = lost.unwrap();
+        assert_eq!(lost.outcome.winner, Some(rhydon));
+        assert_eq!(lost.reward, Some(0));
+        assert_eq!(rewards().await, Difficulty::Normal.reward(true));
+    }
+
+    #[tokio::test]
+    async fn battles_replay_as_stored_and_tampering_is_caught() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let kanto = repo.add_region("Kanto");
+        let thunderbolt = repo.add_ability("Thunderbolt", 45, "paralysis");
+        let tackle = repo.add_ability("Tackle", 20, "none");
+        let water_gun = repo.add_ability("Water Gun", 40, "none");
+        let pikachu = repo.add_pokemon("Pikachu", kanto, &[thunderbolt, tackle]);
+        let squirtle = repo.add_pokemon("Squirtle", kanto, &[water_gun, tackle]);
+        let eevee = repo.add_pokemon("Eevee", kanto, &[tackle]);
+        let ash = repo.add_trainer("Ash", &[pikachu]);
+        let mut state = state(repo.clone());
+        state.limits.battles_per_hour = 0;
+        let battles = BattleService::new(&state);
+
+        let mut fought = vec![battles
+            .fight(ash, pikachu, squirtle, Some(42), None)
+            .await
+            .unwrap()];
+        for strategy in [Strategy::Random, Strategy::Greedy, Strategy::TypeAware] {
+            for difficulty in [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard] {
+                let name = format!("{}-{}", strategy.name(), difficulty.name());
+                let Ok(CreateAi::Created(ai)) = state
+                    .trainers
+                    .create_ai(&name, strategy, difficulty, vec![squirtle, eevee])
+                    .await
+                else {
+                    panic!("{} was not created", name);
+                };
+                fought.push(
+                    battles
+                        .fight_ai(ash, pikachu, ai, Some(7), None)
+                        .await
+                        .unwrap(),
+                );
+            }
+        }
+
+        for battle in &fought {
+            let verification = battles.verify(battle.battle_id).await.unwrap();
+            assert!(
+                verification.consistent,
+                "battle {} did not replay",
+                battle.battle_id
+            );
+            assert_eq!(verification.first_mismatch, None);
+            assert_eq!(verification.replayed_winner, battle.outcome.winner);
+        }
+
+        let tampered = fought[0].battle_id;
+        repo.tamper_with_battle(tampered, 1, |turn| {
+            turn["action"]["damage"] = serde_json::json!(999);
+        });
+        let verification = battles.verify(tampered).await.unwrap();
+        assert!(!verification.consistent);
+        assert_eq!(verification.first_mismatch, Some(2));
+        assert_eq!(verification.winner, verification.replayed_winner);
+
+        assert!(matches!(battles.verify(999).await, Err(ApiError::NotFound)));
+    }
+}
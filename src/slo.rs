@@ -0,0 +1,148 @@
+use crate::AppState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Serialize, ToSchema)]
+pub struct SloTargets {
+    /// Fraction of requests that must not fail with a 5xx.
+    pub availability: f64,
+    /// Requests slower than this count against the latency objective.
+    pub latency_threshold_ms: u64,
+    /// Fraction of requests that must finish under the threshold.
+    pub latency: f64,
+}
+
+impl Default for SloTargets {
+    fn default() -> Self {
+        Self {
+            availability: 0.99,
+            latency_threshold_ms: 300,
+            latency: 0.95,
+        }
+    }
+}
+
+impl SloTargets {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            availability: std::env::var("SLO_AVAILABILITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.availability),
+            latency_threshold_ms: std::env::var("SLO_LATENCY_THRESHOLD_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.latency_threshold_ms),
+            latency: std::env::var("SLO_LATENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.latency),
+        }
+    }
+}
+
+/// How many one-minute buckets each route keeps; bounds the longest window.
+pub(crate) const ROUTE_METRICS_MINUTES: u64 = 60;
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct MinuteBucket {
+    minute: u64,
+    pub(crate) requests: u64,
+    pub(crate) errors: u64,
+    pub(crate) slow: u64,
+}
+
+/// Per-route request counts in one-minute buckets over the last hour.
+#[derive(Default)]
+pub(crate) struct RouteMetrics {
+    routes: Mutex<HashMap<String, VecDeque<MinuteBucket>>>,
+}
+
+impl RouteMetrics {
+    pub(crate) fn record(&self, route: String, minute: u64, error: bool, slow: bool) {
+        let mut routes = self.routes.lock().unwrap();
+        let buckets = routes.entry(route).or_default();
+
+        if buckets.back().map(|b| b.minute) != Some(minute) {
+            buckets.push_back(MinuteBucket {
+                minute,
+                ..Default::default()
+            });
+        }
+        while buckets
+            .front()
+            .is_some_and(|b| b.minute + ROUTE_METRICS_MINUTES <= minute)
+        {
+            buckets.pop_front();
+        }
+
+        let bucket = buckets.back_mut().unwrap();
+        bucket.requests += 1;
+        bucket.errors += error as u64;
+        bucket.slow += slow as u64;
+    }
+
+    /// Sums each route's buckets from the last `minutes` minutes.
+    pub(crate) fn window(&self, now_minute: u64, minutes: u64) -> Vec<(String, MinuteBucket)> {
+        let routes = self.routes.lock().unwrap();
+        let mut totals: Vec<(String, MinuteBucket)> = routes
+            .iter()
+            .map(|(route, buckets)| {
+                let total = buckets
+                    .iter()
+                    .filter(|b| b.minute + minutes > now_minute)
+                    .fold(MinuteBucket::default(), |acc, b| MinuteBucket {
+                        minute: now_minute,
+                        requests: acc.requests + b.requests,
+                        errors: acc.errors + b.errors,
+                        slow: acc.slow + b.slow,
+                    });
+                (route.clone(), total)
+            })
+            .collect();
+        totals.sort_by(|a, b| a.0.cmp(&b.0));
+        totals
+    }
+}
+
+pub(crate) fn now_minute() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 60
+}
+
+pub(crate) async fn track_route_metrics(
+    State(state): State<Arc<AppState>>,
+    matched: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = matched
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| "<unmatched>".to_string());
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed();
+
+    state.route_metrics.record(
+        route,
+        now_minute(),
+        response.status().is_server_error(),
+        elapsed > Duration::from_millis(state.slo.latency_threshold_ms),
+    );
+
+    response
+}
@@ -0,0 +1,141 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Points for each opponent a pokemon hits on its weakness.
+const ADVANTAGE_POINTS: i32 = 50;
+/// Points lost for each opponent whose type hits this pokemon's weakness.
+const DISADVANTAGE_POINTS: i32 = 30;
+
+/// A pokemon as seen by the scorer: its types (attribute names), the types
+/// it is weak to, and the damage of its strongest ability.
+pub struct Combatant {
+    pub pokemon_id: i32,
+    pub name: String,
+    pub types: Vec<String>,
+    pub weaknesses: Vec<String>,
+    pub best_damage: i32,
+}
+
+impl Combatant {
+    fn hits_weakness_of(&self, other: &Combatant) -> bool {
+        self.types
+            .iter()
+            .any(|t| other.weaknesses.iter().any(|w| w.eq_ignore_ascii_case(t)))
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Recommendation {
+    pub pokemon_id: i32,
+    pub name: String,
+    pub score: i32,
+    pub strong_against: Vec<String>,
+    pub weak_against: Vec<String>,
+}
+
+/// Scores every pokemon in `collection` against the whole opposing team and
+/// returns the best `team_size` of them, highest score first.
+///
+/// A pokemon scores `ADVANTAGE_POINTS` for every opponent it is super
+/// effective against, loses `DISADVANTAGE_POINTS` for every opponent that is
+/// super effective against it, and adds its best ability damage, so that raw
+/// power breaks ties between equally matched picks.
+pub fn recommend(
+    collection: Vec<Combatant>,
+    opponents: &[Combatant],
+    team_size: usize,
+) -> Vec<Recommendation> {
+    let mut team: Vec<Recommendation> = collection
+        .into_iter()
+        .map(|candidate| {
+            let strong_against: Vec<String> = opponents
+                .iter()
+                .filter(|o| candidate.hits_weakness_of(o))
+                .map(|o| o.name.clone())
+                .collect();
+            let weak_against: Vec<String> = opponents
+                .iter()
+                .filter(|o| o.hits_weakness_of(&candidate))
+                .map(|o| o.name.clone())
+                .collect();
+
+            let score = (strong_against.len() as i32 * ADVANTAGE_POINTS
+                - weak_against.len() as i32 * DISADVANTAGE_POINTS)
+                .saturating_add(candidate.best_damage);
+
+            Recommendation {
+                pokemon_id: candidate.pokemon_id,
+                name: candidate.name,
+                score,
+                strong_against,
+                weak_against,
+            }
+        })
+        .collect();
+
+    team.sort_by(|a, b| b.score.cmp(&a.score).then(a.pokemon_id.cmp(&b.pokemon_id)));
+    team.truncate(team_size);
+    team
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recommend, Combatant};
+
+    fn combatant(pokemon_id: i32, name: &str, types: &[&str], weaknesses: &[&str]) -> Combatant {
+        Combatant {
+            pokemon_id,
+            name: name.to_string(),
+            types: types.iter().map(|t| t.to_string()).collect(),
+            weaknesses: weaknesses.iter().map(|w| w.to_string()).collect(),
+            best_damage: 0,
+        }
+    }
+
+    #[test]
+    fn picks_score_by_matchups_against_the_whole_team() {
+        let squirtle = combatant(1, "Squirtle", &["Water"], &["Electric", "Grass"]);
+        let pikachu = combatant(2, "Pikachu", &["Electric"], &["Ground"]);
+        let bulbasaur = combatant(3, "Bulbasaur", &["Grass"], &["Fire"]);
+        let opponents = [
+            combatant(10, "Gyarados", &["Water"], &["electric"]),
+            combatant(11, "Geodude", &["Ground"], &["Water", "Grass"]),
+        ];
+
+        let team = recommend(vec![squirtle, pikachu, bulbasaur], &opponents, 6);
+        let scores: Vec<(&str, i32)> = team.iter().map(|r| (r.name.as_str(), r.score)).collect();
+        // Types match weaknesses case-insensitively, and ties go by id.
+        assert_eq!(
+            scores,
+            [("Squirtle", 50), ("Bulbasaur", 50), ("Pikachu", 20)]
+        );
+        assert_eq!(team[0].strong_against, ["Geodude"]);
+        assert!(team[0].weak_against.is_empty());
+        assert_eq!(team[2].strong_against, ["Gyarados"]);
+        assert_eq!(team[2].weak_against, ["Geodude"]);
+    }
+
+    #[test]
+    fn damage_breaks_ties_and_the_team_is_cut_to_size() {
+        let mut weak = combatant(1, "Magikarp", &["Water"], &[]);
+        weak.best_damage = 10;
+        let mut strong = combatant(2, "Gyarados", &["Water"], &[]);
+        strong.best_damage = 80;
+        let mut tied = combatant(3, "Lapras", &["Water"], &[]);
+        tied.best_damage = 80;
+
+        let team = recommend(vec![weak, tied, strong], &[], 2);
+        let ids: Vec<i32> = team.iter().map(|r| r.pokemon_id).collect();
+        assert_eq!(ids, [2, 3]);
+    }
+
+    #[test]
+    fn huge_damage_does_not_overflow() {
+        let mut mew = combatant(1, "Mew", &["Psychic"], &[]);
+        mew.best_damage = i32::MAX;
+        let opponents = [combatant(10, "Machop", &["Fighting"], &["Psychic"])];
+
+        let team = recommend(vec![mew], &opponents, 1);
+        assert_eq!(team[0].score, i32::MAX);
+    }
+}
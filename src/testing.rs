@@ -0,0 +1,136 @@
+use crate::case::FieldCase;
+use crate::contract;
+use crate::repository::InMemoryRepository;
+use crate::{AppState, AuthConfig, Clock, Db, Limits};
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio_postgres::NoTls;
+use tower::ServiceExt;
+
+/// State serving every repository from `repo`. Its database can't be
+/// reached, so a route that goes to it directly answers 503 at once rather
+/// than touching a real one.
+pub(crate) fn state(repo: Arc<InMemoryRepository>) -> AppState {
+    let mut config = tokio_postgres::Config::new();
+    config.host("127.0.0.1").port(1).user("test");
+    let manager = Manager::from_config(
+        config,
+        NoTls,
+        ManagerConfig {
+            recycling_method: RecyclingMethod::Fast,
+        },
+    );
+    let pool = Pool::builder(manager)
+        .wait_timeout(Some(Duration::from_millis(100)))
+        .create_timeout(Some(Duration::from_millis(100)))
+        .runtime(Runtime::Tokio1)
+        .build()
+        .unwrap();
+
+    AppState::new(
+        Db::new(pool, Duration::from_secs(1)),
+        Limits::default(),
+        AuthConfig::new(b"test secret", Duration::from_secs(60)),
+    )
+    .with_trainer_repository(repo.clone())
+    .with_pokemon_repository(repo.clone())
+    .with_ability_repository(repo.clone())
+    .with_user_repository(repo.clone())
+    .with_battle_repository(repo.clone())
+    .with_quiz_repository(repo)
+}
+
+/// A clock that only moves when told to.
+pub(crate) struct TestClock(Mutex<OffsetDateTime>);
+
+impl TestClock {
+    pub(crate) fn at(now: OffsetDateTime) -> Arc<Self> {
+        Arc::new(Self(Mutex::new(now)))
+    }
+
+    pub(crate) fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// A bearer token for user `user_id`, signed with the key `state` checks.
+pub(crate) fn token(state: &AppState, user_id: i32) -> String {
+    state
+        .auth
+        .issue(user_id, format!("user{}", user_id), state.clock.now())
+}
+
+/// Sends one request through `app` and returns the status and the JSON
+/// body, or `Value::Null` for an empty or non-JSON one.
+pub(crate) async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    send_in(FieldCase::Snake, app, method, uri, token, body).await
+}
+
+/// Like `send`, for an app serving field names in `case`.
+pub(crate) async fn send_in(
+    case: FieldCase,
+    app: &Router,
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    respond(case, app, request).await
+}
+
+/// Like `send`, for a request that needs more than a token and a body.
+/// JSON responses are checked against the spec, failing the test if they
+/// drift from it.
+pub(crate) async fn send_request(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    respond(FieldCase::Snake, app, request).await
+}
+
+async fn respond(case: FieldCase, app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let method = request.method().clone();
+    let uri = request.uri().to_string();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|t| t.as_bytes().starts_with(b"application/json"));
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    if is_json {
+        contract::check(case, &method, &uri, status, &body);
+    }
+
+    (status, body)
+}
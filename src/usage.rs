@@ -0,0 +1,271 @@
+use crate::db::DbError;
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::Date;
+use tokio::task::JoinHandle;
+
+/// Client that requests without a valid bearer token are charged to.
+const ANONYMOUS: &str = "anonymous";
+
+tokio::task_local! {
+    /// Cost of the request being handled on this task, charged by `Db`.
+    static REQUEST_COST: Arc<RequestCost>;
+}
+
+#[derive(Default)]
+struct RequestCost {
+    queries: AtomicU64,
+    query_micros: AtomicU64,
+}
+
+/// Charges a query to the request it ran for. Queries run outside a
+/// request, or on a task the handler spawned, are not charged to anyone.
+pub(crate) fn charge_query(elapsed: Duration) {
+    let _ = REQUEST_COST.try_with(|cost| {
+        cost.queries.fetch_add(1, Ordering::Relaxed);
+        cost.query_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    });
+}
+
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    requests: i64,
+    queries: i64,
+    query_micros: i64,
+    request_micros: i64,
+}
+
+impl Totals {
+    fn add(&mut self, other: Totals) {
+        self.requests += other.requests;
+        self.queries += other.queries;
+        self.query_micros += other.query_micros;
+        self.request_micros += other.request_micros;
+    }
+}
+
+/// Per-client, per-day totals not yet written to `api_usage`.
+#[derive(Default)]
+pub(crate) struct UsageLedger {
+    pending: Mutex<HashMap<(String, Date), Totals>>,
+}
+
+impl UsageLedger {
+    fn record(&self, client: String, day: Date, totals: Totals) {
+        self.pending
+            .lock()
+            .unwrap()
+            .entry((client, day))
+            .or_default()
+            .add(totals);
+    }
+
+    /// Adds the pending totals to `api_usage`. On failure they are kept for
+    /// the next flush.
+    pub(crate) async fn flush(&self, state: &AppState) -> Result<(), DbError> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        match write(state, &pending).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let mut kept = self.pending.lock().unwrap();
+                for (key, totals) in pending {
+                    kept.entry(key).or_default().add(totals);
+                }
+
+                Err(e)
+            }
+        }
+    }
+}
+
+async fn write(state: &AppState, pending: &HashMap<(String, Date), Totals>) -> Result<(), DbError> {
+    let mut clients = Vec::with_capacity(pending.len());
+    let mut days = Vec::with_capacity(pending.len());
+    let mut columns: [Vec<i64>; 4] = Default::default();
+    for ((client, day), totals) in pending {
+        clients.push(client.as_str());
+        days.push(*day);
+        columns[0].push(totals.requests);
+        columns[1].push(totals.queries);
+        columns[2].push(totals.query_micros);
+        columns[3].push(totals.request_micros);
+    }
+    let [requests, queries, query_micros, request_micros] = columns;
+
+    let db = state.db.conn().await?;
+    db.execute(
+        "INSERT INTO api_usage (client, day, requests, queries, query_micros, request_micros)
+         SELECT * FROM unnest($1::text[], $2::date[], $3::int8[], $4::int8[], $5::int8[], $6::int8[])
+         ON CONFLICT (client, day) DO UPDATE SET
+             requests = api_usage.requests + EXCLUDED.requests,
+             queries = api_usage.queries + EXCLUDED.queries,
+             query_micros = api_usage.query_micros + EXCLUDED.query_micros,
+             request_micros = api_usage.request_micros + EXCLUDED.request_micros",
+        &[
+            &clients,
+            &days,
+            &requests,
+            &queries,
+            &query_micros,
+            &request_micros,
+        ],
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Counts each request, its latency, and the queries it ran against the
+/// signed-in user who sent it, or against `anonymous`.
+pub(crate) async fn track_usage(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let now = state.clock.now();
+    let client = match state.auth.verify(request.headers(), now) {
+        Ok(claims) => format!("user:{}", claims.username),
+        Err(_) => ANONYMOUS.to_string(),
+    };
+
+    let start = Instant::now();
+    let cost = Arc::new(RequestCost::default());
+    let response = REQUEST_COST.scope(cost.clone(), next.run(request)).await;
+
+    state.usage.record(
+        client,
+        now.date(),
+        Totals {
+            requests: 1,
+            queries: cost.queries.load(Ordering::Relaxed) as i64,
+            query_micros: cost.query_micros.load(Ordering::Relaxed) as i64,
+            request_micros: start.elapsed().as_micros() as i64,
+        },
+    );
+    response
+}
+
+/// Periodically writes the usage gathered in memory to `api_usage`. The
+/// task ends once the state starts shutting down; requests still draining
+/// then are written by a last `flush_usage`.
+pub fn spawn_usage_flusher(state: AppState, every: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        let shutting_down = state.shutting_down();
+        tokio::pin!(shutting_down);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = &mut shutting_down => return,
+            }
+            flush_usage(&state).await;
+        }
+    })
+}
+
+/// Writes the usage gathered in memory so far, logging any failure.
+pub async fn flush_usage(state: &AppState) {
+    if let Err(e) = state.usage.flush(state).await {
+        tracing::error!("Failed to record API usage: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{charge_query, RequestCost, Totals, ANONYMOUS, REQUEST_COST};
+    use crate::build_app;
+    use crate::repository::InMemoryRepository;
+    use crate::testing::{send, state, token};
+    use axum::http::{Method, StatusCode};
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn requests_are_charged_to_their_user_or_to_anonymous() {
+        let repo = Arc::new(InMemoryRepository::default());
+        let user_id = repo.add_user(None, false);
+        let state = state(repo);
+        let today = state.clock.now().date();
+        let usage = state.usage.clone();
+        let user = token(&state, user_id);
+        let app = build_app(state);
+
+        for token in [Some(&user), Some(&user), None] {
+            let (status, _) = send(
+                &app,
+                Method::GET,
+                "/trainer",
+                token.map(|t| t.as_str()),
+                None,
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let pending = usage.pending.lock().unwrap();
+        let requests = |client: &str| pending[&(client.to_string(), today)].requests;
+        assert_eq!(requests(&format!("user:user{}", user_id)), 2);
+        assert_eq!(requests(ANONYMOUS), 1);
+    }
+
+    #[tokio::test]
+    async fn queries_are_charged_only_to_the_request_they_ran_for() {
+        // Outside any request there is no one to charge.
+        charge_query(Duration::from_millis(5));
+
+        let cost = Arc::new(RequestCost::default());
+        REQUEST_COST
+            .scope(cost.clone(), async {
+                charge_query(Duration::from_micros(300));
+                charge_query(Duration::from_micros(200));
+            })
+            .await;
+
+        assert_eq!(cost.queries.load(Ordering::Relaxed), 2);
+        assert_eq!(cost.query_micros.load(Ordering::Relaxed), 500);
+    }
+
+    #[tokio::test]
+    async fn totals_that_fail_to_flush_are_kept_for_the_next_flush() {
+        // The test state's database can't be reached, so every flush fails.
+        let state = state(Arc::new(InMemoryRepository::default()));
+        let today = state.clock.now().date();
+        let totals = Totals {
+            requests: 1,
+            queries: 3,
+            query_micros: 900,
+            request_micros: 2_000,
+        };
+
+        state.usage.record(ANONYMOUS.to_string(), today, totals);
+        assert!(state.usage.flush(&state).await.is_err());
+        state.usage.record(ANONYMOUS.to_string(), today, totals);
+        assert!(state.usage.flush(&state).await.is_err());
+
+        let pending = state.usage.pending.lock().unwrap();
+        let kept = pending[&(ANONYMOUS.to_string(), today)];
+        assert_eq!(
+            (
+                kept.requests,
+                kept.queries,
+                kept.query_micros,
+                kept.request_micros
+            ),
+            (2, 6, 1_800, 4_000)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::error::ApiError;
+use crate::extract::JsonBody;
+use crate::models::{slugify, Description, Metadata, NewReference, Patch, ReferenceKind};
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use utoipa::ToSchema;
+
+/// Longest name accepted for regions, trainers, pokemon and usernames.
+pub(crate) const MAX_NAME_LEN: usize = 64;
+/// Longest ability status effect.
+pub(crate) const MAX_STATUS_EFFECT_LEN: usize = 64;
+/// Most damage an ability may deal. Keeps the simulator's arithmetic, which
+/// scales and doubles it, well inside `i32`.
+pub(crate) const MAX_DAMAGE: i32 = 10_000;
+/// Most items a bulk create request may carry.
+pub(crate) const MAX_BULK_ITEMS: usize = 1000;
+/// Most keys a trainer's or pokemon's `metadata` may have.
+pub(crate) const MAX_METADATA_KEYS: usize = 32;
+/// Longest `metadata` key.
+pub(crate) const MAX_METADATA_KEY_LEN: usize = 64;
+/// Longest description of a pokemon or ability, in markdown.
+pub(crate) const MAX_DESCRIPTION_LEN: usize = 2_000;
+/// Longest lore of a pokemon or ability, in markdown.
+pub(crate) const MAX_LORE_LEN: usize = 20_000;
+/// Longest reason given for a proposed change, or note on reviewing one.
+pub(crate) const MAX_NOTE_LEN: usize = 500;
+/// Longest URL an external reference may point at.
+pub(crate) const MAX_URL_LEN: usize = 2_048;
+/// Host Bulbapedia references must point at.
+const BULBAPEDIA_HOST: &str = "bulbapedia.bulbagarden.net";
+
+/// Whether `value` is a lowercase BCP 47 tag other than English, such as
+/// `fr` or `pt-br`: the locales ability translations are kept under.
+pub(crate) fn is_locale(value: &str) -> bool {
+    let mut subtags = value.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.bytes().all(|b| b.is_ascii_lowercase())
+        && language != "en"
+        && subtags.all(|s| {
+            (1..=8).contains(&s.len())
+                && s.bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        })
+}
+
+/// One rejected field of a request body, path or query string.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct FieldError {
+    /// Items of a bulk request are reported by index, as in `[2].name`.
+    field: String,
+    message: String,
+}
+
+impl FieldError {
+    pub(crate) fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Collects the problems with a request body so that all of them are
+/// reported at once.
+#[derive(Default)]
+pub(crate) struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub(crate) fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError::new(field, message));
+    }
+
+    /// Checks that `value` has something besides whitespace and is at most
+    /// `max` characters long.
+    pub(crate) fn name(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.add(field, "must not be empty");
+        } else {
+            self.max_len(field, value, max);
+        }
+    }
+
+    /// Like `name`, for the names slugs are made from: also checks that the
+    /// slug has a letter in it, so that it is neither empty nor taken for a
+    /// numeric id.
+    pub(crate) fn slug_name(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.add(field, "must not be empty");
+        } else if !slugify(value).bytes().any(|b| b.is_ascii_alphabetic()) {
+            self.add(field, "must contain a letter from A to Z");
+        } else {
+            self.max_len(field, value, max);
+        }
+    }
+
+    /// Checks that `value` is between 0 and `MAX_DAMAGE`.
+    pub(crate) fn damage(&mut self, field: &str, value: i32) {
+        if value < 0 {
+            self.add(field, "must not be negative");
+        } else if value > MAX_DAMAGE {
+            self.add(field, format!("must be at most {}", MAX_DAMAGE));
+        }
+    }
+
+    /// `Err` with every collected problem, if there are any.
+    pub(crate) fn into_result(self) -> Result<(), ApiError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(self.0))
+        }
+    }
+
+    /// Rejects `null` for a PATCH field that can be changed but not cleared.
+    pub(crate) fn not_null<T>(&mut self, field: &str, value: &Patch<T>) {
+        if value.is_null() {
+            self.add(field, "must not be null");
+        }
+    }
+
+    pub(crate) fn max_len(&mut self, field: &str, value: &str, max: usize) {
+        if value.chars().count() > max {
+            self.add(field, format!("must be at most {} characters", max));
+        }
+    }
+
+    pub(crate) fn locale(&mut self, field: &str, value: &str) {
+        if !is_locale(value) {
+            self.add(
+                field,
+                "must be a language tag such as `fr` or `pt-br`, other than English",
+            );
+        }
+    }
+
+    /// Checks that `value` is an `https` URL, and returns its host in
+    /// lowercase. Credentials in the URL are refused.
+    pub(crate) fn https_url(&mut self, field: &str, value: &str) -> Option<String> {
+        if value.chars().count() > MAX_URL_LEN {
+            self.max_len(field, value, MAX_URL_LEN);
+            return None;
+        }
+        let host = value
+            .get(..8)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("https://"))
+            .and_then(|_| value[8..].split(['/', '?', '#']).next())
+            .filter(|authority| !authority.contains('@'))
+            .map(|authority| authority.split(':').next().unwrap_or_default())
+            .filter(|host| {
+                !host.is_empty()
+                    && host
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+            });
+        if host.is_none() || value.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            self.add(field, "must be an https URL");
+            return None;
+        }
+
+        host.map(str::to_ascii_lowercase)
+    }
+
+    pub(crate) fn metadata(&mut self, field: &str, value: &Metadata) {
+        if value.len() > MAX_METADATA_KEYS {
+            self.add(
+                field,
+                format!("must have at most {} keys", MAX_METADATA_KEYS),
+            );
+        }
+        for key in value.keys() {
+            if key.is_empty() {
+                self.add(field, "keys must not be empty");
+            }
+            self.max_len(&format!("{}.{}", field, key), key, MAX_METADATA_KEY_LEN);
+        }
+    }
+}
+
+/// Request bodies that can be checked before a handler runs.
+pub(crate) trait Validate {
+    fn validate(&self, errors: &mut FieldErrors);
+}
+
+/// Bulk request bodies: each item is checked on its own and its problems
+/// are reported under its index.
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if self.len() > MAX_BULK_ITEMS {
+            errors.add(
+                "items",
+                format!("must have at most {} items", MAX_BULK_ITEMS),
+            );
+            return;
+        }
+
+        for (i, item) in self.iter().enumerate() {
+            let mut item_errors = FieldErrors::default();
+            item.validate(&mut item_errors);
+            for e in item_errors.0 {
+                errors.add(format!("[{}].{}", i, e.field), e.message);
+            }
+        }
+    }
+}
+
+impl Validate for NewReference {
+    fn validate(&self, errors: &mut FieldErrors) {
+        match self.kind {
+            ReferenceKind::Bulbapedia => {
+                let host = errors.https_url("value", &self.value);
+                if host.is_some_and(|h| h != BULBAPEDIA_HOST) {
+                    errors.add("value", format!("must be a URL on {}", BULBAPEDIA_HOST));
+                }
+            }
+            ReferenceKind::Sprite => {
+                errors.https_url("value", &self.value);
+            }
+            ReferenceKind::Pokeapi => {
+                let id = self.value.parse::<u32>().ok().filter(|&id| id > 0);
+                if id.map(|id| id.to_string()).as_deref() != Some(self.value.as_str()) {
+                    errors.add("value", "must be a PokeAPI id, such as 25");
+                }
+            }
+        }
+    }
+}
+
+impl Validate for Description {
+    fn validate(&self, errors: &mut FieldErrors) {
+        if let Some(description) = &self.description {
+            errors.max_len("description", description, MAX_DESCRIPTION_LEN);
+        }
+        if let Some(lore) = &self.lore {
+            errors.max_len("lore", lore, MAX_LORE_LEN);
+        }
+    }
+}
+
+/// Like `JsonBody`, but also runs the body's `Validate` impl, answering 422
+/// with every failing field when it is rejected.
+pub(crate) struct Valid<T>(pub(crate) T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Valid<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let JsonBody(value) = JsonBody::<T>::from_request(req, state).await?;
+
+        let mut errors = FieldErrors::default();
+        value.validate(&mut errors);
+        errors.into_result()?;
+
+        Ok(Self(value))
+    }
+}
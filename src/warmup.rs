@@ -0,0 +1,114 @@
+use crate::db::DbError;
+use crate::migrations;
+use crate::routes::pokemon::warm_catalog;
+use crate::AppState;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Names every relation the catalog endpoints read. Preparing it makes each
+/// backend load their metadata before a request needs it.
+const CATALOG_RELATIONS_SQL: &str = "SELECT 1
+     FROM published_pokemon p
+     JOIN region r ON r.region_id = p.region_id
+     JOIN pokemonabilities pa ON pa.pokemon_id = p.pokemon_id
+     JOIN published_ability a ON a.ability_id = pa.ability_id
+     JOIN pokemonattributes pt ON pt.pokemon_id = p.pokemon_id
+     JOIN attribute t ON t.attribute_id = pt.attribute_id
+     JOIN trainerspokemon tp ON tp.pokemon_id = p.pokemon_id
+     JOIN trainer tr ON tr.trainer_id = tp.trainer_id";
+
+/// How long to wait before retrying a warm-up that failed.
+const RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Warms the instance up in the background and holds `/ready` at 503 until
+/// it is done, so the first request a load balancer sends isn't the one that
+/// pays for opening connections and filling caches.
+///
+/// Warming up checks that no migration is pending, opens the whole pool with
+/// each connection's catalog metadata loaded, and caches the default
+/// `/pokemon` page. A failed attempt is retried until the server shuts down.
+pub fn spawn_warm_up(state: AppState) -> JoinHandle<()> {
+    state.warmed_up.store(false, Ordering::Release);
+
+    tokio::spawn(async move {
+        loop {
+            match warm_up(&state).await {
+                Ok(()) => {
+                    state.warmed_up.store(true, Ordering::Release);
+                    tracing::info!("Warm-up complete");
+
+                    return;
+                }
+                Err(e) => tracing::error!("Warm-up failed: {}", e),
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(RETRY_AFTER) => {}
+                _ = state.shutting_down() => return,
+            }
+        }
+    })
+}
+
+async fn warm_up(state: &AppState) -> Result<(), WarmUpError> {
+    let db = state.db.conn().await?;
+    let pending = migrations::pending(&db).await?;
+    if !pending.is_empty() {
+        return Err(WarmUpError::PendingMigrations(pending));
+    }
+    let regions: i64 = db.query("SELECT count(*) FROM region", &[]).await?[0].get(0);
+    if regions == 0 {
+        // Not fatal: an empty catalog is valid, just not much of a demo.
+        tracing::warn!("No regions found; the catalog has not been seeded");
+    }
+    drop(db);
+
+    // Every lookup starts at once and checks out a connection of its own,
+    // so the pool opens all of them.
+    let size = state.db.pool.status().max_size;
+    state
+        .db
+        .lookup_all(0..size, size, |db, _| async move {
+            db.prepare(CATALOG_RELATIONS_SQL).await
+        })
+        .await?;
+
+    warm_catalog(state).await?;
+
+    Ok(())
+}
+
+enum WarmUpError {
+    Db(DbError),
+    PendingMigrations(Vec<&'static str>),
+}
+
+impl From<DbError> for WarmUpError {
+    fn from(e: DbError) -> Self {
+        Self::Db(e)
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for WarmUpError {
+    fn from(e: deadpool_postgres::PoolError) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+impl From<tokio_postgres::Error> for WarmUpError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        Self::Db(e.into())
+    }
+}
+
+impl std::fmt::Display for WarmUpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Db(e) => e.fmt(f),
+            Self::PendingMigrations(names) => {
+                write!(f, "migrations not applied: {}", names.join(", "))
+            }
+        }
+    }
+}